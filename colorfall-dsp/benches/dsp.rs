@@ -0,0 +1,155 @@
+//! Benchmarks for the DSP's hot paths, so performance regressions from new features show up as
+//! numbers. Run with `cargo bench -p colorfall-dsp`, and compare against a baseline with
+//! `-- --save-baseline <name>` and `-- --baseline <name>`.
+//!
+//! The block sizes cover a small host buffer, a typical one, and offline rendering. All
+//! throughputs are in stereo frames.
+use colorfall_dsp::{
+    BiquadCoefficients, Character, ColorFallEngine, CrossoverAllpass, CrossoverSlope,
+    DEFAULT_BANDS, EngineParams, IntensityAdaptation, LinkwitzRileyCrossover, MAX_BANDS,
+    MAX_CHANNELS, RampedBiquad, calculate_target_gr, crossover_frequencies,
+};
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use std::hint::black_box;
+
+const SAMPLE_RATE: f32 = 48000.0;
+const BLOCK_SIZES: [usize; 3] = [64, 512, 4096];
+const NUM_CHANNELS: usize = 2;
+
+/// A stereo test signal with some low end, some top end and a level that moves, so the
+/// compressors and the reactive EQ have something to do.
+fn test_signal(num_samples: usize) -> (Vec<f32>, Vec<f32>) {
+    let sample = |i: usize, detune: f32| {
+        let t = i as f32 / SAMPLE_RATE;
+        let envelope = 0.5 + 0.5 * (2.0 * std::f32::consts::PI * 2.0 * t).sin();
+        envelope
+            * (0.4 * (2.0 * std::f32::consts::PI * 110.0 * detune * t).sin()
+                + 0.1 * (2.0 * std::f32::consts::PI * 3500.0 * detune * t).sin())
+    };
+
+    (
+        (0..num_samples).map(|i| sample(i, 1.0)).collect(),
+        (0..num_samples).map(|i| sample(i, 1.01)).collect(),
+    )
+}
+
+/// The whole per-sample path through [`ColorFallEngine`], at a high Amount so every stage is busy.
+fn engine(c: &mut Criterion) {
+    let mut group = c.benchmark_group("engine");
+    for block_size in BLOCK_SIZES {
+        let (input_left, input_right) = test_signal(block_size);
+        let mut engine = ColorFallEngine::new(SAMPLE_RATE);
+        engine.set_params(EngineParams {
+            amount: 0.8,
+            tilt: 0.3,
+            ..EngineParams::default()
+        });
+        engine.reset();
+        let (mut left, mut right) = (input_left.clone(), input_right.clone());
+
+        group.throughput(Throughput::Elements(block_size as u64));
+        group.bench_function(BenchmarkId::from_parameter(block_size), |b| {
+            b.iter(|| {
+                left.copy_from_slice(&input_left);
+                right.copy_from_slice(&input_right);
+                engine.process_block(black_box(&mut left), black_box(&mut right));
+            })
+        });
+    }
+    group.finish();
+}
+
+/// The biquad cascade of the default band layout: the crossover split, the phase compensation
+/// allpasses and the reactive EQ's peaking filters, with the coefficients ramping like they do
+/// during a Tilt sweep.
+fn biquad_cascade(c: &mut Criterion) {
+    let num_bands = DEFAULT_BANDS;
+    let mut group = c.benchmark_group("biquad_cascade");
+    for block_size in BLOCK_SIZES {
+        let (left, right) = test_signal(block_size);
+        let mut crossovers = [LinkwitzRileyCrossover::default(); MAX_BANDS - 1];
+        let mut allpasses = [CrossoverAllpass::default(); MAX_BANDS - 1];
+        let mut peaking_filters = [RampedBiquad::default(); MAX_BANDS];
+        let mut tilt = 0.0f32;
+
+        group.throughput(Throughput::Elements(block_size as u64));
+        group.bench_function(BenchmarkId::from_parameter(block_size), |b| {
+            b.iter(|| {
+                // One coefficient update per block keeps the ramps running
+                tilt = if tilt > 0.9 { -0.9 } else { tilt + 0.1 };
+                let freqs = crossover_frequencies(SAMPLE_RATE, num_bands, tilt, |_| 0.0);
+                for (j, &freq) in freqs[..(num_bands - 1)].iter().enumerate() {
+                    let ramp_steps = block_size as u32;
+                    crossovers[j].update(SAMPLE_RATE, freq, CrossoverSlope::Slope24, ramp_steps);
+                    allpasses[j].update(SAMPLE_RATE, freq, CrossoverSlope::Slope24, ramp_steps);
+                    peaking_filters[j].ramp_to(
+                        BiquadCoefficients::calculate_peaking(SAMPLE_RATE, freq, 2.0, 3.0),
+                        ramp_steps,
+                    );
+                }
+
+                for (&left, &right) in left.iter().zip(&right) {
+                    let mut frame = [0.0; MAX_CHANNELS];
+                    frame[0] = left;
+                    frame[1] = right;
+
+                    let mut wet = [0.0; MAX_CHANNELS];
+                    let mut last_lp = frame;
+                    for i in (0..(num_bands - 1)).rev() {
+                        let (lp, mut hp) = crossovers[i].process(&last_lp, NUM_CHANNELS);
+                        for allpass in &mut allpasses[..i] {
+                            allpass.process(&mut hp, NUM_CHANNELS);
+                        }
+                        for (wet, sample) in wet.iter_mut().zip(hp) {
+                            *wet += sample;
+                        }
+                        last_lp = lp;
+                    }
+                    for (wet, sample) in wet.iter_mut().zip(last_lp) {
+                        *wet += sample;
+                    }
+                    for filter in &mut peaking_filters[..num_bands] {
+                        filter.process(&mut wet, NUM_CHANNELS);
+                    }
+                    black_box(wet);
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+/// The gain computer, for every band and a sweep of envelope levels from silence to full scale.
+fn target_gr(c: &mut Criterion) {
+    let num_bands = DEFAULT_BANDS;
+    let mut group = c.benchmark_group("calculate_target_gr");
+    for block_size in BLOCK_SIZES {
+        let envelopes: Vec<f32> = (0..block_size)
+            .map(|i| i as f32 / block_size as f32)
+            .collect();
+
+        group.throughput(Throughput::Elements(block_size as u64));
+        group.bench_function(BenchmarkId::from_parameter(block_size), |b| {
+            b.iter(|| {
+                for &envelope in &envelopes {
+                    for band_idx in 0..num_bands {
+                        black_box(calculate_target_gr(
+                            band_idx,
+                            num_bands,
+                            black_box(0.7),
+                            black_box(0.2),
+                            0.0,
+                            envelope,
+                            Character::Smooth,
+                            IntensityAdaptation::default(),
+                        ));
+                    }
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, engine, biquad_cascade, target_gr);
+criterion_main!(benches);
@@ -0,0 +1,248 @@
+//! Dumps the DSP's curves as CSV files for plotting: the summed crossover network's response for
+//! every band count and slope, the full engine's impulse, step and stepped sine responses, the
+//! gain computer's static curves, and the engine's response at every common sample rate compared
+//! with 192 kHz.
+//!
+//! Run with `cargo run -p colorfall-dsp --example analyze -- [output directory]`. The files go to
+//! `analysis/` by default.
+use colorfall_dsp::analysis::{self, FrequencyPoint};
+use colorfall_dsp::{
+    BiquadCoefficients, Character, Choice, ColorFallEngine, CrossoverSlope, DEFAULT_BANDS,
+    EngineParams, MAX_BANDS, MIN_BANDS, util,
+};
+use std::path::Path;
+
+const SAMPLE_RATE: f32 = 48000.0;
+const NUM_POINTS: usize = 200;
+const BLOCK_SIZE: usize = 512;
+/// The sample rates compared with [`REFERENCE_RATE`]. The DSP should sound the same at all of them.
+const COMPARED_RATES: [f32; 3] = [44100.0, 48000.0, 96000.0];
+const REFERENCE_RATE: f32 = 192000.0;
+/// The number of frequencies the sample rates are compared at, log-spaced from 20 Hz to 20 kHz.
+const NUM_COMPARED_FREQS: usize = 31;
+
+fn main() -> std::io::Result<()> {
+    let output_dir = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| String::from("analysis"));
+    let output_dir = Path::new(&output_dir);
+    std::fs::create_dir_all(output_dir)?;
+    let write = |name: &str, csv: String| std::fs::write(output_dir.join(name), csv);
+
+    // The crossover network should sum to a flat magnitude response for every layout
+    for num_bands in MIN_BANDS..=MAX_BANDS {
+        for slope_idx in 0..CrossoverSlope::NAMES.len() {
+            let slope = CrossoverSlope::from_index(slope_idx);
+            let impulse = analysis::impulse_response(
+                16384,
+                analysis::crossover_sum(SAMPLE_RATE, num_bands, 0.0, slope),
+            );
+            let response = analysis::frequency_response(&impulse, SAMPLE_RATE, NUM_POINTS);
+            let max_deviation = max_deviation_db(&response);
+            println!(
+                "Crossover sum, {num_bands} bands, {}: {max_deviation:.4} dB from flat",
+                CrossoverSlope::NAMES[slope_idx]
+            );
+            write(
+                &format!("crossover_sum_{num_bands}_bands_{slope_idx}.csv"),
+                analysis::frequency_response_csv(&response),
+            )?;
+        }
+    }
+
+    // The full engine at a few Amount settings. The impulse is quiet enough to stay below the
+    // compressors' thresholds, so it shows the dry/wet filter network.
+    for amount in [0.0, 0.4, 0.8] {
+        let new_engine = || {
+            let mut engine = ColorFallEngine::new(SAMPLE_RATE);
+            engine.set_params(EngineParams {
+                amount,
+                ..EngineParams::default()
+            });
+            engine.reset();
+            // Processes host-sized blocks, the way the plugin runs in a DAW
+            move |buffer: &mut [f32]| {
+                for left in buffer.chunks_mut(BLOCK_SIZE) {
+                    let mut right = left.to_vec();
+                    engine.process_block(left, &mut right);
+                }
+            }
+        };
+        let name = format!("{:.0}", amount * 100.0);
+
+        let impulse = analysis::impulse_response(16384, |buffer: &mut [f32]| {
+            buffer[0] *= 0.01;
+            new_engine()(buffer);
+        });
+        write(
+            &format!("engine_impulse_{name}.csv"),
+            analysis::samples_csv(&impulse, SAMPLE_RATE),
+        )?;
+        let step = analysis::step_response(SAMPLE_RATE as usize, 0.5, new_engine());
+        write(
+            &format!("engine_step_{name}.csv"),
+            analysis::samples_csv(&step, SAMPLE_RATE),
+        )?;
+        let response = analysis::stepped_sine_response(SAMPLE_RATE, -12.0, 60, new_engine);
+        write(
+            &format!("engine_sine_{name}.csv"),
+            analysis::frequency_response_csv(&response),
+        )?;
+    }
+
+    // The gain computer's static curves for every band of the default layout
+    for character_idx in 0..Character::NAMES.len() {
+        for band_idx in 0..DEFAULT_BANDS {
+            let curve = analysis::gain_reduction_curve(
+                band_idx,
+                DEFAULT_BANDS,
+                0.8,
+                0.0,
+                Character::from_index(character_idx),
+                -60.0,
+            );
+            write(
+                &format!("gain_curve_{character_idx}_band_{band_idx}.csv"),
+                analysis::gain_curve_csv(&curve),
+            )?;
+        }
+    }
+
+    // The same bells and tones at every sample rate. The bells are compared over the audible
+    // range, and the engine gets a quiet tone and one it compresses hard.
+    let freqs: Vec<f32> = (0..NUM_COMPARED_FREQS)
+        .map(|i| 20.0 * 1000.0f32.powf(i as f32 / (NUM_COMPARED_FREQS - 1) as f32))
+        .collect();
+    for center_freq in [1000.0, 8000.0, 14000.0] {
+        let bell_db = |sample_rate: f32, freq: f32| {
+            util::gain_to_db(
+                BiquadCoefficients::calculate_peaking(sample_rate, center_freq, 4.0, 6.0)
+                    .magnitude_at(sample_rate, freq),
+            )
+        };
+        let max_deviation = COMPARED_RATES
+            .iter()
+            .flat_map(|&sample_rate| {
+                freqs.iter().map(move |&freq| {
+                    (bell_db(sample_rate, freq) - bell_db(REFERENCE_RATE, freq)).abs()
+                })
+            })
+            .fold(0.0f32, f32::max);
+        println!(
+            "Reactive EQ bell at {center_freq} Hz: {max_deviation:.3} dB from {REFERENCE_RATE} Hz"
+        );
+    }
+    for (amount, level_db) in [(0.4, -12.0), (0.8, -6.0)] {
+        let tone_response = |sample_rate: f32| -> Vec<FrequencyPoint> {
+            freqs
+                .iter()
+                .map(|&freq| FrequencyPoint {
+                    freq,
+                    magnitude_db: tone_gain_db(sample_rate, amount, freq, level_db),
+                    phase: 0.0,
+                })
+                .collect()
+        };
+        let reference = tone_response(REFERENCE_RATE);
+        for sample_rate in COMPARED_RATES {
+            let response = tone_response(sample_rate);
+            let max_deviation =
+                response
+                    .iter()
+                    .zip(&reference)
+                    .fold(0.0f32, |max, (point, reference)| {
+                        max.max((point.magnitude_db - reference.magnitude_db).abs())
+                    });
+            println!(
+                "Engine at {:.0}% Amount, {level_db} dB tones, {sample_rate} Hz: \
+                 {max_deviation:.3} dB from {REFERENCE_RATE} Hz",
+                amount * 100.0
+            );
+            write(
+                &format!("sample_rate_{:.0}_{sample_rate:.0}.csv", amount * 100.0),
+                analysis::frequency_response_csv(&response),
+            )?;
+        }
+        write(
+            &format!("sample_rate_{:.0}_{REFERENCE_RATE:.0}.csv", amount * 100.0),
+            analysis::frequency_response_csv(&reference),
+        )?;
+    }
+
+    for sample_rate in COMPARED_RATES.into_iter().chain([REFERENCE_RATE]) {
+        println!(
+            "Engine at 80% Amount, {sample_rate} Hz: settles within 0.5 dB in {:.0} ms",
+            settle_ms(sample_rate, 0.8)
+        );
+    }
+
+    println!("Wrote the curves to {}", output_dir.display());
+    Ok(())
+}
+
+/// The engine's gain for a sine tone at `freq` and `level_db`, after it has settled.
+fn tone_gain_db(sample_rate: f32, amount: f32, freq: f32, level_db: f32) -> f32 {
+    let settle_samples = (0.5 * sample_rate) as usize;
+    let measure_samples = (0.2 * sample_rate) as usize;
+    let amplitude = util::db_to_gain(level_db);
+    let omega = 2.0 * std::f64::consts::PI * freq as f64 / sample_rate as f64;
+    let mut left: Vec<f32> = (0..(settle_samples + measure_samples))
+        .map(|i| amplitude * (omega * i as f64).sin() as f32)
+        .collect();
+
+    let mut engine = ColorFallEngine::new(sample_rate);
+    engine.set_params(EngineParams {
+        amount,
+        ..EngineParams::default()
+    });
+    engine.reset();
+    for left in left.chunks_mut(BLOCK_SIZE) {
+        let mut right = left.to_vec();
+        engine.process_block(left, &mut right);
+    }
+
+    let mean_square =
+        left[settle_samples..].iter().map(|s| s * s).sum::<f32>() / measure_samples as f32;
+    util::gain_to_db(mean_square.sqrt() / (amplitude / std::f32::consts::SQRT_2))
+}
+
+/// How long the engine takes to settle within 0.5 dB of its final level on a 1 kHz tone that
+/// starts from silence, measured in 10 ms windows.
+fn settle_ms(sample_rate: f32, amount: f32) -> f32 {
+    let window = (0.01 * sample_rate) as usize;
+    let omega = 2.0 * std::f64::consts::PI * 1000.0 / sample_rate as f64;
+    let mut left: Vec<f32> = (0..(2 * sample_rate as usize))
+        .map(|i| 0.5 * (omega * i as f64).sin() as f32)
+        .collect();
+
+    let mut engine = ColorFallEngine::new(sample_rate);
+    engine.set_params(EngineParams {
+        amount,
+        ..EngineParams::default()
+    });
+    engine.reset();
+    for left in left.chunks_mut(BLOCK_SIZE) {
+        let mut right = left.to_vec();
+        engine.process_block(left, &mut right);
+    }
+
+    let levels_db: Vec<f32> = left
+        .chunks_exact(window)
+        .map(|chunk| {
+            util::gain_to_db((chunk.iter().map(|s| s * s).sum::<f32>() / window as f32).sqrt())
+        })
+        .collect();
+    let final_db = levels_db[levels_db.len() - 1];
+    let settled = levels_db
+        .iter()
+        .rposition(|level_db| (level_db - final_db).abs() > 0.5)
+        .map_or(0, |i| i + 1);
+    settled as f32 * 10.0
+}
+
+/// The largest deviation from 0 dB in a frequency response.
+fn max_deviation_db(response: &[FrequencyPoint]) -> f32 {
+    response
+        .iter()
+        .fold(0.0, |max, point| max.max(point.magnitude_db.abs()))
+}
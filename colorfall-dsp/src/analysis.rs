@@ -0,0 +1,280 @@
+//! # Offline Analysis
+//!
+//! Renders test signals through the DSP and turns the results into curves, so the behavior of the
+//! filters and the gain computers can be checked in code instead of by ear. The processors are
+//! plain closures that process a mono buffer in place, so anything from a single filter to the
+//! whole [`ColorFallEngine`][crate::ColorFallEngine] can be measured. The curves can be written out
+//! as CSV for plotting.
+//!
+//! Checking that the crossover network sums back to a flat magnitude response:
+//!
+//! ```
+//! use colorfall_dsp::CrossoverSlope;
+//! use colorfall_dsp::analysis::{crossover_sum, frequency_response, impulse_response};
+//!
+//! let impulse = impulse_response(8192, crossover_sum(48000.0, 5, 0.0, CrossoverSlope::Slope24));
+//! let response = frequency_response(&impulse, 48000.0, 100);
+//! assert!(response.iter().all(|point| point.magnitude_db.abs() < 0.01));
+//! ```
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::f64::consts::PI;
+use core::fmt::Write;
+
+#[cfg(not(feature = "std"))]
+use crate::math::Float;
+use crate::{
+    Character, CrossoverAllpass, CrossoverSlope, IntensityAdaptation, LinkwitzRileyCrossover,
+    MAX_BANDS, MAX_CHANNELS, calculate_target_gr, crossover_frequencies, util,
+};
+
+/// The lowest frequency of the frequency response curves.
+const MIN_FREQ_HZ: f32 = 20.0;
+/// How long the stepped sine measurement lets every tone settle before measuring it.
+const SETTLE_MS: f32 = 200.0;
+/// How long the stepped sine measurement measures every tone for.
+const MEASURE_MS: f32 = 100.0;
+
+/// A point on a frequency response curve.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrequencyPoint {
+    pub freq: f32,
+    pub magnitude_db: f32,
+    /// The phase shift in radians, from -π to π.
+    pub phase: f32,
+}
+
+/// A point on a gain computer's static curve.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GainPoint {
+    /// The detector's envelope level in decibels.
+    pub input_db: f32,
+    /// The resulting gain reduction in decibels. This is 0 or negative.
+    pub gr_db: f32,
+}
+
+/// Renders the response to a unit impulse, `length` samples long.
+pub fn impulse_response(length: usize, mut process: impl FnMut(&mut [f32])) -> Vec<f32> {
+    let mut buffer = vec![0.0; length];
+    if let Some(first) = buffer.first_mut() {
+        *first = 1.0;
+    }
+    process(&mut buffer);
+
+    buffer
+}
+
+/// Renders the response to a step from silence to `level`, `length` samples long. With a
+/// compressor this shows the attack, and the overshoot before it settles.
+pub fn step_response(length: usize, level: f32, mut process: impl FnMut(&mut [f32])) -> Vec<f32> {
+    let mut buffer = vec![level; length];
+    process(&mut buffer);
+
+    buffer
+}
+
+/// An exponential sine sweep from `start_hz` to `end_hz`, for rendering through a processor and
+/// listening to or looking at the result. The sweep spends the same time on every octave.
+pub fn sine_sweep(
+    sample_rate: f32,
+    start_hz: f32,
+    end_hz: f32,
+    duration_s: f32,
+    level: f32,
+) -> Vec<f32> {
+    let num_samples = (duration_s * sample_rate) as usize;
+    let rate = (end_hz as f64 / start_hz as f64).ln();
+    let duration = duration_s as f64;
+    (0..num_samples)
+        .map(|i| {
+            let t = i as f64 / sample_rate as f64;
+            let phase =
+                2.0 * PI * start_hz as f64 * duration / rate * ((t * rate / duration).exp() - 1.0);
+            level * phase.sin() as f32
+        })
+        .collect()
+}
+
+/// `num_points` log-spaced frequencies from 20 Hz up to just below Nyquist.
+pub fn log_frequencies(sample_rate: f32, num_points: usize) -> Vec<f32> {
+    let max_freq = sample_rate * 0.49;
+    let ratio = max_freq / MIN_FREQ_HZ;
+    (0..num_points)
+        .map(|i| MIN_FREQ_HZ * ratio.powf(i as f32 / (num_points.max(2) - 1) as f32))
+        .collect()
+}
+
+/// The magnitude and phase response of an impulse response at `num_points` log-spaced
+/// frequencies. The impulse response needs to have decayed to silence by its end, or the curve
+/// picks up ripples.
+pub fn frequency_response(
+    impulse: &[f32],
+    sample_rate: f32,
+    num_points: usize,
+) -> Vec<FrequencyPoint> {
+    log_frequencies(sample_rate, num_points)
+        .into_iter()
+        .map(|freq| {
+            // A single bin of the DTFT. The phase advances by `omega` every sample.
+            let omega = 2.0 * PI * freq as f64 / sample_rate as f64;
+            let (mut re, mut im) = (0.0f64, 0.0f64);
+            for (n, &sample) in impulse.iter().enumerate() {
+                let (sin, cos) = (omega * n as f64).sin_cos();
+                re += sample as f64 * cos;
+                im -= sample as f64 * sin;
+            }
+
+            FrequencyPoint {
+                freq,
+                magnitude_db: (20.0 * (re * re + im * im).sqrt().max(1e-12).log10()) as f32,
+                phase: im.atan2(re) as f32,
+            }
+        })
+        .collect()
+}
+
+/// Measures the magnitude response with one sine tone per frequency at `level_db`, for processors
+/// that aren't linear, like the full engine. Every tone runs through a fresh processor from
+/// `new_processor`, settles, and is then compared against the input's level. The phase isn't
+/// measured and is left at 0.
+pub fn stepped_sine_response<P: FnMut(&mut [f32])>(
+    sample_rate: f32,
+    level_db: f32,
+    num_points: usize,
+    mut new_processor: impl FnMut() -> P,
+) -> Vec<FrequencyPoint> {
+    let settle_samples = (SETTLE_MS * sample_rate / 1000.0) as usize;
+    let measure_samples = ((MEASURE_MS * sample_rate / 1000.0) as usize).max(1);
+    let amplitude = util::db_to_gain(level_db);
+    log_frequencies(sample_rate, num_points)
+        .into_iter()
+        .map(|freq| {
+            let omega = 2.0 * core::f32::consts::PI * freq / sample_rate;
+            let mut buffer: Vec<f32> = (0..(settle_samples + measure_samples))
+                .map(|i| amplitude * (omega * i as f32).sin())
+                .collect();
+            new_processor()(&mut buffer);
+
+            let output_rms = rms(&buffer[settle_samples..]);
+            let input_rms = amplitude / core::f32::consts::SQRT_2;
+            FrequencyPoint {
+                freq,
+                magnitude_db: util::gain_to_db(output_rms / input_rms),
+                phase: 0.0,
+            }
+        })
+        .collect()
+}
+
+/// The static curve of a band's downward gain computer, at envelope levels from `min_db` to 0 dB
+/// in 1 dB steps. See [`calculate_target_gr()`] for the other arguments.
+pub fn gain_reduction_curve(
+    band_idx: usize,
+    num_bands: usize,
+    amount: f32,
+    tilt: f32,
+    character: Character,
+    min_db: f32,
+) -> Vec<GainPoint> {
+    let num_points = (-min_db).max(0.0) as usize + 1;
+    (0..num_points)
+        .map(|i| {
+            let input_db = min_db + i as f32;
+            let gr = calculate_target_gr(
+                band_idx,
+                num_bands,
+                amount,
+                tilt,
+                0.0,
+                util::db_to_gain(input_db),
+                character,
+                IntensityAdaptation::default(),
+            );
+
+            GainPoint {
+                input_db,
+                gr_db: util::gain_to_db(gr),
+            }
+        })
+        .collect()
+}
+
+/// A processor that splits the signal into `num_bands` bands with the plugin's crossover network
+/// and phase compensation, and sums them straight back together. This sums to an allpass, so its
+/// magnitude response should be flat.
+pub fn crossover_sum(
+    sample_rate: f32,
+    num_bands: usize,
+    tilt: f32,
+    slope: CrossoverSlope,
+) -> impl FnMut(&mut [f32]) {
+    let mut crossovers = [LinkwitzRileyCrossover::default(); MAX_BANDS - 1];
+    let mut phase_compensation = [[CrossoverAllpass::default(); MAX_BANDS - 2]; MAX_BANDS];
+    let freqs = crossover_frequencies(sample_rate, num_bands, tilt, |_| 0.0);
+    for (j, &freq) in freqs[..(num_bands - 1)].iter().enumerate() {
+        crossovers[j].update(sample_rate, freq, slope, 0);
+        for allpasses in &mut phase_compensation[(j + 2)..num_bands] {
+            allpasses[j].update(sample_rate, freq, slope, 0);
+        }
+    }
+
+    move |buffer| {
+        for sample in buffer {
+            let mut frame = [0.0; MAX_CHANNELS];
+            frame[0] = *sample;
+
+            let mut band_signals = [[0.0; MAX_CHANNELS]; MAX_BANDS];
+            let mut last_lp = frame;
+            for i in (0..(num_bands - 1)).rev() {
+                let (lp, hp) = crossovers[i].process(&last_lp, 1);
+                band_signals[i + 1] = hp;
+                last_lp = lp;
+            }
+            band_signals[0] = last_lp;
+
+            *sample = 0.0;
+            for (i, band_signal) in band_signals[..num_bands].iter_mut().enumerate() {
+                for allpass in &mut phase_compensation[i][..i.saturating_sub(1)] {
+                    allpass.process(band_signal, 1);
+                }
+                *sample += band_signal[0];
+            }
+        }
+    }
+}
+
+/// Formats a frequency response as CSV, with a header row.
+pub fn frequency_response_csv(points: &[FrequencyPoint]) -> String {
+    let mut csv = String::from("frequency_hz,magnitude_db,phase_rad\n");
+    for point in points {
+        let _ = writeln!(csv, "{},{},{}", point.freq, point.magnitude_db, point.phase);
+    }
+
+    csv
+}
+
+/// Formats a gain reduction curve as CSV, with a header row.
+pub fn gain_curve_csv(points: &[GainPoint]) -> String {
+    let mut csv = String::from("input_db,gr_db\n");
+    for point in points {
+        let _ = writeln!(csv, "{},{}", point.input_db, point.gr_db);
+    }
+
+    csv
+}
+
+/// Formats a rendered signal, like an impulse or step response, as CSV with one sample per row.
+pub fn samples_csv(samples: &[f32], sample_rate: f32) -> String {
+    let mut csv = String::from("time_s,value\n");
+    for (i, sample) in samples.iter().enumerate() {
+        let _ = writeln!(csv, "{},{}", i as f32 / sample_rate, sample);
+    }
+
+    csv
+}
+
+/// The RMS level of a signal.
+fn rms(samples: &[f32]) -> f32 {
+    (samples.iter().map(|sample| sample * sample).sum::<f32>() / samples.len().max(1) as f32).sqrt()
+}
@@ -0,0 +1,315 @@
+//! # Spectral Balance
+//!
+//! Measures how a signal's energy is spread over the spectrum with a bank of octave band filters.
+//! Pink noise, which has the same energy in every octave, is the balanced reference: its energy
+//! sits in the middle of the bands. Material with more low end leans below the middle and bright
+//! material above it, which [`suggested_tilt()`] turns into the Tilt setting that centers the
+//! processing on where the energy is. [`SpectralBalance::band_excess_db()`] compares the spectrum
+//! with a [`TargetCurve`] instead, band by band, and [`SpectralBalance::band_excess_db_relative()`]
+//! with another signal's spectrum.
+//!
+//! ```
+//! use colorfall_dsp::balance::{SpectralBalance, suggested_tilt};
+//!
+//! // A 100 Hz sine is several octaves below the middle of the bands
+//! let mut balance = SpectralBalance::default();
+//! balance.set_sample_rate(48000.0);
+//! for i in 0..48000 {
+//!     balance.process((2.0 * std::f32::consts::PI * 100.0 * i as f32 / 48000.0).sin());
+//! }
+//! let centroid = balance.centroid_octaves().unwrap();
+//! assert!(centroid < -2.0);
+//! assert_eq!(suggested_tilt(centroid), -1.0);
+//! ```
+use crate::loudness::SILENCE_MEAN_SQUARE;
+#[cfg(not(feature = "std"))]
+use crate::math::Float;
+use crate::util::{db_to_gain, gain_to_db};
+use crate::{Biquad, BiquadCoefficients, MAX_BANDS, MAX_CHANNELS, band_edges};
+
+/// The number of octave bands.
+pub const NUM_OCTAVE_BANDS: usize = 9;
+/// The center frequency of the lowest octave band. The bands are an octave apart from there, so
+/// the highest one is at 8 kHz. A 16 kHz band would come out too weak at 44.1 and 48 kHz, where
+/// the band-pass filter gets squeezed against Nyquist, and bias the measurement towards the lows.
+pub const LOWEST_OCTAVE_HZ: f32 = 31.25;
+/// The band-pass filters' Q, which makes them an octave wide.
+const OCTAVE_BAND_Q: f32 = core::f32::consts::SQRT_2;
+/// Bands centered above this fraction of the sample rate can't be measured and are left out.
+const MAX_BAND_FREQ_FRACTION: f32 = 0.45;
+/// How far the centroid needs to be from the middle of the bands for [`suggested_tilt()`] to ask
+/// for the full Tilt, in octaves.
+pub const FULL_TILT_OCTAVES: f32 = 2.0;
+
+/// How much louder every octave band of a flat spectrum is than the one below it, in decibels. A
+/// flat spectrum has twice the energy in an octave as in the octave below.
+const FLAT_DB_PER_OCTAVE: f32 = 3.01;
+/// The Modern Master target's level in every octave band relative to pink noise, in decibels.
+const MODERN_MASTER_LEVELS_DB: [f32; NUM_OCTAVE_BANDS] =
+    [3.0, 3.0, 2.0, 1.0, 0.0, -0.5, -1.0, -2.0, -3.5];
+
+choice! {
+    /// A long-term spectrum to push material towards, see [`SpectralBalance::band_excess_db()`].
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+    pub enum TargetCurve {
+        /// The same level at every frequency, like white noise. This is a lot brighter than most
+        /// music, so it mostly reins in the low end.
+        Flat => "Flat",
+        /// The same energy in every octave, like pink noise.
+        #[default]
+        Pink => "Pink",
+        /// Pink noise with a fuller low end and a softer top, the long-term balance of loud modern
+        /// masters.
+        ModernMaster => "Modern Master",
+    }
+}
+
+impl TargetCurve {
+    /// The curve's level in every octave band relative to pink noise, in decibels. Only the
+    /// differences between the bands matter.
+    pub fn octave_band_levels_db(self) -> [f32; NUM_OCTAVE_BANDS] {
+        match self {
+            TargetCurve::Flat => core::array::from_fn(|band_idx| {
+                FLAT_DB_PER_OCTAVE * (band_idx as f32 - (NUM_OCTAVE_BANDS - 1) as f32 / 2.0)
+            }),
+            TargetCurve::Pink => [0.0; NUM_OCTAVE_BANDS],
+            TargetCurve::ModernMaster => MODERN_MASTER_LEVELS_DB,
+        }
+    }
+}
+
+/// The center frequency of every octave band.
+pub fn octave_band_frequencies() -> [f32; NUM_OCTAVE_BANDS] {
+    core::array::from_fn(|band_idx| LOWEST_OCTAVE_HZ * (1 << band_idx) as f32)
+}
+
+/// The Tilt that centers the processing on a signal whose energy centroid is `centroid_octaves`
+/// away from the middle of the octave bands, from -1 (the low bands) to 1 (the high bands).
+pub fn suggested_tilt(centroid_octaves: f32) -> f32 {
+    (centroid_octaves / FULL_TILT_OCTAVES).clamp(-1.0, 1.0)
+}
+
+/// Measures the energy of a mono signal in every octave band. By default the energy is summed
+/// from the last [`reset()`][Self::reset()] on, so the measurement covers everything processed
+/// since then. [`with_averaging_time()`][Self::with_averaging_time()] makes it a running average
+/// that follows the signal instead.
+#[derive(Clone)]
+pub struct SpectralBalance {
+    filters: [Biquad; NUM_OCTAVE_BANDS],
+    /// The number of bands below the highest measurable frequency.
+    num_bands: usize,
+    /// The time constant of the running average in seconds, or `None` to sum the whole
+    /// measurement.
+    averaging_time: Option<f32>,
+    /// The fraction of the running average kept for every new sample.
+    average_coefficient: f64,
+
+    /// The power of every band, summed over the measurement, or its running average.
+    band_sums: [f64; NUM_OCTAVE_BANDS],
+    num_samples: usize,
+}
+
+impl Default for SpectralBalance {
+    fn default() -> Self {
+        let mut balance = Self {
+            filters: [Biquad::default(); NUM_OCTAVE_BANDS],
+            num_bands: NUM_OCTAVE_BANDS,
+            averaging_time: None,
+            average_coefficient: 1.0,
+            band_sums: [0.0; NUM_OCTAVE_BANDS],
+            num_samples: 0,
+        };
+        balance.set_sample_rate(44100.0);
+
+        balance
+    }
+}
+
+impl SpectralBalance {
+    /// A measurement that averages the band powers with a time constant of `averaging_time`
+    /// seconds, for following the balance of a signal as it plays.
+    pub fn with_averaging_time(averaging_time: f32) -> Self {
+        let mut balance = Self {
+            averaging_time: Some(averaging_time),
+            ..Self::default()
+        };
+        balance.set_sample_rate(44100.0);
+
+        balance
+    }
+
+    /// Updates the band filters for a new sample rate. This starts a new measurement.
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.average_coefficient = self.averaging_time.map_or(1.0, |averaging_time| {
+            (-1.0 / (averaging_time as f64 * sample_rate as f64)).exp()
+        });
+        let max_freq = sample_rate * MAX_BAND_FREQ_FRACTION;
+        let freqs = octave_band_frequencies();
+        self.num_bands = freqs.iter().filter(|&&freq| freq <= max_freq).count();
+        for (filter, &freq) in self.filters.iter_mut().zip(&freqs[..self.num_bands]) {
+            filter.set_coefficients(BiquadCoefficients::calculate_bandpass(
+                sample_rate,
+                freq,
+                OCTAVE_BAND_Q,
+            ));
+        }
+
+        self.reset();
+    }
+
+    /// Clears the filters and starts a new measurement.
+    pub fn reset(&mut self) {
+        for filter in &mut self.filters {
+            filter.reset();
+        }
+        self.band_sums = [0.0; NUM_OCTAVE_BANDS];
+        self.num_samples = 0;
+    }
+
+    /// Adds a sample to the measurement.
+    pub fn process(&mut self, sample: f32) {
+        for (filter, sum) in self.filters[..self.num_bands]
+            .iter_mut()
+            .zip(&mut self.band_sums)
+        {
+            let mut frame = [0.0; MAX_CHANNELS];
+            frame[0] = sample;
+            filter.process(&mut frame, 1);
+            let power = (frame[0] * frame[0]) as f64;
+            *sum = if self.averaging_time.is_some() {
+                *sum * self.average_coefficient + power * (1.0 - self.average_coefficient)
+            } else {
+                *sum + power
+            };
+        }
+
+        self.num_samples += 1;
+    }
+
+    /// The number of samples the measurement covers.
+    pub fn num_samples(&self) -> usize {
+        self.num_samples
+    }
+
+    /// The mean square of every octave band over the measurement, or its running average. Bands
+    /// that can't be measured at the current sample rate read as 0.
+    pub fn band_mean_squares(&self) -> [f32; NUM_OCTAVE_BANDS] {
+        let num_samples = if self.averaging_time.is_some() {
+            1.0
+        } else {
+            self.num_samples.max(1) as f64
+        };
+        self.band_sums.map(|sum| (sum / num_samples) as f32)
+    }
+
+    /// The energy-weighted average position of the octave bands, in octaves from the middle of
+    /// the bands. This is 0 for pink noise, negative when the low end dominates and positive for
+    /// bright material. Returns `None` if the signal was silent.
+    pub fn centroid_octaves(&self) -> Option<f32> {
+        let mean_squares = self.band_mean_squares();
+        let total: f32 = mean_squares.iter().sum();
+        if total <= SILENCE_MEAN_SQUARE {
+            return None;
+        }
+
+        let middle = (NUM_OCTAVE_BANDS - 1) as f32 / 2.0;
+        let centroid = mean_squares
+            .iter()
+            .enumerate()
+            .map(|(band_idx, mean_square)| band_idx as f32 * mean_square)
+            .sum::<f32>()
+            / total;
+
+        Some(centroid - middle)
+    }
+
+    /// How much louder every band of a `num_bands` band split at `crossover_freqs` is than
+    /// `target`, in decibels. A band's level is summed from the octave bands centered between its
+    /// [edges][band_edges()], or taken from the nearest octave band if it doesn't contain any. The
+    /// target is matched to the measurement's overall level first, so only the balance between the
+    /// bands counts. Bands from `num_bands` on read as 0. Returns `None` if the signal was silent.
+    pub fn band_excess_db(
+        &self,
+        target: TargetCurve,
+        sample_rate: f32,
+        crossover_freqs: &[f32],
+        num_bands: usize,
+    ) -> Option<[f32; MAX_BANDS]> {
+        let target_powers = target
+            .octave_band_levels_db()
+            .map(|level_db| db_to_gain(level_db).powi(2));
+
+        self.excess_over(&target_powers, sample_rate, crossover_freqs, num_bands)
+    }
+
+    /// [`band_excess_db()`][Self::band_excess_db()] with the spectrum `reference` measured as the
+    /// target. Both measurements need to run at the same sample rate. Returns `None` if either
+    /// signal was silent.
+    pub fn band_excess_db_relative(
+        &self,
+        reference: &SpectralBalance,
+        sample_rate: f32,
+        crossover_freqs: &[f32],
+        num_bands: usize,
+    ) -> Option<[f32; MAX_BANDS]> {
+        let reference_powers = reference.band_mean_squares();
+        if reference_powers.iter().sum::<f32>() <= SILENCE_MEAN_SQUARE {
+            return None;
+        }
+
+        self.excess_over(&reference_powers, sample_rate, crossover_freqs, num_bands)
+    }
+
+    /// The level of every band relative to the octave band powers in `target_powers`, see
+    /// [`band_excess_db()`][Self::band_excess_db()].
+    fn excess_over(
+        &self,
+        target_powers: &[f32; NUM_OCTAVE_BANDS],
+        sample_rate: f32,
+        crossover_freqs: &[f32],
+        num_bands: usize,
+    ) -> Option<[f32; MAX_BANDS]> {
+        let mean_squares = self.band_mean_squares();
+        let total: f32 = mean_squares.iter().sum();
+        if total <= SILENCE_MEAN_SQUARE {
+            return None;
+        }
+
+        // Only the octave bands that could be measured at this sample rate count towards the
+        // target's level
+        let target_scale = total / target_powers[..self.num_bands].iter().sum::<f32>();
+
+        let freqs = octave_band_frequencies();
+        let mut excess_db = [0.0; MAX_BANDS];
+        for (band_idx, excess_db) in excess_db[..num_bands].iter_mut().enumerate() {
+            let (lower, upper) = band_edges(sample_rate, crossover_freqs, num_bands, band_idx);
+            let mut octave_band_idxs = (0..self.num_bands)
+                .filter(|&octave_idx| (lower..upper).contains(&freqs[octave_idx]))
+                .peekable();
+            let (measured, target) = if octave_band_idxs.peek().is_some() {
+                octave_band_idxs.fold((0.0, 0.0), |(measured, target), octave_idx| {
+                    (
+                        measured + mean_squares[octave_idx],
+                        target + target_powers[octave_idx],
+                    )
+                })
+            } else {
+                let center_freq = (lower * upper).sqrt();
+                let nearest_idx = (0..self.num_bands)
+                    .min_by(|&a, &b| {
+                        let distance =
+                            |octave_idx: usize| (freqs[octave_idx] / center_freq).ln().abs();
+                        distance(a).total_cmp(&distance(b))
+                    })
+                    .unwrap_or(0);
+                (mean_squares[nearest_idx], target_powers[nearest_idx])
+            };
+
+            // A reference can be silent in some bands, which reads as far too loud rather than NaN
+            *excess_db =
+                gain_to_db((measured / (target * target_scale).max(f32::MIN_POSITIVE)).sqrt());
+        }
+
+        Some(excess_db)
+    }
+}
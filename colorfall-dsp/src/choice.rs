@@ -0,0 +1,56 @@
+//! Settings with a fixed list of named options, like the saturation model or the crossover slope.
+//! The names are part of the engine's interface, since they're what hosts and GUIs show, so they
+//! live next to the options themselves. The plugin turns every [`Choice`] into a choice parameter.
+
+/// A setting with a fixed list of named options. Implemented with the [`choice!`] macro.
+pub trait Choice: Copy + PartialEq + Send + Sync + 'static {
+    /// The options' display names, in the order of their indices.
+    const NAMES: &'static [&'static str];
+
+    /// The option's index in [`Choice::NAMES`].
+    fn to_index(self) -> usize;
+
+    /// The option at `index`. Out of range indices give the last option.
+    fn from_index(index: usize) -> Self;
+}
+
+/// Defines an enum without fields and implements [`Choice`] for it. Every variant is followed by
+/// its display name:
+///
+/// ```
+/// colorfall_dsp::choice! {
+///     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///     pub enum Shape {
+///         Sine => "Sine",
+///         /// Attributes and doc comments go in front of the variant.
+///         SampleAndHold => "Sample & Hold",
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! choice {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            $($(#[$variant_meta:meta])* $variant:ident => $display_name:literal,)+
+        }
+    ) => {
+        $(#[$meta])*
+        $vis enum $name {
+            $($(#[$variant_meta])* $variant,)+
+        }
+
+        impl $crate::Choice for $name {
+            const NAMES: &'static [&'static str] = &[$($display_name),+];
+
+            fn to_index(self) -> usize {
+                self as usize
+            }
+
+            fn from_index(index: usize) -> Self {
+                const VARIANTS: &[$name] = &[$($name::$variant),+];
+                VARIANTS[index.min(VARIANTS.len() - 1)]
+            }
+        }
+    };
+}
@@ -0,0 +1,513 @@
+//! # Block Processing Engine
+//!
+//! ColorFall's processing chain behind a plain block-processing interface, for game audio engines,
+//! embedded targets, and anything else that can't host a plugin. [`ColorFallEngine`] runs the
+//! crossover engine at the `Normal` quality: the tilt-shifted band split, the saturators, the band
+//! compressors, the reactive EQ, the loudness compensation, the dry/wet mix and the true-peak
+//! limiter. This is the plugin's signal path with every control outside of [`EngineParams`] at its
+//! default, and without the modulation, the MIDI ducking and the spectral mode. The engine is
+//! stereo by default, and [`ColorFallEngine::with_channels()`] creates one for mono or for up to
+//! [`MAX_CHANNELS`] channels.
+//!
+//! The engine only allocates when it's created, so [`ColorFallEngine::process_block()`] and
+//! [`ColorFallEngine::process_channels()`] are safe to call from a real-time audio thread.
+use core::f32::consts::FRAC_PI_2;
+
+use crate::loudness::{self, LoudnessTracker};
+#[cfg(not(feature = "std"))]
+use crate::math::Float;
+use crate::{
+    AntiAliasing, BiquadCoefficients, Character, CrossoverAllpass, CrossoverSlope, DEFAULT_BANDS,
+    DEFAULT_GR_SMOOTHING, EnvelopeCurve, GrSmoother, IntensityAdaptation, LinkwitzRileyCrossover,
+    MAX_BANDS, MAX_CHANNELS, MAX_COMPENSATION_DB, MIN_BANDS, ProcessingBand, Quality, ReleaseModel,
+    SaturationModel, TruePeakLimiter, band_position, band_side, util,
+};
+
+/// The channel count of [`ColorFallEngine::new()`].
+const STEREO: usize = 2;
+/// The engine runs at the plugin's default quality mode.
+const QUALITY: Quality = Quality::Normal;
+/// How long the continuous settings take to glide to a new value, like the plugin's parameter
+/// smoothers.
+const PARAM_SMOOTHING_MS: f32 = 50.0;
+/// How long the loudness compensation takes to follow the measured loudness difference.
+const LOUDNESS_CORRECTION_SMOOTHING_MS: f32 = 200.0;
+
+/// The settings for a [`ColorFallEngine`]. The defaults match the plugin's defaults.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EngineParams {
+    /// The main macro control, from 0 to 1.
+    pub amount: f32,
+    /// Moves the crossovers and the processing focus towards the lows or the highs, from -1 to 1.
+    pub tilt: f32,
+    /// The constant-power dry/wet mix, from 0 to 1.
+    pub mix: f32,
+    /// The output gain in decibels, applied before the limiter.
+    pub output_db: f32,
+    /// The limiter's true-peak ceiling in decibels.
+    pub ceiling_db: f32,
+    /// Matches the wet signal's loudness to the dry signal's.
+    pub auto_gain: bool,
+    /// The number of bands, from [`MIN_BANDS`] to [`MAX_BANDS`]. Changing this resets the filters.
+    pub num_bands: usize,
+    pub character: Character,
+    pub saturation: SaturationModel,
+    pub crossover_slope: CrossoverSlope,
+}
+
+impl Default for EngineParams {
+    fn default() -> Self {
+        Self {
+            amount: 0.4,
+            tilt: 0.0,
+            mix: 1.0,
+            output_db: 0.0,
+            ceiling_db: 0.0,
+            auto_gain: true,
+            num_bands: DEFAULT_BANDS,
+            character: Character::Smooth,
+            saturation: SaturationModel::Cubic,
+            crossover_slope: CrossoverSlope::Slope24,
+        }
+    }
+}
+
+/// A one-pole smoother that gets within 0.01% of its target in the smoothing time, which is how
+/// nih-plug's exponential smoothers behave.
+#[derive(Clone, Copy)]
+struct Smoother {
+    current: f32,
+    target: f32,
+    coefficient: f32,
+}
+
+impl Smoother {
+    fn new(time_ms: f32, sample_rate: f32, value: f32) -> Self {
+        let steps = (time_ms * sample_rate / 1000.0).max(1.0);
+        Self {
+            current: value,
+            target: value,
+            coefficient: 0.0001f32.powf(1.0 / steps),
+        }
+    }
+
+    fn set_target(&mut self, target: f32) {
+        self.target = target;
+    }
+
+    /// Jumps straight to `value`.
+    fn reset(&mut self, value: f32) {
+        self.current = value;
+        self.target = value;
+    }
+
+    fn next(&mut self) -> f32 {
+        self.current = self.target + self.coefficient * (self.current - self.target);
+        self.current
+    }
+}
+
+/// ColorFall's processing chain for a stereo or multichannel signal, without any plugin framework.
+/// See the [module documentation][self] for what it includes.
+pub struct ColorFallEngine {
+    sample_rate: f32,
+    num_channels: usize,
+    params: EngineParams,
+    /// The band count the filter states were last used with.
+    active_bands: usize,
+
+    amount: Smoother,
+    tilt: Smoother,
+    mix: Smoother,
+    output_gain: Smoother,
+    ceiling: Smoother,
+
+    crossovers: [LinkwitzRileyCrossover; MAX_BANDS - 1],
+    /// The allpass response of the whole crossover network, for the dry signal.
+    dry_phase_compensation: [CrossoverAllpass; MAX_BANDS - 1],
+    bands: [ProcessingBand; MAX_BANDS],
+    /// The gain reduction smoothers for every band and channel.
+    gr_smoothers: [[GrSmoother; MAX_CHANNELS]; MAX_BANDS],
+    band_center_freqs: [f32; MAX_BANDS],
+    /// The number of samples until the crossover and reactive EQ coefficients are recalculated.
+    coefficient_update_countdown: usize,
+
+    dry_loudness: LoudnessTracker,
+    wet_loudness: LoudnessTracker,
+    loudness_correction: Smoother,
+    limiter: TruePeakLimiter,
+}
+
+impl ColorFallEngine {
+    /// Creates a stereo engine for audio at `sample_rate`, with the default settings.
+    pub fn new(sample_rate: f32) -> Self {
+        Self::with_channels(sample_rate, STEREO)
+    }
+
+    /// Creates an engine for `num_channels` channels of audio at `sample_rate`, with the default
+    /// settings. The channel count is clamped between 1 and [`MAX_CHANNELS`]. Every channel gets
+    /// its own detectors, and the bands' gain reduction is applied per channel.
+    pub fn with_channels(sample_rate: f32, num_channels: usize) -> Self {
+        let params = EngineParams::default();
+        let param_smoother = |value| Smoother::new(PARAM_SMOOTHING_MS, sample_rate, value);
+        let mut engine = Self {
+            sample_rate,
+            num_channels: num_channels.clamp(1, MAX_CHANNELS),
+            params,
+            active_bands: params.num_bands,
+            amount: param_smoother(params.amount),
+            tilt: param_smoother(params.tilt),
+            mix: param_smoother(params.mix),
+            output_gain: param_smoother(util::db_to_gain(params.output_db)),
+            ceiling: param_smoother(util::db_to_gain(params.ceiling_db)),
+            crossovers: Default::default(),
+            dry_phase_compensation: Default::default(),
+            bands: Default::default(),
+            gr_smoothers: [[GrSmoother::default(); MAX_CHANNELS]; MAX_BANDS],
+            band_center_freqs: [1000.0; MAX_BANDS],
+            coefficient_update_countdown: 0,
+            dry_loudness: LoudnessTracker::default(),
+            wet_loudness: LoudnessTracker::default(),
+            loudness_correction: Smoother::new(LOUDNESS_CORRECTION_SMOOTHING_MS, sample_rate, 1.0),
+            limiter: TruePeakLimiter::default(),
+        };
+        for band in &mut engine.bands {
+            band.initialize(sample_rate);
+        }
+        engine.dry_loudness.set_sample_rate(sample_rate);
+        engine.wet_loudness.set_sample_rate(sample_rate);
+        engine.limiter.initialize(sample_rate);
+        engine.reset();
+
+        engine
+    }
+
+    /// The number of channels the engine processes.
+    pub fn num_channels(&self) -> usize {
+        self.num_channels
+    }
+
+    /// The current settings.
+    pub fn params(&self) -> &EngineParams {
+        &self.params
+    }
+
+    /// Changes the settings. The continuous settings glide to their new values, so this can be
+    /// called for every block.
+    pub fn set_params(&mut self, params: EngineParams) {
+        self.params = params;
+        self.amount.set_target(params.amount.clamp(0.0, 1.0));
+        self.tilt.set_target(params.tilt.clamp(-1.0, 1.0));
+        self.mix.set_target(params.mix.clamp(0.0, 1.0));
+        self.output_gain
+            .set_target(util::db_to_gain(params.output_db));
+        self.ceiling.set_target(util::db_to_gain(params.ceiling_db));
+    }
+
+    /// The delay the limiter's lookahead adds, in samples.
+    pub fn latency_samples(&self) -> u32 {
+        self.limiter.latency_samples()
+    }
+
+    /// Clears all filter and envelope states and moves the settings straight to their targets, for
+    /// when playback restarts.
+    pub fn reset(&mut self) {
+        self.flush_audio_state();
+        for (smoother, value) in [
+            (&mut self.amount, self.params.amount.clamp(0.0, 1.0)),
+            (&mut self.tilt, self.params.tilt.clamp(-1.0, 1.0)),
+            (&mut self.mix, self.params.mix.clamp(0.0, 1.0)),
+            (
+                &mut self.output_gain,
+                util::db_to_gain(self.params.output_db),
+            ),
+            (&mut self.ceiling, util::db_to_gain(self.params.ceiling_db)),
+        ] {
+            smoother.reset(value);
+        }
+
+        // The crossovers start out at their current frequencies instead of gliding there
+        self.active_bands = self.num_bands();
+        self.update_filters(self.active_bands, self.tilt.current, 0);
+        self.coefficient_update_countdown = QUALITY.coefficient_update_interval(self.sample_rate);
+    }
+
+    /// Processes a block of stereo audio in place. Both channels need to have the same length, and
+    /// the engine needs to be a stereo engine.
+    pub fn process_block(&mut self, left: &mut [f32], right: &mut [f32]) {
+        self.process_channels(&mut [left, right]);
+    }
+
+    /// Processes a block of audio in place, with one slice per channel. There need to be exactly
+    /// [`num_channels()`][Self::num_channels()] slices, all with the same length.
+    pub fn process_channels(&mut self, channels: &mut [&mut [f32]]) {
+        let num_channels = self.num_channels;
+        assert_eq!(channels.len(), num_channels);
+        let num_samples = channels[0].len();
+        assert!(channels.iter().all(|samples| samples.len() == num_samples));
+
+        // Switching the band count rearranges every crossover, so we start from a clean filter
+        // state when it changes
+        let num_bands = self.num_bands();
+        if num_bands != self.active_bands {
+            self.active_bands = num_bands;
+            self.reset_filters();
+        }
+        let coefficient_update_interval = QUALITY.coefficient_update_interval(self.sample_rate);
+        let EngineParams {
+            character,
+            saturation,
+            ..
+        } = self.params;
+
+        // The loudness correction follows the measurement, and is picked up again whenever a
+        // measurement segment completes during the block, like in the plugin
+        let auto_gain = self.params.auto_gain;
+        self.loudness_correction.set_target(if auto_gain {
+            loudness::correction_gain(
+                self.dry_loudness.mean_square(),
+                self.wet_loudness.mean_square(),
+                1.0,
+            )
+        } else {
+            1.0
+        });
+
+        for sample_idx in 0..num_samples {
+            // NaN and infinite samples are replaced with silence before they reach the filters
+            let mut input = [0.0; MAX_CHANNELS];
+            for (input, samples) in input.iter_mut().zip(channels.iter()) {
+                *input = crate::sanitize(samples[sample_idx]);
+            }
+
+            let amount = self.amount.next();
+            let tilt = self.tilt.next();
+            let mix = self.mix.next();
+            let output_gain = self.output_gain.next();
+            let ceiling = self.ceiling.next();
+            let loudness_correction = self.loudness_correction.next();
+
+            let update_coefficients = self.coefficient_update_countdown == 0;
+            if update_coefficients {
+                self.coefficient_update_countdown = coefficient_update_interval;
+                self.update_filters(num_bands, tilt, coefficient_update_interval as u32);
+            }
+            self.coefficient_update_countdown -= 1;
+
+            // The dry signal gets the allpass response of the crossovers, so the mix stays
+            // phase-coherent
+            let mut dry = input;
+            for allpass in &mut self.dry_phase_compensation[..(num_bands - 1)] {
+                allpass.process(&mut dry, num_channels);
+            }
+            self.dry_loudness.process(&dry, num_channels);
+
+            // Split the input into the bands, starting from the highest crossover
+            let mut band_signals = [[0.0; MAX_CHANNELS]; MAX_BANDS];
+            let mut last_lp = input;
+            for i in (0..(num_bands - 1)).rev() {
+                let (lp, hp) = self.crossovers[i].process(&last_lp, num_channels);
+                band_signals[i + 1] = hp;
+                last_lp = lp;
+            }
+            band_signals[0] = last_lp;
+
+            // Saturate and compress every band, then sum them back together
+            let mut wet = [0.0; MAX_CHANNELS];
+            let mut gr_factors = [1.0; MAX_BANDS];
+            for i in 0..num_bands {
+                let band = &mut self.bands[i];
+                let band_signal = &mut band_signals[i];
+                band.compensate_phase(i, band_signal, num_channels);
+                for (channel, sample) in band_signal.iter_mut().enumerate().take(num_channels) {
+                    let saturated = crate::saturate(
+                        *sample,
+                        amount,
+                        1.0,
+                        saturation,
+                        AntiAliasing::Off,
+                        &mut band.adaa_states[channel],
+                        &mut band.hysteresis_states[channel],
+                    );
+                    let rolled_off = band.tape_rolloff.process(channel, saturated);
+                    let saturated = if saturation == SaturationModel::TapeHysteresis {
+                        rolled_off
+                    } else {
+                        saturated
+                    };
+                    *sample = band.dc_blocker.process(channel, saturated);
+                }
+
+                let (attack, release) = crate::calculate_dynamic_time_constants(
+                    self.sample_rate,
+                    self.band_center_freqs[i],
+                    i,
+                    amount,
+                );
+                let attack_alpha = 1.0 - (-1.0 / crate::to_real(attack)).exp();
+                let release_alpha = 1.0 - (-1.0 / crate::to_real(release)).exp();
+                let gr_alphas = GrSmoother::alphas(attack, release, DEFAULT_GR_SMOOTHING);
+
+                let mut gr_sum = 0.0;
+                for channel in 0..num_channels {
+                    let sample = band_signal[channel];
+                    let detector_value = crate::to_real(sample * sample);
+                    let envelope = &mut band.envelopes[channel];
+                    let (target, alpha) = if detector_value > *envelope {
+                        (detector_value, attack_alpha)
+                    } else {
+                        ReleaseModel::Vca.release_step(
+                            *envelope,
+                            0.0,
+                            detector_value,
+                            release_alpha,
+                        )
+                    };
+                    crate::follow_envelope(
+                        envelope,
+                        &mut band.envelope_stages[channel],
+                        target,
+                        alpha,
+                        EnvelopeCurve::Exponential,
+                    );
+                    let envelope_level = crate::from_real(envelope.sqrt());
+
+                    let target_gr = crate::calculate_target_gr(
+                        i,
+                        num_bands,
+                        amount,
+                        tilt,
+                        0.0,
+                        envelope_level,
+                        character,
+                        IntensityAdaptation::default(),
+                    );
+                    let gr = self.gr_smoothers[i][channel].next(target_gr, gr_alphas);
+                    band_signal[channel] *= gr;
+                    wet[channel] += band_signal[channel];
+                    gr_sum += gr;
+                }
+                gr_factors[i] = gr_sum / num_channels as f32;
+            }
+
+            // The reactive EQ boosts every band by the gain reduction its compressor applies
+            for (i, &gr_factor) in gr_factors.iter().enumerate().take(num_bands) {
+                if update_coefficients {
+                    let tilt_effect = tilt.abs().powf(1.5) * tilt.signum();
+                    let band_tilt_factor =
+                        (1.0 + tilt_effect * 0.6 * band_side(i, num_bands)).clamp(0.4, 1.6);
+                    let q_base = 0.7 + (8.0 * amount.powf(2.0));
+                    let q_tilt_factor = 1.0 + (tilt * band_position(i, num_bands) * 2.0 * 0.4);
+                    let q_factor = (q_base * q_tilt_factor).clamp(0.5, 20.0f32);
+                    let compensation_gain_db = (util::gain_to_db(gr_factor).abs() / 24.0)
+                        * MAX_COMPENSATION_DB
+                        * (amount * band_tilt_factor)
+                        * character.eq_compensation_depth();
+
+                    self.bands[i].compensation_eq.ramp_to(
+                        BiquadCoefficients::calculate_peaking(
+                            self.sample_rate,
+                            self.band_center_freqs[i],
+                            q_factor,
+                            compensation_gain_db,
+                        ),
+                        coefficient_update_interval as u32,
+                    );
+                }
+
+                self.bands[i]
+                    .compensation_eq
+                    .process(&mut wet, num_channels);
+            }
+
+            // The wet loudness is measured before the correction, so the correction doesn't
+            // measure its own output
+            self.wet_loudness.process(&wet, num_channels);
+            if auto_gain && self.wet_loudness.segment_completed() {
+                self.loudness_correction
+                    .set_target(loudness::correction_gain(
+                        self.dry_loudness.mean_square(),
+                        self.wet_loudness.mean_square(),
+                        1.0,
+                    ));
+            }
+
+            let mix_phase = mix * FRAC_PI_2;
+            let (dry_gain, wet_gain) = (mix_phase.cos(), mix_phase.sin());
+            let mut output = [0.0; MAX_CHANNELS];
+            for (channel, sample) in output.iter_mut().enumerate().take(num_channels) {
+                let wet_sample = wet[channel] * loudness_correction;
+                *sample = ((dry[channel] * dry_gain) + (wet_sample * wet_gain)) * output_gain;
+            }
+            self.limiter.process(
+                &mut output,
+                num_channels,
+                ceiling,
+                QUALITY.true_peak_detection(),
+            );
+            // If a filter or an envelope blew up anyway, everything is flushed so the engine
+            // recovers instead of staying stuck on NaN
+            if !crate::is_finite_frame(&output, num_channels) {
+                self.flush_audio_state();
+                output = [0.0; MAX_CHANNELS];
+            }
+
+            for (samples, output) in channels.iter_mut().zip(output) {
+                samples[sample_idx] = output;
+            }
+        }
+    }
+
+    /// The active band count.
+    fn num_bands(&self) -> usize {
+        self.params.num_bands.clamp(MIN_BANDS, MAX_BANDS)
+    }
+
+    /// Moves the crossovers, their allpasses and the band center frequencies to the given tilt
+    /// over `ramp_steps` samples. See `ColorFall::update_crossover_filters()` in the plugin.
+    fn update_filters(&mut self, num_bands: usize, tilt: f32, ramp_steps: u32) {
+        let crossover_freqs =
+            crate::crossover_frequencies(self.sample_rate, num_bands, tilt, |_| 0.0);
+        for (i, center_freq) in self.band_center_freqs[..num_bands].iter_mut().enumerate() {
+            *center_freq =
+                crate::band_center_frequency(self.sample_rate, &crossover_freqs, num_bands, i);
+        }
+
+        let slope = self.params.crossover_slope;
+        for (j, &freq) in crossover_freqs[..(num_bands - 1)].iter().enumerate() {
+            self.crossovers[j].update(self.sample_rate, freq, slope, ramp_steps);
+            self.dry_phase_compensation[j].update(self.sample_rate, freq, slope, ramp_steps);
+            for band in &mut self.bands[(j + 2)..num_bands] {
+                band.phase_compensation[j].update(self.sample_rate, freq, slope, ramp_steps);
+            }
+        }
+    }
+
+    /// Clears the filter and envelope states, the loudness measurements and the limiter, without
+    /// touching the settings.
+    fn flush_audio_state(&mut self) {
+        self.reset_filters();
+        self.loudness_correction.reset(1.0);
+        self.dry_loudness.reset();
+        self.wet_loudness.reset();
+        self.limiter.reset();
+    }
+
+    /// Clears the filter and envelope states.
+    fn reset_filters(&mut self) {
+        for crossover in &mut self.crossovers {
+            crossover.reset();
+        }
+        for allpass in &mut self.dry_phase_compensation {
+            allpass.reset();
+        }
+        for band in &mut self.bands {
+            band.reset();
+        }
+        for smoothers in &mut self.gr_smoothers {
+            for smoother in smoothers {
+                smoother.reset();
+            }
+        }
+    }
+}
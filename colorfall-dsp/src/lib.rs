@@ -0,0 +1,2725 @@
+//! # ColorFall DSP
+//!
+//! The digital signal processing behind the ColorFall plugin, without any plugin framework
+//! attached, so it can be reused in other plugins, tests and offline tools. It includes the biquad
+//! filter implementation, multiband crossover, dynamic parameter calculations, the saturation
+//! algorithm and its oversampler, the true-peak output limiter, the loudness measurement in
+//! [`loudness`], and the spectral balance measurement in [`balance`].
+//!
+//! Parameter smoothing is left to the host code. The gain computers return target gains, which the
+//! plugin smooths with its own smoothers before applying them. [`ColorFallEngine`] puts the
+//! building blocks together into a ready to use stereo processor, smoothing included.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(not(any(feature = "std", feature = "libm")))]
+compile_error!("colorfall-dsp needs either the `std` or the `libm` feature for its math functions");
+
+extern crate alloc;
+
+#[macro_use]
+mod choice;
+pub mod analysis;
+pub mod balance;
+pub mod engine;
+pub mod loudness;
+#[cfg(not(feature = "std"))]
+mod math;
+pub mod util;
+
+pub use choice::Choice;
+pub use engine::{ColorFallEngine, EngineParams};
+
+use crate::loudness::{SILENCE_MEAN_SQUARE, WeightingFilter};
+#[cfg(not(feature = "std"))]
+use crate::math::Float;
+use alloc::vec;
+use alloc::vec::Vec;
+
+// --- CORE DSP CONSTANTS ---
+pub const TILT_MAX_SHIFT_SEMITONES: f32 = 4.0;
+pub const MIN_BANDS: usize = 3;
+pub const MAX_BANDS: usize = 6;
+pub const DEFAULT_BANDS: usize = 5;
+/// The maximum number of main channels the engine can process, enough for 7.1 surround.
+pub const MAX_CHANNELS: usize = 8;
+/// The length of the sliding window used by the RMS detector.
+pub const RMS_WINDOW_MS: f32 = 10.0;
+/// The cutoff of the DC blockers that follow the saturators.
+pub const DC_BLOCKER_HZ: f32 = 5.0;
+/// The cutoff of the high frequency loss after the tape hysteresis saturator.
+pub const TAPE_ROLLOFF_HZ: f32 = 12000.0;
+pub const MAX_COMPENSATION_DB: f32 = 6.0; // Max Q-Boost gain, and the default EQ Depth
+pub const KNEE_MAX_DB: f32 = 8.0; // Max knee width at Amount = 1.0
+pub const UPWARD_MAX_GAIN_DB: f32 = 18.0; // Max upward compression boost
+pub const UPWARD_FLOOR_DB: f32 = -80.0; // Signals below this are never brought up
+pub const GR_RANGE_MAX_DB: f32 = 40.0; // The Range setting that leaves the gain reduction uncapped
+pub const EXPANDER_RANGE_DB: f32 = 60.0; // Max downward expansion, where the expander acts as a gate
+pub const TRANSIENT_ATTACK_MS: f32 = 0.5; // Attack time of the transient shaper's fast envelope
+pub const TRANSIENT_MAX_GAIN_DB: f32 = 12.0; // Max transient shaper boost or cut
+/// The center and Q of a de-essing band's detection band-pass, which spans about 5 to 9 kHz.
+pub const DE_ESS_CENTER_HZ: f32 = 6700.0;
+pub const DE_ESS_Q: f32 = 1.7;
+/// How much faster a de-essing band attacks and releases than its program-derived times.
+pub const DE_ESS_ATTACK_SCALE: f32 = 0.25;
+pub const DE_ESS_RELEASE_SCALE: f32 = 0.5;
+/// How much a de-essing band's detector level is raised before the gain computer, which lowers
+/// its threshold by as much for deeper gain reduction.
+pub const DE_ESS_SENSITIVITY_DB: f32 = 12.0;
+/// How much Adaptive Timing shortens the attack and lengthens the release around a hit at 100%.
+pub const ADAPTIVE_ATTACK_SCALE: f32 = 0.25;
+pub const ADAPTIVE_RELEASE_SCALE: f32 = 2.0;
+/// The default GR Smoothing, which smooths the gain reduction over a tenth of the band's attack
+/// and release times. At the default Amount that's a time constant of 1 to 2 ms while the gain
+/// reduction deepens, and 6 to 50 ms while it recovers, depending on the band.
+pub const DEFAULT_GR_SMOOTHING: f32 = 0.1;
+/// The attack and release times of the [`TransientDetector`]'s fast and slow envelopes. Both are
+/// peak followers, so a steady tone keeps them together even in the lowest band.
+const HIT_FAST_ATTACK_MS: f32 = 1.0;
+const HIT_FAST_RELEASE_MS: f32 = 20.0;
+const HIT_SLOW_ATTACK_MS: f32 = 50.0;
+const HIT_SLOW_RELEASE_MS: f32 = 250.0;
+/// The time constant the [`TransientDetector`] falls back to 0 with after a hit.
+const HIT_HOLD_MS: f32 = 60.0;
+pub const LIMITER_LOOKAHEAD_MS: f32 = 1.5;
+pub const LIMITER_RELEASE_MS: f32 = 50.0;
+/// The band center frequency the compressors' base attack and release times are tuned for. Bands
+/// above it get faster times and bands below it get slower ones.
+const TIME_CONSTANT_REFERENCE_HZ: f32 = 2000.0;
+/// The length of a crest factor measurement segment. The Auto Intensity window is made up of these.
+const CREST_SEGMENT_MS: f32 = 100.0;
+/// The number of segments in the 3 second Auto Intensity window.
+const CREST_SEGMENTS: usize = 30;
+/// The program level the thresholds are tuned for. Auto Intensity moves them along with the
+/// input's RMS level relative to this.
+const AUTO_REFERENCE_RMS_DB: f32 = -18.0;
+/// The crest factor the ratios are tuned for. Auto Intensity raises the ratios for denser material
+/// and lowers them for more dynamic material.
+const AUTO_REFERENCE_CREST_DB: f32 = 12.0;
+/// How far Auto Intensity can move the thresholds.
+const AUTO_MAX_THRESHOLD_OFFSET_DB: f32 = 18.0;
+/// The lowest and highest ratio scale Auto Intensity can apply.
+const AUTO_RATIO_SCALE_RANGE: (f32, f32) = (0.5, 2.0);
+/// The noise layer's level relative to its band with Noise and Amount all the way up.
+pub const NOISE_MAX_DB: f32 = -30.0;
+/// The average number of crackle clicks per second and channel.
+pub const CRACKLE_RATE_HZ: f32 = 30.0;
+/// The sample rate the sample based intervals are specified at. They're scaled to the actual
+/// sample rate so the plugin behaves the same at any sample rate.
+const REFERENCE_SAMPLE_RATE: f32 = 48000.0;
+/// The number of taps of the oversampler's half-band filter at the oversampled rate.
+const HALFBAND_TAPS: usize = 63;
+/// The number of non-zero taps of the half-band filter, not counting the center tap.
+const HALFBAND_SIDE_TAPS: usize = HALFBAND_TAPS.div_ceil(4) * 2;
+/// The delay of a round trip through the [`Oversampler`], in samples at the original rate.
+pub const OVERSAMPLING_LATENCY: usize = (HALFBAND_TAPS - 1) / 2;
+
+// --- PROCESSING PRECISION ---
+/// The floating point type the biquads and the envelope followers run at. Building with the `f64`
+/// feature switches this to double precision, since the state of high-Q filters at low
+/// frequencies quantizes audibly in single precision.
+#[cfg(not(feature = "f64"))]
+pub type Real = f32;
+#[cfg(feature = "f64")]
+pub type Real = f64;
+
+#[cfg(not(feature = "f64"))]
+type RealX4 = wide::f32x4;
+#[cfg(feature = "f64")]
+type RealX4 = wide::f64x4;
+
+#[cfg(not(feature = "f64"))]
+use core::f32::consts as real_consts;
+#[cfg(feature = "f64")]
+use core::f64::consts as real_consts;
+
+/// Converts a sample or parameter value to the processing precision.
+#[cfg(not(feature = "f64"))]
+#[inline]
+pub fn to_real(value: f32) -> Real {
+    value
+}
+
+/// Converts a sample or parameter value to the processing precision.
+#[cfg(feature = "f64")]
+#[inline]
+pub fn to_real(value: f32) -> Real {
+    f64::from(value)
+}
+
+/// Converts a value at the processing precision back to a sample or parameter value.
+#[cfg(not(feature = "f64"))]
+#[inline]
+pub fn from_real(value: Real) -> f32 {
+    value
+}
+
+/// Converts a value at the processing precision back to a sample or parameter value.
+#[cfg(feature = "f64")]
+#[inline]
+pub fn from_real(value: Real) -> f32 {
+    value as f32
+}
+
+// --- NON-FINITE PROTECTION ---
+/// Replaces a NaN or infinite sample with silence. A single non-finite sample from the host would
+/// otherwise get stuck in every filter and envelope it passes through.
+#[inline]
+pub fn sanitize(sample: f32) -> f32 {
+    if sample.is_finite() { sample } else { 0.0 }
+}
+
+/// [`sanitize()`]s the first `num_channels` samples of a frame.
+#[inline]
+pub fn sanitize_frame(frame: &mut [f32; MAX_CHANNELS], num_channels: usize) {
+    for sample in &mut frame[..num_channels] {
+        *sample = sanitize(*sample);
+    }
+}
+
+/// Whether the first `num_channels` samples of a frame are finite. The processing checks its
+/// outputs with this, since a filter or envelope that blew up keeps producing non-finite values
+/// even after the input is sanitized, and the only way out is flushing its state.
+#[inline]
+pub fn is_finite_frame(frame: &[f32; MAX_CHANNELS], num_channels: usize) -> bool {
+    frame[..num_channels]
+        .iter()
+        .all(|sample| sample.is_finite())
+}
+
+/// The true-peak detector's oversampling factor.
+const TRUE_PEAK_OVERSAMPLING: usize = 4;
+/// The number of taps per phase of the true-peak detector's interpolation filter.
+const TRUE_PEAK_TAPS: usize = 12;
+
+/// State for a single processing band.
+#[derive(Clone)]
+pub struct ProcessingBand {
+    /// The serial compensation EQ filter for this band's frequency region.
+    pub compensation_eq: RampedBiquad,
+
+    /// Allpass filters that give this band the phase shift of the lower crossovers it never passed
+    /// through, so all bands sum back to a single allpass response. See
+    /// [`ProcessingBand::compensate_phase()`].
+    pub phase_compensation: [CrossoverAllpass; MAX_BANDS - 2],
+
+    /// The saturator's previous input samples, one per channel.
+    pub adaa_states: [AdaaState; MAX_CHANNELS],
+    /// The tape hysteresis model's memory, one per channel.
+    pub hysteresis_states: [HysteresisState; MAX_CHANNELS],
+    /// The tape hysteresis model's high frequency loss.
+    pub tape_rolloff: TapeRolloff,
+    /// Removes the DC offset the asymmetric saturation models add before it reaches the detectors.
+    pub dc_blocker: DcBlocker,
+    /// The band-filtered hiss or crackle of the noise layer.
+    pub noise: NoiseLayer,
+    /// The previous unprocessed sample for every channel, used to line the band's dry signal up
+    /// with the saturated signal for the band's Mix.
+    pub dry_history: [f32; MAX_CHANNELS],
+    /// Runs the saturator at twice the sample rate in the High quality mode.
+    pub oversampler: Oversampler,
+    /// Delays the band's dry signal by the latency of the band processing, so the band's Mix
+    /// stays aligned. Its delay is set by the plugin's latency bookkeeping.
+    pub dry_compensation_delay: DelayLine,
+    /// The band's output after the gain reduction on the previous sample, which the detectors
+    /// listen to with the feedback topology. This is always updated so the topology can be
+    /// switched without a jump.
+    pub feedback_frame: [f32; MAX_CHANNELS],
+    /// The detector sidechain's high-pass filter. This only filters the signal the envelope
+    /// detector sees, the band's audio is left alone.
+    pub detector_hpf: Biquad,
+    /// The detector sidechain's perceptual weighting, after the high-pass filter.
+    pub detector_weighting: WeightingFilter,
+    /// Narrows the detector sidechain down to the sibilance range while the band is de-essing.
+    pub de_ess_filter: Biquad,
+
+    // Envelope and GR states, one per channel
+    pub rms_windows: [RmsWindow; MAX_CHANNELS],
+    pub envelopes: [Real; MAX_CHANNELS],
+    /// The S-curve envelope shape's intermediate stage. See [`follow_envelope()`].
+    pub envelope_stages: [Real; MAX_CHANNELS],
+    /// The envelope at the start of the current release, for the opto release model. See
+    /// [`ReleaseModel::release_step()`].
+    pub release_peaks: [Real; MAX_CHANNELS],
+    /// The transient shaper's fast and slow envelopes. See [`calculate_transient_gain()`].
+    pub transient_envelopes: [(Real, Real); MAX_CHANNELS],
+    /// The envelope followers' attack, release, and transient release coefficients, followed by
+    /// the attack and release coefficients Adaptive Timing moves towards around a hit. The Eco
+    /// quality mode only recalculates these along with the filter coefficients.
+    pub envelope_alphas: [Real; 5],
+    /// Spots the hits Adaptive Timing reacts to. This is shared by all channels.
+    pub transient_detector: TransientDetector,
+    /// The MIDI-triggered ducking envelope. This is shared by all channels.
+    pub duck_envelope: TriggerEnvelope,
+}
+
+impl Default for ProcessingBand {
+    fn default() -> Self {
+        Self {
+            compensation_eq: RampedBiquad::default(),
+            phase_compensation: Default::default(),
+            adaa_states: [AdaaState::default(); MAX_CHANNELS],
+            hysteresis_states: [HysteresisState::default(); MAX_CHANNELS],
+            tape_rolloff: TapeRolloff::default(),
+            dc_blocker: DcBlocker::default(),
+            noise: NoiseLayer::default(),
+            dry_history: [0.0; MAX_CHANNELS],
+            oversampler: Oversampler::default(),
+            dry_compensation_delay: DelayLine::default(),
+            feedback_frame: [0.0; MAX_CHANNELS],
+            detector_hpf: Biquad::default(),
+            detector_weighting: WeightingFilter::default(),
+            de_ess_filter: Biquad::default(),
+            rms_windows: Default::default(),
+            envelopes: [0.0; MAX_CHANNELS],
+            envelope_stages: [0.0; MAX_CHANNELS],
+            release_peaks: [0.0; MAX_CHANNELS],
+            transient_envelopes: [(0.0, 0.0); MAX_CHANNELS],
+            envelope_alphas: [1.0; 5],
+            transient_detector: TransientDetector::default(),
+            duck_envelope: TriggerEnvelope::default(),
+        }
+    }
+}
+
+impl ProcessingBand {
+    /// Sizes the band's sample-rate dependent buffers. This allocates, so it should only be called
+    /// from `initialize()`.
+    pub fn initialize(&mut self, sample_rate: f32) {
+        self.dc_blocker.set_sample_rate(sample_rate);
+        self.tape_rolloff.set_sample_rate(sample_rate);
+        self.noise.set_sample_rate(sample_rate);
+        self.transient_detector.set_sample_rate(sample_rate);
+        self.de_ess_filter
+            .set_coefficients(BiquadCoefficients::calculate_bandpass(
+                sample_rate,
+                DE_ESS_CENTER_HZ,
+                DE_ESS_Q,
+            ));
+        self.dry_compensation_delay.set_length(OVERSAMPLING_LATENCY);
+
+        let rms_window_length = (sample_rate * RMS_WINDOW_MS / 1000.0).round() as usize;
+        for window in &mut self.rms_windows {
+            window.set_length(rms_window_length);
+        }
+    }
+
+    /// Resets the state of the processing band.
+    pub fn reset(&mut self) {
+        self.compensation_eq.reset();
+        for allpass in &mut self.phase_compensation {
+            allpass.reset();
+        }
+        self.adaa_states = [AdaaState::default(); MAX_CHANNELS];
+        self.hysteresis_states = [HysteresisState::default(); MAX_CHANNELS];
+        self.tape_rolloff.reset();
+        self.dc_blocker.reset();
+        self.noise.reset();
+        self.dry_history = [0.0; MAX_CHANNELS];
+        self.oversampler.reset();
+        self.dry_compensation_delay.reset();
+        self.feedback_frame = [0.0; MAX_CHANNELS];
+        self.detector_hpf.reset();
+        self.detector_weighting.reset();
+        self.de_ess_filter.reset();
+        for window in &mut self.rms_windows {
+            window.reset();
+        }
+        self.envelopes = [0.0; MAX_CHANNELS];
+        self.envelope_stages = [0.0; MAX_CHANNELS];
+        self.release_peaks = [0.0; MAX_CHANNELS];
+        self.transient_envelopes = [(0.0, 0.0); MAX_CHANNELS];
+        self.transient_detector.reset();
+        self.duck_envelope.reset();
+    }
+
+    /// Runs the band's split signal through the phase compensation allpasses. The band split runs
+    /// from the highest crossover down, so band `i` never passes through crossovers `0..i - 1` and
+    /// needs their allpass response added to line up with the lower bands.
+    pub fn compensate_phase(
+        &mut self,
+        band_idx: usize,
+        frame: &mut [f32; MAX_CHANNELS],
+        num_channels: usize,
+    ) {
+        let num_sections = band_idx.saturating_sub(1);
+        for allpass in &mut self.phase_compensation[..num_sections] {
+            allpass.process(frame, num_channels);
+        }
+    }
+}
+
+choice! {
+    /// A personality for the derived parameters. Every character swaps the tables that map Amount
+    /// and Tilt to the band compressors and the reactive EQ.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Character {
+        /// The original ColorFall mapping, leaning on the low end with a smooth ratio curve.
+        Smooth => "Smooth",
+        /// An evenly spread compressor that reaches its ratio early, with a lighter EQ bloom.
+        Punchy => "Punchy",
+        /// Heavy ratios and a deep reactive EQ for squashed, resonant sounds.
+        Crushed => "Crushed",
+        /// Leans on the upper bands, so the reactive EQ opens up the top end.
+        Airy => "Airy",
+    }
+}
+
+/// The mapping tables behind a [`Character`].
+struct CharacterTables {
+    /// The inherent compression bias from the lowest to the highest band. Band counts other than
+    /// five interpolate along this curve.
+    freq_factor_curve: [f32; 5],
+    /// How strongly Tilt raises or lowers the intensity of the bands on either side.
+    tilt_bias_depth: f32,
+    /// The compression ratio at full Amount, on top of the minimum 1.1:1 ratio.
+    ratio_range: f32,
+    /// The exponent of the ratio's Amount curve. Lower values reach high ratios sooner.
+    ratio_curve: f32,
+    /// Scales the reactive EQ's compensation gain.
+    eq_compensation_depth: f32,
+}
+
+impl Character {
+    fn tables(self) -> &'static CharacterTables {
+        match self {
+            // Lower frequencies often have more energy in typical music, so they get compressed
+            // more heavily by default
+            Character::Smooth => &CharacterTables {
+                freq_factor_curve: [1.5, 1.2, 1.0, 0.8, 0.5],
+                tilt_bias_depth: 0.8,
+                ratio_range: 15.0,
+                ratio_curve: 2.5,
+                eq_compensation_depth: 1.0,
+            },
+            Character::Punchy => &CharacterTables {
+                freq_factor_curve: [1.3, 1.2, 1.0, 0.9, 0.7],
+                tilt_bias_depth: 0.6,
+                ratio_range: 8.0,
+                ratio_curve: 1.5,
+                eq_compensation_depth: 0.7,
+            },
+            Character::Crushed => &CharacterTables {
+                freq_factor_curve: [1.6, 1.4, 1.2, 1.1, 0.9],
+                tilt_bias_depth: 0.9,
+                ratio_range: 30.0,
+                ratio_curve: 1.5,
+                eq_compensation_depth: 1.3,
+            },
+            Character::Airy => &CharacterTables {
+                freq_factor_curve: [0.9, 0.9, 1.0, 1.2, 1.4],
+                tilt_bias_depth: 0.8,
+                ratio_range: 6.0,
+                ratio_curve: 2.5,
+                eq_compensation_depth: 1.5,
+            },
+        }
+    }
+
+    /// Scales the reactive EQ's compensation gain for this character.
+    pub fn eq_compensation_depth(self) -> f32 {
+        self.tables().eq_compensation_depth
+    }
+}
+
+/// Where a band sits within the active band layout, from -1.0 (lowest band) to 1.0 (highest band).
+pub fn band_position(band_idx: usize, num_bands: usize) -> f32 {
+    if num_bands <= 1 {
+        return 0.0;
+    }
+    (band_idx as f32 / (num_bands - 1) as f32) * 2.0 - 1.0
+}
+
+/// Which side of the spectrum a band belongs to for the Tilt bias: -1.0 for the lower half, 1.0
+/// for the upper half, and 0.0 for the center band of an odd band count.
+pub fn band_side(band_idx: usize, num_bands: usize) -> f32 {
+    let position = band_position(band_idx, num_bands);
+    if position.abs() < 1.0e-6 {
+        0.0
+    } else {
+        position.signum()
+    }
+}
+
+/// The inherent compression bias for a band, interpolated from the character's frequency factor
+/// curve.
+pub fn band_freq_factor(band_idx: usize, num_bands: usize, character: Character) -> f32 {
+    let curve = &character.tables().freq_factor_curve;
+    let curve_pos = (band_position(band_idx, num_bands) + 1.0) * 0.5 * (curve.len() - 1) as f32;
+    let lower = (curve_pos.floor() as usize).min(curve.len() - 1);
+    let upper = (lower + 1).min(curve.len() - 1);
+    let t = curve_pos - lower as f32;
+    curve[lower] + (curve[upper] - curve[lower]) * t
+}
+
+/// A sliding-window mean square detector for a single channel, used by the RMS detector mode.
+#[derive(Default, Clone)]
+pub struct RmsWindow {
+    buffer: Vec<f32>,
+    pos: usize,
+    /// The running sum of the squared samples in `buffer`. This is kept in double precision so
+    /// the additions and subtractions don't drift over time.
+    sum: f64,
+}
+
+impl RmsWindow {
+    /// Resizes the window to `length` samples. This allocates.
+    pub fn set_length(&mut self, length: usize) {
+        self.buffer = vec![0.0; length.max(1)];
+        self.pos = 0;
+        self.sum = 0.0;
+    }
+
+    /// Pushes a squared sample into the window and returns the window's mean square.
+    pub fn process(&mut self, power: f32) -> f32 {
+        if self.buffer.is_empty() {
+            return power;
+        }
+
+        self.sum += power as f64 - self.buffer[self.pos] as f64;
+        self.buffer[self.pos] = power;
+        self.pos = (self.pos + 1) % self.buffer.len();
+
+        (self.sum.max(0.0) / self.buffer.len() as f64) as f32
+    }
+
+    /// Clears the window.
+    pub fn reset(&mut self) {
+        self.buffer.fill(0.0);
+        self.pos = 0;
+        self.sum = 0.0;
+    }
+}
+
+/// How Auto Intensity adapts the band compressors to the program material. The default leaves them
+/// unchanged.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IntensityAdaptation {
+    /// Moves the downward and upward thresholds.
+    pub threshold_offset_db: f32,
+    /// Scales the part of the downward compression ratio that Amount adds.
+    pub ratio_scale: f32,
+}
+
+impl Default for IntensityAdaptation {
+    fn default() -> Self {
+        Self {
+            threshold_offset_db: 0.0,
+            ratio_scale: 1.0,
+        }
+    }
+}
+
+/// Measures the input's RMS level and peak level over a sliding 3 second window for Auto
+/// Intensity. The window is split into segments like the loudness measurement, so the sliding
+/// peak and mean square don't need a per-sample history.
+pub struct CrestTracker {
+    segment_sum: f32,
+    segment_peak: f32,
+    segment_len: usize,
+    segment_pos: usize,
+
+    /// The mean squares and peaks of the last completed segments.
+    mean_squares: [f32; CREST_SEGMENTS],
+    peaks: [f32; CREST_SEGMENTS],
+    segments_pos: usize,
+}
+
+impl Default for CrestTracker {
+    fn default() -> Self {
+        let mut tracker = Self {
+            segment_sum: 0.0,
+            segment_peak: 0.0,
+            segment_len: 1,
+            segment_pos: 0,
+            mean_squares: [0.0; CREST_SEGMENTS],
+            peaks: [0.0; CREST_SEGMENTS],
+            segments_pos: 0,
+        };
+        tracker.set_sample_rate(44100.0);
+
+        tracker
+    }
+}
+
+impl CrestTracker {
+    /// Updates the segment length for a new sample rate.
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.segment_len = ((sample_rate * CREST_SEGMENT_MS / 1000.0).round() as usize).max(1);
+    }
+
+    /// Clears the measurement window.
+    pub fn reset(&mut self) {
+        self.segment_sum = 0.0;
+        self.segment_peak = 0.0;
+        self.segment_pos = 0;
+        self.mean_squares = [0.0; CREST_SEGMENTS];
+        self.peaks = [0.0; CREST_SEGMENTS];
+        self.segments_pos = 0;
+    }
+
+    /// Adds a frame to the measurement.
+    pub fn process(&mut self, frame: &[f32; MAX_CHANNELS], num_channels: usize) {
+        for &sample in &frame[..num_channels] {
+            self.segment_sum += sample * sample / num_channels as f32;
+            self.segment_peak = self.segment_peak.max(sample.abs());
+        }
+
+        self.segment_pos += 1;
+        if self.segment_pos >= self.segment_len {
+            self.mean_squares[self.segments_pos] = self.segment_sum / self.segment_len as f32;
+            self.peaks[self.segments_pos] = self.segment_peak;
+            self.segments_pos = (self.segments_pos + 1) % CREST_SEGMENTS;
+            self.segment_sum = 0.0;
+            self.segment_peak = 0.0;
+            self.segment_pos = 0;
+        }
+    }
+
+    /// The adaptation for the measured material. The thresholds follow the RMS level and the
+    /// ratios follow the crest factor, so the same Amount gives a similar amount of gain reduction
+    /// on quiet, dynamic material and on dense, loud material. Returns `None` while the input is
+    /// silent, so the caller can hold on to the previous adaptation through pauses.
+    pub fn adaptation(&self) -> Option<IntensityAdaptation> {
+        let mean_square = self.mean_squares.iter().sum::<f32>() / CREST_SEGMENTS as f32;
+        if mean_square < SILENCE_MEAN_SQUARE {
+            return None;
+        }
+
+        let peak = self
+            .peaks
+            .iter()
+            .fold(0.0f32, |peak, &segment| peak.max(segment));
+        let rms_db = 10.0 * mean_square.log10();
+        let crest_db = util::gain_to_db(peak) - rms_db;
+        let (min_ratio_scale, max_ratio_scale) = AUTO_RATIO_SCALE_RANGE;
+
+        Some(IntensityAdaptation {
+            threshold_offset_db: (rms_db - AUTO_REFERENCE_RMS_DB)
+                .clamp(-AUTO_MAX_THRESHOLD_OFFSET_DB, AUTO_MAX_THRESHOLD_OFFSET_DB),
+            ratio_scale: (AUTO_REFERENCE_CREST_DB / crest_db.max(1.0))
+                .clamp(min_ratio_scale, max_ratio_scale),
+        })
+    }
+}
+
+/// A one-pole DC blocking high-pass filter, `y[n] = x[n] - x[n - 1] + r * y[n - 1]`.
+#[derive(Clone, Copy)]
+pub struct DcBlocker {
+    r: f32,
+    /// The previous input and output sample for every channel.
+    states: [(f32, f32); MAX_CHANNELS],
+}
+
+impl Default for DcBlocker {
+    fn default() -> Self {
+        let mut dc_blocker = Self {
+            r: 0.0,
+            states: [(0.0, 0.0); MAX_CHANNELS],
+        };
+        dc_blocker.set_sample_rate(44100.0);
+
+        dc_blocker
+    }
+}
+
+impl DcBlocker {
+    /// Updates the pole for a new sample rate, keeping the cutoff at [`DC_BLOCKER_HZ`].
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.r = (-2.0 * core::f32::consts::PI * DC_BLOCKER_HZ / sample_rate).exp();
+    }
+
+    /// Processes a single sample for a specific channel.
+    pub fn process(&mut self, channel: usize, sample: f32) -> f32 {
+        let (x1, y1) = &mut self.states[channel];
+        let y = sample - *x1 + self.r * *y1;
+        *x1 = sample;
+        *y1 = y;
+
+        y
+    }
+
+    /// Clears the filter state for all channels.
+    pub fn reset(&mut self) {
+        self.states = [(0.0, 0.0); MAX_CHANNELS];
+    }
+}
+
+/// A one-shot envelope for MIDI-triggered ducking. Every trigger ramps the envelope up linearly to
+/// the trigger's level, after which it decays exponentially back to zero, regardless of when the
+/// note ends.
+#[derive(Default, Clone, Copy)]
+pub struct TriggerEnvelope {
+    value: f32,
+    /// The level the attack ramps up to.
+    peak: f32,
+    attacking: bool,
+}
+
+impl TriggerEnvelope {
+    /// Starts a new attack towards `level`. A trigger that's quieter than the envelope's current
+    /// value holds the envelope where it is instead of cutting it short.
+    pub fn trigger(&mut self, level: f32) {
+        self.peak = level.max(self.value);
+        self.attacking = true;
+    }
+
+    /// Advances the envelope by one sample and returns its value. `attack_step` is the amount the
+    /// envelope rises per sample and `release_coefficient` the amount it decays by.
+    pub fn next(&mut self, attack_step: f32, release_coefficient: f32) -> f32 {
+        if self.attacking {
+            self.value += attack_step;
+            if self.value >= self.peak {
+                self.value = self.peak;
+                self.attacking = false;
+            }
+        } else {
+            self.value *= release_coefficient;
+        }
+
+        self.value
+    }
+
+    /// Silences the envelope.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// Spots the hits in a band for Adaptive Timing. A fast and a slow envelope follow the band's peak
+/// level, and the detector's output rises from 0 to 1 as the fast envelope jumps from 3.5 dB to
+/// 6 dB ahead of the slow one. After a hit it falls back to 0 over [`HIT_HOLD_MS`].
+#[derive(Default, Clone, Copy)]
+pub struct TransientDetector {
+    fast_envelope: f32,
+    slow_envelope: f32,
+    hit: f32,
+    /// The attack and release coefficients of the fast and the slow envelope.
+    fast_alphas: (f32, f32),
+    slow_alphas: (f32, f32),
+    /// The fraction of the hit kept for every sample.
+    hold_coefficient: f32,
+}
+
+impl TransientDetector {
+    /// Updates the time constants for a new sample rate.
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        let alpha = |time_ms: f32| 1.0 - (-1000.0 / (time_ms * sample_rate)).exp();
+        self.fast_alphas = (alpha(HIT_FAST_ATTACK_MS), alpha(HIT_FAST_RELEASE_MS));
+        self.slow_alphas = (alpha(HIT_SLOW_ATTACK_MS), alpha(HIT_SLOW_RELEASE_MS));
+        self.hold_coefficient = 1.0 - alpha(HIT_HOLD_MS);
+    }
+
+    /// Follows the band's peak `level` over all channels for one sample, and returns how hard the
+    /// band is being hit, from 0 to 1.
+    pub fn process(&mut self, level: f32) -> f32 {
+        for (envelope, (attack_alpha, release_alpha)) in [
+            (&mut self.fast_envelope, self.fast_alphas),
+            (&mut self.slow_envelope, self.slow_alphas),
+        ] {
+            let alpha = if level > *envelope {
+                attack_alpha
+            } else {
+                release_alpha
+            };
+            *envelope += alpha * (level - *envelope);
+        }
+
+        // The ripple of a steady tone keeps the envelopes less than 3.5 dB (1.5x) apart
+        let strength = ((self.fast_envelope - (1.5 * self.slow_envelope))
+            / (0.5 * self.slow_envelope).max(util::MINUS_INFINITY_GAIN))
+        .clamp(0.0, 1.0);
+        self.hit = strength.max(self.hit * self.hold_coefficient);
+
+        self.hit
+    }
+
+    /// Forgets the envelopes and any hit.
+    pub fn reset(&mut self) {
+        self.fast_envelope = 0.0;
+        self.slow_envelope = 0.0;
+        self.hit = 0.0;
+    }
+}
+
+/// Smooths a band's gain reduction on its way to the audio. It glides along with a fraction of the
+/// band's attack time while the gain reduction deepens and a fraction of its release time while it
+/// recovers, so the smoothing follows the envelope's timing instead of blurring it with a fixed
+/// time.
+#[derive(Debug, Clone, Copy)]
+pub struct GrSmoother {
+    current: f32,
+}
+
+impl Default for GrSmoother {
+    fn default() -> Self {
+        Self { current: 1.0 }
+    }
+}
+
+impl GrSmoother {
+    /// The attack and release coefficients for the band's `attack` and `release` times in
+    /// samples, with `smoothing` as the fraction of them to smooth over. At 0 the gain reduction
+    /// isn't smoothed at all.
+    pub fn alphas(attack: f32, release: f32, smoothing: f32) -> (f32, f32) {
+        let alpha = |time: f32| {
+            let time = time * smoothing;
+            if time > 0.0 {
+                1.0 - (-1.0 / time).exp()
+            } else {
+                1.0
+            }
+        };
+        (alpha(attack), alpha(release))
+    }
+
+    /// Moves one sample towards the gain computer's `target` gain reduction factor, using the
+    /// coefficients from [`GrSmoother::alphas()`].
+    pub fn next(&mut self, target: f32, (attack_alpha, release_alpha): (f32, f32)) -> f32 {
+        let alpha = if target < self.current {
+            attack_alpha
+        } else {
+            release_alpha
+        };
+        self.current += alpha * (target - self.current);
+        self.current
+    }
+
+    /// Jumps back to unity gain.
+    pub fn reset(&mut self) {
+        self.current = 1.0;
+    }
+}
+
+/// Shifts a frequency by a certain number of semitones based on the `tilt` parameter.
+pub fn shift_frequency(base_freq: f32, tilt: f32) -> f32 {
+    let shift = tilt * TILT_MAX_SHIFT_SEMITONES;
+    base_freq * 2.0_f32.powf(shift / 12.0)
+}
+
+/// The base crossover frequencies before any tilt is applied, for every supported band count.
+const BASE_CROSSOVER_FREQS_3: [f32; 2] = [250.0, 3000.0];
+const BASE_CROSSOVER_FREQS_4: [f32; 3] = [150.0, 1200.0, 6000.0];
+const BASE_CROSSOVER_FREQS_5: [f32; 4] = [150.0, 800.0, 4000.0, 9000.0];
+const BASE_CROSSOVER_FREQS_6: [f32; 5] = [100.0, 400.0, 1500.0, 4500.0, 10000.0];
+/// The lowest and highest frequency a crossover can end up at.
+const MIN_CROSSOVER_HZ: f32 = 30.0;
+const MAX_CROSSOVER_HZ: f32 = 18000.0;
+/// The minimum ratio between two neighbouring crossovers, a third of an octave.
+const MIN_CROSSOVER_RATIO: f32 = 1.26;
+/// The highest a crossover can go as a fraction of the sample rate, so the top band keeps some
+/// width below Nyquist at low sample rates.
+const MAX_CROSSOVER_FRACTION: f32 = 0.4;
+/// The outer edges of the lowest and the highest band. The top band's upper edge is Nyquist up to
+/// 48 kHz and stays at 24 kHz above that, so its center frequency, which drives its time
+/// constants, its reactive EQ and its noise layer, doesn't drift off into the ultrasonic range at
+/// high sample rates.
+pub const MIN_BAND_EDGE_HZ: f32 = 20.0;
+pub const MAX_BAND_EDGE_HZ: f32 = 24000.0;
+
+/// Returns the base crossover frequencies for the given band count. The returned slice always
+/// contains `num_bands - 1` frequencies.
+pub fn base_crossover_freqs(num_bands: usize) -> &'static [f32] {
+    match num_bands {
+        3 => &BASE_CROSSOVER_FREQS_3,
+        4 => &BASE_CROSSOVER_FREQS_4,
+        5 => &BASE_CROSSOVER_FREQS_5,
+        _ => &BASE_CROSSOVER_FREQS_6,
+    }
+}
+
+/// The crossover frequencies at `sample_rate` for `num_bands` bands with the given tilt, with every
+/// crossover moved by `shift_semitones(crossover_idx)` on top. Only the first `num_bands - 1`
+/// frequencies are used. The crossovers are kept in order with at least a third of an octave
+/// between them, and well below Nyquist.
+pub fn crossover_frequencies(
+    sample_rate: f32,
+    num_bands: usize,
+    tilt: f32,
+    shift_semitones: impl Fn(usize) -> f32,
+) -> [f32; MAX_BANDS - 1] {
+    let mut freqs = [0.0; MAX_BANDS - 1];
+    let mut min_freq = MIN_CROSSOVER_HZ;
+    for (j, &base_freq) in base_crossover_freqs(num_bands).iter().enumerate() {
+        let shifted_freq =
+            shift_frequency(base_freq, tilt) * 2.0f32.powf(shift_semitones(j) / 12.0);
+        freqs[j] = shifted_freq.min(MAX_CROSSOVER_HZ).max(min_freq);
+        min_freq = freqs[j] * MIN_CROSSOVER_RATIO;
+    }
+
+    // The spacing can push the upper crossovers past the limit, so they're brought back down from
+    // the top, keeping the spacing intact
+    let mut max_freq = MAX_CROSSOVER_HZ.min(sample_rate * MAX_CROSSOVER_FRACTION);
+    for freq in freqs[..(num_bands - 1)].iter_mut().rev() {
+        *freq = freq.min(max_freq);
+        max_freq = *freq / MIN_CROSSOVER_RATIO;
+    }
+
+    freqs
+}
+
+/// The lower and upper edge of band `band_idx` for the given crossover frequencies. The inner
+/// edges are the crossovers, and the outer bands run out to [`MIN_BAND_EDGE_HZ`] and to Nyquist,
+/// but no higher than [`MAX_BAND_EDGE_HZ`].
+pub fn band_edges(
+    sample_rate: f32,
+    crossover_freqs: &[f32],
+    num_bands: usize,
+    band_idx: usize,
+) -> (f32, f32) {
+    let lower = if band_idx == 0 {
+        MIN_BAND_EDGE_HZ
+    } else {
+        crossover_freqs[band_idx - 1]
+    };
+    let upper = if band_idx == num_bands - 1 {
+        (sample_rate / 2.0).min(MAX_BAND_EDGE_HZ)
+    } else {
+        crossover_freqs[band_idx]
+    };
+
+    (lower, upper)
+}
+
+/// The center frequency of band `band_idx`, the geometric mean of its [edges][band_edges()].
+pub fn band_center_frequency(
+    sample_rate: f32,
+    crossover_freqs: &[f32],
+    num_bands: usize,
+    band_idx: usize,
+) -> f32 {
+    let (lower, upper) = band_edges(sample_rate, crossover_freqs, num_bands, band_idx);
+
+    (lower * upper).sqrt()
+}
+
+/// The number of channels the filters process at once. Frames are processed in groups of this
+/// many channels, so stereo runs as a single SIMD operation.
+const SIMD_LANES: usize = 4;
+
+/// Loads a group of [`SIMD_LANES`] channels into a SIMD register, converting them to the
+/// processing precision.
+fn load_lanes(values: &[f32]) -> RealX4 {
+    RealX4::from([
+        to_real(values[0]),
+        to_real(values[1]),
+        to_real(values[2]),
+        to_real(values[3]),
+    ])
+}
+
+/// Loads a group of [`SIMD_LANES`] filter states into a SIMD register.
+fn load_state_lanes(values: &[Real]) -> RealX4 {
+    RealX4::from(<[Real; SIMD_LANES]>::try_from(values).unwrap())
+}
+
+// --- FILTER DESIGN LIMITS ---
+/// The lowest frequency the filter designs accept.
+pub const MIN_FILTER_FREQ_HZ: f32 = 10.0;
+/// The lowest frequency the filter designs accept, as a fraction of the sample rate. At very high
+/// sample rates, the poles of the lowest filters get too close to DC for single precision
+/// coefficients to keep them inside the unit circle. This only raises the limit above 10 Hz
+/// from 166.7 kHz on.
+pub const MIN_FILTER_FREQ_RATIO: f32 = 6e-5;
+/// The highest frequency the filter designs accept, as a fraction of the sample rate. The designs
+/// degenerate at Nyquist and mirror back down above it, so the filters stop just short of it.
+pub const MAX_FILTER_FREQ_RATIO: f32 = 0.49;
+/// The range of Qs the filter designs accept.
+pub const MIN_FILTER_Q: f32 = 0.1;
+pub const MAX_FILTER_Q: f32 = 40.0;
+/// The largest boost or cut the shelf and peaking designs accept, in decibels.
+pub const MAX_FILTER_GAIN_DB: f32 = 48.0;
+
+/// Clamps a filter frequency to the range the designs in [`BiquadCoefficients`] stay stable in at
+/// `sample_rate`. The crossovers can be pushed above Nyquist at low sample rates by Tilt and the
+/// crossover shifts, and this keeps them just below it. NaN ends up at the lowest frequency.
+#[inline]
+pub fn clamp_filter_freq(sample_rate: f32, freq: f32) -> f32 {
+    let min_freq = MIN_FILTER_FREQ_HZ.max(sample_rate * MIN_FILTER_FREQ_RATIO);
+    freq.max(min_freq).min(sample_rate * MAX_FILTER_FREQ_RATIO)
+}
+
+/// Clamps a filter Q to the range from [`MIN_FILTER_Q`] to [`MAX_FILTER_Q`]. Unlike `clamp()`,
+/// this turns NaN into the lower limit.
+#[allow(clippy::manual_clamp)]
+#[inline]
+pub fn clamp_filter_q(q: f32) -> f32 {
+    q.max(MIN_FILTER_Q).min(MAX_FILTER_Q)
+}
+
+/// Clamps a shelf or peaking gain to [`MAX_FILTER_GAIN_DB`] in either direction. A full cut would
+/// put the peaking filter's poles at infinity. NaN becomes the largest cut.
+#[allow(clippy::manual_clamp)]
+#[inline]
+pub fn clamp_filter_gain_db(gain_db: f32) -> f32 {
+    gain_db.max(-MAX_FILTER_GAIN_DB).min(MAX_FILTER_GAIN_DB)
+}
+
+/// Coefficients for a biquad filter, calculated from specifications. These are stored and
+/// calculated at the processing precision.
+///
+/// The designs clamp their inputs with [`clamp_filter_freq()`], [`clamp_filter_q()`] and
+/// [`clamp_filter_gain_db()`], so any settings give a stable filter. Debug builds assert that the
+/// results are [stable][Self::is_stable()].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BiquadCoefficients {
+    pub a1: Real,
+    pub a2: Real,
+    pub b0: Real,
+    pub b1: Real,
+    pub b2: Real,
+}
+
+impl BiquadCoefficients {
+    /// Coefficients that pass the signal through unchanged.
+    pub const IDENTITY: Self = Self {
+        a1: 0.0,
+        a2: 0.0,
+        b0: 1.0,
+        b1: 0.0,
+        b2: 0.0,
+    };
+
+    /// Calculates coefficients for a 2nd order Butterworth high-pass filter, one section of a 4th
+    /// order Linkwitz-Riley high-pass filter.
+    pub fn calculate_lr_highpass(sample_rate: f32, cutoff_freq: f32) -> Self {
+        Self::calculate_highpass(sample_rate, cutoff_freq, core::f32::consts::FRAC_1_SQRT_2)
+    }
+
+    /// Calculates coefficients for a 2nd order low-pass filter based on the Audio EQ Cookbook.
+    /// Cascading these with the right Qs builds the higher order Butterworth and Linkwitz-Riley
+    /// filters.
+    pub fn calculate_lowpass(sample_rate: f32, cutoff_freq: f32, q: f32) -> Self {
+        let (cutoff_freq, q) = (
+            clamp_filter_freq(sample_rate, cutoff_freq),
+            clamp_filter_q(q),
+        );
+        let w0 = 2.0 * real_consts::PI * to_real(cutoff_freq) / to_real(sample_rate);
+        let cos_w0 = w0.cos();
+        let alpha = w0.sin() / (2.0 * to_real(q));
+
+        let b0 = (1.0 - cos_w0) / 2.0;
+        let b1 = 1.0 - cos_w0;
+        let b2 = (1.0 - cos_w0) / 2.0;
+        let a0 = 1.0 + alpha;
+        let a1 = -2.0 * cos_w0;
+        let a2 = 1.0 - alpha;
+
+        let d = a0;
+        Self {
+            b0: b0 / d,
+            b1: b1 / d,
+            b2: b2 / d,
+            a1: a1 / d,
+            a2: a2 / d,
+        }
+    }
+
+    /// Calculates coefficients for a 2nd order high-pass filter based on the Audio EQ Cookbook,
+    /// the high-pass counterpart of [`BiquadCoefficients::calculate_lowpass()`].
+    pub fn calculate_highpass(sample_rate: f32, cutoff_freq: f32, q: f32) -> Self {
+        let (cutoff_freq, q) = (
+            clamp_filter_freq(sample_rate, cutoff_freq),
+            clamp_filter_q(q),
+        );
+        let w0 = 2.0 * real_consts::PI * to_real(cutoff_freq) / to_real(sample_rate);
+        let cos_w0 = w0.cos();
+        let alpha = w0.sin() / (2.0 * to_real(q));
+
+        let b0 = (1.0 + cos_w0) / 2.0;
+        let b1 = -(1.0 + cos_w0);
+        let b2 = (1.0 + cos_w0) / 2.0;
+        let a0 = 1.0 + alpha;
+        let a1 = -2.0 * cos_w0;
+        let a2 = 1.0 - alpha;
+
+        let d = a0;
+        Self {
+            b0: b0 / d,
+            b1: b1 / d,
+            b2: b2 / d,
+            a1: a1 / d,
+            a2: a2 / d,
+        }
+    }
+
+    /// Calculates coefficients for a 1st order allpass filter, with the same prewarping as the
+    /// cookbook filters. This matches the summed response of a 2nd order Linkwitz-Riley crossover
+    /// with its high-pass output inverted.
+    pub fn calculate_first_order_allpass(sample_rate: f32, freq: f32) -> Self {
+        let freq = clamp_filter_freq(sample_rate, freq);
+        let k = (real_consts::PI * to_real(freq) / to_real(sample_rate)).tan();
+        let a = (k - 1.0) / (k + 1.0);
+
+        Self {
+            b0: a,
+            b1: 1.0,
+            b2: 0.0,
+            a1: a,
+            a2: 0.0,
+        }
+    }
+
+    /// Calculates coefficients for a 2nd order allpass filter based on the Audio EQ Cookbook.
+    /// With `q = 1/sqrt(2)` this matches the summed response of a 4th order Linkwitz-Riley
+    /// crossover at the same frequency.
+    pub fn calculate_allpass(sample_rate: f32, freq: f32, q: f32) -> Self {
+        let (freq, q) = (clamp_filter_freq(sample_rate, freq), clamp_filter_q(q));
+        let w0 = 2.0 * real_consts::PI * to_real(freq) / to_real(sample_rate);
+        let cos_w0 = w0.cos();
+        let alpha = w0.sin() / (2.0 * to_real(q));
+
+        let b0 = 1.0 - alpha;
+        let b1 = -2.0 * cos_w0;
+        let b2 = 1.0 + alpha;
+        let a0 = 1.0 + alpha;
+        let a1 = -2.0 * cos_w0;
+        let a2 = 1.0 - alpha;
+
+        let d = a0;
+        Self {
+            b0: b0 / d,
+            b1: b1 / d,
+            b2: b2 / d,
+            a1: a1 / d,
+            a2: a2 / d,
+        }
+    }
+
+    /// Calculates coefficients for a band-pass filter based on the Audio EQ Cookbook, with a
+    /// constant 0 dB peak gain at `freq`.
+    pub fn calculate_bandpass(sample_rate: f32, freq: f32, q: f32) -> Self {
+        let (freq, q) = (clamp_filter_freq(sample_rate, freq), clamp_filter_q(q));
+        let w0 = 2.0 * real_consts::PI * to_real(freq) / to_real(sample_rate);
+        let cos_w0 = w0.cos();
+        let alpha = w0.sin() / (2.0 * to_real(q));
+
+        let b0 = alpha;
+        let b1 = 0.0;
+        let b2 = -alpha;
+        let a0 = 1.0 + alpha;
+        let a1 = -2.0 * cos_w0;
+        let a2 = 1.0 - alpha;
+
+        let d = a0;
+        Self {
+            b0: b0 / d,
+            b1: b1 / d,
+            b2: b2 / d,
+            a1: a1 / d,
+            a2: a2 / d,
+        }
+    }
+
+    /// Calculates coefficients for a low shelf filter based on the Audio EQ Cookbook. `gain_db` is
+    /// the gain below the shelf frequency.
+    pub fn calculate_low_shelf(sample_rate: f32, freq: f32, q: f32, gain_db: f32) -> Self {
+        let (freq, q) = (clamp_filter_freq(sample_rate, freq), clamp_filter_q(q));
+        let gain_db = clamp_filter_gain_db(gain_db);
+        let a = to_real(util::db_to_gain(gain_db / 2.0)); // Square root of the linear gain
+        let w0 = 2.0 * real_consts::PI * to_real(freq) / to_real(sample_rate);
+        let cos_w0 = w0.cos();
+        let alpha = w0.sin() / (2.0 * to_real(q));
+        let sqrt_a_alpha = 2.0 * a.sqrt() * alpha;
+
+        let b0 = a * ((a + 1.0) - (a - 1.0) * cos_w0 + sqrt_a_alpha);
+        let b1 = 2.0 * a * ((a - 1.0) - (a + 1.0) * cos_w0);
+        let b2 = a * ((a + 1.0) - (a - 1.0) * cos_w0 - sqrt_a_alpha);
+        let a0 = (a + 1.0) + (a - 1.0) * cos_w0 + sqrt_a_alpha;
+        let a1 = -2.0 * ((a - 1.0) + (a + 1.0) * cos_w0);
+        let a2 = (a + 1.0) + (a - 1.0) * cos_w0 - sqrt_a_alpha;
+
+        let d = a0;
+        Self {
+            b0: b0 / d,
+            b1: b1 / d,
+            b2: b2 / d,
+            a1: a1 / d,
+            a2: a2 / d,
+        }
+    }
+
+    /// Calculates coefficients for a high shelf filter based on the Audio EQ Cookbook. `gain_db`
+    /// is the gain above the shelf frequency.
+    pub fn calculate_high_shelf(sample_rate: f32, freq: f32, q: f32, gain_db: f32) -> Self {
+        let (freq, q) = (clamp_filter_freq(sample_rate, freq), clamp_filter_q(q));
+        let gain_db = clamp_filter_gain_db(gain_db);
+        let a = to_real(util::db_to_gain(gain_db / 2.0)); // Square root of the linear gain
+        let w0 = 2.0 * real_consts::PI * to_real(freq) / to_real(sample_rate);
+        let cos_w0 = w0.cos();
+        let alpha = w0.sin() / (2.0 * to_real(q));
+        let sqrt_a_alpha = 2.0 * a.sqrt() * alpha;
+
+        let b0 = a * ((a + 1.0) + (a - 1.0) * cos_w0 + sqrt_a_alpha);
+        let b1 = -2.0 * a * ((a - 1.0) + (a + 1.0) * cos_w0);
+        let b2 = a * ((a + 1.0) + (a - 1.0) * cos_w0 - sqrt_a_alpha);
+        let a0 = (a + 1.0) - (a - 1.0) * cos_w0 + sqrt_a_alpha;
+        let a1 = 2.0 * ((a - 1.0) - (a + 1.0) * cos_w0);
+        let a2 = (a + 1.0) - (a - 1.0) * cos_w0 - sqrt_a_alpha;
+
+        let d = a0;
+        Self {
+            b0: b0 / d,
+            b1: b1 / d,
+            b2: b2 / d,
+            a1: a1 / d,
+            a2: a2 / d,
+        }
+    }
+
+    /// Calculates coefficients for a peaking EQ filter. This is the Audio EQ Cookbook's analog
+    /// prototype with `a` as the linear gain, so the peak reaches twice `gain_db`.
+    ///
+    /// The cookbook's bilinear transform squeezes the upper half of a bell towards Nyquist, so high
+    /// bells would get narrower at lower sample rates. Instead, the poles are matched to the analog
+    /// prototype's and the zeros are chosen to match its magnitude at DC, at the center frequency
+    /// and at Nyquist (Vicanek, "Matched Second Order Digital Filters"). The response then follows
+    /// the analog prototype at any sample rate. The intermediate terms lose too much precision at
+    /// low frequencies for single precision, so this is always calculated at double precision.
+    pub fn calculate_peaking(sample_rate: f32, freq: f32, q: f32, gain_db: f32) -> Self {
+        let (freq, q) = (clamp_filter_freq(sample_rate, freq), clamp_filter_q(q));
+        let gain_db = clamp_filter_gain_db(gain_db);
+        let a = util::db_to_gain(gain_db) as f64;
+        let w0 = 2.0 * core::f64::consts::PI * freq as f64 / sample_rate as f64;
+        // The peak gain, and the damping of the prototype's poles
+        let g = a * a;
+        let zeta = 1.0 / (2.0 * a * q as f64);
+
+        let a1 = if zeta <= 1.0 {
+            -2.0 * (-zeta * w0).exp() * ((1.0 - zeta * zeta).sqrt() * w0).cos()
+        } else {
+            // Two real poles. `exp(-zeta * w0) * cosh(s * w0)` is written out as the sum of the
+            // poles, since the cosh overflows for the heavily damped poles of deep cuts.
+            let s = (zeta * zeta - 1.0).sqrt();
+            -((-w0 / (zeta + s)).exp() + (-(zeta + s) * w0).exp())
+        };
+        let a2 = (-2.0 * zeta * w0).exp();
+
+        // The squared magnitude of the denominator at DC and Nyquist, and its cross term
+        let den_dc = (1.0 + a1 + a2).powi(2);
+        let den_nyquist = (1.0 - a1 + a2).powi(2);
+        let den_cross = -4.0 * a2;
+        let phi1 = (w0 / 2.0).sin().powi(2);
+        let phi0 = 1.0 - phi1;
+        let phi2 = 4.0 * phi0 * phi1;
+
+        // The numerator's squared magnitude terms that give the prototype's gain at DC, at the
+        // center frequency, and at Nyquist
+        let r1 = (den_dc * phi0 + den_nyquist * phi1 + den_cross * phi2) * g * g;
+        let r2 = (-den_dc + den_nyquist + 4.0 * (phi0 - phi1) * den_cross) * g * g;
+        let num_dc = den_dc;
+        let num_cross = (r1 - r2 * phi1 - num_dc) / (4.0 * phi1 * phi1);
+        let num_nyquist = (r2 + num_dc + 4.0 * (phi1 - phi0) * num_cross).max(0.0);
+
+        let w = (num_dc.sqrt() + num_nyquist.sqrt()) / 2.0;
+        let b0 = (w + (w * w + num_cross).max(0.0).sqrt()) / 2.0;
+        let b1 = (num_dc.sqrt() - num_nyquist.sqrt()) / 2.0;
+        let b2 = -num_cross / (4.0 * b0);
+
+        Self {
+            b0: b0 as Real,
+            b1: b1 as Real,
+            b2: b2 as Real,
+            a1: a1 as Real,
+            a2: a2 as Real,
+        }
+    }
+
+    /// Whether the coefficients are finite and the poles lie inside the unit circle, so the
+    /// filter's output can't grow without bound. This is the stability triangle of the
+    /// denominator `1 + a1 z^-1 + a2 z^-2`. The poles of low filters sit right next to DC, so
+    /// `a1` gets a few units of rounding error of leeway.
+    pub fn is_stable(&self) -> bool {
+        [self.b0, self.b1, self.b2].iter().all(|b| b.is_finite())
+            && self.a2.abs() < 1.0
+            && self.a1.abs() < 1.0 + self.a2 + (4.0 * Real::EPSILON)
+    }
+
+    /// The filter's magnitude response at `freq` as a linear gain, evaluated directly from the
+    /// transfer function.
+    pub fn magnitude_at(&self, sample_rate: f32, freq: f32) -> f32 {
+        let w = 2.0 * real_consts::PI * to_real(freq) / to_real(sample_rate);
+        let (sin_w, cos_w) = w.sin_cos();
+        let (sin_2w, cos_2w) = (2.0 * w).sin_cos();
+
+        let num_re = self.b0 + self.b1 * cos_w + self.b2 * cos_2w;
+        let num_im = -(self.b1 * sin_w + self.b2 * sin_2w);
+        let den_re = 1.0 + self.a1 * cos_w + self.a2 * cos_2w;
+        let den_im = -(self.a1 * sin_w + self.a2 * sin_2w);
+
+        let magnitude_squared =
+            (num_re * num_re + num_im * num_im) / (den_re * den_re + den_im * den_im);
+        from_real(magnitude_squared.sqrt())
+    }
+}
+
+/// A multichannel biquad filter using a transposed direct form 2 structure. All channels share
+/// the same coefficients and have their own state. The channels are processed in SIMD groups of
+/// [`SIMD_LANES`].
+#[derive(Default, Clone, Copy)]
+pub struct Biquad {
+    coefs: BiquadCoefficients,
+    /// The filter state for every channel, stored per state variable so a group of channels can be
+    /// loaded into a SIMD register at once.
+    z1: [Real; MAX_CHANNELS],
+    z2: [Real; MAX_CHANNELS],
+}
+
+impl Biquad {
+    /// Processes a frame in place. Only the channel groups containing the first `num_channels`
+    /// channels are processed, the other channels in those groups are filtered along with them
+    /// and should be ignored.
+    pub fn process(&mut self, frame: &mut [f32; MAX_CHANNELS], num_channels: usize) {
+        let c = self.coefs;
+        let splat = RealX4::splat;
+        let (b0, b1, b2) = (splat(c.b0), splat(c.b1), splat(c.b2));
+        let (a1, a2) = (splat(c.a1), splat(c.a2));
+
+        let num_lanes = num_channels.div_ceil(SIMD_LANES) * SIMD_LANES;
+        for ((samples, z1), z2) in frame[..num_lanes]
+            .chunks_exact_mut(SIMD_LANES)
+            .zip(self.z1.chunks_exact_mut(SIMD_LANES))
+            .zip(self.z2.chunks_exact_mut(SIMD_LANES))
+        {
+            let sample = load_lanes(samples);
+            let (state_z1, state_z2) = (load_state_lanes(z1), load_state_lanes(z2));
+
+            // Direct Form 2 Transposed
+            let out = b0 * sample + state_z1;
+            let new_z1 = b1 * sample - a1 * out + state_z2;
+            let new_z2 = b2 * sample - a2 * out;
+
+            for (sample, out) in samples.iter_mut().zip(out.to_array()) {
+                *sample = from_real(out);
+            }
+            z1.copy_from_slice(&new_z1.to_array());
+            z2.copy_from_slice(&new_z2.to_array());
+        }
+    }
+
+    /// Replaces the filter's coefficients with precalculated ones.
+    pub fn set_coefficients(&mut self, coefs: BiquadCoefficients) {
+        debug_assert!(coefs.is_stable(), "unstable biquad coefficients: {coefs:?}");
+        self.coefs = coefs;
+    }
+
+    /// Resets the filter's internal state.
+    pub fn reset(&mut self) {
+        self.z1 = [0.0; MAX_CHANNELS];
+        self.z2 = [0.0; MAX_CHANNELS];
+    }
+}
+
+/// A [`Biquad`] whose coefficients glide linearly to new targets instead of jumping, so its
+/// expensive coefficient calculations only need to happen every so often and fast modulation
+/// doesn't cause zipper noise. Linear interpolation between the coefficients of two nearby
+/// filters of the same type stays stable, as long as the targets are updated frequently enough
+/// that they don't drift far apart.
+#[derive(Clone, Copy)]
+pub struct RampedBiquad {
+    filter: Biquad,
+    target: BiquadCoefficients,
+    /// The per-sample change in coefficients while ramping.
+    step: BiquadCoefficients,
+    steps_left: u32,
+}
+
+impl Default for RampedBiquad {
+    fn default() -> Self {
+        let mut filter = Biquad::default();
+        filter.set_coefficients(BiquadCoefficients::IDENTITY);
+
+        Self {
+            filter,
+            target: BiquadCoefficients::IDENTITY,
+            step: BiquadCoefficients::default(),
+            steps_left: 0,
+        }
+    }
+}
+
+impl RampedBiquad {
+    /// Starts gliding from the current coefficients to `target` over the next `num_steps`
+    /// processed frames. With `num_steps` set to 0 or 1 the target is used as is for the next
+    /// frame.
+    pub fn ramp_to(&mut self, target: BiquadCoefficients, num_steps: u32) {
+        debug_assert!(
+            target.is_stable(),
+            "unstable biquad coefficients: {target:?}"
+        );
+        let current = self.filter.coefs;
+        let steps = num_steps.max(1) as Real;
+        self.target = target;
+        self.step = BiquadCoefficients {
+            a1: (target.a1 - current.a1) / steps,
+            a2: (target.a2 - current.a2) / steps,
+            b0: (target.b0 - current.b0) / steps,
+            b1: (target.b1 - current.b1) / steps,
+            b2: (target.b2 - current.b2) / steps,
+        };
+        self.steps_left = num_steps.max(1);
+    }
+
+    /// Advances the coefficient ramp by one step and processes a frame in place.
+    pub fn process(&mut self, frame: &mut [f32; MAX_CHANNELS], num_channels: usize) {
+        if self.steps_left > 0 {
+            self.steps_left -= 1;
+            let coefs = &mut self.filter.coefs;
+            if self.steps_left == 0 {
+                // Land exactly on the target so rounding errors don't accumulate
+                *coefs = self.target;
+            } else {
+                coefs.a1 += self.step.a1;
+                coefs.a2 += self.step.a2;
+                coefs.b0 += self.step.b0;
+                coefs.b1 += self.step.b1;
+                coefs.b2 += self.step.b2;
+            }
+        }
+
+        self.filter.process(frame, num_channels);
+    }
+
+    /// Resets the filter's state. A ramp in progress skips straight to its target, so the
+    /// filter keeps its latest specification.
+    pub fn reset(&mut self) {
+        self.filter.reset();
+        self.filter.coefs = self.target;
+        self.steps_left = 0;
+    }
+}
+
+/// The Qs of the two sections of a 4th order Butterworth filter.
+const BUTTERWORTH_4_QS: [f32; 2] = [0.541_196_1, 1.306_563];
+
+choice! {
+    /// The slope of the crossover filters. Steeper slopes keep the bands apart, gentler slopes let
+    /// them overlap more.
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+    pub enum CrossoverSlope {
+        /// A 2nd order Linkwitz-Riley crossover, made of a single section per output.
+        Slope12 => "12 dB/oct",
+        /// A 4th order Linkwitz-Riley crossover. This is the original ColorFall crossover.
+        #[default]
+        Slope24 => "24 dB/oct",
+        /// An 8th order Linkwitz-Riley crossover, made of two cascaded 4th order Butterworth
+        /// filters.
+        Slope48 => "48 dB/oct",
+    }
+}
+
+impl CrossoverSlope {
+    /// The Qs of the cascaded sections of each crossover output.
+    fn section_qs(self) -> &'static [f32] {
+        match self {
+            CrossoverSlope::Slope12 => &[0.5],
+            CrossoverSlope::Slope24 => &[core::f32::consts::FRAC_1_SQRT_2; 2],
+            CrossoverSlope::Slope48 => &[
+                BUTTERWORTH_4_QS[0],
+                BUTTERWORTH_4_QS[1],
+                BUTTERWORTH_4_QS[0],
+                BUTTERWORTH_4_QS[1],
+            ],
+        }
+    }
+}
+
+/// A multichannel Linkwitz-Riley crossover with a selectable slope. Both outputs are cascaded
+/// Butterworth sections, so the low and high parts sum to a flat magnitude response with the phase
+/// of the matching [`CrossoverAllpass`]. The 2nd order crossover's high output is inverted, which
+/// is what makes its sum flat.
+#[derive(Default, Clone, Copy)]
+pub struct LinkwitzRileyCrossover {
+    lowpass: [RampedBiquad; 4],
+    highpass: [RampedBiquad; 4],
+    slope: CrossoverSlope,
+}
+
+impl LinkwitzRileyCrossover {
+    /// Splits a frame into its `(low, high)` parts.
+    pub fn process(
+        &mut self,
+        frame: &[f32; MAX_CHANNELS],
+        num_channels: usize,
+    ) -> ([f32; MAX_CHANNELS], [f32; MAX_CHANNELS]) {
+        let num_sections = self.slope.section_qs().len();
+        let mut low = *frame;
+        for section in &mut self.lowpass[..num_sections] {
+            section.process(&mut low, num_channels);
+        }
+        let mut high = *frame;
+        for section in &mut self.highpass[..num_sections] {
+            section.process(&mut high, num_channels);
+        }
+        if self.slope == CrossoverSlope::Slope12 {
+            for sample in &mut high[..num_channels] {
+                *sample = -*sample;
+            }
+        }
+
+        (low, high)
+    }
+
+    /// Moves the crossover to a new frequency, gliding there over the next `ramp_steps` frames.
+    /// Changing the slope swaps out the sections, so that starts them from scratch instead.
+    pub fn update(
+        &mut self,
+        sample_rate: f32,
+        cutoff_freq: f32,
+        slope: CrossoverSlope,
+        ramp_steps: u32,
+    ) {
+        let ramp_steps = if slope != self.slope {
+            self.slope = slope;
+            self.reset();
+            0
+        } else {
+            ramp_steps
+        };
+
+        for ((lowpass, highpass), &q) in self
+            .lowpass
+            .iter_mut()
+            .zip(&mut self.highpass)
+            .zip(slope.section_qs())
+        {
+            lowpass.ramp_to(
+                BiquadCoefficients::calculate_lowpass(sample_rate, cutoff_freq, q),
+                ramp_steps,
+            );
+            highpass.ramp_to(
+                BiquadCoefficients::calculate_highpass(sample_rate, cutoff_freq, q),
+                ramp_steps,
+            );
+        }
+    }
+
+    /// Resets the crossover's internal state.
+    pub fn reset(&mut self) {
+        for section in self.lowpass.iter_mut().chain(self.highpass.iter_mut()) {
+            section.reset();
+        }
+    }
+}
+
+/// The allpass response of a [`LinkwitzRileyCrossover`]'s summed outputs. Bands that skip a
+/// crossover, and the dry signal, go through one of these so everything lines up in phase.
+#[derive(Default, Clone, Copy)]
+pub struct CrossoverAllpass {
+    sections: [RampedBiquad; 2],
+    slope: CrossoverSlope,
+}
+
+impl CrossoverAllpass {
+    /// Processes a frame in place.
+    pub fn process(&mut self, frame: &mut [f32; MAX_CHANNELS], num_channels: usize) {
+        let num_sections = match self.slope {
+            CrossoverSlope::Slope12 | CrossoverSlope::Slope24 => 1,
+            CrossoverSlope::Slope48 => 2,
+        };
+        for section in &mut self.sections[..num_sections] {
+            section.process(frame, num_channels);
+        }
+    }
+
+    /// Follows the crossover to a new frequency and slope. See
+    /// [`LinkwitzRileyCrossover::update()`].
+    pub fn update(&mut self, sample_rate: f32, freq: f32, slope: CrossoverSlope, ramp_steps: u32) {
+        let ramp_steps = if slope != self.slope {
+            self.slope = slope;
+            self.reset();
+            0
+        } else {
+            ramp_steps
+        };
+
+        match slope {
+            CrossoverSlope::Slope12 => self.sections[0].ramp_to(
+                BiquadCoefficients::calculate_first_order_allpass(sample_rate, freq),
+                ramp_steps,
+            ),
+            CrossoverSlope::Slope24 => self.sections[0].ramp_to(
+                BiquadCoefficients::calculate_allpass(
+                    sample_rate,
+                    freq,
+                    core::f32::consts::FRAC_1_SQRT_2,
+                ),
+                ramp_steps,
+            ),
+            // The sum of two cascaded Butterworth filters has the Butterworth filter's poles
+            CrossoverSlope::Slope48 => {
+                for (section, &q) in self.sections.iter_mut().zip(&BUTTERWORTH_4_QS) {
+                    section.ramp_to(
+                        BiquadCoefficients::calculate_allpass(sample_rate, freq, q),
+                        ramp_steps,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Resets the allpass filters' state.
+    pub fn reset(&mut self) {
+        for section in &mut self.sections {
+            section.reset();
+        }
+    }
+}
+
+/// The Q of the Phase Color allpasses just above 0%. Low Qs spread the phase shift out gently.
+const PHASE_COLOR_MIN_Q: f32 = 0.5;
+/// The Q of the Phase Color allpasses at 100%, where the phase turns sharply enough around the
+/// crossovers to ring.
+const PHASE_COLOR_MAX_Q: f32 = 8.0;
+
+/// The Phase Color allpass chain. One 2nd order allpass sits at every crossover frequency, so the
+/// phase rotation follows Tilt along with the bands. Higher settings raise the allpasses' Q, which
+/// concentrates the phase shift around the crossovers and smears transients into a resonant ring.
+/// The magnitude response stays flat.
+#[derive(Default, Clone, Copy)]
+pub struct PhaseColor {
+    allpasses: [RampedBiquad; MAX_BANDS - 1],
+}
+
+impl PhaseColor {
+    /// Processes a frame in place.
+    pub fn process(&mut self, frame: &mut [f32; MAX_CHANNELS], num_channels: usize) {
+        for allpass in &mut self.allpasses {
+            allpass.process(frame, num_channels);
+        }
+    }
+
+    /// Moves the allpasses to the crossover frequencies and the Phase Color setting, gliding there
+    /// over the next `ramp_steps` frames. The allpasses glide to and from pass-through filters
+    /// when Phase Color is turned on or off, and the allpasses for unused crossovers stay
+    /// pass-through.
+    pub fn update(
+        &mut self,
+        sample_rate: f32,
+        crossover_freqs: &[f32],
+        color: f32,
+        ramp_steps: u32,
+    ) {
+        let q = PHASE_COLOR_MIN_Q * (PHASE_COLOR_MAX_Q / PHASE_COLOR_MIN_Q).powf(color);
+        for (j, allpass) in self.allpasses.iter_mut().enumerate() {
+            let target = match crossover_freqs.get(j) {
+                Some(&freq) if color > 0.0 => {
+                    BiquadCoefficients::calculate_allpass(sample_rate, freq, q)
+                }
+                _ => BiquadCoefficients::IDENTITY,
+            };
+            allpass.ramp_to(target, ramp_steps);
+        }
+    }
+
+    /// Resets the allpasses' state.
+    pub fn reset(&mut self) {
+        for allpass in &mut self.allpasses {
+            allpass.reset();
+        }
+    }
+}
+
+/// A harmonic exciter for the top band. The band is full-wave rectified, which adds even
+/// harmonics starting an octave above the input, and then high-passed at the band's lower edge to
+/// remove the DC offset and the difference tones the rectifier also creates. What's left is sheen
+/// above the source's own content that can be blended back into the band.
+#[derive(Default, Clone, Copy)]
+pub struct Exciter {
+    highpass: [RampedBiquad; 2],
+}
+
+impl Exciter {
+    /// Generates the harmonics for a frame. The input is left untouched.
+    pub fn process(
+        &mut self,
+        frame: &[f32; MAX_CHANNELS],
+        num_channels: usize,
+    ) -> [f32; MAX_CHANNELS] {
+        let mut harmonics = [0.0; MAX_CHANNELS];
+        for (harmonic, sample) in harmonics.iter_mut().zip(frame).take(num_channels) {
+            *harmonic = sample.abs();
+        }
+        for section in &mut self.highpass {
+            section.process(&mut harmonics, num_channels);
+        }
+
+        harmonics
+    }
+
+    /// Moves the high-pass filter to a new cutoff frequency, gliding there over the next
+    /// `ramp_steps` frames.
+    pub fn update(&mut self, sample_rate: f32, cutoff_freq: f32, ramp_steps: u32) {
+        let highpass = BiquadCoefficients::calculate_lr_highpass(sample_rate, cutoff_freq);
+        for section in &mut self.highpass {
+            section.ramp_to(highpass, ramp_steps);
+        }
+    }
+
+    /// Resets the high-pass filter's state.
+    pub fn reset(&mut self) {
+        for section in &mut self.highpass {
+            section.reset();
+        }
+    }
+}
+
+/// The width of the noise layer's band-pass filters, about two octaves.
+const NOISE_Q: f32 = 0.7;
+/// The crackle clicks are much sparser than the hiss, so they're louder to stand out as much.
+const CRACKLE_GAIN: f32 = 4.0;
+
+choice! {
+    /// The texture of the per-band noise layer.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum NoiseTexture {
+        /// Steady tape hiss.
+        Hiss => "Hiss",
+        /// Sparse vinyl clicks of random height.
+        Crackle => "Crackle",
+    }
+}
+
+/// A band-filtered noise generator for the per-band noise layer. Every channel gets its own
+/// random sequence so the noise is decorrelated across a stereo pair. The caller scales the noise
+/// with the band's level, see [`calculate_noise_gain()`].
+#[derive(Clone, Copy)]
+pub struct NoiseLayer {
+    bandpass: RampedBiquad,
+    rng_states: [u32; MAX_CHANNELS],
+    /// The chance of a crackle click on any given sample.
+    crackle_probability: f32,
+}
+
+impl Default for NoiseLayer {
+    fn default() -> Self {
+        let mut noise = Self {
+            bandpass: RampedBiquad::default(),
+            // Xorshift gets stuck at zero
+            rng_states: array_init::array_init(|channel| channel as u32 + 1),
+            crackle_probability: 0.0,
+        };
+        noise.set_sample_rate(44100.0);
+
+        noise
+    }
+}
+
+impl NoiseLayer {
+    /// Updates the crackle rate for a new sample rate, keeping it at [`CRACKLE_RATE_HZ`].
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.crackle_probability = CRACKLE_RATE_HZ / sample_rate;
+    }
+
+    /// Moves the band-pass filter to the band's new center frequency, gliding there over the next
+    /// `ramp_steps` frames.
+    pub fn update(&mut self, sample_rate: f32, center_freq: f32, ramp_steps: u32) {
+        let bandpass = BiquadCoefficients::calculate_bandpass(sample_rate, center_freq, NOISE_Q);
+        self.bandpass.ramp_to(bandpass, ramp_steps);
+    }
+
+    /// Generates the next frame of band-filtered noise.
+    pub fn process(&mut self, texture: NoiseTexture, num_channels: usize) -> [f32; MAX_CHANNELS] {
+        let mut frame = [0.0; MAX_CHANNELS];
+        for (channel, sample) in frame.iter_mut().enumerate().take(num_channels) {
+            *sample = match texture {
+                NoiseTexture::Hiss => self.next_random(channel),
+                NoiseTexture::Crackle => {
+                    if (self.next_random(channel) + 1.0) / 2.0 < self.crackle_probability {
+                        CRACKLE_GAIN * self.next_random(channel)
+                    } else {
+                        0.0
+                    }
+                }
+            };
+        }
+        self.bandpass.process(&mut frame, num_channels);
+
+        frame
+    }
+
+    /// Resets the band-pass filter's state. The random sequences keep going.
+    pub fn reset(&mut self) {
+        self.bandpass.reset();
+    }
+
+    /// Draws a uniformly distributed value from -1 to 1 for a channel.
+    fn next_random(&mut self, channel: usize) -> f32 {
+        // Xorshift32, plenty for noise
+        let x = &mut self.rng_states[channel];
+        *x ^= *x << 13;
+        *x ^= *x >> 17;
+        *x ^= *x << 5;
+
+        ((*x as f32 / u32::MAX as f32) * 2.0) - 1.0
+    }
+}
+
+choice! {
+    /// The character of the per-band saturator. Every model shares the same drive mapping, so
+    /// Amount behaves the same way regardless of the selected model.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum SaturationModel {
+        /// The original ColorFall cubic waveshaper.
+        Cubic => "Cubic",
+        /// A biased tanh curve, adding some even harmonics to the smooth tanh compression.
+        Tape => "Tape",
+        /// An asymmetric exponential curve that clips the negative half harder.
+        Tube => "Tube",
+        /// Folds everything above full scale back down instead of clipping it.
+        Foldback => "Foldback",
+        /// A plain hard clipper.
+        HardClip => "Hard Clip",
+        /// A tanh curve with magnetic memory: part of the output lags behind the input whenever it
+        /// changes direction, which smears loud passages together, followed by a gentle high
+        /// frequency loss. Quiet signals come out slightly softer than with the other models.
+        TapeHysteresis => "Tape Hysteresis",
+    }
+}
+
+/// The DC bias added before the tape model's tanh curve.
+const TAPE_BIAS: f32 = 0.2;
+/// How much harder the tube model clips the negative half of the waveform.
+const TUBE_ASYMMETRY: f32 = 1.5;
+/// How far the tape hysteresis model's input needs to move back before the lagging part follows
+/// it again after a change of direction, in the driven signal's scale.
+const HYSTERESIS_WIDTH: f32 = 0.05;
+/// The part of the tape hysteresis model's output that lags behind the input.
+const HYSTERESIS_DEPTH: f32 = 0.3;
+
+choice! {
+    /// How the saturator suppresses aliasing. Antiderivative antialiasing (ADAA) is a lot cheaper
+    /// than oversampling, at the cost of a half (first order) or full (second order) sample of
+    /// delay in the wet signal. Only the cubic model has an ADAA formulation, the other models
+    /// always run without it.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum AntiAliasing {
+        Off => "Off",
+        FirstOrder => "ADAA 1st Order",
+        SecondOrder => "ADAA 2nd Order",
+    }
+}
+
+choice! {
+    /// Trades CPU usage for processing quality. This doesn't change the plugin's character, so the
+    /// same settings can run cheaply while tracking and at the best quality for the final bounce.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Quality {
+        /// Updates the filters less often, recalculates the band detectors' time constants along
+        /// with them, and only looks at sample peaks in the output limiter.
+        Eco => "Eco",
+        Normal => "Normal",
+        /// Updates the filters more often and runs the saturators at twice the sample rate instead
+        /// of using ADAA. The oversampling adds [`OVERSAMPLING_LATENCY`] samples of latency.
+        High => "High",
+    }
+}
+
+impl Quality {
+    /// How often the crossover and reactive EQ coefficients are recalculated, in samples. The
+    /// filters glide linearly between the targets. The interval is scaled with the sample rate so
+    /// the filters follow the controls and the compressors at the same speed at any sample rate.
+    pub fn coefficient_update_interval(self, sample_rate: f32) -> usize {
+        let interval_at_reference_rate = match self {
+            Quality::Eco => 64.0,
+            Quality::Normal => 32.0,
+            Quality::High => 8.0,
+        };
+
+        ((interval_at_reference_rate * sample_rate / REFERENCE_SAMPLE_RATE).round() as usize).max(1)
+    }
+
+    /// Whether the saturators run at twice the sample rate.
+    pub fn oversampling(self) -> bool {
+        self == Quality::High
+    }
+
+    /// Whether the band detectors' time constants are recalculated for every sample. Otherwise
+    /// they're only recalculated along with the filter coefficients.
+    pub fn per_sample_time_constants(self) -> bool {
+        self != Quality::Eco
+    }
+
+    /// Whether the output limiter estimates the inter-sample peaks.
+    pub fn true_peak_detection(self) -> bool {
+        self != Quality::Eco
+    }
+}
+
+impl AntiAliasing {
+    /// Delays an unsaturated signal by the same half or full sample the ADAA saturators add, so it
+    /// lines up with the saturated signal. `previous` holds the channel's previous sample.
+    pub fn align(self, sample: f32, previous: &mut f32) -> f32 {
+        let previous = core::mem::replace(previous, sample);
+        match self {
+            AntiAliasing::Off => sample,
+            // Averaging two samples is a half sample delay
+            AntiAliasing::FirstOrder => (sample + previous) / 2.0,
+            AntiAliasing::SecondOrder => previous,
+        }
+    }
+}
+
+/// Below this distance between two input samples the ADAA difference quotients become
+/// ill-conditioned, and the saturator falls back to evaluating the curve directly.
+const ADAA_EPSILON: f64 = 1.0e-5;
+
+/// The saturator's last two input samples for a single channel, used for ADAA.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AdaaState {
+    x1: f64,
+    x2: f64,
+}
+
+/// The tape hysteresis model's memory for a single channel.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct HysteresisState {
+    /// The lagging input, which only follows the driven input once it has moved more than
+    /// [`HYSTERESIS_WIDTH`] away. This is a play operator, so the loop's shape doesn't depend on
+    /// the signal's frequency or the sample rate.
+    magnetization: f32,
+}
+
+impl HysteresisState {
+    /// Drags the lagging input along with `input` and returns it.
+    fn follow(&mut self, input: f32) -> f32 {
+        self.magnetization = self
+            .magnetization
+            .clamp(input - HYSTERESIS_WIDTH, input + HYSTERESIS_WIDTH);
+
+        self.magnetization
+    }
+}
+
+/// A one-pole low-pass filter for the tape hysteresis model's high frequency loss.
+#[derive(Clone, Copy)]
+pub struct TapeRolloff {
+    alpha: f32,
+    states: [f32; MAX_CHANNELS],
+}
+
+impl Default for TapeRolloff {
+    fn default() -> Self {
+        let mut rolloff = Self {
+            alpha: 1.0,
+            states: [0.0; MAX_CHANNELS],
+        };
+        rolloff.set_sample_rate(44100.0);
+
+        rolloff
+    }
+}
+
+impl TapeRolloff {
+    /// Updates the coefficient for a new sample rate, keeping the cutoff at [`TAPE_ROLLOFF_HZ`].
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.alpha = 1.0 - (-2.0 * core::f32::consts::PI * TAPE_ROLLOFF_HZ / sample_rate).exp();
+    }
+
+    /// Processes a single sample for a specific channel.
+    pub fn process(&mut self, channel: usize, sample: f32) -> f32 {
+        let state = &mut self.states[channel];
+        *state += self.alpha * (sample - *state);
+
+        *state
+    }
+
+    /// Clears the filter state for all channels.
+    pub fn reset(&mut self) {
+        self.states = [0.0; MAX_CHANNELS];
+    }
+}
+
+/// A multi-model saturator with soft clipping.
+/// The intensity of the saturation is linked to the `amount` parameter, and `drive_scale` is a
+/// linear multiplier on top of that for per-band drive offsets (1.0 leaves the drive unchanged).
+/// All models have a small-signal gain of roughly the drive. `adaa_state` and `hysteresis_state`
+/// are always updated so the model and the anti-aliasing mode can be switched without clicks.
+/// The tape hysteresis model's high frequency loss is left to the caller, see [`TapeRolloff`].
+pub fn saturate(
+    sample: f32,
+    amount: f32,
+    drive_scale: f32,
+    model: SaturationModel,
+    anti_aliasing: AntiAliasing,
+    adaa_state: &mut AdaaState,
+    hysteresis_state: &mut HysteresisState,
+) -> f32 {
+    // The 'drive' determines how hard the signal is pushed into the saturator.
+    // It scales from a gentle 0.1 to a full 1.0 as `amount` goes from 0 to 1.
+    let drive = (amount.powf(1.5) * 0.9 + 0.1) * drive_scale;
+    let magnetization = hysteresis_state.follow(drive * sample);
+
+    let out = match model {
+        // This is a cubic waveshaper, a common and computationally cheap way to add
+        // odd-order harmonics, characteristic of many analog saturation circuits.
+        SaturationModel::Cubic => {
+            let (drive, x, x1, x2) = (drive as f64, sample as f64, adaa_state.x1, adaa_state.x2);
+            let out = match anti_aliasing {
+                AntiAliasing::Off => cubic(drive, x),
+                AntiAliasing::FirstOrder => cubic_adaa1(drive, x, x1),
+                AntiAliasing::SecondOrder => cubic_adaa2(drive, x, x1, x2),
+            };
+
+            out as f32
+        }
+        // The bias shifts the operating point so the curve compresses one half of the waveform
+        // slightly more than the other. Subtracting the biased zero point keeps silence silent.
+        SaturationModel::Tape => (drive * sample + TAPE_BIAS).tanh() - TAPE_BIAS.tanh(),
+        SaturationModel::Tube => {
+            let x = drive * sample;
+            if x >= 0.0 {
+                1.0 - (-x).exp()
+            } else {
+                -(1.0 - (x * TUBE_ASYMMETRY).exp()) / TUBE_ASYMMETRY
+            }
+        }
+        // A triangle fold at full scale
+        SaturationModel::Foldback => 1.0 - ((drive * sample + 1.0).rem_euclid(4.0) - 2.0).abs(),
+        SaturationModel::HardClip => (drive * sample).clamp(-1.0, 1.0),
+        // Blending in the lagging curve opens up a hysteresis loop around the tanh curve
+        SaturationModel::TapeHysteresis => {
+            let anhysteretic = (drive * sample).tanh();
+            anhysteretic + HYSTERESIS_DEPTH * (magnetization.tanh() - anhysteretic)
+        }
+    };
+    adaa_state.x2 = adaa_state.x1;
+    adaa_state.x1 = sample as f64;
+
+    // A final soft-clipping stage tames the output, with the clipping becoming
+    // gentler as `amount` increases, to prevent harshness at extreme settings.
+    (out * (1.0 - amount * 0.3)).clamp(-1.0, 1.0)
+}
+
+/// The cubic waveshaper curve.
+fn cubic(drive: f64, x: f64) -> f64 {
+    drive * x - (drive.powi(2) / 3.0) * x.powi(3)
+}
+
+/// The first antiderivative of [`cubic()`].
+fn cubic_ad1(drive: f64, x: f64) -> f64 {
+    drive * x.powi(2) / 2.0 - (drive.powi(2) / 12.0) * x.powi(4)
+}
+
+/// The second antiderivative of [`cubic()`].
+fn cubic_ad2(drive: f64, x: f64) -> f64 {
+    drive * x.powi(3) / 6.0 - (drive.powi(2) / 60.0) * x.powi(5)
+}
+
+/// First order ADAA of [`cubic()`]: the average of the curve over the line between the previous
+/// and the current input sample.
+fn cubic_adaa1(drive: f64, x: f64, x1: f64) -> f64 {
+    let dx = x - x1;
+    if dx.abs() > ADAA_EPSILON {
+        (cubic_ad1(drive, x) - cubic_ad1(drive, x1)) / dx
+    } else {
+        cubic(drive, (x + x1) / 2.0)
+    }
+}
+
+/// Second order ADAA of [`cubic()`]: the curve averaged with a triangular kernel centered on the
+/// previous input sample.
+fn cubic_adaa2(drive: f64, x: f64, x1: f64, x2: f64) -> f64 {
+    let difference_quotient = |a: f64, b: f64| {
+        if (a - b).abs() > ADAA_EPSILON {
+            (cubic_ad2(drive, a) - cubic_ad2(drive, b)) / (a - b)
+        } else {
+            cubic_ad1(drive, (a + b) / 2.0)
+        }
+    };
+
+    if (x - x2).abs() > ADAA_EPSILON {
+        2.0 * (difference_quotient(x, x1) - difference_quotient(x1, x2)) / (x - x2)
+    } else {
+        // The input went to `x1` and came straight back, so both halves of the kernel cover the
+        // same line
+        let dx = x - x1;
+        if dx.abs() > ADAA_EPSILON {
+            2.0 * (cubic_ad2(drive, x) - cubic_ad2(drive, x1) - dx * cubic_ad1(drive, x1))
+                / dx.powi(2)
+        } else {
+            cubic(drive, x1)
+        }
+    }
+}
+
+/// The last [`HALFBAND_SIDE_TAPS`] samples of a signal. Every sample is written twice so the
+/// samples can always be read as one contiguous slice.
+#[derive(Clone, Copy)]
+struct SampleHistory {
+    samples: [f32; HALFBAND_SIDE_TAPS * 2],
+    pos: usize,
+}
+
+impl Default for SampleHistory {
+    fn default() -> Self {
+        Self {
+            samples: [0.0; HALFBAND_SIDE_TAPS * 2],
+            pos: 0,
+        }
+    }
+}
+
+impl SampleHistory {
+    fn push(&mut self, sample: f32) {
+        self.pos = (self.pos + 1) % HALFBAND_SIDE_TAPS;
+        self.samples[self.pos] = sample;
+        self.samples[self.pos + HALFBAND_SIDE_TAPS] = sample;
+    }
+
+    /// The samples from the oldest to the newest.
+    fn window(&self) -> &[f32] {
+        &self.samples[(self.pos + 1)..=(self.pos + HALFBAND_SIDE_TAPS)]
+    }
+}
+
+/// The oversampler's state for a single channel.
+#[derive(Clone, Copy, Default)]
+struct OversamplerChannel {
+    /// The input at the original rate.
+    input: SampleHistory,
+    /// The processed signal at the oversampled rate, split into the samples that line up with the
+    /// input samples and the samples in between them.
+    even: SampleHistory,
+    odd: SampleHistory,
+}
+
+/// A 2x oversampler built from a linear-phase half-band FIR filter, used to run the saturators at
+/// twice the sample rate. Every other tap of a half-band filter is zero, so the up- and
+/// downsampling filters are split into their non-zero taps and the center tap. A round trip
+/// delays the signal by [`OVERSAMPLING_LATENCY`] samples.
+#[derive(Clone)]
+pub struct Oversampler {
+    /// The non-zero taps besides the center tap. These are symmetric, so their order doesn't
+    /// matter.
+    side_taps: [f32; HALFBAND_SIDE_TAPS],
+    center_tap: f32,
+    channels: [OversamplerChannel; MAX_CHANNELS],
+}
+
+impl Default for Oversampler {
+    fn default() -> Self {
+        // A Blackman windowed sinc with its cutoff at the original Nyquist frequency
+        let center = (HALFBAND_TAPS - 1) / 2;
+        let tap = |k: usize| {
+            let t = (k as f32 - center as f32) / 2.0;
+            let sinc = if k == center {
+                1.0
+            } else {
+                (core::f32::consts::PI * t).sin() / (core::f32::consts::PI * t)
+            };
+            let window_pos = 2.0 * core::f32::consts::PI * k as f32 / (HALFBAND_TAPS - 1) as f32;
+            let window = 0.42 - 0.5 * window_pos.cos() + 0.08 * (2.0 * window_pos).cos();
+            sinc * window / 2.0
+        };
+        // The center tap sits at an odd index, so the non-zero side taps are the even ones
+        let mut side_taps: [f32; HALFBAND_SIDE_TAPS] = array_init::array_init(|i| tap(2 * i));
+        let mut center_tap = tap(center);
+
+        // The filter should pass DC at unity gain
+        let sum = side_taps.iter().sum::<f32>() + center_tap;
+        for tap in &mut side_taps {
+            *tap /= sum;
+        }
+        center_tap /= sum;
+
+        Self {
+            side_taps,
+            center_tap,
+            channels: [OversamplerChannel::default(); MAX_CHANNELS],
+        }
+    }
+}
+
+impl Oversampler {
+    /// Upsamples `sample`, runs both oversampled samples through `f`, and returns the downsampled
+    /// result. The result is delayed by [`OVERSAMPLING_LATENCY`] samples.
+    pub fn process(&mut self, channel: usize, sample: f32, mut f: impl FnMut(f32) -> f32) -> f32 {
+        let state = &mut self.channels[channel];
+        state.input.push(sample);
+
+        // The zero stuffing halves the level, so the upsampling filter has twice the gain
+        let input = state.input.window();
+        let even: f32 = 2.0
+            * self
+                .side_taps
+                .iter()
+                .zip(input)
+                .map(|(tap, x)| tap * x)
+                .sum::<f32>();
+        let odd = 2.0 * self.center_tap * input[HALFBAND_SIDE_TAPS / 2];
+
+        state.even.push(f(even));
+        state.odd.push(f(odd));
+
+        let even_sum: f32 = self
+            .side_taps
+            .iter()
+            .zip(state.even.window())
+            .map(|(tap, x)| tap * x)
+            .sum();
+        even_sum + (self.center_tap * state.odd.window()[HALFBAND_SIDE_TAPS / 2 - 1])
+    }
+
+    pub fn reset(&mut self) {
+        self.channels = [OversamplerChannel::default(); MAX_CHANNELS];
+    }
+}
+
+/// Computes how hard a band is processed, combining the global Amount and Tilt with the band's
+/// position and the user's per-band trim. `band_trim` scales the band's derived intensity, with
+/// 0.0 leaving it untouched and 0.5 making it 50% stronger.
+fn band_intensity(
+    band_idx: usize,
+    num_bands: usize,
+    amount: f32,
+    tilt: f32,
+    band_trim: f32,
+    character: Character,
+) -> f32 {
+    // Tilt Bias: This determines how much the 'Tilt' control affects the processing
+    // intensity for this specific band.
+    // A non-linear curve makes the tilt feel more responsive and impactful at the extremes.
+    // More processing on tilted-towards bands: the lower half of the bands follows negative tilt,
+    // the upper half follows positive tilt, and the center band (if any) is left alone.
+    let tilt_effect = tilt.abs().powf(1.5) * tilt.signum();
+    let tilt_bias = (1.0
+        + tilt_effect * character.tables().tilt_bias_depth * band_side(band_idx, num_bands))
+    .clamp(0.2, 1.8f32);
+
+    // Band Frequency Factor: Depending on the character, some bands get compressed more heavily
+    // by default.
+    let freq_factor = band_freq_factor(band_idx, num_bands, character);
+
+    // The final intensity is a combination of the main 'Amount', the 'Tilt' bias,
+    // the inherent frequency factor of the band, and the user's per-band trim.
+    amount * tilt_bias * freq_factor * (1.0 + band_trim)
+}
+
+/// Computes target gain reduction (in linear gain, 0 to 1) for a band. See [`band_intensity()`]
+/// for the meaning of `band_trim`, and [`CrestTracker::adaptation()`] for `adaptation`.
+#[allow(clippy::too_many_arguments)]
+pub fn calculate_target_gr(
+    band_idx: usize,
+    num_bands: usize,
+    amount: f32,
+    tilt: f32,
+    band_trim: f32,
+    envelope: f32,
+    character: Character,
+    adaptation: IntensityAdaptation,
+) -> f32 {
+    // --- 1. Dynamic Parameter Calculation based on Amount and Tilt ---
+    let intensity = band_intensity(band_idx, num_bands, amount, tilt, band_trim, character);
+
+    // Threshold: The compression threshold drops as intensity increases, meaning more
+    // of the signal gets compressed.
+    let threshold_db =
+        -10.0 - (25.0 * intensity) - (tilt * -5.0 * (band_position(band_idx, num_bands) * 0.5));
+    // Auto Intensity moves the threshold along with the program level
+    let threshold_db = threshold_db + adaptation.threshold_offset_db;
+
+    // Ratio: The compression ratio increases non-linearly with 'Amount' for a more
+    // aggressive "squash" at higher settings. The character decides how far and how quickly.
+    let tables = character.tables();
+    let ratio =
+        1.1 + (tables.ratio_range * amount.powf(tables.ratio_curve) * adaptation.ratio_scale);
+
+    // Knee: The compressor knee widens as 'Amount' increases, providing a smoother,
+    // more "musical" transition into compression at lower settings.
+    let knee_db = KNEE_MAX_DB * amount.powf(1.5);
+
+    // --- 2. Gain Computer (Simplified Soft-Knee) ---
+    // This is a standard gain computer formula with a soft knee.
+    let input_db = util::gain_to_db(envelope);
+    let gr_db = if input_db < threshold_db - (knee_db / 2.0) {
+        // Below Knee (No GR)
+        0.0
+    } else if input_db > threshold_db + (knee_db / 2.0) {
+        // Above Knee (Hard Ratio GR)
+        (threshold_db - input_db) * (1.0 - (1.0 / ratio))
+    } else {
+        // Inside Knee (Soft Knee GR)
+        // This is a quadratic interpolation for the soft knee.
+        let knee_range = knee_db; // Use a separate variable for clarity
+        let x = input_db - (threshold_db - knee_range / 2.0);
+        -(1.0 - 1.0 / ratio) * (x * x) / (2.0 * knee_range)
+    };
+
+    // The final gain reduction factor must be non-positive (i.e., attenuation only).
+    util::db_to_gain(gr_db.min(0.0))
+}
+
+/// Computes the target upward compression gain (in linear gain, 1 and up) for a band. This is the
+/// mirror image of [`calculate_target_gr()`]: everything below the upward threshold is brought up
+/// towards it. The adaptation only moves the threshold, the noise floor stays where it is.
+#[allow(clippy::too_many_arguments)]
+pub fn calculate_target_upward_gain(
+    band_idx: usize,
+    num_bands: usize,
+    amount: f32,
+    tilt: f32,
+    band_trim: f32,
+    envelope: f32,
+    character: Character,
+    adaptation: IntensityAdaptation,
+) -> f32 {
+    let intensity = band_intensity(band_idx, num_bands, amount, tilt, band_trim, character);
+
+    // Threshold: The upward threshold rises with intensity so more of the quiet material gets
+    // lifted. It sits well below the downward threshold so the two don't fight each other.
+    let threshold_db = -40.0 + (15.0 * intensity) + adaptation.threshold_offset_db;
+
+    // Ratio: Upward compression gets loud quickly, so its ratio is gentler than the downward one.
+    let ratio = 1.1 + (3.0 * amount.powf(1.5));
+
+    let input_db = util::gain_to_db(envelope);
+    let gain_db =
+        ((threshold_db - input_db) * (1.0 - (1.0 / ratio))).clamp(0.0, UPWARD_MAX_GAIN_DB);
+
+    // Fade the boost out towards the noise floor so silence and reverb tails don't get pumped up.
+    let floor_fade = ((input_db - UPWARD_FLOOR_DB) / 20.0).clamp(0.0, 1.0);
+
+    util::db_to_gain(gain_db * floor_fade)
+}
+
+/// Computes the target downward expansion gain (in linear gain, 0 to 1) for a band. Everything
+/// below `threshold_db` is pushed further down, by at most [`EXPANDER_RANGE_DB`], so at high
+/// Amount settings the expander closes like a gate.
+pub fn calculate_target_expander_gain(amount: f32, threshold_db: f32, envelope: f32) -> f32 {
+    // Ratio: From a gentle 1.5:1 expander at low Amount settings up to a 10:1 gate
+    let ratio = 1.5 + (8.5 * amount.powi(2));
+
+    let input_db = util::gain_to_db(envelope);
+    let gain_db = ((input_db - threshold_db) * (ratio - 1.0)).clamp(-EXPANDER_RANGE_DB, 0.0);
+
+    util::db_to_gain(gain_db)
+}
+
+/// Computes the transient shaper gain (in linear gain) from the difference between a fast and a
+/// slow envelope. The fast envelope jumps ahead of the slow one on every attack and drops below it
+/// during the sustain, so a positive `depth` boosts attacks and shortens the sustain, while a
+/// negative `depth` softens attacks and brings the sustain up.
+pub fn calculate_transient_gain(depth: f32, fast_envelope: f32, slow_envelope: f32) -> f32 {
+    let difference_db = util::gain_to_db(fast_envelope) - util::gain_to_db(slow_envelope);
+    let gain_db = (depth * difference_db).clamp(-TRANSIENT_MAX_GAIN_DB, TRANSIENT_MAX_GAIN_DB);
+
+    util::db_to_gain(gain_db)
+}
+
+/// Computes how much of the exciter's harmonics gets blended into the top band. Like Punch, Air
+/// has some effect at low Amount settings and reaches full depth with Amount all the way up.
+/// Tilting towards the high bands adds up to 50% more sheen, and tilting towards the lows takes up
+/// to half of it away.
+pub fn calculate_air_gain(air: f32, amount: f32, tilt: f32) -> f32 {
+    air * (0.25 + (0.75 * amount)) * (1.0 + (0.5 * tilt))
+}
+
+/// Computes the noise layer's gain for a band. The noise follows the band's envelope, so it swells
+/// and fades with the band instead of sitting underneath it, and like Air it grows with Amount.
+pub fn calculate_noise_gain(noise: f32, amount: f32, envelope_level: f32) -> f32 {
+    noise * util::db_to_gain(NOISE_MAX_DB) * (0.25 + (0.75 * amount)) * envelope_level
+}
+
+choice! {
+    /// The shape of the envelope followers' attack or release.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum EnvelopeCurve {
+        /// The classic analog-style one-pole curve. It slows down as it gets closer to the target,
+        /// which makes for a soft, smooth response.
+        Exponential => "Exponential",
+        /// Moves at a constant rate in decibels and stops at the target instead of creeping up on
+        /// it, for a harder, more obvious attack or release.
+        LinearDb => "Linear dB",
+        /// Eases in and out of every change: starts slowly, speeds up, and settles smoothly.
+        SCurve => "S-Curve",
+    }
+}
+
+/// Moves an envelope follower towards `target` by one sample along `curve`. `alpha` is the
+/// exponential curve's coefficient for the attack or release time. `stage` is the S-curve's
+/// intermediate state, which is kept in sync with the envelope by the other curves so the curves
+/// can be switched at any time.
+pub fn follow_envelope(
+    envelope: &mut Real,
+    stage: &mut Real,
+    target: Real,
+    alpha: Real,
+    curve: EnvelopeCurve,
+) {
+    match curve {
+        EnvelopeCurve::Exponential => {
+            *envelope += alpha * (target - *envelope);
+            *stage = *envelope;
+        }
+        // The constant rate matches the exponential curve's release into silence. It never moves
+        // slower than the exponential curve, so attacks from silence aren't held back.
+        EnvelopeCurve::LinearDb => {
+            let exponential_step = alpha * (target - *envelope).abs();
+            *envelope = if target > *envelope {
+                let linear_db_step = *envelope * alpha / (1.0 - alpha).max(Real::EPSILON);
+                (*envelope + exponential_step.max(linear_db_step)).min(target)
+            } else {
+                let linear_db_step = *envelope * alpha;
+                (*envelope - exponential_step.max(linear_db_step)).max(target)
+            };
+            *stage = *envelope;
+        }
+        // Two one-pole stages at half the time constant each. Their combined step response is
+        // S-shaped and reaches about the same point after the time constant as a single stage.
+        EnvelopeCurve::SCurve => {
+            let stage_alpha = 1.0 - (1.0 - alpha) * (1.0 - alpha);
+            *stage += stage_alpha * (target - *stage);
+            *envelope += stage_alpha * (*stage - *envelope);
+        }
+    }
+}
+
+/// The opto release model's fast first slope runs until the envelope has fallen to this fraction of
+/// where the release started.
+const OPTO_KNEE: Real = 0.5;
+/// How much faster than the release time the opto release's first slope moves.
+const OPTO_FAST_SPEED: Real = 2.0;
+/// How much slower than the release time the opto release's tail moves after a quiet peak. Louder
+/// peaks slow it down further, down to half this speed at full scale.
+const OPTO_TAIL_SPEED: Real = 0.5;
+/// How much faster than the release time the FET release moves.
+const FET_RELEASE_SPEED: Real = 2.0;
+/// How far below the detector level the FET release aims, so it overshoots slightly before the
+/// attack catches it again.
+const FET_OVERSHOOT: Real = 0.1;
+
+choice! {
+    /// The modeled release behavior of the band compressors. These change how fast the envelope
+    /// followers release, the Attack Curve and Release Curve still set the shape of every step.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum ReleaseModel {
+        /// A clean, constant release at the release time. This is the original ColorFall behavior.
+        Vca => "VCA",
+        /// An optical cell's two-stage release: a fast initial recovery followed by a slow tail
+        /// that gets slower the harder the band was hit.
+        Opto => "Opto",
+        /// A fast release that dips slightly past the signal level before settling, for an
+        /// aggressive, lively sound.
+        Fet => "FET",
+    }
+}
+
+impl ReleaseModel {
+    /// Adjusts a release step of an envelope follower. `release_peak` is where the envelope was
+    /// when the release started. Returns the target and the coefficient to release towards.
+    pub fn release_step(
+        self,
+        envelope: Real,
+        release_peak: Real,
+        target: Real,
+        alpha: Real,
+    ) -> (Real, Real) {
+        match self {
+            ReleaseModel::Vca => (target, alpha),
+            ReleaseModel::Opto => {
+                let speed = if envelope > OPTO_KNEE * release_peak {
+                    OPTO_FAST_SPEED
+                } else {
+                    OPTO_TAIL_SPEED / (1.0 + release_peak.min(1.0))
+                };
+                (target, (alpha * speed).min(1.0))
+            }
+            ReleaseModel::Fet => (
+                target * (1.0 - FET_OVERSHOOT),
+                (alpha * FET_RELEASE_SPEED).min(1.0),
+            ),
+        }
+    }
+}
+
+/// Calculates dynamic attack/release times in samples based on Amount and Frequency. The times are
+/// worked out in milliseconds from the band's center frequency, which [`band_center_frequency()`]
+/// keeps in the audible range, so they're the same at every sample rate.
+pub fn calculate_dynamic_time_constants(
+    sample_rate: f32,
+    band_center_freq: f32,
+    _band_idx: usize,
+    amount: f32,
+) -> (f32, f32) {
+    // Frequency Scaling: Higher frequencies get faster times. The scaling levels off two octaves
+    // either side of the reference, so the outer bands don't get extreme times.
+    let freq_scale = (band_center_freq / TIME_CONSTANT_REFERENCE_HZ)
+        .sqrt()
+        .clamp(0.5, 2.0);
+
+    // Amount Scaling: Higher amount means faster dynamics (more aggressive 'snap')
+    // A non-linear curve makes the control feel more responsive and musical.
+    let amount_scale = 1.0 - (amount.powf(0.75) * 0.8);
+
+    // Base Attack (ms): 1ms (fast) to 20ms (slow)
+    let attack_ms = (20.0 * amount_scale) / freq_scale.powf(0.5);
+
+    // Base Release (ms): 50ms (fast) to 400ms (slow)
+    let release_ms = (300.0 * amount_scale) / freq_scale.powf(1.5);
+
+    // Convert ms to samples/sample_rate
+    let attack_samples = sample_rate * (attack_ms / 1000.0);
+    let release_samples = sample_rate * (release_ms / 1000.0);
+
+    (attack_samples, release_samples)
+}
+// --- OUTPUT LIMITER ---
+
+/// A true-peak level detector. The signal is 4x oversampled with a polyphase interpolation filter
+/// to estimate the peaks between the samples, as specified by ITU-R BS.1770.
+pub struct TruePeakDetector {
+    /// The polyphase interpolation filter used to estimate the inter-sample peaks, one set of
+    /// taps per oversampled phase. The taps are ordered from the oldest to the newest sample.
+    interpolation_taps: [[f32; TRUE_PEAK_TAPS]; TRUE_PEAK_OVERSAMPLING],
+    /// The last `TRUE_PEAK_TAPS` input samples for every channel. Every sample is written twice so
+    /// the most recent samples can always be read as one contiguous slice.
+    history: [[f32; TRUE_PEAK_TAPS * 2]; MAX_CHANNELS],
+    history_pos: usize,
+}
+
+impl Default for TruePeakDetector {
+    fn default() -> Self {
+        // A Blackman windowed sinc with its cutoff at the original Nyquist frequency
+        let num_taps = TRUE_PEAK_TAPS * TRUE_PEAK_OVERSAMPLING;
+        let center = (num_taps - 1) as f32 / 2.0;
+        let mut interpolation_taps = [[0.0; TRUE_PEAK_TAPS]; TRUE_PEAK_OVERSAMPLING];
+        for (phase, taps) in interpolation_taps.iter_mut().enumerate() {
+            for (tap_idx, tap) in taps.iter_mut().enumerate() {
+                // Tap 0 is applied to the oldest sample in the window
+                let k = (TRUE_PEAK_TAPS - 1 - tap_idx) * TRUE_PEAK_OVERSAMPLING + phase;
+                let t = (k as f32 - center) / TRUE_PEAK_OVERSAMPLING as f32;
+                let sinc = if t.abs() < 1.0e-6 {
+                    1.0
+                } else {
+                    (core::f32::consts::PI * t).sin() / (core::f32::consts::PI * t)
+                };
+                let window_pos = 2.0 * core::f32::consts::PI * k as f32 / (num_taps - 1) as f32;
+                let window = 0.42 - 0.5 * window_pos.cos() + 0.08 * (2.0 * window_pos).cos();
+                *tap = sinc * window;
+            }
+
+            // Every phase should pass DC at unity gain
+            let phase_sum: f32 = taps.iter().sum();
+            for tap in taps.iter_mut() {
+                *tap /= phase_sum;
+            }
+        }
+
+        Self {
+            interpolation_taps,
+            history: [[0.0; TRUE_PEAK_TAPS * 2]; MAX_CHANNELS],
+            history_pos: 0,
+        }
+    }
+}
+
+impl TruePeakDetector {
+    /// Clears the sample history.
+    pub fn reset(&mut self) {
+        self.history = [[0.0; TRUE_PEAK_TAPS * 2]; MAX_CHANNELS];
+        self.history_pos = 0;
+    }
+
+    /// Returns the highest absolute level across the frame's channels, in linear gain. The
+    /// interpolated peaks lag behind the input by about half of `TRUE_PEAK_TAPS`. Without
+    /// `true_peak` only the sample peaks are measured.
+    pub fn process(
+        &mut self,
+        frame: &[f32; MAX_CHANNELS],
+        num_channels: usize,
+        true_peak: bool,
+    ) -> f32 {
+        let mut peak = 0.0f32;
+        for (channel, &sample) in frame.iter().enumerate().take(num_channels) {
+            let history = &mut self.history[channel];
+            history[self.history_pos] = sample;
+            history[self.history_pos + TRUE_PEAK_TAPS] = sample;
+            let window = &history[(self.history_pos + 1)..=(self.history_pos + TRUE_PEAK_TAPS)];
+
+            // The history is kept up to date either way so switching modes doesn't use stale
+            // samples
+            peak = peak.max(sample.abs());
+            if !true_peak {
+                continue;
+            }
+            for taps in &self.interpolation_taps {
+                let interpolated: f32 = taps.iter().zip(window).map(|(tap, x)| tap * x).sum();
+                peak = peak.max(interpolated.abs());
+            }
+        }
+        self.history_pos = (self.history_pos + 1) % TRUE_PEAK_TAPS;
+
+        peak
+    }
+}
+
+/// A brickwall limiter with 4x oversampled true-peak detection and lookahead. All channels share a
+/// single gain so the limiter doesn't shift the stereo or surround image.
+pub struct TruePeakLimiter {
+    detector: TruePeakDetector,
+    /// The audio delay that gives the gain computer its lookahead.
+    delay: DelayLine,
+
+    /// The required gains over the lookahead window plus the interpolation filter's delay, used
+    /// to hold the lowest gain until the peak it belongs to has left the delay line.
+    required_gains: Vec<f32>,
+    required_gains_pos: usize,
+    /// The held gains over the lookahead window. Averaging these gives a smooth attack that is
+    /// guaranteed to reach the held gain in time.
+    held_gains: Vec<f32>,
+    held_gains_pos: usize,
+    held_gains_sum: f64,
+
+    gain: f32,
+    release_alpha: f32,
+}
+
+impl Default for TruePeakLimiter {
+    fn default() -> Self {
+        Self {
+            detector: TruePeakDetector::default(),
+            delay: DelayLine::default(),
+            required_gains: Vec::new(),
+            required_gains_pos: 0,
+            held_gains: Vec::new(),
+            held_gains_pos: 0,
+            held_gains_sum: 0.0,
+            gain: 1.0,
+            release_alpha: 1.0,
+        }
+    }
+}
+
+impl TruePeakLimiter {
+    /// Sizes the lookahead buffers for the sample rate. This allocates, so it should only be
+    /// called from `initialize()`.
+    pub fn initialize(&mut self, sample_rate: f32) {
+        let lookahead = ((sample_rate * LIMITER_LOOKAHEAD_MS / 1000.0).round() as usize).max(1);
+        // The interpolation filter lags behind the input by about half its length, so the gain
+        // is held for that much longer
+        let hold_length = lookahead + (TRUE_PEAK_TAPS / 2);
+
+        self.delay.set_length(hold_length - 1);
+        self.required_gains = vec![1.0; hold_length];
+        self.held_gains = vec![1.0; lookahead];
+        self.release_alpha = 1.0 - (-1.0 / (sample_rate * LIMITER_RELEASE_MS / 1000.0)).exp();
+
+        self.reset();
+    }
+
+    /// The latency introduced by the lookahead, in samples.
+    pub fn latency_samples(&self) -> u32 {
+        self.delay.len() as u32
+    }
+
+    /// Clears the limiter's buffers and releases all gain reduction.
+    pub fn reset(&mut self) {
+        self.detector.reset();
+        self.delay.reset();
+        self.required_gains.fill(1.0);
+        self.required_gains_pos = 0;
+        self.held_gains.fill(1.0);
+        self.held_gains_pos = 0;
+        self.held_gains_sum = self.held_gains.len() as f64;
+        self.gain = 1.0;
+    }
+
+    /// Limits a single frame so its true peak level stays below `ceiling` (in linear gain). The
+    /// frame is delayed by [`TruePeakLimiter::latency_samples()`]. Without `true_peak` only the
+    /// sample peaks are limited, which is cheaper but lets inter-sample peaks through.
+    pub fn process(
+        &mut self,
+        frame: &mut [f32; MAX_CHANNELS],
+        num_channels: usize,
+        ceiling: f32,
+        true_peak: bool,
+    ) {
+        if self.held_gains.is_empty() || self.delay.is_empty() {
+            return;
+        }
+
+        // --- 1. True Peak Detection ---
+        let peak = self.detector.process(frame, num_channels, true_peak);
+
+        // --- 2. Gain Computer ---
+        let required_gain = if peak > ceiling { ceiling / peak } else { 1.0 };
+        self.required_gains[self.required_gains_pos] = required_gain;
+        self.required_gains_pos = (self.required_gains_pos + 1) % self.required_gains.len();
+        let held_gain = self.required_gains.iter().copied().fold(1.0, f32::min);
+
+        self.held_gains_sum += held_gain as f64 - self.held_gains[self.held_gains_pos] as f64;
+        self.held_gains[self.held_gains_pos] = held_gain;
+        self.held_gains_pos = (self.held_gains_pos + 1) % self.held_gains.len();
+        let attack_gain = (self.held_gains_sum / self.held_gains.len() as f64) as f32;
+
+        // Attacks follow the averaged gain exactly, releases are smoothed further
+        self.gain = if attack_gain < self.gain {
+            attack_gain
+        } else {
+            self.gain + self.release_alpha * (attack_gain - self.gain)
+        };
+
+        // --- 3. Delay and Apply ---
+        self.delay.process(frame, num_channels);
+        for sample in &mut frame[..num_channels] {
+            *sample *= self.gain;
+        }
+    }
+}
+
+/// A multichannel delay line. The buffers are sized up front, and the delay can then be changed
+/// anywhere up to that length without allocating.
+#[derive(Default, Clone)]
+pub struct DelayLine {
+    buffers: [Vec<f32>; MAX_CHANNELS],
+    length: usize,
+    pos: usize,
+}
+
+impl DelayLine {
+    /// Resizes the delay line to `length` samples and clears it. This allocates.
+    pub fn set_length(&mut self, length: usize) {
+        for buffer in &mut self.buffers {
+            *buffer = vec![0.0; length];
+        }
+        self.length = length;
+        self.pos = 0;
+    }
+
+    /// Changes the delay to `length` samples, at most the length passed to
+    /// [`DelayLine::set_length()`], and clears the delay line. This doesn't allocate.
+    pub fn set_delay(&mut self, length: usize) {
+        self.length = length.min(self.buffers[0].len());
+        self.reset();
+    }
+
+    /// The delay in samples.
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    /// Whether the delay line has a length of zero, in which case it passes audio through as is.
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    /// Writes a frame into the delay line and replaces it with the frame from `len()` samples ago.
+    pub fn process(&mut self, frame: &mut [f32; MAX_CHANNELS], num_channels: usize) {
+        if self.is_empty() {
+            return;
+        }
+
+        for (channel, sample) in frame.iter_mut().enumerate().take(num_channels) {
+            *sample = core::mem::replace(&mut self.buffers[channel][self.pos], *sample);
+        }
+        self.pos = (self.pos + 1) % self.len();
+    }
+
+    /// Clears the delay line.
+    pub fn reset(&mut self) {
+        for buffer in &mut self.buffers {
+            buffer.fill(0.0);
+        }
+        self.pos = 0;
+    }
+}
@@ -0,0 +1,289 @@
+//! # K-Weighted Loudness Measurement
+//!
+//! This module implements the ITU-R BS.1770 K-weighting prefilters and a short-term loudness
+//! tracker. The automatic loudness compensation uses these to compare the dry and wet signals by
+//! perceived loudness, so it isn't dominated by low frequency energy the way a raw power match is.
+//! The same filters, along with an A-weighting approximation, can also weight the band detectors.
+#[cfg(not(feature = "std"))]
+use crate::math::Float;
+use crate::{Biquad, BiquadCoefficients, MAX_CHANNELS, Real};
+
+/// The length of a loudness measurement segment. The short-term window is made up of these.
+const SEGMENT_MS: f32 = 100.0;
+/// The number of segments in the 3 second short-term loudness window.
+const SHORT_TERM_SEGMENTS: usize = 30;
+
+/// The mean square below which a signal counts as silent. This is the -70 LUFS absolute gate
+/// from BS.1770.
+pub const SILENCE_MEAN_SQUARE: f32 = 1.17e-7;
+
+/// The makeup gain that brings a signal measuring `wet_mean_square` to the loudness of one
+/// measuring `dry_mean_square`. `strength` scales the gain in decibels, so 0.5 applies half of it.
+/// If either signal is silent the gain is left at 1.
+pub fn correction_gain(dry_mean_square: f32, wet_mean_square: f32, strength: f32) -> f32 {
+    if dry_mean_square > SILENCE_MEAN_SQUARE && wet_mean_square > SILENCE_MEAN_SQUARE {
+        (dry_mean_square / wet_mean_square).sqrt().powf(strength)
+    } else {
+        1.0
+    }
+}
+
+/// Calculates the coefficients for the first K-weighting stage, a high shelf of about +4 dB that
+/// models the acoustic effect of the head.
+fn k_weighting_shelf(sample_rate: f32) -> BiquadCoefficients {
+    // The analog prototype from BS.1770, matched at any sample rate
+    let f0 = 1681.974450955533_f64;
+    let gain_db = 3.999843853973347_f64;
+    let q = 0.7071752369554196_f64;
+
+    let k = (core::f64::consts::PI * f0 / sample_rate as f64).tan();
+    let vh = 10.0f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+
+    BiquadCoefficients {
+        b0: ((vh + vb * k / q + k * k) / a0) as Real,
+        b1: (2.0 * (k * k - vh) / a0) as Real,
+        b2: ((vh - vb * k / q + k * k) / a0) as Real,
+        a1: (2.0 * (k * k - 1.0) / a0) as Real,
+        a2: ((1.0 - k / q + k * k) / a0) as Real,
+    }
+}
+
+/// Calculates the coefficients for the second K-weighting stage, the RLB high-pass filter at
+/// about 38 Hz.
+fn k_weighting_highpass(sample_rate: f32) -> BiquadCoefficients {
+    let f0 = 38.13547087602444_f64;
+    let q = 0.5003270373238773_f64;
+
+    let k = (core::f64::consts::PI * f0 / sample_rate as f64).tan();
+    let a0 = 1.0 + k / q + k * k;
+
+    BiquadCoefficients {
+        b0: 1.0,
+        b1: -2.0,
+        b2: 1.0,
+        a1: (2.0 * (k * k - 1.0) / a0) as Real,
+        a2: ((1.0 - k / q + k * k) / a0) as Real,
+    }
+}
+
+/// The A-weighting curve's pole frequencies. The curve is made of a double pole at the lowest
+/// frequency, single poles at the two middle frequencies, and a double pole at the highest one.
+const A_WEIGHTING_POLES_HZ: [f64; 4] = [20.598997, 107.65265, 737.86223, 12194.217];
+
+/// A second order high-pass or low-pass filter with the given pole frequency and Q, matched at
+/// `f0` with a prewarped bilinear transform.
+fn weighting_stage(sample_rate: f32, f0: f64, q: f64, highpass: bool) -> BiquadCoefficients {
+    // Poles above Nyquist can't be matched, so they're pulled just below it
+    let f0 = f0.min(sample_rate as f64 * 0.45);
+    let k = (core::f64::consts::PI * f0 / sample_rate as f64).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let (b0, b1, b2) = if highpass {
+        (1.0, -2.0, 1.0)
+    } else {
+        (k * k, 2.0 * k * k, k * k)
+    };
+
+    BiquadCoefficients {
+        b0: (b0 / a0) as Real,
+        b1: (b1 / a0) as Real,
+        b2: (b2 / a0) as Real,
+        a1: (2.0 * (k * k - 1.0) / a0) as Real,
+        a2: ((1.0 - k / q + k * k) / a0) as Real,
+    }
+}
+
+/// Calculates the three stages of the A-weighting approximation, normalized to unity gain at
+/// 1 kHz.
+fn a_weighting_stages(sample_rate: f32) -> [BiquadCoefficients; 3] {
+    let [f1, f2, f3, f4] = A_WEIGHTING_POLES_HZ;
+    // Two single poles make a second order filter at their geometric mean
+    let middle_f0 = (f2 * f3).sqrt();
+    let mut stages = [
+        // A double pole is a second order filter with a Q of 0.5
+        weighting_stage(sample_rate, f1, 0.5, true),
+        weighting_stage(sample_rate, middle_f0, middle_f0 / (f2 + f3), true),
+        weighting_stage(sample_rate, f4, 0.5, false),
+    ];
+
+    let gain_at_1k: f32 = stages
+        .iter()
+        .map(|stage| stage.magnitude_at(sample_rate, 1000.0))
+        .product();
+    let normalization = (1.0 / gain_at_1k.max(1.0e-6)) as Real;
+    stages[2].b0 *= normalization;
+    stages[2].b1 *= normalization;
+    stages[2].b2 *= normalization;
+
+    stages
+}
+
+choice! {
+    /// A perceptual weighting curve for the band detectors.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Weighting {
+        /// The detectors see the band as it is.
+        Flat => "Flat",
+        /// The BS.1770 K-weighting curve, which rolls off the deep lows and lifts the highs a bit.
+        K => "K-Weighting",
+        /// An approximation of the A-weighting curve, which rolls off the lows much more steeply.
+        A => "A-Weighting",
+    }
+}
+
+/// Weights a detector signal with one of the [`Weighting`] curves.
+#[derive(Clone)]
+pub struct WeightingFilter {
+    stages: [Biquad; 3],
+    /// The number of stages the current weighting uses.
+    num_stages: usize,
+    weighting: Weighting,
+    sample_rate: f32,
+}
+
+impl Default for WeightingFilter {
+    fn default() -> Self {
+        Self {
+            stages: [Biquad::default(); 3],
+            num_stages: 0,
+            weighting: Weighting::Flat,
+            sample_rate: 0.0,
+        }
+    }
+}
+
+impl WeightingFilter {
+    /// Switches to a different weighting or sample rate. The coefficients are only recalculated
+    /// when either of them actually changes.
+    pub fn update(&mut self, weighting: Weighting, sample_rate: f32) {
+        if weighting == self.weighting && sample_rate == self.sample_rate {
+            return;
+        }
+        self.weighting = weighting;
+        self.sample_rate = sample_rate;
+
+        let coefficients: &[BiquadCoefficients] = match weighting {
+            Weighting::Flat => &[],
+            Weighting::K => &[
+                k_weighting_shelf(sample_rate),
+                k_weighting_highpass(sample_rate),
+            ],
+            Weighting::A => &a_weighting_stages(sample_rate),
+        };
+        self.num_stages = coefficients.len();
+        for (stage, &coefs) in self.stages.iter_mut().zip(coefficients) {
+            stage.set_coefficients(coefs);
+            stage.reset();
+        }
+    }
+
+    /// Weights a frame in place.
+    pub fn process(&mut self, frame: &mut [f32; MAX_CHANNELS], num_channels: usize) {
+        for stage in &mut self.stages[..self.num_stages] {
+            stage.process(frame, num_channels);
+        }
+    }
+
+    pub fn reset(&mut self) {
+        for stage in &mut self.stages {
+            stage.reset();
+        }
+    }
+}
+
+/// The BS.1770 weight for a channel. Surround channels count for about 1.5 dB more, and the LFE
+/// channel of the 5.1 and 7.1 layouts is left out entirely.
+fn channel_weight(channel: usize, num_channels: usize) -> f32 {
+    if num_channels < 6 {
+        return 1.0;
+    }
+
+    match channel {
+        0..=2 => 1.0,
+        3 => 0.0,
+        _ => 1.41,
+    }
+}
+
+/// Tracks the short-term (3 second) K-weighted loudness of a multichannel signal.
+pub struct LoudnessTracker {
+    shelf: Biquad,
+    highpass: Biquad,
+
+    /// The weighted power summed over the current segment.
+    segment_sum: f32,
+    segment_len: usize,
+    segment_pos: usize,
+
+    /// The mean squares of the last completed segments.
+    segments: [f32; SHORT_TERM_SEGMENTS],
+    segments_pos: usize,
+}
+
+impl Default for LoudnessTracker {
+    fn default() -> Self {
+        let mut tracker = Self {
+            shelf: Biquad::default(),
+            highpass: Biquad::default(),
+            segment_sum: 0.0,
+            segment_len: 1,
+            segment_pos: 0,
+            segments: [0.0; SHORT_TERM_SEGMENTS],
+            segments_pos: 0,
+        };
+        tracker.set_sample_rate(44100.0);
+
+        tracker
+    }
+}
+
+impl LoudnessTracker {
+    /// Updates the K-weighting filters and the segment length for a new sample rate.
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.shelf.set_coefficients(k_weighting_shelf(sample_rate));
+        self.highpass
+            .set_coefficients(k_weighting_highpass(sample_rate));
+        self.segment_len = ((sample_rate * SEGMENT_MS / 1000.0).round() as usize).max(1);
+    }
+
+    /// Clears the filters and the measurement window.
+    pub fn reset(&mut self) {
+        self.shelf.reset();
+        self.highpass.reset();
+        self.segment_sum = 0.0;
+        self.segment_pos = 0;
+        self.segments = [0.0; SHORT_TERM_SEGMENTS];
+        self.segments_pos = 0;
+    }
+
+    /// Adds a frame to the measurement.
+    pub fn process(&mut self, frame: &[f32; MAX_CHANNELS], num_channels: usize) {
+        let mut weighted = *frame;
+        self.shelf.process(&mut weighted, num_channels);
+        self.highpass.process(&mut weighted, num_channels);
+        for (channel, &sample) in weighted.iter().enumerate().take(num_channels) {
+            self.segment_sum += channel_weight(channel, num_channels) * sample * sample;
+        }
+
+        self.segment_pos += 1;
+        if self.segment_pos >= self.segment_len {
+            self.segments[self.segments_pos] = self.segment_sum / self.segment_len as f32;
+            self.segments_pos = (self.segments_pos + 1) % SHORT_TERM_SEGMENTS;
+            self.segment_sum = 0.0;
+            self.segment_pos = 0;
+        }
+    }
+
+    /// Whether the last frame completed a measurement segment. The
+    /// [`mean_square()`][Self::mean_square()] only changes when this happens.
+    pub fn segment_completed(&self) -> bool {
+        self.segment_pos == 0
+    }
+
+    /// The weighted mean square over the short-term window. Loudness ratios can be computed
+    /// directly from this without going through LUFS.
+    pub fn mean_square(&self) -> f32 {
+        self.segments.iter().sum::<f32>() / SHORT_TERM_SEGMENTS as f32
+    }
+}
@@ -0,0 +1,133 @@
+//! The floating point math functions for `no_std` builds. Without the standard library, `f32` and
+//! `f64` don't have methods like `sin()` or `exp()`, so [`Float`] provides them through `libm`
+//! under the same names. Modules that use them import the trait when the `std` feature is off, and
+//! the code itself stays the same either way.
+
+/// The standard library's float methods that the DSP uses, implemented with `libm`.
+pub trait Float: Sized {
+    fn powf(self, n: Self) -> Self;
+    fn powi(self, n: i32) -> Self;
+    fn sqrt(self) -> Self;
+    fn exp(self) -> Self;
+    fn ln(self) -> Self;
+    fn log10(self) -> Self;
+    fn sin(self) -> Self;
+    fn cos(self) -> Self;
+    fn sin_cos(self) -> (Self, Self);
+    fn tan(self) -> Self;
+    fn atan2(self, other: Self) -> Self;
+    fn tanh(self) -> Self;
+    fn rem_euclid(self, rhs: Self) -> Self;
+    fn round(self) -> Self;
+    fn floor(self) -> Self;
+}
+
+macro_rules! impl_float {
+    (
+        $ty:ty,
+        $powf:ident,
+        $sqrt:ident,
+        $exp:ident,
+        $ln:ident,
+        $log10:ident,
+        $sin:ident,
+        $cos:ident,
+        $sin_cos:ident,
+        $tan:ident,
+        $atan2:ident,
+        $tanh:ident,
+        $fmod:ident,
+        $round:ident,
+        $floor:ident
+    ) => {
+        impl Float for $ty {
+            #[inline]
+            fn powf(self, n: Self) -> Self {
+                libm::$powf(self, n)
+            }
+
+            #[inline]
+            fn powi(self, n: i32) -> Self {
+                libm::$powf(self, n as $ty)
+            }
+
+            #[inline]
+            fn sqrt(self) -> Self {
+                libm::$sqrt(self)
+            }
+
+            #[inline]
+            fn exp(self) -> Self {
+                libm::$exp(self)
+            }
+
+            #[inline]
+            fn ln(self) -> Self {
+                libm::$ln(self)
+            }
+
+            #[inline]
+            fn log10(self) -> Self {
+                libm::$log10(self)
+            }
+
+            #[inline]
+            fn sin(self) -> Self {
+                libm::$sin(self)
+            }
+
+            #[inline]
+            fn cos(self) -> Self {
+                libm::$cos(self)
+            }
+
+            #[inline]
+            fn sin_cos(self) -> (Self, Self) {
+                libm::$sin_cos(self)
+            }
+
+            #[inline]
+            fn tan(self) -> Self {
+                libm::$tan(self)
+            }
+
+            #[inline]
+            fn atan2(self, other: Self) -> Self {
+                libm::$atan2(self, other)
+            }
+
+            #[inline]
+            fn tanh(self) -> Self {
+                libm::$tanh(self)
+            }
+
+            #[inline]
+            fn rem_euclid(self, rhs: Self) -> Self {
+                let remainder = libm::$fmod(self, rhs);
+                if remainder < 0.0 {
+                    remainder + rhs.abs()
+                } else {
+                    remainder
+                }
+            }
+
+            #[inline]
+            fn round(self) -> Self {
+                libm::$round(self)
+            }
+
+            #[inline]
+            fn floor(self) -> Self {
+                libm::$floor(self)
+            }
+        }
+    };
+}
+
+impl_float!(
+    f32, powf, sqrtf, expf, logf, log10f, sinf, cosf, sincosf, tanf, atan2f, tanhf, fmodf, roundf,
+    floorf
+);
+impl_float!(
+    f64, pow, sqrt, exp, log, log10, sin, cos, sincos, tan, atan2, tanh, fmod, round, floor
+);
@@ -0,0 +1,27 @@
+//! Level conversions. These match nih-plug's, so the engine measures and applies levels the same
+//! way inside and outside the plugin.
+
+#[cfg(not(feature = "std"))]
+use crate::math::Float;
+
+/// Levels at or below this are treated as silence.
+pub const MINUS_INFINITY_DB: f32 = -100.0;
+/// [`MINUS_INFINITY_DB`] as a linear gain.
+pub const MINUS_INFINITY_GAIN: f32 = 1e-5;
+
+/// Converts a level in decibels to a linear gain. Levels at or below [`MINUS_INFINITY_DB`] give 0.
+#[inline]
+pub fn db_to_gain(dbs: f32) -> f32 {
+    if dbs > MINUS_INFINITY_DB {
+        10.0f32.powf(dbs * 0.05)
+    } else {
+        0.0
+    }
+}
+
+/// Converts a linear gain to a level in decibels. Gains below [`MINUS_INFINITY_GAIN`] give
+/// [`MINUS_INFINITY_DB`].
+#[inline]
+pub fn gain_to_db(gain: f32) -> f32 {
+    f32::max(gain, MINUS_INFINITY_GAIN).log10() * 20.0
+}
@@ -0,0 +1,138 @@
+//! Renders WAV files through ColorFall, for batch processing and for comparing the output between
+//! versions of the DSP. The whole plugin runs offline through [`OfflineRenderer`], so the output
+//! is what the plugin would produce in a host with the same settings.
+//!
+//! Run with `--help` to see the available options.
+use colorfall::OfflineRenderer;
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: colorfall-render [options] <input.wav> <output.wav>
+
+Renders a WAV file through ColorFall and writes the result as a 32-bit float WAV file. Mono,
+stereo, 5.1 and 7.1 files are supported. The plugin's latency is compensated for, so the output
+lines up with the input.
+
+Options:
+  --amount <value>    The Amount, like 60%
+  --tilt <value>      The Tilt, from -1 to 1
+  --mix <value>       The dry/wet Mix, like 50%
+  --preset <file>     Applies a preset file with one `parameter_id = value` line per setting,
+                      before any of the other options
+  --set <id>=<value>  Sets any other parameter by its ID, like `--set saturation=Tape`
+  -h, --help          Prints this message
+
+Parameters are set with the same text the plugin displays. The parameters the snapshot morph
+leaves out, like the quality mode, always use their defaults.";
+
+fn main() -> ExitCode {
+    match run(std::env::args().skip(1).collect()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: Vec<String>) -> Result<(), String> {
+    let mut paths = Vec::new();
+    let mut preset_path = None;
+    // Parameter IDs and values, in the order they're given
+    let mut settings = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("'{arg}' needs a value"));
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return Ok(());
+            }
+            "--amount" => settings.push((String::from("amount"), value()?)),
+            "--tilt" => settings.push((String::from("tilt"), value()?)),
+            "--mix" => settings.push((String::from("mix"), value()?)),
+            "--preset" => preset_path = Some(value()?),
+            "--set" => {
+                let setting = value()?;
+                let (id, value) = setting
+                    .split_once('=')
+                    .ok_or_else(|| format!("Expected '<id>=<value>', got '{setting}'"))?;
+                settings.push((id.to_owned(), value.to_owned()));
+            }
+            _ if arg.starts_with('-') => return Err(format!("Unknown option '{arg}'\n\n{USAGE}")),
+            _ => paths.push(arg),
+        }
+    }
+    let [input_path, output_path] = <[String; 2]>::try_from(paths)
+        .map_err(|_| format!("Expected an input and an output file\n\n{USAGE}"))?;
+
+    let (mut channels, sample_rate) = read_wav(&input_path)?;
+    let mut renderer = OfflineRenderer::new(sample_rate as f32, channels.len())?;
+    if let Some(preset_path) = preset_path {
+        let preset = std::fs::read_to_string(&preset_path)
+            .map_err(|err| format!("Couldn't read '{preset_path}': {err}"))?;
+        renderer
+            .apply_preset(&preset)
+            .map_err(|err| format!("{preset_path}: {err}"))?;
+    }
+    for (id, value) in &settings {
+        renderer.set_parameter(id, value)?;
+    }
+
+    renderer.render(&mut channels);
+    write_wav(&output_path, &channels, sample_rate)
+}
+
+/// Reads a WAV file into one buffer per channel, along with its sample rate.
+fn read_wav(path: &str) -> Result<(Vec<Vec<f32>>, u32), String> {
+    let read_error = |err: hound::Error| format!("Couldn't read '{path}': {err}");
+    let mut reader = hound::WavReader::open(path).map_err(read_error)?;
+    let spec = reader.spec();
+    let samples: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>(),
+        hound::SampleFormat::Int => {
+            let scale = 1.0 / (1u64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|sample| sample.map(|sample| sample as f32 * scale))
+                .collect()
+        }
+    }
+    .map_err(read_error)?;
+
+    let num_channels = spec.channels as usize;
+    let channels = (0..num_channels)
+        .map(|channel| {
+            samples
+                .iter()
+                .skip(channel)
+                .step_by(num_channels)
+                .copied()
+                .collect()
+        })
+        .collect();
+
+    Ok((channels, spec.sample_rate))
+}
+
+/// Writes the channels to a 32-bit float WAV file.
+fn write_wav(path: &str, channels: &[Vec<f32>], sample_rate: u32) -> Result<(), String> {
+    let write_error = |err: hound::Error| format!("Couldn't write '{path}': {err}");
+    let spec = hound::WavSpec {
+        channels: channels.len() as u16,
+        sample_rate,
+        bits_per_sample: 32,
+        sample_format: hound::SampleFormat::Float,
+    };
+    let mut writer = hound::WavWriter::create(path, spec).map_err(write_error)?;
+    let num_samples = channels.first().map_or(0, Vec::len);
+    for sample_idx in 0..num_samples {
+        for channel in channels {
+            writer
+                .write_sample(channel[sample_idx])
+                .map_err(write_error)?;
+        }
+    }
+
+    writer.finalize().map_err(write_error)
+}
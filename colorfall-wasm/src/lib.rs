@@ -0,0 +1,146 @@
+//! # ColorFall for WebAssembly
+//!
+//! Exports [`ColorFallEngine`] through plain C functions, so a browser demo can run the real DSP.
+//! AudioWorklets can't fetch anything or load JS glue code, so the module doesn't import anything
+//! and can be instantiated straight from a compiled `WebAssembly.Module` inside the worklet.
+//! `web/colorfall-processor.js` is the matching `AudioWorkletProcessor`.
+//!
+//! The JS side holds on to the pointer [`colorfall_new()`] returns and passes it to every other
+//! function. Audio goes through the engine's own buffers in the module's memory: every render
+//! quantum is copied into [`colorfall_left_buffer()`] and [`colorfall_right_buffer()`], processed in
+//! place by [`colorfall_process()`], and copied back out.
+use colorfall_dsp::{
+    Character, Choice, ColorFallEngine, CrossoverSlope, EngineParams, SaturationModel,
+};
+
+/// The most samples a single [`colorfall_process()`] call can process. Web Audio renders in quanta
+/// of 128 samples.
+pub const MAX_BLOCK_SIZE: usize = 1024;
+
+/// The engine and its stereo I/O buffers.
+pub struct WasmEngine {
+    engine: ColorFallEngine,
+    left: Box<[f32]>,
+    right: Box<[f32]>,
+}
+
+/// Creates an engine for audio at `sample_rate` with the default settings. Free it with
+/// [`colorfall_free()`].
+#[unsafe(no_mangle)]
+pub extern "C" fn colorfall_new(sample_rate: f32) -> *mut WasmEngine {
+    Box::into_raw(Box::new(WasmEngine {
+        engine: ColorFallEngine::new(sample_rate),
+        left: vec![0.0; MAX_BLOCK_SIZE].into_boxed_slice(),
+        right: vec![0.0; MAX_BLOCK_SIZE].into_boxed_slice(),
+    }))
+}
+
+/// Frees an engine created with [`colorfall_new()`].
+///
+/// # Safety
+///
+/// `engine` needs to come from [`colorfall_new()`], and can't be used again afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn colorfall_free(engine: *mut WasmEngine) {
+    // SAFETY: The caller hands back ownership of the engine
+    drop(unsafe { Box::from_raw(engine) });
+}
+
+/// The left channel's buffer, [`MAX_BLOCK_SIZE`] samples long.
+///
+/// # Safety
+///
+/// `engine` needs to be a live engine from [`colorfall_new()`]. The buffer lives as long as the
+/// engine does.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn colorfall_left_buffer(engine: *mut WasmEngine) -> *mut f32 {
+    // SAFETY: See above
+    unsafe { (*engine).left.as_mut_ptr() }
+}
+
+/// The right channel's buffer, [`MAX_BLOCK_SIZE`] samples long.
+///
+/// # Safety
+///
+/// See [`colorfall_left_buffer()`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn colorfall_right_buffer(engine: *mut WasmEngine) -> *mut f32 {
+    // SAFETY: See above
+    unsafe { (*engine).right.as_mut_ptr() }
+}
+
+/// Processes the first `num_samples` samples of the buffers in place. Longer blocks are cut off at
+/// [`MAX_BLOCK_SIZE`].
+///
+/// # Safety
+///
+/// `engine` needs to be a live engine from [`colorfall_new()`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn colorfall_process(engine: *mut WasmEngine, num_samples: usize) {
+    // SAFETY: See above
+    let engine = unsafe { &mut *engine };
+    let num_samples = num_samples.min(MAX_BLOCK_SIZE);
+    engine.engine.process_block(
+        &mut engine.left[..num_samples],
+        &mut engine.right[..num_samples],
+    );
+}
+
+/// Changes the settings. See [`EngineParams`] for their meaning. The choices are indices into
+/// the options' [`Choice::NAMES`], and `auto_gain` is 0 for off and anything else for on.
+///
+/// # Safety
+///
+/// `engine` needs to be a live engine from [`colorfall_new()`].
+#[unsafe(no_mangle)]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn colorfall_set_params(
+    engine: *mut WasmEngine,
+    amount: f32,
+    tilt: f32,
+    mix: f32,
+    output_db: f32,
+    ceiling_db: f32,
+    auto_gain: u32,
+    num_bands: u32,
+    character: u32,
+    saturation: u32,
+    crossover_slope: u32,
+) {
+    // SAFETY: See above
+    let engine = unsafe { &mut *engine };
+    engine.engine.set_params(EngineParams {
+        amount,
+        tilt,
+        mix,
+        output_db,
+        ceiling_db,
+        auto_gain: auto_gain != 0,
+        num_bands: num_bands as usize,
+        character: Character::from_index(character as usize),
+        saturation: SaturationModel::from_index(saturation as usize),
+        crossover_slope: CrossoverSlope::from_index(crossover_slope as usize),
+    });
+}
+
+/// Clears the engine's state, for when playback restarts.
+///
+/// # Safety
+///
+/// `engine` needs to be a live engine from [`colorfall_new()`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn colorfall_reset(engine: *mut WasmEngine) {
+    // SAFETY: See above
+    unsafe { (*engine).engine.reset() };
+}
+
+/// The engine's latency in samples.
+///
+/// # Safety
+///
+/// `engine` needs to be a live engine from [`colorfall_new()`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn colorfall_latency_samples(engine: *const WasmEngine) -> u32 {
+    // SAFETY: See above
+    unsafe { (*engine).engine.latency_samples() }
+}
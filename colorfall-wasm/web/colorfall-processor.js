@@ -0,0 +1,92 @@
+// Runs ColorFall's DSP engine inside an AudioWorklet. The compiled `colorfall_wasm.wasm` module
+// is passed in through `processorOptions.module`, since worklets can't fetch it themselves:
+//
+//     const module = await WebAssembly.compileStreaming(fetch("colorfall_wasm.wasm"));
+//     await context.audioWorklet.addModule("colorfall-processor.js");
+//     const node = new AudioWorkletNode(context, "colorfall", {
+//       outputChannelCount: [2],
+//       processorOptions: { module },
+//     });
+//
+// The continuous settings are AudioParams. The switches and choices are sent through the port as
+// an object with any of `autoGain`, `bands`, `character`, `saturation` and `crossoverSlope`, where
+// the choices are indices into the option lists in `colorfall-dsp`.
+
+class ColorFallProcessor extends AudioWorkletProcessor {
+  static get parameterDescriptors() {
+    return [
+      { name: "amount", defaultValue: 0.4, minValue: 0, maxValue: 1 },
+      { name: "tilt", defaultValue: 0, minValue: -1, maxValue: 1 },
+      { name: "mix", defaultValue: 1, minValue: 0, maxValue: 1 },
+      { name: "output", defaultValue: 0, minValue: -24, maxValue: 24 },
+      { name: "ceiling", defaultValue: 0, minValue: -12, maxValue: 0 },
+    ].map((descriptor) => ({ ...descriptor, automationRate: "k-rate" }));
+  }
+
+  constructor(options) {
+    super();
+    const instance = new WebAssembly.Instance(options.processorOptions.module, {});
+    this.wasm = instance.exports;
+    this.engine = this.wasm.colorfall_new(sampleRate);
+    this.settings = {
+      autoGain: true,
+      bands: 5,
+      character: 0,
+      saturation: 0,
+      crossoverSlope: 1,
+    };
+    this.port.onmessage = (event) => {
+      if (event.data === "reset") {
+        this.wasm.colorfall_reset(this.engine);
+      } else {
+        Object.assign(this.settings, event.data);
+      }
+    };
+    this.port.postMessage({ latency: this.wasm.colorfall_latency_samples(this.engine) });
+  }
+
+  process(inputs, outputs, parameters) {
+    const input = inputs[0];
+    const output = outputs[0];
+    const numSamples = output[0].length;
+
+    const settings = this.settings;
+    this.wasm.colorfall_set_params(
+      this.engine,
+      parameters.amount[0],
+      parameters.tilt[0],
+      parameters.mix[0],
+      parameters.output[0],
+      parameters.ceiling[0],
+      settings.autoGain ? 1 : 0,
+      settings.bands,
+      settings.character,
+      settings.saturation,
+      settings.crossoverSlope,
+    );
+
+    // The views are created for every quantum, since they go stale if the module's memory grows
+    const memory = this.wasm.memory.buffer;
+    const left = new Float32Array(memory, this.wasm.colorfall_left_buffer(this.engine), numSamples);
+    const right = new Float32Array(memory, this.wasm.colorfall_right_buffer(this.engine), numSamples);
+    // Mono inputs feed both channels, and a disconnected input is silence
+    if (input.length > 0) {
+      left.set(input[0]);
+      right.set(input[input.length > 1 ? 1 : 0]);
+    } else {
+      left.fill(0);
+      right.fill(0);
+    }
+
+    this.wasm.colorfall_process(this.engine, numSamples);
+
+    output[0].set(left);
+    if (output.length > 1) {
+      output[1].set(right);
+    }
+
+    return true;
+  }
+}
+
+registerProcessor("colorfall", ColorFallProcessor);
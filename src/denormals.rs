@@ -0,0 +1,102 @@
+//! # Denormal Handling
+//!
+//! Filter states and envelope followers that decay towards silence end up as denormal numbers,
+//! which most CPUs process many times slower than normal numbers. Instead of nudging the signal
+//! away from zero, [`ScopedFtz`] switches the CPU to flush denormals to zero for the duration of a
+//! `process()` call.
+use std::marker::PhantomData;
+
+/// Enables flush-to-zero mode (and denormals-are-zero mode on x86) until it's dropped, after which
+/// the previous floating point mode is restored. The mode only applies to the current thread, so
+/// this can't be sent to another thread.
+pub struct ScopedFtz {
+    /// The floating point control register from before FTZ was enabled, if it had to be changed.
+    previous: Option<arch::ControlRegister>,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl ScopedFtz {
+    /// Enables flush-to-zero mode for the current thread.
+    pub fn enable() -> Self {
+        let current = arch::read();
+        let previous = if current & arch::FLUSH_BITS != arch::FLUSH_BITS {
+            arch::write(current | arch::FLUSH_BITS);
+            Some(current)
+        } else {
+            None
+        };
+
+        Self {
+            previous,
+            _not_send: PhantomData,
+        }
+    }
+}
+
+impl Drop for ScopedFtz {
+    fn drop(&mut self) {
+        if let Some(previous) = self.previous {
+            arch::write(previous);
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod arch {
+    use std::arch::asm;
+
+    pub type ControlRegister = u32;
+
+    /// The MXCSR flush-to-zero (bit 15) and denormals-are-zero (bit 6) flags.
+    pub const FLUSH_BITS: ControlRegister = (1 << 15) | (1 << 6);
+
+    pub fn read() -> ControlRegister {
+        let mut mxcsr: ControlRegister = 0;
+        // SAFETY: Storing MXCSR only writes to the provided location
+        unsafe { asm!("stmxcsr [{}]", in(reg) &mut mxcsr, options(nostack, preserves_flags)) };
+        mxcsr
+    }
+
+    pub fn write(mxcsr: ControlRegister) {
+        // SAFETY: Only the rounding and exception handling modes are changed, which Rust code
+        // doesn't rely on
+        unsafe { asm!("ldmxcsr [{}]", in(reg) &mxcsr, options(nostack, preserves_flags)) };
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod arch {
+    use std::arch::asm;
+
+    pub type ControlRegister = u64;
+
+    /// The FPCR flush-to-zero flag. On ARM this also treats denormal inputs as zero.
+    pub const FLUSH_BITS: ControlRegister = 1 << 24;
+
+    pub fn read() -> ControlRegister {
+        let fpcr: ControlRegister;
+        // SAFETY: Reading FPCR has no side effects
+        unsafe { asm!("mrs {}, fpcr", out(reg) fpcr, options(nomem, nostack, preserves_flags)) };
+        fpcr
+    }
+
+    pub fn write(fpcr: ControlRegister) {
+        // SAFETY: Only the rounding and exception handling modes are changed, which Rust code
+        // doesn't rely on
+        unsafe { asm!("msr fpcr, {}", in(reg) fpcr, options(nomem, nostack, preserves_flags)) };
+    }
+}
+
+/// Other architectures keep their default floating point mode.
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+mod arch {
+    pub type ControlRegister = u32;
+
+    pub const FLUSH_BITS: ControlRegister = 0;
+
+    pub fn read() -> ControlRegister {
+        0
+    }
+
+    pub fn write(_: ControlRegister) {}
+}
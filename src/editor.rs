@@ -1,38 +1,556 @@
-use nih_plug::prelude::{AtomicF32, Editor};
+use nih_plug::prelude::{util, AtomicF32, Editor, Param};
+use nih_plug_vizia::vizia::vg;
 use nih_plug_vizia::widgets::*;
-use nih_plug_vizia::{assets, create_vizia_editor, ViziaState, ViziaTheming};
+use nih_plug_vizia::{assets, create_vizia_editor, ViziaTheming};
 use std::sync::Arc;
-use std::sync::atomic::Ordering;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 
+use colorfall_dsp::balance::TargetCurve;
+use colorfall_dsp::Choice;
 use nih_plug_vizia::vizia::prelude::*;
 
-use crate::{ColorFallParams, TILT_MAX_SHIFT_SEMITONES};
+use crate::morph::{self, Snapshot};
+use crate::presets::{self, Preset};
+use crate::{
+    ColorFallParams, EqCurve, MeterLevels, ScopeBuffer, TargetLearn, TiltSuggestion, MAX_BANDS,
+    TILT_MAX_SHIFT_SEMITONES,
+};
+
+mod dice;
+mod gesture;
+mod goniometer;
+mod knob;
+mod oscilloscope;
+
+use dice::DiceTarget;
+use gesture::ParamGesture;
+use goniometer::Goniometer;
+use knob::ParamKnob;
+use oscilloscope::Oscilloscope;
+
+/// The help bar's text when the mouse isn't over any control.
+const DEFAULT_HELP_TEXT: &str =
+    "Hover over a control to see what it does, or click ? to see the signal flow.";
+
+/// The stages of the signal flow overlay, in processing order.
+const SIGNAL_FLOW: [(&str, &str); 5] = [
+    (
+        "Band Split",
+        "The optional Tilt EQ tilts the input, then crossovers split it into 3 to 6 bands.",
+    ),
+    (
+        "Saturate",
+        "Every band is saturated, driven harder as Amount goes up.",
+    ),
+    (
+        "Compress",
+        "Every band is compressed. Tilt moves the focus to the low or high bands.",
+    ),
+    (
+        "Reactive EQ",
+        "A peaking filter per band boosts what its compressor takes away.",
+    ),
+    (
+        "Loudness Comp",
+        "The result is matched to the input's loudness, mixed with the dry signal and limited.",
+    ),
+];
+
+/// The editor sizes the scale button steps through, as factors on top of the system's scale.
+/// nih-plug stores the chosen factor in [`ColorFallParams::editor_state`], so the size is restored
+/// with the plugin's state.
+const GUI_SCALES: [f64; 6] = [0.75, 1.0, 1.25, 1.5, 1.75, 2.0];
+
+/// The editor's color themes. The theme is stored in [`ColorFallParams::editor_theme`] so it is
+/// restored with the plugin's state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Data)]
+enum EditorTheme {
+    Dark,
+    Light,
+    HighContrast,
+}
+
+impl EditorTheme {
+    const ALL: [EditorTheme; 3] = [
+        EditorTheme::Dark,
+        EditorTheme::Light,
+        EditorTheme::HighContrast,
+    ];
+
+    /// The theme stored at `index`, falling back to the dark theme for unknown indices.
+    fn from_index(index: u32) -> Self {
+        Self::ALL
+            .get(index as usize)
+            .copied()
+            .unwrap_or(EditorTheme::Dark)
+    }
+
+    fn index(self) -> u32 {
+        self as u32
+    }
+
+    /// The theme that comes after this one in the theme selector.
+    fn next(self) -> Self {
+        Self::from_index((self.index() + 1) % Self::ALL.len() as u32)
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            EditorTheme::Dark => "Dark",
+            EditorTheme::Light => "Light",
+            EditorTheme::HighContrast => "High Contrast",
+        }
+    }
+
+    /// The class that applies the theme's stylesheet to the editor's root view.
+    fn class(self) -> &'static str {
+        match self {
+            EditorTheme::Dark => "theme-dark",
+            EditorTheme::Light => "theme-light",
+            EditorTheme::HighContrast => "theme-high-contrast",
+        }
+    }
+}
 
 #[derive(Lens)]
 struct Data {
     params: Arc<ColorFallParams>,
     gain_reduction: Arc<AtomicF32>,
+    meter_levels: Arc<MeterLevels>,
+    tilt_suggestion: Arc<TiltSuggestion>,
+    target_learn: Arc<TargetLearn>,
+
+    /// The help text for the control under the mouse.
+    help_text: String,
+    /// Whether the signal flow overlay is shown.
+    show_signal_flow: bool,
+    theme: EditorTheme,
+    /// The user scale factor, one of [`GUI_SCALES`] unless the host or an older version set it to
+    /// something else.
+    gui_scale: f64,
+    /// Mirrors [`ColorFallParams::learn_target`].
+    learn_target: TargetCurve,
+    /// Mirrors [`ColorFallParams::dice_seed`] and [`ColorFallParams::dice_locks`].
+    dice_seed: u64,
+    dice_locks: u32,
+    /// Tells the audio thread to pick up new morph snapshots.
+    morph_snapshots_changed: Arc<AtomicBool>,
+    /// Whether snapshots A and B are stored.
+    morph_snapshots_stored: [bool; 2],
+
+    /// Whether the preset browser is shown.
+    show_presets: bool,
+    /// The factory presets followed by the user presets. This is refreshed whenever the browser
+    /// is opened.
+    presets: Vec<Preset>,
+    /// The names of `presets`, for the browser's list.
+    preset_names: Vec<String>,
+    /// Mirrors [`ColorFallParams::preset_name`].
+    current_preset: String,
+    /// The name in the browser's text field, used by Save As.
+    save_name: String,
+    /// The settings and the current preset from before the browser previewed its first preset,
+    /// so Revert can bring them back.
+    preview_revert: Option<(Snapshot, String)>,
+    /// The outcome of the last preset action, shown at the bottom of the browser.
+    preset_status: String,
+}
+
+enum EditorEvent {
+    /// Shows the help text for the control the mouse entered.
+    ShowHelp(&'static str),
+    /// Resets the help bar when the mouse leaves a control.
+    ClearHelp,
+    ToggleSignalFlow,
+    /// Switches to the next color theme.
+    NextTheme,
+    /// Steps to the next size in [`GUI_SCALES`].
+    NextGuiScale,
+    /// Starts listening for Suggest Tilt, or applies its suggestion once it has one.
+    SuggestTilt,
+    /// Switches Learn to the next target curve.
+    NextLearnTarget,
+    /// Starts Learn, or applies the band trims it learned once it has them.
+    LearnTarget,
+    /// Rolls the dice with a new seed.
+    RollDice,
+    /// Rolls the dice with the last seed again.
+    RecallDice,
+    ToggleDiceLock(DiceTarget),
+    /// Stores the current settings as morph snapshot A (0) or B (1).
+    StoreSnapshot(usize),
+    /// Removes both morph snapshots, handing the controls back to their own values.
+    ClearSnapshots,
+    /// Clears the output clip indicator's latched peak.
+    ResetClip,
+    /// Opens or closes the preset browser.
+    TogglePresets,
+    /// Loads the preset at this index in the browser's list.
+    LoadPreset(usize),
+    PreviousPreset,
+    NextPreset,
+    /// Goes back to the settings from before the first preview.
+    RevertPreview,
+    /// Saves the current settings to the current preset, if it's a user preset.
+    OverwritePreset,
+    /// Saves the current settings as a user preset named after the browser's text field.
+    SavePresetAs,
+    SetSaveName(String),
+}
+
+impl Model for Data {
+    fn event(&mut self, cx: &mut EventContext, event: &mut Event) {
+        event.map(|editor_event, _| match editor_event {
+            EditorEvent::ShowHelp(text) => self.help_text = text.to_string(),
+            EditorEvent::ClearHelp => self.help_text = DEFAULT_HELP_TEXT.to_string(),
+            EditorEvent::ToggleSignalFlow => self.show_signal_flow = !self.show_signal_flow,
+            EditorEvent::NextTheme => {
+                self.theme = self.theme.next();
+                self.params
+                    .editor_theme
+                    .store(self.theme.index(), Ordering::Relaxed);
+            }
+            EditorEvent::NextGuiScale => {
+                self.gui_scale = next_gui_scale(self.gui_scale);
+                // nih-plug stores the new factor in the editor state and resizes the window
+                cx.set_user_scale_factor(self.gui_scale);
+                cx.emit(GuiContextEvent::Resize);
+            }
+            EditorEvent::SuggestTilt => {
+                let suggestion = &self.tilt_suggestion;
+                let tilt = suggestion.tilt.load(Ordering::Relaxed);
+                if tilt.is_finite() && !is_listening(suggestion) {
+                    let param = &self.params.tilt;
+                    gesture::set_once(cx, param.as_ptr(), param.preview_normalized(tilt));
+                    suggestion.tilt.store(f32::NAN, Ordering::Relaxed);
+                } else {
+                    // Starts a new measurement, or starts over if it's already listening
+                    suggestion.tilt.store(f32::NAN, Ordering::Relaxed);
+                    suggestion.progress.store(0.0, Ordering::Relaxed);
+                    suggestion.start.store(true, Ordering::Relaxed);
+                }
+            }
+            EditorEvent::NextLearnTarget => {
+                let next_idx = (self.learn_target.to_index() + 1) % TargetCurve::NAMES.len();
+                self.learn_target = TargetCurve::from_index(next_idx);
+                self.params
+                    .learn_target
+                    .store(next_idx as u32, Ordering::Relaxed);
+            }
+            EditorEvent::LearnTarget => {
+                let learn = &self.target_learn;
+                if has_learned_trims(learn) && !is_learning(learn) {
+                    self.apply_learned_trims(cx);
+                } else {
+                    // Starts a new measurement, or starts over if it's already listening
+                    for trim in &learn.trims {
+                        trim.store(f32::NAN, Ordering::Relaxed);
+                    }
+                    learn
+                        .target
+                        .store(self.learn_target.to_index() as u32, Ordering::Relaxed);
+                    learn.progress.store(0.0, Ordering::Relaxed);
+                    learn.start.store(true, Ordering::Relaxed);
+                }
+            }
+            EditorEvent::RollDice => {
+                self.dice_seed = dice::new_seed(self.dice_seed);
+                self.params
+                    .dice_seed
+                    .store(self.dice_seed, Ordering::Relaxed);
+                self.roll_dice(cx);
+            }
+            EditorEvent::RecallDice => self.roll_dice(cx),
+            EditorEvent::ToggleDiceLock(target) => {
+                self.dice_locks ^= target.lock_bit();
+                self.params
+                    .dice_locks
+                    .store(self.dice_locks, Ordering::Relaxed);
+            }
+            EditorEvent::StoreSnapshot(slot_idx) => {
+                morph::store(
+                    &self.params.morph_snapshots,
+                    &self.morph_snapshots_changed,
+                    *slot_idx,
+                    Some(morph::capture(&*self.params)),
+                );
+                self.morph_snapshots_stored[*slot_idx] = true;
+            }
+            EditorEvent::ClearSnapshots => {
+                for slot_idx in 0..2 {
+                    morph::store(
+                        &self.params.morph_snapshots,
+                        &self.morph_snapshots_changed,
+                        slot_idx,
+                        None,
+                    );
+                }
+                self.morph_snapshots_stored = [false; 2];
+            }
+            EditorEvent::ResetClip => self
+                .meter_levels
+                .output_true_peak_hold
+                .store(util::MINUS_INFINITY_DB, Ordering::Relaxed),
+            EditorEvent::TogglePresets => {
+                self.show_presets = !self.show_presets;
+                if self.show_presets {
+                    self.refresh_presets();
+                    self.preset_status.clear();
+                } else {
+                    // Closing the browser keeps the previewed preset
+                    self.preview_revert = None;
+                }
+            }
+            EditorEvent::LoadPreset(preset_idx) => self.load_preset(cx, *preset_idx),
+            EditorEvent::PreviousPreset | EditorEvent::NextPreset => {
+                // Stepping wraps around at both ends of the list
+                let num_presets = self.presets.len();
+                if num_presets > 0 {
+                    let step = if matches!(editor_event, EditorEvent::PreviousPreset) {
+                        num_presets - 1
+                    } else {
+                        1
+                    };
+                    let preset_idx = self
+                        .current_preset_idx()
+                        .map_or(0, |preset_idx| (preset_idx + step) % num_presets);
+                    self.load_preset(cx, preset_idx);
+                }
+            }
+            EditorEvent::RevertPreview => {
+                if let Some((snapshot, preset_name)) = self.preview_revert.take() {
+                    self.set_params(cx, &snapshot);
+                    self.set_current_preset(preset_name);
+                    self.preset_status = String::from("Reverted to the previous settings");
+                }
+            }
+            EditorEvent::OverwritePreset => {
+                match self.current_preset_idx().map(|preset_idx| &self.presets[preset_idx]) {
+                    Some(preset) if !preset.is_factory() => {
+                        let name = preset.name.clone();
+                        self.save_preset(&name);
+                    }
+                    Some(_) => {
+                        self.preset_status =
+                            String::from("Factory presets can't be overwritten, use Save As")
+                    }
+                    None => self.preset_status = String::from("Load or save a preset first"),
+                }
+            }
+            EditorEvent::SavePresetAs => {
+                let name = self.save_name.clone();
+                self.save_preset(&name);
+            }
+            EditorEvent::SetSaveName(name) => self.save_name = name.clone(),
+        });
+    }
 }
 
-impl Model for Data {}
+impl Data {
+    /// Sets the parameters to the values in `snapshot`, with one gesture per parameter.
+    fn set_params(&self, cx: &mut EventContext, snapshot: &Snapshot) {
+        presets::apply(&*self.params, snapshot, |param, normalized| {
+            gesture::set_once(cx, param, normalized);
+        });
+    }
 
+    /// Sets the band trims Learn came up with in a single gesture, so the host records them as one
+    /// change, and clears them from the Learn button.
+    fn apply_learned_trims(&self, cx: &mut EventContext) {
+        let trims: Vec<_> = self
+            .target_learn
+            .trims
+            .iter()
+            .zip(&self.params.bands)
+            .filter_map(|(trim, band)| {
+                let trim = trim.swap(f32::NAN, Ordering::Relaxed);
+                trim.is_finite().then(|| {
+                    let param = &band.amount_trim;
+                    (param.as_ptr(), param.preview_normalized(trim))
+                })
+            })
+            .collect();
+        let param_ptrs: Vec<_> = trims.iter().map(|&(param, _)| param).collect();
+
+        let mut gesture = ParamGesture::default();
+        gesture.begin(cx, &param_ptrs);
+        for (param, normalized) in trims {
+            gesture.set(cx, param, normalized);
+        }
+        gesture.end(cx);
+    }
+
+    /// Lists the presets again, picking up user presets that were added or removed.
+    fn refresh_presets(&mut self) {
+        self.presets = presets::list();
+        self.preset_names = self
+            .presets
+            .iter()
+            .map(|preset| preset.name.clone())
+            .collect();
+    }
+
+    /// The current preset's index in the list, if it's in there.
+    fn current_preset_idx(&self) -> Option<usize> {
+        self.presets
+            .iter()
+            .position(|preset| preset.name == self.current_preset)
+    }
+
+    fn set_current_preset(&mut self, name: String) {
+        if let Ok(mut preset_name) = self.params.preset_name.write() {
+            *preset_name = name.clone();
+        }
+        self.current_preset = name;
+    }
+
+    /// Loads a preset from the list. The settings from before the first preview are kept until
+    /// the browser is closed, so they can be brought back with Revert.
+    fn load_preset(&mut self, cx: &mut EventContext, preset_idx: usize) {
+        let Some(preset) = self.presets.get(preset_idx).cloned() else {
+            return;
+        };
+        match preset
+            .read()
+            .and_then(|settings| presets::parse(&*self.params, &settings))
+        {
+            Ok(snapshot) => {
+                if self.preview_revert.is_none() {
+                    self.preview_revert =
+                        Some((morph::capture(&*self.params), self.current_preset.clone()));
+                }
+                self.set_params(cx, &snapshot);
+                self.save_name.clone_from(&preset.name);
+                self.set_current_preset(preset.name);
+                self.preset_status.clear();
+            }
+            Err(err) => self.preset_status = format!("{}: {err}", preset.name),
+        }
+    }
+
+    /// Saves the current settings as the user preset `name`. This commits any preview.
+    fn save_preset(&mut self, name: &str) {
+        match presets::save(&*self.params, name) {
+            Ok(name) => {
+                self.preset_status = format!("Saved '{name}'");
+                self.preview_revert = None;
+                self.set_current_preset(name);
+                self.refresh_presets();
+            }
+            Err(err) => self.preset_status = err,
+        }
+    }
+
+    /// Sets the unlocked controls to the values rolled for the current seed. Every control gets its
+    /// own gesture so the host records the roll as regular automation.
+    fn roll_dice(&self, cx: &mut EventContext) {
+        dice::roll(
+            &self.params,
+            self.dice_locks,
+            self.dice_seed,
+            |param, normalized| gesture::set_once(cx, param, normalized),
+        );
+    }
+}
+
+/// Adds hover help to a view.
+trait HelpModifiers {
+    /// Shows `text` in the help bar at the bottom of the editor while the mouse is over the view.
+    fn help(self, text: &'static str) -> Self;
+}
+
+impl<V: View> HelpModifiers for Handle<'_, V> {
+    fn help(self, text: &'static str) -> Self {
+        self.on_hover(move |cx| cx.emit(EditorEvent::ShowHelp(text)))
+            .on_hover_out(|cx| cx.emit(EditorEvent::ClearHelp))
+    }
+}
+
+/// Describes nih-plug's parameter widgets to screen readers.
+trait ParamAccessibilityModifiers {
+    /// Announces the view as a `role` named after the parameter, with the parameter's displayed
+    /// value. This tells the per-band controls apart, since the band number is only in the
+    /// column's heading otherwise.
+    fn describe_param<P: Param + 'static>(
+        self,
+        role: Role,
+        params_to_param: impl Fn(&ColorFallParams) -> &P + Copy + 'static,
+    ) -> Self;
+}
+
+impl<V: View> ParamAccessibilityModifiers for Handle<'_, V> {
+    fn describe_param<P: Param + 'static>(
+        self,
+        role: Role,
+        params_to_param: impl Fn(&ColorFallParams) -> &P + Copy + 'static,
+    ) -> Self {
+        self.role(role)
+            .name(Data::params.map(move |params| params_to_param(params).name().to_owned()))
+            .text_value(Data::params.map(move |params| params_to_param(params).to_string()))
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn create(
     params: Arc<ColorFallParams>,
     gain_reduction: Arc<AtomicF32>,
-    editor_state: Arc<ViziaState>,
+    meter_levels: Arc<MeterLevels>,
+    eq_curve: Arc<EqCurve>,
+    scope_buffer: Arc<ScopeBuffer>,
+    tilt_suggestion: Arc<TiltSuggestion>,
+    target_learn: Arc<TargetLearn>,
+    morph_snapshots_changed: Arc<AtomicBool>,
 ) -> Option<Box<dyn Editor>> {
-    create_vizia_editor(editor_state, ViziaTheming::Custom, move |cx, _| {
+    create_vizia_editor(params.editor_state.clone(), ViziaTheming::Custom, move |cx, _| {
         // Build the main data structure that Vizia's UI will observe.
         // The `Lens` derive macro provides a clean way to access nested data.
         Data {
             params: params.clone(),
             gain_reduction: gain_reduction.clone(),
+            meter_levels: meter_levels.clone(),
+            tilt_suggestion: tilt_suggestion.clone(),
+            target_learn: target_learn.clone(),
+            help_text: DEFAULT_HELP_TEXT.to_string(),
+            show_signal_flow: false,
+            theme: EditorTheme::from_index(params.editor_theme.load(Ordering::Relaxed)),
+            gui_scale: params.editor_state.user_scale_factor(),
+            learn_target: TargetCurve::from_index(
+                params.learn_target.load(Ordering::Relaxed) as usize,
+            ),
+            dice_seed: params.dice_seed.load(Ordering::Relaxed),
+            dice_locks: params.dice_locks.load(Ordering::Relaxed),
+            morph_snapshots_changed: morph_snapshots_changed.clone(),
+            morph_snapshots_stored: params
+                .morph_snapshots
+                .read()
+                .map_or([false; 2], |snapshots| {
+                    snapshots.each_ref().map(Option::is_some)
+                }),
+            show_presets: false,
+            presets: Vec::new(),
+            preset_names: Vec::new(),
+            current_preset: params
+                .preset_name
+                .read()
+                .map(|preset_name| preset_name.clone())
+                .unwrap_or_default(),
+            save_name: String::new(),
+            preview_revert: None,
+            preset_status: String::new(),
         }.build(cx);
 
         // Custom styling for the GUI
         cx.add_stylesheet(include_style!("src/style.css"))
             .expect("Failed to load stylesheet");
+        // The colors for every theme. These are all scoped to a theme class, and the root view
+        // below only has the class for the selected theme.
+        cx.add_stylesheet(include_style!("src/themes/dark.css"))
+            .expect("Failed to load the dark theme");
+        cx.add_stylesheet(include_style!("src/themes/light.css"))
+            .expect("Failed to load the light theme");
+        cx.add_stylesheet(include_style!("src/themes/high_contrast.css"))
+            .expect("Failed to load the high contrast theme");
         
         // Register the custom fonts from the assets module.
         assets::register_noto_sans_light(cx);
@@ -40,47 +558,113 @@ pub(crate) fn create(
 
         // The main layout of the plugin, a vertical stack.
         VStack::new(cx, |cx| {
-            // Header
-            Label::new(cx, "ColorFall")
-                .font_size(30.0)
-                .height(Pixels(50.0))
-                // We'll use a class to apply the bold font from the stylesheet
-                .class("title")
-                .child_top(Stretch(1.0))
-                .child_bottom(Pixels(0.0));
+            // Header, with the button for the signal flow overlay on the right
+            HStack::new(cx, |cx| {
+                Label::new(cx, "ColorFall")
+                    .font_size(30.0)
+                    .height(Pixels(50.0))
+                    // We'll use a class to apply the bold font from the stylesheet
+                    .class("title")
+                    .width(Stretch(1.0))
+                    .child_top(Stretch(1.0))
+                    .child_bottom(Pixels(0.0));
+                Label::new(
+                    cx,
+                    Data::current_preset.map(|preset_name| {
+                        if preset_name.is_empty() {
+                            String::from("Presets")
+                        } else {
+                            preset_name.clone()
+                        }
+                    }),
+                )
+                .class("header-button")
+                .class("preset-button")
+                .checked(Data::show_presets)
+                .role(Role::Button)
+                .on_press(|cx| cx.emit(EditorEvent::TogglePresets))
+                .help("Opens the preset browser. Shows the preset that was loaded or saved last.");
+                Label::new(cx, Data::theme.map(|theme| theme.name().to_string()))
+                    .class("header-button")
+                    .class("theme-button")
+                    .role(Role::Button)
+                    .on_press(|cx| cx.emit(EditorEvent::NextTheme))
+                    .help("Switches between the dark, light and high contrast themes.");
+                Label::new(cx, Data::gui_scale.map(|&scale| format_gui_scale(scale)))
+                    .class("header-button")
+                    .class("scale-button")
+                    .role(Role::Button)
+                    .name(
+                        Data::gui_scale
+                            .map(|&scale| format!("GUI Scale {}", format_gui_scale(scale))),
+                    )
+                    .on_press(|cx| cx.emit(EditorEvent::NextGuiScale))
+                    .help("Makes the editor bigger, from 75% up to 200% and then back to 75%.");
+                Label::new(cx, "?")
+                    .class("header-button")
+                    .class("help-button")
+                    .checked(Data::show_signal_flow)
+                    .role(Role::Button)
+                    .name("Signal Flow")
+                    .on_press(|cx| cx.emit(EditorEvent::ToggleSignalFlow))
+                    .help("Shows how the signal flows through ColorFall.");
+            })
+            .height(Pixels(50.0))
+            .col_between(Pixels(10.0));
 
-            // A horizontal stack for the main controls and the meter.
+            // A horizontal stack for the main controls and the meters.
             HStack::new(cx, |cx| {
+                // Input level meter
+                level_meter(
+                    cx,
+                    "In",
+                    "The peak and RMS level of the input.",
+                    Data::meter_levels.map(|levels| levels.input_peak.load(Ordering::Relaxed)),
+                    Data::meter_levels.map(|levels| levels.input_rms.load(Ordering::Relaxed)),
+                );
+
                 VStack::new(cx, |cx| {
                     // A vertical stack for the 'Amount' knob and its label.
                     // Amount Knob
                     VStack::new(cx, |cx| {
                         Label::new(cx, "Amount").bottom(Pixels(2.0));
-                        ParamSlider::new(cx, Data::params, |p| &p.amount)
-                            .width(Pixels(75.0))
-                            .class("amount");
+                        ParamKnob::new(cx, Data::params, |p| &p.amount).class("amount");
                         // Use the parameter's own formatter for a clean percentage display.
                         Label::new(cx, Data::params.map(|p| p.amount.to_string()))
                             .top(Pixels(2.0))
                             .class("value-label");
                     })
                     .row_between(Pixels(2.0))
-                    .height(Auto);
+                    .height(Auto)
+                    .help("How hard the bands are saturated and compressed.");
 
                     // A vertical stack for the 'Tilt' knob and its label.
                     // Tilt Knob
                     VStack::new(cx, |cx| {
                         Label::new(cx, "Tilt").bottom(Pixels(2.0));
-                        ParamSlider::new(cx, Data::params, |p| &p.tilt)
-                            .width(Pixels(75.0))
-                            .class("tilt");
+                        ParamKnob::new(cx, Data::params, |p| &p.tilt).class("tilt");
                         // Display the tilt value in semitones for user clarity.
                         Label::new(cx, Data::params.map(|p| format!("{:.2}", p.tilt.value() * TILT_MAX_SHIFT_SEMITONES)))
                             .top(Pixels(2.0))
                             .class("value-label");
                     })
                     .row_between(Pixels(2.0))
-                    .height(Auto);
+                    .height(Auto)
+                    .help("Focuses the processing on the low or the high bands.");
+
+                    Label::new(
+                        cx,
+                        Data::tilt_suggestion.map(|suggestion| suggest_tilt_text(suggestion)),
+                    )
+                    .class("header-button")
+                    .class("suggest-tilt-button")
+                    .role(Role::Button)
+                    .on_press(|cx| cx.emit(EditorEvent::SuggestTilt))
+                    .help(
+                        "Listens to the input for a few seconds and suggests the Tilt that \
+                         centers the processing on where its energy is. Click the suggestion to \
+                         use it.",
+                    );
                 })
                 .row_between(Pixels(15.0))
                 .child_left(Stretch(1.0))
@@ -96,9 +680,13 @@ pub(crate) fn create(
                         Some(Duration::from_millis(600)),
                     )
                     //.gradient() // Gradient is handled by CSS now
-                        .width(Pixels(20.0));
+                        .width(Pixels(20.0))
+                        .role(Role::Meter)
+                        .name("Gain Reduction")
+                        .numeric_value(Data::gain_reduction.map(|gr| gr.load(Ordering::Relaxed)));
                 })
                 .height(Stretch(1.0))
+                .help("The gain reduction of the band compressors, averaged over the bands.")
                 .child_left(Stretch(1.0))
                 .child_right(Stretch(1.0));
 
@@ -107,39 +695,834 @@ pub(crate) fn create(
                     // Mix Knob
                     VStack::new(cx, |cx| {
                         Label::new(cx, "Mix").bottom(Pixels(2.0));
-                        ParamSlider::new(cx, Data::params, |p| &p.mix)
-                            .width(Pixels(75.0))
-                            .class("mix");
+                        ParamKnob::new(cx, Data::params, |p| &p.mix).class("mix");
                         // Use the parameter's own formatter for a clean percentage display.
                         Label::new(cx, Data::params.map(|p| p.mix.to_string()))
                             .top(Pixels(2.0))
                             .class("value-label");
                     })
                     .row_between(Pixels(2.0))
-                    .height(Auto);
+                    .height(Auto)
+                    .help("Blends the processed signal with the dry input.");
 
                     // A vertical stack for the 'Output' knob and its label.
                     // Output Knob
                     VStack::new(cx, |cx| {
                         Label::new(cx, "Output").bottom(Pixels(2.0));
-                        ParamSlider::new(cx, Data::params, |p| &p.output)
-                            .width(Pixels(75.0))
-                            .class("output");
+                        ParamKnob::new(cx, Data::params, |p| &p.output).class("output");
                         Label::new(cx, Data::params.map(|p| p.output.to_string()))
                             .top(Pixels(2.0))
                             .class("value-label");
                     })
                     .row_between(Pixels(2.0))
-                    .height(Auto);
+                    .height(Auto)
+                    .help("The output gain, applied before the limiter.");
                 })
                 .row_between(Pixels(15.0))
                 .child_left(Stretch(1.0))
                 .child_right(Stretch(1.0));
+
+                // Output level meter, with the clip indicator underneath
+                VStack::new(cx, |cx| {
+                    level_meter(
+                        cx,
+                        "Out",
+                        "The peak and RMS level of the output, after the limiter.",
+                        Data::meter_levels.map(|levels| levels.output_peak.load(Ordering::Relaxed)),
+                        Data::meter_levels.map(|levels| levels.output_rms.load(Ordering::Relaxed)),
+                    );
+                    let peak_hold = Data::meter_levels
+                        .map(|levels| levels.output_true_peak_hold.load(Ordering::Relaxed));
+                    let readout = peak_hold
+                        .clone()
+                        .map(|&hold_db| clip_indicator_text(hold_db));
+                    Label::new(cx, readout)
+                        .class("clip-indicator")
+                        .checked(peak_hold.map(|&hold_db| hold_db > 0.0))
+                        .role(Role::Button)
+                        .on_press(|cx| cx.emit(EditorEvent::ResetClip))
+                        .help(
+                            "The highest true peak of the output since the last reset. Lights up \
+                             above 0 dBTP. Click to reset.",
+                        );
+                })
+                .row_between(Pixels(4.0))
+                .width(Auto);
             })
             .col_between(Pixels(20.0));
+
+            HStack::new(cx, |cx| {
+                // The band layout, with draggable crossovers, the live level of every band, and
+                // the reactive EQ's curve on top.
+                CrossoverDisplay::new(
+                    cx,
+                    params.clone(),
+                    eq_curve.clone(),
+                    Data::meter_levels.map(|levels| {
+                        std::array::from_fn(|band_idx| {
+                            levels.band_levels[band_idx].load(Ordering::Relaxed)
+                        })
+                    }),
+                )
+                .width(Stretch(1.0))
+                .name("Band Display")
+                .help("The bands and their levels. Drag a crossover to move it.");
+
+                Oscilloscope::new(cx, scope_buffer.clone())
+                    .width(Pixels(200.0))
+                    .name("Oscilloscope")
+                    .help(
+                        "The dry signal (faint) against the processed signal, before the mix. \
+                         Shows how Amount flattens the peaks and reshapes the envelope.",
+                    );
+
+                VStack::new(cx, |cx| {
+                    Goniometer::new(
+                        cx,
+                        scope_buffer.clone(),
+                        Data::meter_levels
+                            .map(|levels| levels.output_correlation.load(Ordering::Relaxed)),
+                    )
+                    .height(Stretch(1.0))
+                    .name("Goniometer");
+                    Label::new(
+                        cx,
+                        Data::meter_levels.map(|levels| {
+                            format!(
+                                "Corr {:+.2}",
+                                levels.output_correlation.load(Ordering::Relaxed)
+                            )
+                        }),
+                    )
+                    .class("value-label");
+                })
+                .width(Pixels(100.0))
+                .row_between(Pixels(2.0))
+                .help(
+                    "The stereo image of the output. A vertical line is mono, and a wide or \
+                     horizontal picture is out of phase. The bar and the readout show the \
+                     correlation: below 0 the output loses level when summed to mono.",
+                );
+            })
+            .height(Pixels(120.0))
+            .col_between(Pixels(10.0));
+
+            // A row of per-band controls, preceded by the band count selector.
+            HStack::new(cx, |cx| {
+                VStack::new(cx, |cx| {
+                    Label::new(cx, "Bands").class("value-label");
+                    ParamSlider::new(cx, Data::params, |p| &p.band_count)
+                        .describe_param(Role::Slider, |p| &p.band_count)
+                        .width(Pixels(60.0))
+                        .class("band-count")
+                        .help("The number of bands.");
+                    Label::new(
+                        cx,
+                        Data::learn_target
+                            .map(|target| TargetCurve::NAMES[target.to_index()].to_string()),
+                    )
+                    .class("header-button")
+                    .class("learn-target-button")
+                    .role(Role::Button)
+                    .name(Data::learn_target.map(|target| {
+                        format!("Learn Target {}", TargetCurve::NAMES[target.to_index()])
+                    }))
+                    .on_press(|cx| cx.emit(EditorEvent::NextLearnTarget))
+                    .help(
+                        "The spectrum Learn pushes the input towards: Flat, Pink or a Modern \
+                         Master curve with a fuller low end and a softer top.",
+                    );
+                    Label::new(cx, Data::target_learn.map(|learn| learn_text(learn)))
+                        .class("header-button")
+                        .class("learn-button")
+                        .role(Role::Button)
+                        .name(
+                            Data::target_learn.map(|learn| format!("Learn {}", learn_text(learn))),
+                        )
+                        .on_press(|cx| cx.emit(EditorEvent::LearnTarget))
+                        .help(
+                            "Listens to the input for 10 seconds and sets the band trims that \
+                             push it towards the target. Click again to apply them.",
+                        );
+                })
+                .row_between(Pixels(2.0))
+                .height(Auto);
+
+                for band_idx in 0..MAX_BANDS {
+                    VStack::new(cx, |cx| {
+                        Label::new(cx, format!("Band {}", band_idx + 1)).class("value-label");
+                        ParamButton::new(cx, Data::params, move |p| &p.bands[band_idx].solo)
+                            .describe_param(Role::ToggleButton, move |p| &p.bands[band_idx].solo)
+                            .with_label("Solo")
+                            .class("solo")
+                            .help("Listens to this band only.");
+                        ParamButton::new(cx, Data::params, move |p| &p.bands[band_idx].mute)
+                            .describe_param(Role::ToggleButton, move |p| &p.bands[band_idx].mute)
+                            .with_label("Mute")
+                            .class("mute")
+                            .help("Silences this band.");
+                        ParamButton::new(cx, Data::params, move |p| &p.bands[band_idx].bypass)
+                            .describe_param(Role::ToggleButton, move |p| &p.bands[band_idx].bypass)
+                            .with_label("Bypass")
+                            .class("bypass")
+                            .help("Leaves this band unprocessed.");
+                        ParamSlider::new(cx, Data::params, move |p| &p.bands[band_idx].amount_trim)
+                            .describe_param(Role::Slider, move |p| &p.bands[band_idx].amount_trim)
+                            .width(Pixels(60.0))
+                            .class("band-trim")
+                            .help("Scales how hard this band is compressed.");
+                        ParamSlider::new(cx, Data::params, move |p| &p.bands[band_idx].drive)
+                            .describe_param(Role::Slider, move |p| &p.bands[band_idx].drive)
+                            .width(Pixels(60.0))
+                            .class("band-drive")
+                            .help("Drives this band's saturator harder or softer.");
+                        ParamSlider::new(cx, Data::params, move |p| &p.bands[band_idx].width)
+                            .describe_param(Role::Slider, move |p| &p.bands[band_idx].width)
+                            .width(Pixels(60.0))
+                            .class("band-width")
+                            .help("Narrows or widens this band's stereo image.");
+                        ParamSlider::new(cx, Data::params, move |p| &p.bands[band_idx].mix)
+                            .describe_param(Role::Slider, move |p| &p.bands[band_idx].mix)
+                            .width(Pixels(60.0))
+                            .class("band-mix")
+                            .help("Blends this band's processing with its unprocessed signal.");
+                        ParamButton::new(cx, Data::params, move |p| &p.bands[band_idx].duck)
+                            .describe_param(Role::ToggleButton, move |p| &p.bands[band_idx].duck)
+                            .with_label("Duck")
+                            .class("duck")
+                            .help("Lets MIDI notes duck this band.");
+                        ParamButton::new(cx, Data::params, move |p| &p.bands[band_idx].expand)
+                            .describe_param(Role::ToggleButton, move |p| &p.bands[band_idx].expand)
+                            .with_label("Expand")
+                            .class("expand")
+                            .help("Pushes everything below the expand threshold further down.");
+                        ParamSlider::new(cx, Data::params, move |p| {
+                            &p.bands[band_idx].expand_threshold
+                        })
+                        .describe_param(Role::Slider, move |p| {
+                            &p.bands[band_idx].expand_threshold
+                        })
+                        .width(Pixels(60.0))
+                        .class("band-expand-threshold")
+                        .help("The level below which the expander kicks in.");
+                        ParamButton::new(cx, Data::params, move |p| &p.bands[band_idx].de_ess)
+                            .describe_param(Role::ToggleButton, move |p| &p.bands[band_idx].de_ess)
+                            .with_label("De-Ess")
+                            .class("de-ess")
+                            .help("Turns this band into a de-esser for vocal sibilance.");
+                    })
+                    .row_between(Pixels(2.0))
+                    .height(Auto)
+                    .child_left(Stretch(1.0))
+                    .child_right(Stretch(1.0));
+                }
+            })
+            .height(Auto)
+            .col_between(Pixels(10.0));
+
+            // The Dice randomizer, with a lock for every group of controls it changes
+            HStack::new(cx, |cx| {
+                Label::new(cx, "Dice")
+                    .class("header-button")
+                    .class("dice-button")
+                    .role(Role::Button)
+                    .on_press(|cx| cx.emit(EditorEvent::RollDice))
+                    .help("Sets the unlocked controls to random values within musical ranges.");
+                Label::new(cx, "Recall")
+                    .class("header-button")
+                    .class("dice-button")
+                    .role(Role::Button)
+                    .on_press(|cx| cx.emit(EditorEvent::RecallDice))
+                    .help("Rolls the last seed again, bringing back the last roll.");
+                Label::new(
+                    cx,
+                    Data::dice_seed.map(|seed| format!("Seed {:016X}", seed)),
+                )
+                .class("value-label")
+                .class("dice-seed");
+                for target in DiceTarget::ALL {
+                    Label::new(cx, target.name())
+                        .class("dice-lock")
+                        .checked(Data::dice_locks.map(move |locks| locks & target.lock_bit() != 0))
+                        .role(Role::CheckBox)
+                        .on_press(move |cx| cx.emit(EditorEvent::ToggleDiceLock(target)))
+                        .help("Locks this control so the dice leave it alone.");
+                }
+            })
+            .height(Auto)
+            .col_between(Pixels(6.0));
+
+            // The A/B snapshots and the Morph slider blending between them
+            HStack::new(cx, |cx| {
+                for (slot_idx, name) in ["Store A", "Store B"].into_iter().enumerate() {
+                    Label::new(cx, name)
+                        .class("header-button")
+                        .class("morph-button")
+                        .checked(Data::morph_snapshots_stored.map(move |stored| stored[slot_idx]))
+                        .role(Role::Button)
+                        .on_press(move |cx| cx.emit(EditorEvent::StoreSnapshot(slot_idx)))
+                        .help("Stores the current settings as a snapshot for the Morph slider.");
+                }
+                ParamSlider::new(cx, Data::params, |p| &p.morph)
+                    .describe_param(Role::Slider, |p| &p.morph)
+                    .class("morph-slider")
+                    .help("Blends every control between snapshot A and snapshot B.");
+                Label::new(cx, "Clear")
+                    .class("header-button")
+                    .class("morph-button")
+                    .role(Role::Button)
+                    .on_press(|cx| cx.emit(EditorEvent::ClearSnapshots))
+                    .help("Removes both snapshots, handing the controls back to their own values.");
+            })
+            .height(Auto)
+            .col_between(Pixels(6.0));
+
+            // Explains the control under the mouse
+            Label::new(cx, Data::help_text)
+                .class("help-bar")
+                .width(Stretch(1.0));
+
+            // The preset browser slides out over the right side of the editor
+            Binding::new(cx, Data::show_presets, |cx, show_presets| {
+                if show_presets.get(cx) {
+                    preset_browser(cx);
+                }
+            });
+
+            // The overlay is drawn on top of everything else, so it needs to come last
+            Binding::new(cx, Data::show_signal_flow, |cx, show_signal_flow| {
+                if show_signal_flow.get(cx) {
+                    signal_flow_overlay(cx);
+                }
+            });
         })
         .row_between(Pixels(10.0))
         .child_left(Stretch(1.0))
-        .child_right(Stretch(1.0));
+        .child_right(Stretch(1.0))
+        .toggle_class(
+            EditorTheme::Dark.class(),
+            Data::theme.map(|theme| *theme == EditorTheme::Dark),
+        )
+        .toggle_class(
+            EditorTheme::Light.class(),
+            Data::theme.map(|theme| *theme == EditorTheme::Light),
+        )
+        .toggle_class(
+            EditorTheme::HighContrast.class(),
+            Data::theme.map(|theme| *theme == EditorTheme::HighContrast),
+        );
+    })
+}
+
+/// An overlay that annotates the stages of the signal flow. Clicking anywhere closes it.
+fn signal_flow_overlay(cx: &mut Context) {
+    VStack::new(cx, |cx| {
+        Label::new(cx, "Signal Flow").class("title").font_size(20.0);
+        for (stage_idx, (stage, description)) in SIGNAL_FLOW.iter().enumerate() {
+            VStack::new(cx, |cx| {
+                Label::new(cx, format!("{}. {}", stage_idx + 1, stage)).class("signal-flow-stage");
+                Label::new(cx, *description).class("value-label");
+            })
+            .height(Auto)
+            .row_between(Pixels(2.0));
+        }
+        Label::new(cx, "Click anywhere to close").class("value-label");
+    })
+    .class("signal-flow")
+    .position_type(PositionType::SelfDirected)
+    .left(Pixels(0.0))
+    .top(Pixels(0.0))
+    .width(Stretch(1.0))
+    .height(Stretch(1.0))
+    .row_between(Pixels(12.0))
+    .child_space(Stretch(1.0))
+    .on_press(|cx| cx.emit(EditorEvent::ToggleSignalFlow));
+}
+
+/// The preset browser, covering the right side of the editor. Clicking a preset loads it right
+/// away so it can be auditioned, and Revert goes back to the settings from before the first one.
+fn preset_browser(cx: &mut Context) {
+    VStack::new(cx, |cx| {
+        HStack::new(cx, |cx| {
+            Label::new(cx, "Presets")
+                .class("title")
+                .font_size(20.0)
+                .width(Stretch(1.0));
+            Label::new(cx, "<")
+                .class("header-button")
+                .class("preset-step")
+                .role(Role::Button)
+                .name("Previous Preset")
+                .on_press(|cx| cx.emit(EditorEvent::PreviousPreset))
+                .help("Loads the previous preset.");
+            Label::new(cx, ">")
+                .class("header-button")
+                .class("preset-step")
+                .role(Role::Button)
+                .name("Next Preset")
+                .on_press(|cx| cx.emit(EditorEvent::NextPreset))
+                .help("Loads the next preset.");
+            Label::new(cx, "Close")
+                .class("header-button")
+                .class("preset-action")
+                .role(Role::Button)
+                .on_press(|cx| cx.emit(EditorEvent::TogglePresets))
+                .help("Closes the browser and keeps the current settings.");
+        })
+        .height(Auto)
+        .col_between(Pixels(6.0));
+
+        ScrollView::new(cx, 0.0, 0.0, false, true, |cx| {
+            Binding::new(cx, Data::preset_names, |cx, preset_names| {
+                for (preset_idx, name) in preset_names.get(cx).into_iter().enumerate() {
+                    if preset_idx == 0 {
+                        Label::new(cx, "Factory").class("preset-section");
+                    } else if preset_idx == presets::FACTORY_PRESETS.len() {
+                        Label::new(cx, "User").class("preset-section");
+                    }
+                    let item_name = name.clone();
+                    Label::new(cx, name)
+                        .class("preset-item")
+                        .checked(Data::current_preset.map(move |current| *current == item_name))
+                        .role(Role::Button)
+                        .on_press(move |cx| cx.emit(EditorEvent::LoadPreset(preset_idx)));
+                }
+            });
+        })
+        .height(Stretch(1.0))
+        .help("Click a preset to load it.");
+
+        HStack::new(cx, |cx| {
+            Label::new(cx, "Revert")
+                .class("header-button")
+                .class("preset-action")
+                .role(Role::Button)
+                .on_press(|cx| cx.emit(EditorEvent::RevertPreview))
+                .help("Goes back to the settings from before the first preset you clicked.");
+            Label::new(cx, "Overwrite")
+                .class("header-button")
+                .class("preset-action")
+                .role(Role::Button)
+                .on_press(|cx| cx.emit(EditorEvent::OverwritePreset))
+                .help("Saves the current settings to the current user preset.");
+        })
+        .height(Auto)
+        .col_between(Pixels(6.0));
+
+        HStack::new(cx, |cx| {
+            Textbox::new(cx, Data::save_name)
+                .class("preset-name")
+                .name("Preset Name")
+                .width(Stretch(1.0))
+                .on_edit(|cx, text| cx.emit(EditorEvent::SetSaveName(text)))
+                .help("The name for Save As.");
+            Label::new(cx, "Save As")
+                .class("header-button")
+                .class("preset-action")
+                .role(Role::Button)
+                .on_press(|cx| cx.emit(EditorEvent::SavePresetAs))
+                .help("Saves the current settings as a new user preset with this name.");
+        })
+        .height(Auto)
+        .col_between(Pixels(6.0));
+
+        Label::new(cx, Data::preset_status)
+            .class("value-label")
+            .width(Stretch(1.0));
+    })
+    .class("preset-browser")
+    .position_type(PositionType::SelfDirected)
+    .left(Stretch(1.0))
+    .top(Pixels(0.0))
+    .width(Pixels(300.0))
+    .height(Stretch(1.0))
+    .row_between(Pixels(8.0))
+    .child_space(Pixels(10.0));
+}
+
+/// A labeled pair of level meters, showing the peak level with a peak hold next to the RMS level.
+fn level_meter(
+    cx: &mut Context,
+    label: &str,
+    help: &'static str,
+    peak: impl Lens<Target = f32>,
+    rms: impl Lens<Target = f32>,
+) {
+    VStack::new(cx, |cx| {
+        Label::new(cx, label).bottom(Pixels(2.0));
+        HStack::new(cx, |cx| {
+            PeakMeter::new(cx, peak.clone(), Some(Duration::from_millis(600)))
+                .width(Pixels(10.0))
+                .class("level-peak")
+                .role(Role::Meter)
+                .name(format!("{label} Peak"))
+                .numeric_value(peak);
+            PeakMeter::new(cx, rms.clone(), None)
+                .width(Pixels(10.0))
+                .class("level-rms")
+                .role(Role::Meter)
+                .name(format!("{label} RMS"))
+                .numeric_value(rms);
+        })
+        .col_between(Pixels(2.0));
     })
-}
\ No newline at end of file
+    .height(Stretch(1.0))
+    .child_left(Stretch(1.0))
+    .child_right(Stretch(1.0))
+    .help(help);
+}
+
+/// The scale after `scale` in [`GUI_SCALES`], wrapping around to the smallest one.
+fn next_gui_scale(scale: f64) -> f64 {
+    GUI_SCALES
+        .into_iter()
+        .find(|&next| next > scale + 0.01)
+        .unwrap_or(GUI_SCALES[0])
+}
+
+/// The scale button's label for a user scale factor.
+fn format_gui_scale(scale: f64) -> String {
+    format!("{:.0}%", scale * 100.0)
+}
+
+/// Whether Suggest Tilt is listening to the input, or is about to.
+fn is_listening(suggestion: &TiltSuggestion) -> bool {
+    suggestion.start.load(Ordering::Relaxed) || suggestion.progress.load(Ordering::Relaxed) < 1.0
+}
+
+/// The Suggest Tilt button's label. Suggestions are shown in semitones, like the Tilt readout.
+fn suggest_tilt_text(suggestion: &TiltSuggestion) -> String {
+    let tilt = suggestion.tilt.load(Ordering::Relaxed);
+    if is_listening(suggestion) {
+        let progress = suggestion.progress.load(Ordering::Relaxed);
+        format!("Listening {:.0}%", progress * 100.0)
+    } else if tilt.is_finite() {
+        format!("Use {:+.2}", tilt * TILT_MAX_SHIFT_SEMITONES)
+    } else {
+        String::from("Suggest Tilt")
+    }
+}
+
+/// Whether Learn is listening to the input, or is about to.
+fn is_learning(learn: &TargetLearn) -> bool {
+    learn.start.load(Ordering::Relaxed) || learn.progress.load(Ordering::Relaxed) < 1.0
+}
+
+/// Whether Learn has band trims waiting to be applied.
+fn has_learned_trims(learn: &TargetLearn) -> bool {
+    learn
+        .trims
+        .iter()
+        .any(|trim| trim.load(Ordering::Relaxed).is_finite())
+}
+
+/// The Learn button's label.
+fn learn_text(learn: &TargetLearn) -> String {
+    if is_learning(learn) {
+        let progress = learn.progress.load(Ordering::Relaxed);
+        format!("{:.0}%", progress * 100.0)
+    } else if has_learned_trims(learn) {
+        String::from("Apply")
+    } else {
+        String::from("Learn")
+    }
+}
+
+/// The clip indicator's readout for the latched true peak level.
+fn clip_indicator_text(hold_db: f32) -> String {
+    if hold_db <= util::MINUS_INFINITY_DB {
+        String::from("-inf dBTP")
+    } else if hold_db > 0.0 {
+        format!("CLIP +{hold_db:.1}")
+    } else {
+        format!("{hold_db:.1} dBTP")
+    }
+}
+
+/// The frequency range shown by the crossover display.
+const DISPLAY_MIN_HZ: f32 = 20.0;
+const DISPLAY_MAX_HZ: f32 = 20000.0;
+/// The band level that sits at the bottom of the crossover display.
+const DISPLAY_MIN_DB: f32 = -60.0;
+/// The EQ curve's range above and below 0 dB. 0 dB sits in the middle of the crossover display.
+const EQ_DISPLAY_RANGE_DB: f32 = 24.0;
+/// How close the mouse needs to be to a crossover to grab it, in logical pixels.
+const HANDLE_GRAB_DISTANCE: f32 = 6.0;
+/// How fast a crossover follows the mouse while Shift is held down.
+const GRANULAR_DRAG_MULTIPLIER: f32 = 0.1;
+/// The colors of the band regions, from low to high.
+const BAND_COLORS: [(u8, u8, u8); MAX_BANDS] = [
+    (0xC2, 0x54, 0x4B),
+    (0xE0, 0x8A, 0x41),
+    (0xE0, 0xB3, 0x41),
+    (0x33, 0xAF, 0x74),
+    (0x4B, 0x8B, 0xC2),
+    (0x8A, 0x5C, 0xC2),
+];
+
+/// A view of the band layout on a logarithmic frequency axis. Every band region shows the band's
+/// current level, and the crossovers between them can be dragged to change the crossover shift
+/// parameters. Holding Shift while dragging moves a crossover in finer steps, and Ctrl+clicking
+/// (Cmd+clicking on macOS) or double clicking a crossover resets it. The reactive EQ's combined
+/// response is drawn on top.
+struct CrossoverDisplay<L: Lens<Target = [f32; MAX_BANDS]>> {
+    params: Arc<ColorFallParams>,
+    eq_curve: Arc<EqCurve>,
+    band_levels: L,
+
+    /// The index of the crossover that is currently being dragged, if any.
+    drag_crossover: Option<usize>,
+    /// The last mouse position and the position of the dragged crossover. The crossover follows
+    /// the mouse's movement rather than its position, so it can move slower while Shift is held.
+    drag_x: (f32, f32),
+    /// The dragged crossover's shift parameter, while it's being dragged.
+    gesture: ParamGesture,
+}
+
+impl<L: Lens<Target = [f32; MAX_BANDS]>> CrossoverDisplay<L> {
+    fn new(
+        cx: &mut Context,
+        params: Arc<ColorFallParams>,
+        eq_curve: Arc<EqCurve>,
+        band_levels: L,
+    ) -> Handle<Self> {
+        Self {
+            params,
+            eq_curve,
+            band_levels,
+            drag_crossover: None,
+            drag_x: (0.0, 0.0),
+            gesture: ParamGesture::default(),
+        }
+        .build(cx, |_| {})
+        .role(Role::Image)
+    }
+
+    /// The current crossover frequencies. Only the first `num_bands - 1` are used.
+    fn crossover_frequencies(&self, num_bands: usize) -> [f32; MAX_BANDS - 1] {
+        self.params.crossover_frequencies(
+            self.eq_curve.sample_rate.load(Ordering::Relaxed),
+            num_bands,
+            self.params.tilt.value(),
+        )
+    }
+
+    /// The crossover closest to `x`, if it is within grabbing distance.
+    fn crossover_at(&self, cx: &EventContext, x: f32) -> Option<usize> {
+        let bounds = cx.bounds();
+        let num_bands = self.params.num_bands();
+        let grab_distance = HANDLE_GRAB_DISTANCE * cx.scale_factor();
+
+        self.crossover_frequencies(num_bands)[..(num_bands - 1)]
+            .iter()
+            .enumerate()
+            .map(|(crossover_idx, &freq)| {
+                (crossover_idx, (frequency_to_x(freq, bounds) - x).abs())
+            })
+            .filter(|&(_, distance)| distance <= grab_distance)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(crossover_idx, _)| crossover_idx)
+    }
+
+    /// Moves the dragged crossover to the frequency under `x`. Must only be called during a drag.
+    fn set_crossover_position(&self, cx: &mut EventContext, crossover_idx: usize, x: f32) {
+        let freq = x_to_frequency(x, cx.bounds());
+        let shift = self.params.crossover_shift_for_frequency(
+            crossover_idx,
+            self.params.num_bands(),
+            self.params.tilt.value(),
+            freq,
+        );
+
+        let param = &self.params.crossovers[crossover_idx].shift;
+        self.gesture.set(cx, param.as_ptr(), param.preview_normalized(shift));
+    }
+
+    /// Resets a crossover's shift to its default.
+    fn reset_crossover(&self, cx: &mut EventContext, crossover_idx: usize) {
+        let param = &self.params.crossovers[crossover_idx].shift;
+        gesture::set_once(cx, param.as_ptr(), param.default_normalized_value());
+    }
+
+    /// Stops the current drag, if any.
+    fn end_drag(&mut self, cx: &mut EventContext) {
+        if self.drag_crossover.take().is_some() {
+            cx.release();
+            cx.set_active(false);
+
+            self.gesture.end(cx);
+        }
+    }
+}
+
+impl<L: Lens<Target = [f32; MAX_BANDS]>> View for CrossoverDisplay<L> {
+    fn element(&self) -> Option<&'static str> {
+        Some("crossover-display")
+    }
+
+    fn event(&mut self, cx: &mut EventContext, event: &mut Event) {
+        event.map(|window_event, meta| match window_event {
+            WindowEvent::MouseDown(MouseButton::Left) => {
+                let x = cx.mouse().cursorx;
+                if let Some(crossover_idx) = self.crossover_at(cx, x) {
+                    if cx.modifiers().command() {
+                        self.reset_crossover(cx, crossover_idx);
+                    } else {
+                        cx.capture();
+                        cx.set_active(true);
+
+                        let param = &self.params.crossovers[crossover_idx].shift;
+                        self.gesture.begin(cx, &[param.as_ptr()]);
+                        // A Shift+click grabs the crossover where it is instead of moving it to the
+                        // mouse
+                        let handle_x = if cx.modifiers().shift() {
+                            let num_bands = self.params.num_bands();
+                            frequency_to_x(
+                                self.crossover_frequencies(num_bands)[crossover_idx],
+                                cx.bounds(),
+                            )
+                        } else {
+                            x
+                        };
+                        self.set_crossover_position(cx, crossover_idx, handle_x);
+                        self.drag_crossover = Some(crossover_idx);
+                        self.drag_x = (x, handle_x);
+                    }
+
+                    meta.consume();
+                }
+            }
+            WindowEvent::MouseDoubleClick(MouseButton::Left) => {
+                // The first click of a double click starts a drag, which is ended before the reset
+                // so the reset gets a gesture of its own
+                if let Some(crossover_idx) = self.crossover_at(cx, cx.mouse().cursorx) {
+                    self.end_drag(cx);
+                    self.reset_crossover(cx, crossover_idx);
+
+                    meta.consume();
+                }
+            }
+            WindowEvent::MouseMove(x, _y) => {
+                if let Some(crossover_idx) = self.drag_crossover {
+                    let speed = if cx.modifiers().shift() {
+                        GRANULAR_DRAG_MULTIPLIER
+                    } else {
+                        1.0
+                    };
+                    let (last_x, handle_x) = self.drag_x;
+                    let handle_x = handle_x + (speed * (*x - last_x));
+                    self.drag_x = (*x, handle_x);
+
+                    self.set_crossover_position(cx, crossover_idx, handle_x);
+                    cx.needs_redraw();
+                }
+            }
+            WindowEvent::MouseUp(MouseButton::Left) => {
+                if self.drag_crossover.is_some() {
+                    self.end_drag(cx);
+
+                    meta.consume();
+                }
+            }
+            _ => {}
+        });
+    }
+
+    fn draw(&self, cx: &mut DrawContext, canvas: &mut Canvas) {
+        let bounds = cx.bounds();
+        if bounds.w == 0.0 || bounds.h == 0.0 {
+            return;
+        }
+
+        let mut path = vg::Path::new();
+        path.rect(bounds.x, bounds.y, bounds.w, bounds.h);
+        canvas.fill_path(&path, &vg::Paint::color(cx.background_color().into()));
+
+        // The band regions span from one crossover to the next, with the outer bands running to
+        // the edges of the display
+        let num_bands = self.params.num_bands();
+        let crossover_freqs = self.crossover_frequencies(num_bands);
+        let mut band_edges = [bounds.x + bounds.w; MAX_BANDS + 1];
+        band_edges[0] = bounds.x;
+        for (crossover_idx, &freq) in crossover_freqs[..(num_bands - 1)].iter().enumerate() {
+            band_edges[crossover_idx + 1] = frequency_to_x(freq, bounds);
+        }
+
+        let band_levels = self.band_levels.get(cx);
+        for band_idx in 0..num_bands {
+            let (left, right) = (band_edges[band_idx], band_edges[band_idx + 1]);
+            let (r, g, b) = BAND_COLORS[band_idx];
+
+            let mut path = vg::Path::new();
+            path.rect(left, bounds.y, right - left, bounds.h);
+            canvas.fill_path(&path, &vg::Paint::color(vg::Color::rgba(r, g, b, 40)));
+
+            let level =
+                ((band_levels[band_idx] - DISPLAY_MIN_DB) / -DISPLAY_MIN_DB).clamp(0.0, 1.0);
+            let bar_height = bounds.h * level;
+            let mut path = vg::Path::new();
+            path.rect(
+                left,
+                bounds.y + bounds.h - bar_height,
+                right - left,
+                bar_height,
+            );
+            canvas.fill_path(&path, &vg::Paint::color(vg::Color::rgba(r, g, b, 150)));
+        }
+
+        // The reactive EQ's combined response, evaluated at every pixel. This and the crossover
+        // handles are drawn in the theme's text color.
+        let line_color: vg::Color = cx.font_color().into();
+        let num_points = bounds.w.ceil().max(2.0) as usize;
+        let xs: Vec<f32> = (0..num_points)
+            .map(|point_idx| bounds.x + bounds.w * point_idx as f32 / (num_points - 1) as f32)
+            .collect();
+        let freqs: Vec<f32> = xs.iter().map(|&x| x_to_frequency(x, bounds)).collect();
+        let response_db = self.eq_curve.response_db(&freqs);
+        let mut path = vg::Path::new();
+        for (point_idx, (&x, &gain_db)) in xs.iter().zip(&response_db).enumerate() {
+            let y = bounds.y + bounds.h / 2.0
+                - (gain_db / EQ_DISPLAY_RANGE_DB).clamp(-1.0, 1.0) * bounds.h / 2.0;
+            if point_idx == 0 {
+                path.move_to(x, y);
+            } else {
+                path.line_to(x, y);
+            }
+        }
+        canvas.stroke_path(
+            &path,
+            &vg::Paint::color(line_color).with_line_width(1.5 * cx.scale_factor()),
+        );
+
+        // The crossover handles are drawn on top, with the one being dragged drawn thicker
+        for (crossover_idx, &x) in band_edges[1..num_bands].iter().enumerate() {
+            let line_width = if self.drag_crossover == Some(crossover_idx) {
+                3.0 * cx.scale_factor()
+            } else {
+                2.0 * cx.scale_factor()
+            };
+
+            let mut path = vg::Path::new();
+            path.move_to(x, bounds.y);
+            path.line_to(x, bounds.y + bounds.h);
+            canvas.stroke_path(
+                &path,
+                &vg::Paint::color(line_color).with_line_width(line_width),
+            );
+        }
+    }
+}
+
+/// Maps a frequency to its x-coordinate in the crossover display.
+fn frequency_to_x(freq: f32, bounds: BoundingBox) -> f32 {
+    let position = (freq / DISPLAY_MIN_HZ).ln() / (DISPLAY_MAX_HZ / DISPLAY_MIN_HZ).ln();
+
+    bounds.x + bounds.w * position.clamp(0.0, 1.0)
+}
+
+/// The inverse of [`frequency_to_x()`].
+fn x_to_frequency(x: f32, bounds: BoundingBox) -> f32 {
+    let position = ((x - bounds.x) / bounds.w).clamp(0.0, 1.0);
+
+    DISPLAY_MIN_HZ * (DISPLAY_MAX_HZ / DISPLAY_MIN_HZ).powf(position)
+}
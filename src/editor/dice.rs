@@ -0,0 +1,156 @@
+//! The Dice randomizer. Rolling the dice sets the controls that shape the sound to random values
+//! within ranges that stay musical, so the Amount/Tilt/Character space can be explored quickly.
+//! Every roll is derived from a seed that's stored with the plugin's state, so a roll can always be
+//! recalled.
+use nih_plug::prelude::{Param, ParamPtr};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::ColorFallParams;
+
+/// The groups of controls the dice can change. Every group can be locked on its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiceTarget {
+    Amount,
+    Tilt,
+    TiltEq,
+    Character,
+    Saturation,
+    UpDown,
+    Punch,
+    Air,
+    /// Both the attack and the release scale.
+    Timing,
+}
+
+impl DiceTarget {
+    pub const ALL: [DiceTarget; 9] = [
+        DiceTarget::Amount,
+        DiceTarget::Tilt,
+        DiceTarget::TiltEq,
+        DiceTarget::Character,
+        DiceTarget::Saturation,
+        DiceTarget::UpDown,
+        DiceTarget::Punch,
+        DiceTarget::Air,
+        DiceTarget::Timing,
+    ];
+
+    /// The target's bit in [`ColorFallParams::dice_locks`].
+    pub fn lock_bit(self) -> u32 {
+        1 << (self as u32)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DiceTarget::Amount => "Amount",
+            DiceTarget::Tilt => "Tilt",
+            DiceTarget::TiltEq => "Tilt EQ",
+            DiceTarget::Character => "Character",
+            DiceTarget::Saturation => "Saturation",
+            DiceTarget::UpDown => "Up/Down",
+            DiceTarget::Punch => "Punch",
+            DiceTarget::Air => "Air",
+            DiceTarget::Timing => "Timing",
+        }
+    }
+}
+
+/// A SplitMix64 generator. The sequence only depends on the seed, which is what makes rolls
+/// reproducible.
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A uniformly distributed value in `min..max`.
+    fn range(&mut self, min: f32, max: f32) -> f32 {
+        let unit = (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32;
+        min + (unit * (max - min))
+    }
+
+    /// A random step of a stepped parameter, as a normalized value.
+    fn step(&mut self, param: &impl Param) -> f32 {
+        let step_count = param.step_count().unwrap_or(0);
+        if step_count == 0 {
+            return param.default_normalized_value();
+        }
+
+        (self.next_u64() % (step_count as u64 + 1)) as f32 / step_count as f32
+    }
+}
+
+/// Picks a new seed for a fresh roll.
+pub fn new_seed(previous: u64) -> u64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_nanos() as u64);
+
+    Rng(previous ^ nanos).next_u64()
+}
+
+/// Rolls the dice for `seed`, calling `set` with the new normalized value of every unlocked
+/// control. Every target draws its values even when it's locked, so locking a target doesn't
+/// change what the same seed does to the others.
+pub fn roll(params: &ColorFallParams, locks: u32, seed: u64, mut set: impl FnMut(ParamPtr, f32)) {
+    let mut rng = Rng(seed);
+    for target in DiceTarget::ALL {
+        let locked = locks & target.lock_bit() != 0;
+        let mut apply = |param: ParamPtr, normalized: f32| {
+            if !locked {
+                set(param, normalized);
+            }
+        };
+
+        match target {
+            DiceTarget::Amount => apply(
+                params.amount.as_ptr(),
+                params.amount.preview_normalized(rng.range(0.2, 0.8)),
+            ),
+            DiceTarget::Tilt => apply(
+                params.tilt.as_ptr(),
+                params.tilt.preview_normalized(rng.range(-0.6, 0.6)),
+            ),
+            DiceTarget::TiltEq => apply(
+                params.tilt_eq.as_ptr(),
+                params.tilt_eq.preview_normalized(rng.range(0.0, 6.0)),
+            ),
+            DiceTarget::Character => apply(params.character.as_ptr(), rng.step(&params.character)),
+            DiceTarget::Saturation => {
+                apply(params.saturation.as_ptr(), rng.step(&params.saturation))
+            }
+            DiceTarget::UpDown => apply(
+                params.up_down.as_ptr(),
+                params.up_down.preview_normalized(rng.range(0.0, 0.5)),
+            ),
+            DiceTarget::Punch => apply(
+                params.punch.as_ptr(),
+                params.punch.preview_normalized(rng.range(-0.4, 0.4)),
+            ),
+            DiceTarget::Air => apply(
+                params.air.as_ptr(),
+                params.air.preview_normalized(rng.range(0.0, 0.4)),
+            ),
+            // The scales are spread evenly in octaves around 1x
+            DiceTarget::Timing => {
+                apply(
+                    params.attack_scale.as_ptr(),
+                    params
+                        .attack_scale
+                        .preview_normalized(2.0f32.powf(rng.range(-1.0, 1.0))),
+                );
+                apply(
+                    params.release_scale.as_ptr(),
+                    params
+                        .release_scale
+                        .preview_normalized(2.0f32.powf(rng.range(-1.0, 1.0))),
+                );
+            }
+        }
+    }
+}
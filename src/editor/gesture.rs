@@ -0,0 +1,50 @@
+//! Automation gestures for the custom widgets. Hosts need every change made from the editor to be
+//! wrapped in a begin and an end event to record automation and to make touch and latch modes
+//! work, and every begin needs to be matched by exactly one end.
+use nih_plug::prelude::ParamPtr;
+use nih_plug_vizia::vizia::prelude::*;
+use nih_plug_vizia::widgets::RawParamEvent;
+
+/// The parameters a widget is changing in a drag. Widgets that change several parameters at once,
+/// like an XY pad, begin and end the gesture for all of them together, so the host sees them being
+/// touched for the same stretch of time. Beginning a new gesture ends the previous one first, so a
+/// drag that never got its mouse up doesn't leave a parameter touched.
+#[derive(Debug, Default)]
+pub struct ParamGesture {
+    params: Vec<ParamPtr>,
+}
+
+impl ParamGesture {
+    /// Begins a gesture for `params`.
+    pub fn begin(&mut self, cx: &mut EventContext, params: &[ParamPtr]) {
+        self.end(cx);
+        for &param in params {
+            cx.emit(RawParamEvent::BeginSetParameter(param));
+        }
+        self.params.extend_from_slice(params);
+    }
+
+    /// Sets one of the gesture's parameters.
+    pub fn set(&self, cx: &mut EventContext, param: ParamPtr, normalized: f32) {
+        debug_assert!(
+            self.params.contains(&param),
+            "The parameter isn't part of the gesture"
+        );
+        cx.emit(RawParamEvent::SetParameterNormalized(param, normalized));
+    }
+
+    /// Ends the gesture, if it has begun.
+    pub fn end(&mut self, cx: &mut EventContext) {
+        for param in self.params.drain(..) {
+            cx.emit(RawParamEvent::EndSetParameter(param));
+        }
+    }
+}
+
+/// Sets a parameter in a gesture of its own, for one-off changes like resets, presets and dice
+/// rolls.
+pub fn set_once(cx: &mut EventContext, param: ParamPtr, normalized: f32) {
+    cx.emit(RawParamEvent::BeginSetParameter(param));
+    cx.emit(RawParamEvent::SetParameterNormalized(param, normalized));
+    cx.emit(RawParamEvent::EndSetParameter(param));
+}
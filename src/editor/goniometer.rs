@@ -0,0 +1,122 @@
+//! A goniometer with a phase correlation meter for the output.
+
+use nih_plug_vizia::vizia::prelude::*;
+use nih_plug_vizia::vizia::vg;
+use std::sync::Arc;
+
+use crate::ScopeBuffer;
+
+/// The opacity of the dots, so the picture doesn't drown out the axes.
+const DOT_ALPHA: u8 = 110;
+/// The opacity of the axes and the center line of the correlation bar.
+const GUIDE_ALPHA: u8 = 50;
+/// The height of the correlation bar, in logical pixels.
+const CORRELATION_BAR_HEIGHT: f32 = 8.0;
+/// Quieter signals are scaled up as if they peaked at this level, so the picture doesn't shrink
+/// to a dot but noise doesn't fill the display either.
+const MIN_SCALE_PEAK: f32 = 0.05;
+
+/// A goniometer (or vectorscope) for the output's left and right channels, rotated so mono
+/// material is a vertical line and out of phase material a horizontal one. Left leans to the upper
+/// left and right to the upper right. The picture is scaled to its own peak. The correlation bar
+/// underneath runs from -1 on the left to 1 on the right, with the fill starting from the center.
+///
+/// The background is drawn with the `background-color` and the dots and the bar with the `color`
+/// style properties. A negative correlation is drawn in the `outline-color`.
+pub struct Goniometer<L: Lens<Target = f32>> {
+    scope_buffer: Arc<ScopeBuffer>,
+    correlation: L,
+}
+
+impl<L: Lens<Target = f32>> Goniometer<L> {
+    pub fn new(cx: &mut Context, scope_buffer: Arc<ScopeBuffer>, correlation: L) -> Handle<Self> {
+        Self {
+            scope_buffer,
+            correlation,
+        }
+        .build(cx, |_| {})
+        .role(Role::Image)
+    }
+}
+
+impl<L: Lens<Target = f32>> View for Goniometer<L> {
+    fn element(&self) -> Option<&'static str> {
+        Some("goniometer")
+    }
+
+    fn draw(&self, cx: &mut DrawContext, canvas: &mut Canvas) {
+        let bounds = cx.bounds();
+        let bar_height = CORRELATION_BAR_HEIGHT * cx.scale_factor();
+        if bounds.w == 0.0 || bounds.h <= bar_height {
+            return;
+        }
+
+        let mut path = vg::Path::new();
+        path.rect(bounds.x, bounds.y, bounds.w, bounds.h);
+        canvas.fill_path(&path, &vg::Paint::color(cx.background_color().into()));
+
+        let color: vg::Color = cx.font_color().into();
+        let mut guide_color = color;
+        guide_color.set_alpha(GUIDE_ALPHA);
+        let mut dot_color = color;
+        dot_color.set_alpha(DOT_ALPHA);
+        let line_width = cx.scale_factor();
+
+        // The picture is a square above the correlation bar, with the mid and side axes
+        let scope_height = bounds.h - bar_height;
+        let radius = scope_height.min(bounds.w) / 2.0;
+        let (center_x, center_y) = (bounds.x + bounds.w / 2.0, bounds.y + scope_height / 2.0);
+        let mut path = vg::Path::new();
+        path.move_to(center_x, center_y - radius);
+        path.line_to(center_x, center_y + radius);
+        path.move_to(center_x - radius, center_y);
+        path.line_to(center_x + radius, center_y);
+        canvas.stroke_path(
+            &path,
+            &vg::Paint::color(guide_color).with_line_width(line_width),
+        );
+
+        let (left, right) = self.scope_buffer.read_stereo();
+        let peak = left
+            .iter()
+            .chain(&right)
+            .fold(MIN_SCALE_PEAK, |peak, sample| peak.max(sample.abs()));
+        // Mid goes up and side goes to the right. Neither L + R nor R - L can be more than twice
+        // the peak.
+        let scale = radius / (2.0 * peak);
+        let dot_size = line_width;
+        let mut path = vg::Path::new();
+        for (&l, &r) in left.iter().zip(&right) {
+            let x = center_x + (r - l) * scale;
+            let y = center_y - (l + r) * scale;
+            path.rect(x - dot_size / 2.0, y - dot_size / 2.0, dot_size, dot_size);
+        }
+        canvas.fill_path(&path, &vg::Paint::color(dot_color));
+
+        // The correlation bar fills from the center towards the current reading
+        let correlation = self.correlation.get(cx).clamp(-1.0, 1.0);
+        let bar_y = bounds.y + scope_height;
+        let bar_center_x = bounds.x + bounds.w / 2.0;
+        let fill_color: vg::Color = if correlation < 0.0 {
+            cx.outline_color().into()
+        } else {
+            color
+        };
+        let mut path = vg::Path::new();
+        path.rect(
+            bar_center_x.min(bar_center_x + correlation * bounds.w / 2.0),
+            bar_y,
+            (correlation * bounds.w / 2.0).abs(),
+            bar_height,
+        );
+        canvas.fill_path(&path, &vg::Paint::color(fill_color));
+
+        let mut path = vg::Path::new();
+        path.move_to(bar_center_x, bar_y);
+        path.line_to(bar_center_x, bar_y + bar_height);
+        canvas.stroke_path(
+            &path,
+            &vg::Paint::color(guide_color).with_line_width(line_width),
+        );
+    }
+}
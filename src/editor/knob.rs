@@ -0,0 +1,325 @@
+//! A rotary knob for the main macro controls.
+
+use nih_plug::prelude::Param;
+use nih_plug_vizia::vizia::prelude::*;
+use nih_plug_vizia::vizia::vg;
+use nih_plug_vizia::widgets::param_base::ParamWidgetBase;
+
+/// The angle of the knob at its minimum value, in radians. Zero points to the right and the angle
+/// increases clockwise. The knob sweeps 270 degrees, leaving a gap at the bottom.
+const MIN_ANGLE: f32 = 0.75 * std::f32::consts::PI;
+const MAX_ANGLE: f32 = 2.25 * std::f32::consts::PI;
+/// How far the mouse needs to be dragged vertically to sweep the knob's full range, in logical
+/// pixels.
+const DRAG_DISTANCE: f32 = 200.0;
+/// The drag speed multiplier used while Shift is held down.
+const GRANULAR_DRAG_MULTIPLIER: f32 = 0.1;
+/// The width of the arc, relative to the knob's radius.
+const ARC_WIDTH: f32 = 0.15;
+
+/// A rotary knob that draws an arc from the parameter's default value to its current value.
+/// Dragging vertically changes the value, holding Shift while dragging allows finer adjustments,
+/// Ctrl+clicking (Cmd+clicking on macOS) resets the parameter to its default value, and double
+/// clicking opens a text field to type in a value. The text is parsed with the parameter's own
+/// string to value conversion, so units can be left out. Once the knob has focus, the arrow keys
+/// step it up and down, with Shift taking finer steps.
+///
+/// Screen readers see the knob as a slider named after the parameter, with the parameter's
+/// displayed value, so it's announced as "Amount, 40 %".
+///
+/// The knob's track is drawn with the `background-color` and the value arc and pointer with the
+/// `color` style properties. The text field has the `value-entry` class.
+#[derive(Lens)]
+pub struct ParamKnob {
+    #[lens(ignore)]
+    param_base: ParamWidgetBase,
+
+    /// The vertical mouse position and normalized value the current drag is relative to. This is
+    /// reset whenever Shift is pressed or released so the knob doesn't jump when switching between
+    /// regular and granular dragging. `None` when the knob isn't being dragged.
+    #[lens(ignore)]
+    drag_anchor: Option<(f32, f32)>,
+    /// Whether the current drag is a granular drag.
+    #[lens(ignore)]
+    granular_drag: bool,
+    /// Whether the text field is open.
+    text_input_active: bool,
+}
+
+enum ParamKnobEvent {
+    /// The text field was submitted with this text.
+    TextInput(String),
+    /// The text field lost focus without being submitted.
+    CancelTextInput,
+}
+
+impl ParamKnob {
+    /// Creates a new [`ParamKnob`] for the parameter selected by `params_to_param`.
+    pub fn new<L, Params, P, FMap>(
+        cx: &mut Context,
+        params: L,
+        params_to_param: FMap,
+    ) -> Handle<Self>
+    where
+        L: Lens<Target = Params> + Clone,
+        Params: 'static,
+        P: Param + 'static,
+        FMap: Fn(&Params) -> &P + Copy + 'static,
+    {
+        let param_base = ParamWidgetBase::new(cx, params.clone(), params_to_param);
+        let name = param_base.name().to_owned();
+        let display_value =
+            ParamWidgetBase::make_lens(params.clone(), params_to_param, |param| param.to_string());
+        let normalized_value =
+            ParamWidgetBase::make_lens(params.clone(), params_to_param, |param| {
+                param.unmodulated_normalized_value()
+            });
+
+        Self {
+            param_base,
+
+            drag_anchor: None,
+            granular_drag: false,
+            text_input_active: false,
+        }
+        .build(
+            cx,
+            ParamWidgetBase::build_view(params, params_to_param, |cx, param_data| {
+                Binding::new(
+                    cx,
+                    ParamKnob::text_input_active,
+                    move |cx, text_input_active| {
+                        if text_input_active.get(cx) {
+                            let display_value = param_data.make_lens(|param| {
+                                param.normalized_value_to_string(
+                                    param.unmodulated_normalized_value(),
+                                    false,
+                                )
+                            });
+                            Self::text_input_view(cx, display_value);
+                        }
+                    },
+                );
+            }),
+        )
+        .role(Role::Slider)
+        .name(name)
+        .text_value(display_value)
+        .numeric_value(normalized_value)
+    }
+
+    /// The text field that opens on a double click, with the current value selected.
+    fn text_input_view(cx: &mut Context, display_value: impl Lens<Target = String>) {
+        Textbox::new(cx, display_value)
+            .class("value-entry")
+            .on_submit(|cx, text, success| {
+                if success {
+                    cx.emit(ParamKnobEvent::TextInput(text));
+                } else {
+                    cx.emit(ParamKnobEvent::CancelTextInput);
+                }
+            })
+            .on_build(|cx| {
+                cx.emit(TextEvent::StartEdit);
+                cx.emit(TextEvent::SelectAll);
+            })
+            .top(Stretch(1.0))
+            .bottom(Stretch(1.0))
+            .left(Stretch(1.0))
+            .right(Stretch(1.0));
+    }
+
+    /// Stops the current drag, if any.
+    fn end_drag(&mut self, cx: &mut EventContext) {
+        if self.drag_anchor.take().is_some() {
+            cx.release();
+            cx.set_active(false);
+
+            self.param_base.end_set_parameter(cx);
+        }
+    }
+
+    /// Maps a normalized value to the knob's angle.
+    fn value_to_angle(normalized_value: f32) -> f32 {
+        MIN_ANGLE + (MAX_ANGLE - MIN_ANGLE) * normalized_value.clamp(0.0, 1.0)
+    }
+}
+
+impl View for ParamKnob {
+    fn element(&self) -> Option<&'static str> {
+        Some("param-knob")
+    }
+
+    fn event(&mut self, cx: &mut EventContext, event: &mut Event) {
+        event.map(|knob_event, meta| {
+            match knob_event {
+                ParamKnobEvent::TextInput(text) => {
+                    if let Some(normalized_value) = self.param_base.string_to_normalized_value(text)
+                    {
+                        self.param_base.begin_set_parameter(cx);
+                        self.param_base.set_normalized_value(cx, normalized_value);
+                        self.param_base.end_set_parameter(cx);
+                    }
+                }
+                ParamKnobEvent::CancelTextInput => (),
+            }
+            self.text_input_active = false;
+            cx.set_active(false);
+
+            meta.consume();
+        });
+
+        // The text field handles its own mouse events
+        if self.text_input_active {
+            return;
+        }
+        event.map(|window_event, meta| match window_event {
+            // Ctrl+click (Cmd+click on macOS) resets the parameter instead of starting a drag
+            WindowEvent::MouseDown(MouseButton::Left) if cx.modifiers().command() => {
+                self.param_base.begin_set_parameter(cx);
+                self.param_base
+                    .set_normalized_value(cx, self.param_base.default_normalized_value());
+                self.param_base.end_set_parameter(cx);
+
+                meta.consume();
+            }
+            WindowEvent::MouseDown(MouseButton::Left) => {
+                // A drag that never got its mouse up, for example because the window lost focus,
+                // is ended first so the host sees every gesture end
+                self.end_drag(cx);
+
+                cx.capture();
+                cx.focus();
+                cx.set_active(true);
+
+                self.param_base.begin_set_parameter(cx);
+                self.drag_anchor = Some((
+                    cx.mouse().cursory,
+                    self.param_base.unmodulated_normalized_value(),
+                ));
+                self.granular_drag = cx.modifiers().shift();
+
+                meta.consume();
+            }
+            // The second click of a double click starts a drag, which is ended before the text
+            // field opens
+            WindowEvent::MouseDoubleClick(MouseButton::Left) => {
+                self.end_drag(cx);
+                self.text_input_active = true;
+                cx.set_active(true);
+
+                meta.consume();
+            }
+            WindowEvent::MouseMove(_x, y) => {
+                if let Some((anchor_y, anchor_value)) = self.drag_anchor {
+                    // Switching between regular and granular dragging starts a new drag from the
+                    // current position
+                    let granular_drag = cx.modifiers().shift();
+                    if granular_drag != self.granular_drag {
+                        self.granular_drag = granular_drag;
+                        self.drag_anchor =
+                            Some((*y, self.param_base.unmodulated_normalized_value()));
+                        return;
+                    }
+
+                    let speed = if granular_drag {
+                        GRANULAR_DRAG_MULTIPLIER
+                    } else {
+                        1.0
+                    };
+                    let delta = (anchor_y - *y) / (DRAG_DISTANCE * cx.scale_factor()) * speed;
+                    self.param_base
+                        .set_normalized_value(cx, (anchor_value + delta).clamp(0.0, 1.0));
+                }
+            }
+            WindowEvent::MouseUp(MouseButton::Left) => {
+                if self.drag_anchor.is_some() {
+                    self.end_drag(cx);
+
+                    meta.consume();
+                }
+            }
+            // The arrow keys take the same steps as the mouse wheel on nih-plug's sliders
+            WindowEvent::KeyDown(code, _) if self.drag_anchor.is_none() => {
+                let current_value = self.param_base.unmodulated_normalized_value();
+                let finer = cx.modifiers().shift();
+                let new_value = match code {
+                    Code::ArrowUp | Code::ArrowRight => {
+                        self.param_base.next_normalized_step(current_value, finer)
+                    }
+                    Code::ArrowDown | Code::ArrowLeft => self
+                        .param_base
+                        .previous_normalized_step(current_value, finer),
+                    _ => return,
+                };
+
+                self.param_base.begin_set_parameter(cx);
+                self.param_base.set_normalized_value(cx, new_value);
+                self.param_base.end_set_parameter(cx);
+
+                meta.consume();
+            }
+            _ => {}
+        });
+    }
+
+    fn draw(&self, cx: &mut DrawContext, canvas: &mut Canvas) {
+        let bounds = cx.bounds();
+        if bounds.w == 0.0 || bounds.h == 0.0 {
+            return;
+        }
+
+        let radius = bounds.w.min(bounds.h) / 2.0;
+        let arc_width = radius * ARC_WIDTH;
+        let arc_radius = radius - arc_width / 2.0;
+        let (center_x, center_y) = bounds.center();
+
+        // The track spans the knob's entire range
+        let mut path = vg::Path::new();
+        path.arc(
+            center_x,
+            center_y,
+            arc_radius,
+            MIN_ANGLE,
+            MAX_ANGLE,
+            vg::Solidity::Hole,
+        );
+        let track_paint = vg::Paint::color(cx.background_color().into())
+            .with_line_width(arc_width)
+            .with_line_cap(vg::LineCap::Round);
+        canvas.stroke_path(&path, &track_paint);
+
+        // The value arc starts at the default value, so bipolar parameters like Tilt fill outwards
+        // from the center
+        let default_angle = Self::value_to_angle(self.param_base.default_normalized_value());
+        let value_angle = Self::value_to_angle(self.param_base.unmodulated_normalized_value());
+        let value_paint = vg::Paint::color(cx.font_color().into())
+            .with_line_width(arc_width)
+            .with_line_cap(vg::LineCap::Round);
+        if (value_angle - default_angle).abs() > f32::EPSILON {
+            let mut path = vg::Path::new();
+            path.arc(
+                center_x,
+                center_y,
+                arc_radius,
+                default_angle.min(value_angle),
+                default_angle.max(value_angle),
+                vg::Solidity::Hole,
+            );
+            canvas.stroke_path(&path, &value_paint);
+        }
+
+        // A pointer from the center of the knob to the current value
+        let pointer_length = arc_radius - arc_width * 1.5;
+        let mut path = vg::Path::new();
+        path.move_to(
+            center_x + value_angle.cos() * pointer_length * 0.3,
+            center_y + value_angle.sin() * pointer_length * 0.3,
+        );
+        path.line_to(
+            center_x + value_angle.cos() * pointer_length,
+            center_y + value_angle.sin() * pointer_length,
+        );
+        canvas.stroke_path(&path, &value_paint);
+    }
+}
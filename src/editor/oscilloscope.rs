@@ -0,0 +1,65 @@
+//! An oscilloscope comparing the dry and the wet signal.
+
+use nih_plug_vizia::vizia::prelude::*;
+use nih_plug_vizia::vizia::vg;
+use std::sync::Arc;
+
+use crate::ScopeBuffer;
+
+/// The opacity of the dry trace, which sits behind the wet trace in the same color.
+const DRY_TRACE_ALPHA: u8 = 90;
+
+/// A triggered oscilloscope that overlays the wet signal on the dry signal, both summed to mono.
+/// The traces start at a rising zero crossing of the dry signal, so periodic material stands
+/// still, and the wet trace shows how the saturation flattens the peaks and how the compression
+/// and the envelope shaping change the level.
+///
+/// The background is drawn with the `background-color` and the traces with the `color` style
+/// properties. The dry trace is drawn translucent.
+pub struct Oscilloscope {
+    scope_buffer: Arc<ScopeBuffer>,
+}
+
+impl Oscilloscope {
+    pub fn new(cx: &mut Context, scope_buffer: Arc<ScopeBuffer>) -> Handle<Self> {
+        Self { scope_buffer }.build(cx, |_| {}).role(Role::Image)
+    }
+}
+
+impl View for Oscilloscope {
+    fn element(&self) -> Option<&'static str> {
+        Some("oscilloscope")
+    }
+
+    fn draw(&self, cx: &mut DrawContext, canvas: &mut Canvas) {
+        let bounds = cx.bounds();
+        if bounds.w == 0.0 || bounds.h == 0.0 {
+            return;
+        }
+
+        let mut path = vg::Path::new();
+        path.rect(bounds.x, bounds.y, bounds.w, bounds.h);
+        canvas.fill_path(&path, &vg::Paint::color(cx.background_color().into()));
+
+        let (dry, wet) = self.scope_buffer.read_triggered();
+        let wet_color: vg::Color = cx.font_color().into();
+        let mut dry_color = wet_color;
+        dry_color.set_alpha(DRY_TRACE_ALPHA);
+
+        // 0 dBFS reaches the top and bottom edges
+        let line_width = 1.5 * cx.scale_factor();
+        for (samples, color) in [(&dry, dry_color), (&wet, wet_color)] {
+            let mut path = vg::Path::new();
+            for (sample_idx, &sample) in samples.iter().enumerate() {
+                let x = bounds.x + bounds.w * sample_idx as f32 / (samples.len() - 1) as f32;
+                let y = bounds.y + bounds.h / 2.0 - sample.clamp(-1.0, 1.0) * bounds.h / 2.0;
+                if sample_idx == 0 {
+                    path.move_to(x, y);
+                } else {
+                    path.line_to(x, y);
+                }
+            }
+            canvas.stroke_path(&path, &vg::Paint::color(color).with_line_width(line_width));
+        }
+    }
+}
@@ -0,0 +1,245 @@
+//! # LFOs
+//!
+//! Free-running or tempo-synced LFOs, which turn the plugin into a rhythmic spectral animator. They
+//! are sources in the modulation matrix, see [`crate::modulation`].
+use nih_plug::prelude::*;
+
+use crate::morph::Morph;
+
+/// The number of LFOs.
+pub const NUM_LFOS: usize = 2;
+
+/// Every LFO's output is slewed over this time so the steps of the sample & hold shape and the
+/// saw's reset don't click.
+const LFO_SLEW_MS: f32 = 2.0;
+
+/// The tempo synced LFOs assume when the host doesn't report one.
+const DEFAULT_TEMPO: f64 = 120.0;
+
+/// The waveform of an LFO.
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LfoShape {
+    #[name = "Sine"]
+    Sine,
+    #[name = "Triangle"]
+    Triangle,
+    #[name = "Saw"]
+    Saw,
+    /// A new random value at the start of every cycle.
+    #[name = "Sample & Hold"]
+    SampleAndHold,
+}
+
+/// The cycle length of a tempo synced LFO.
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LfoDivision {
+    #[name = "4 Bars"]
+    FourBars,
+    #[name = "2 Bars"]
+    TwoBars,
+    #[name = "1 Bar"]
+    OneBar,
+    #[name = "1/2"]
+    Half,
+    #[name = "1/4"]
+    Quarter,
+    #[name = "1/4 T"]
+    QuarterTriplet,
+    #[name = "1/8"]
+    Eighth,
+    #[name = "1/8 T"]
+    EighthTriplet,
+    #[name = "1/16"]
+    Sixteenth,
+}
+
+impl LfoDivision {
+    /// The cycle length in quarter notes, given the length of a bar in quarter notes.
+    fn quarter_notes(self, bar_length: f64) -> f64 {
+        match self {
+            LfoDivision::FourBars => 4.0 * bar_length,
+            LfoDivision::TwoBars => 2.0 * bar_length,
+            LfoDivision::OneBar => bar_length,
+            LfoDivision::Half => 2.0,
+            LfoDivision::Quarter => 1.0,
+            LfoDivision::QuarterTriplet => 2.0 / 3.0,
+            LfoDivision::Eighth => 0.5,
+            LfoDivision::EighthTriplet => 1.0 / 3.0,
+            LfoDivision::Sixteenth => 0.25,
+        }
+    }
+}
+
+/// The parameters of a single LFO.
+#[derive(Params)]
+pub struct LfoParams {
+    #[id = "shape"]
+    pub shape: EnumParam<LfoShape>,
+
+    /// Locks the LFO to the host's tempo and song position.
+    #[id = "sync"]
+    pub sync: BoolParam,
+
+    /// The LFO's rate when it isn't synced.
+    #[id = "rate"]
+    pub rate: FloatParam,
+
+    /// The LFO's cycle length when it's synced.
+    #[id = "division"]
+    pub division: EnumParam<LfoDivision>,
+}
+
+impl LfoParams {
+    pub fn new(lfo_idx: usize) -> Self {
+        Self {
+            shape: EnumParam::new(format!("LFO {} Shape", lfo_idx + 1), LfoShape::Sine),
+            sync: BoolParam::new(format!("LFO {} Sync", lfo_idx + 1), true),
+            rate: FloatParam::new(
+                format!("LFO {} Rate", lfo_idx + 1),
+                1.0,
+                FloatRange::Skewed {
+                    min: 0.01,
+                    max: 20.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_unit(" Hz")
+            .with_value_to_string(formatters::v2s_f32_rounded(2)),
+            division: EnumParam::new(format!("LFO {} Division", lfo_idx + 1), LfoDivision::OneBar),
+        }
+    }
+}
+
+/// The parts of the host's transport the LFOs follow. Without a host, as in the offline renderer,
+/// everything is left unset and synced LFOs run freely at the default tempo.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TransportInfo {
+    pub playing: bool,
+    pub tempo: Option<f64>,
+    pub time_sig_numerator: Option<i32>,
+    pub time_sig_denominator: Option<i32>,
+    /// The song position in quarter notes.
+    pub pos_beats: Option<f64>,
+}
+
+impl From<&Transport> for TransportInfo {
+    fn from(transport: &Transport) -> Self {
+        Self {
+            playing: transport.playing,
+            tempo: transport.tempo,
+            time_sig_numerator: transport.time_sig_numerator,
+            time_sig_denominator: transport.time_sig_denominator,
+            pos_beats: transport.pos_beats(),
+        }
+    }
+}
+
+/// The state of a single LFO.
+pub struct Lfo {
+    /// The position in the current cycle, from 0 to 1.
+    phase: f64,
+    /// The phase increment per sample for the current block.
+    increment: f64,
+    shape: LfoShape,
+    /// The sample & hold shape's current value.
+    held: f32,
+    /// The sample & hold shape's random number generator.
+    rng_state: u32,
+    /// The LFO's slewed output.
+    output: f32,
+    slew_alpha: f32,
+}
+
+impl Lfo {
+    /// Creates an LFO. Every seed gives a different sample & hold sequence.
+    pub fn new(seed: u32) -> Self {
+        Self {
+            phase: 0.0,
+            increment: 0.0,
+            shape: LfoShape::Sine,
+            held: 0.0,
+            // Xorshift gets stuck at zero
+            rng_state: seed.max(1),
+            output: 0.0,
+            slew_alpha: 1.0,
+        }
+    }
+
+    /// Picks up the LFO's settings for the coming block, with the snapshot morph applied. Synced
+    /// LFOs follow the host's tempo, and are locked to the song position while the transport is
+    /// running.
+    pub fn begin_block(
+        &mut self,
+        params: &LfoParams,
+        morph: &Morph,
+        transport: &TransportInfo,
+        sample_rate: f32,
+    ) {
+        self.shape = morph.value(&params.shape);
+        self.slew_alpha = 1.0 - (-1.0 / (LFO_SLEW_MS * sample_rate / 1000.0)).exp();
+
+        if !morph.value(&params.sync) {
+            self.increment = morph.value(&params.rate) as f64 / sample_rate as f64;
+            return;
+        }
+
+        let bar_length = match (transport.time_sig_numerator, transport.time_sig_denominator) {
+            (Some(numerator), Some(denominator)) if denominator > 0 => {
+                numerator as f64 * 4.0 / denominator as f64
+            }
+            _ => 4.0,
+        };
+        let cycle_quarter_notes = morph.value(&params.division).quarter_notes(bar_length);
+        let tempo = transport.tempo.unwrap_or(DEFAULT_TEMPO);
+        self.increment = tempo / 60.0 / cycle_quarter_notes / sample_rate as f64;
+
+        if transport.playing
+            && let Some(pos_beats) = transport.pos_beats
+        {
+            let phase = (pos_beats / cycle_quarter_notes).rem_euclid(1.0);
+            // Jumping past the end of a cycle still starts a new one
+            if phase < self.phase - 0.5 {
+                self.next_random();
+            }
+            self.phase = phase;
+        }
+    }
+
+    /// Advances the LFO by one sample and returns its output, from -1 to 1.
+    pub fn next(&mut self) -> f32 {
+        let phase = self.phase as f32;
+        let value = match self.shape {
+            LfoShape::Sine => (std::f32::consts::TAU * phase).sin(),
+            LfoShape::Triangle => 1.0 - (4.0 * ((phase + 0.25).fract() - 0.5).abs()),
+            LfoShape::Saw => (2.0 * (phase + 0.5).fract()) - 1.0,
+            LfoShape::SampleAndHold => self.held,
+        };
+        self.output += self.slew_alpha * (value - self.output);
+
+        self.phase += self.increment;
+        if self.phase >= 1.0 {
+            self.phase = self.phase.fract();
+            self.next_random();
+        }
+
+        self.output
+    }
+
+    /// Restarts the LFO from the beginning of its cycle.
+    pub fn reset(&mut self) {
+        self.phase = 0.0;
+        self.output = 0.0;
+    }
+
+    /// Draws the sample & hold shape's next value.
+    fn next_random(&mut self) {
+        // Xorshift32, more than random enough for an LFO
+        let mut x = self.rng_state;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.rng_state = x;
+
+        self.held = ((x as f32 / u32::MAX as f32) * 2.0) - 1.0;
+    }
+}
@@ -2,37 +2,68 @@
 //!
 //! A multiband dynamics processor with a colorful character.
 //!
-//! A 5-band dynamic processor designed as a creative "soundgoodizer" and sound design tool.
+//! A 3- to 6-band (5 by default) dynamic processor designed as a creative "soundgoodizer" and sound
+//! design tool.
 //!
 //!
 //! ## Architecture
 //!
 //! The plugin employs a hybrid parallel/serial signal flow:
 //!
-//! 1.  **Parallel Dynamics:** The incoming audio is split into 5 frequency bands using Linkwitz-Riley
-//!     crossover filters. Each band is processed in parallel with its own compressor and saturator.
+//! 1.  **Parallel Dynamics:** The incoming audio is split into 3-6 frequency bands using 4th order
+//!     Linkwitz-Riley crossover filters, with allpass compensation so the bands sum back flat. Each band is processed in parallel with its own compressor and saturator.
 //!     The parameters for these processes (threshold, ratio, attack, release, saturation drive) are
 //!     dynamically linked to the main "Amount" and "Tilt" knobs.
-//! 2.  **Serial EQ:** After the dynamics stage, the 5 processed bands are summed back together. This
-//!     "wet" signal is then passed through a series of 5 cascading peaking EQ filters. The gain and
+//! 2.  **Serial EQ:** After the dynamics stage, the processed bands are summed back together. This
+//!     "wet" signal is then passed through a series of cascading peaking EQ filters, one per band. The gain and
 //!     Q of these filters are also dynamically linked to the "Amount" and "Tilt" knobs, creating
 //!     a resonant, shifting character that interacts with itself.
-//! 3.  **Loudness Compensation:** The RMS level of the final wet signal is compared to the original
-//!     dry signal, and an automatic gain correction is applied to maintain consistent perceived loudness.
+//! 3.  **Dry Path:** The dry signal runs through an allpass chain that mirrors the crossover phase
+//!     response, keeping partial Mix settings free of comb filtering.
+//! 4.  **Loudness Compensation:** The K-weighted short-term loudness of the wet signal is compared
+//!     to the dry signal, and an automatic gain correction is applied to maintain consistent
+//!     perceived loudness.
 use nih_plug_vizia::ViziaState;
 
 #[cfg(feature = "vizia")]
 mod editor;
 
-// All of our DSP code is in here
-mod dsp;
-
-use dsp::{
-    Biquad, MAX_BANDS, MAX_COMPENSATION_DB, ProcessingBand, TILT_MAX_SHIFT_SEMITONES,
-    shift_frequency,
+mod denormals;
+mod lfo;
+mod midi;
+mod modulation;
+mod morph;
+mod offline;
+mod presets;
+mod scope;
+mod spectral;
+mod state;
+
+pub use offline::OfflineRenderer;
+
+use colorfall_dsp::balance::{self, SpectralBalance, TargetCurve};
+use colorfall_dsp::loudness::{self, LoudnessTracker, Weighting};
+use colorfall_dsp::{
+    self as dsp, AntiAliasing, BiquadCoefficients, Character, Choice, CrestTracker,
+    CrossoverAllpass, CrossoverSlope, DEFAULT_BANDS, DEFAULT_GR_SMOOTHING, DelayLine,
+    EnvelopeCurve, Exciter, GR_RANGE_MAX_DB, GrSmoother, IntensityAdaptation,
+    LinkwitzRileyCrossover, MAX_BANDS, MAX_CHANNELS, MAX_COMPENSATION_DB, MIN_BANDS, NoiseTexture,
+    OVERSAMPLING_LATENCY, PhaseColor, ProcessingBand, Quality, RampedBiquad, ReleaseModel,
+    SaturationModel, TILT_MAX_SHIFT_SEMITONES, TruePeakDetector, TruePeakLimiter, band_position,
+    band_side, from_real, shift_frequency, to_real,
+};
+use denormals::ScopedFtz;
+use lfo::{LfoParams, NUM_LFOS, TransportInfo};
+use midi::{
+    MidiCcAssignments, MidiCcMapper, MidiLearn, MidiMappingParams, MidiTarget, NUM_MIDI_TARGETS,
 };
+use modulation::{ModMatrix, ModSlotParams, NUM_MOD_SLOTS};
+use morph::{Morph, MorphSnapshots};
 use nih_plug::prelude::*;
-use std::{f32::consts::FRAC_PI_2, sync::atomic::Ordering};
+use scope::ScopeBuffer;
+use spectral::{SPECTRAL_LATENCY, SpectralEngine, SpectralSettings};
+use std::f32::consts::FRAC_PI_2;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::{num::NonZeroU32, sync::Arc};
 
 // --- PLUGIN PARAMETERS ---
@@ -43,6 +74,27 @@ struct ColorFallParams {
     #[cfg(feature = "vizia")]
     #[persist = "editor-state"]
     editor_state: Arc<ViziaState>,
+    /// The index of the editor's color theme, see `editor::EditorTheme`.
+    #[cfg(feature = "vizia")]
+    #[persist = "editor-theme"]
+    editor_theme: Arc<std::sync::atomic::AtomicU32>,
+    /// The seed of the last Dice roll, so the roll can be recalled. See `editor::dice`.
+    #[cfg(feature = "vizia")]
+    #[persist = "dice-seed"]
+    dice_seed: Arc<std::sync::atomic::AtomicU64>,
+    /// The controls the Dice leave alone, one bit per `editor::dice::DiceTarget`.
+    #[cfg(feature = "vizia")]
+    #[persist = "dice-locks"]
+    dice_locks: Arc<std::sync::atomic::AtomicU32>,
+    /// The name of the preset that was loaded or saved last, empty if there is none. See
+    /// `presets`.
+    #[cfg(feature = "vizia")]
+    #[persist = "preset-name"]
+    preset_name: Arc<std::sync::RwLock<String>>,
+    /// The index of the `balance::TargetCurve` that Learn compares the input with.
+    #[cfg(feature = "vizia")]
+    #[persist = "learn-target"]
+    learn_target: Arc<std::sync::atomic::AtomicU32>,
     /// The main control knob. Drives compression, saturation, and EQ gain.
     /// Ranges from 0.0 (subtle) to 1.0 (mangled).
     #[id = "amount"]
@@ -53,6 +105,34 @@ struct ColorFallParams {
     #[id = "tilt"]
     pub tilt: FloatParam,
 
+    /// The difference between the lows and the highs of the broadband tilt EQ at full Tilt. The
+    /// EQ is neutral at 0 dB.
+    #[id = "tilt_eq"]
+    pub tilt_eq: FloatParam,
+
+    /// How far the Tilt follows the input's spectral centroid, towards the low bands for dark
+    /// material and the high bands for bright material. Off at 0%.
+    #[id = "tilt_follow"]
+    pub tilt_follow: FloatParam,
+
+    /// How long Tilt Follow takes to catch up with a change in the input's brightness.
+    #[id = "tilt_follow_speed"]
+    pub tilt_follow_speed: FloatParam,
+
+    /// Rotates the phase of the processed signal around the Tilt-shifted crossover frequencies
+    /// without changing its magnitude response. Higher settings ring more. Off at 0%.
+    #[id = "phase_color"]
+    pub phase_color: FloatParam,
+
+    /// Biases the band trims to push the input towards the sidechain's spectral balance. Off at
+    /// 0%, and without a signal on the sidechain.
+    #[id = "reference_match"]
+    pub reference_match: FloatParam,
+
+    /// Crossfades to the unprocessed input while keeping the processing running in the background.
+    #[id = "bypass"]
+    pub bypass: BoolParam,
+
     /// The dry/wet mix of the plugin.
     #[id = "mix"]
     pub mix: FloatParam,
@@ -60,6 +140,584 @@ struct ColorFallParams {
     /// A final output gain stage.
     #[id = "output"]
     pub output: FloatParam,
+
+    /// Enables the automatic loudness compensation that matches the wet signal's level to the dry
+    /// signal.
+    #[id = "auto_gain"]
+    pub auto_gain: BoolParam,
+
+    /// How much of the automatic loudness compensation is applied, in the decibel domain.
+    #[id = "auto_gain_strength"]
+    pub auto_gain_strength: FloatParam,
+
+    /// The true-peak ceiling of the output limiter, which runs after the output gain stage.
+    #[id = "ceiling"]
+    pub ceiling: FloatParam,
+
+    /// Whether the bands are split with crossover filters or on FFT bins.
+    #[id = "mode"]
+    pub mode: EnumParam<Mode>,
+
+    /// The number of active bands, from 3 to 6. Each band count has its own crossover layout.
+    #[id = "band_count"]
+    pub band_count: IntParam,
+
+    /// The slope of the crossover filters that split the bands.
+    #[id = "crossover_slope"]
+    pub crossover_slope: EnumParam<DspEnum<CrossoverSlope>>,
+
+    /// Scales the program-derived attack times of all bands, from 0.25x to 4x.
+    #[id = "attack_scale"]
+    pub attack_scale: FloatParam,
+
+    /// Scales the program-derived release times of all bands, from 0.25x to 4x.
+    #[id = "release_scale"]
+    pub release_scale: FloatParam,
+
+    /// Shortens the attack and lengthens the release of every band around the hits its transient
+    /// detector spots. Off at 0%.
+    #[id = "adaptive_timing"]
+    pub adaptive_timing: FloatParam,
+
+    /// How much of the band's attack and release time the applied gain reduction is smoothed
+    /// over. At 0% the audio gets the gain computer's output as is.
+    #[id = "gr_smoothing"]
+    pub gr_smoothing: FloatParam,
+
+    /// The shape of the band envelope followers' attack.
+    #[id = "attack_curve"]
+    pub attack_curve: EnumParam<DspEnum<EnvelopeCurve>>,
+
+    /// The shape of the band envelope followers' release.
+    #[id = "release_curve"]
+    pub release_curve: EnumParam<DspEnum<EnvelopeCurve>>,
+
+    /// The modeled release behavior of the band envelope followers.
+    #[id = "release_model"]
+    pub release_model: EnumParam<DspEnum<ReleaseModel>>,
+
+    /// The most gain reduction any band's compressor can apply, no matter how hard Amount pushes
+    /// it. The highest setting leaves the gain reduction uncapped.
+    #[id = "range"]
+    pub range: FloatParam,
+
+    /// Adapts the band compressors' thresholds and ratios to the input's level and crest factor,
+    /// so Amount gives a similar amount of gain reduction on any material.
+    #[id = "auto_intensity"]
+    pub auto_intensity: BoolParam,
+
+    /// Swaps the tables that map Amount and Tilt to the compressors and the reactive EQ.
+    #[id = "character"]
+    pub character: EnumParam<DspEnum<Character>>,
+
+    /// The character of the per-band saturators.
+    #[id = "saturation"]
+    pub saturation: EnumParam<DspEnum<SaturationModel>>,
+
+    /// How the saturators suppress aliasing. The High quality mode oversamples the saturators
+    /// instead, which overrides this.
+    #[id = "anti_aliasing"]
+    pub anti_aliasing: EnumParam<DspEnum<AntiAliasing>>,
+
+    /// Trades CPU usage for quality: the oversampling, the filter coefficient update rate, and
+    /// the detectors' precision. See [`Quality`].
+    #[id = "quality"]
+    pub quality: EnumParam<DspEnum<Quality>>,
+
+    /// Outputs the difference between the processed and the dry signal instead of the processed
+    /// signal itself.
+    #[id = "delta"]
+    pub delta: BoolParam,
+
+    /// Balances downward and upward compression. 0% is downward only, 50% runs both at full depth
+    /// and 100% is upward only.
+    #[id = "up_down"]
+    pub up_down: FloatParam,
+
+    /// The transient shaper macro. Positive values add punch and tighten the sustain, negative
+    /// values soften the attacks and bring the sustain up. The depth grows with Amount.
+    #[id = "punch"]
+    pub punch: FloatParam,
+
+    /// How much of the exciter's upper harmonics is blended into the top band.
+    #[id = "air"]
+    pub air: FloatParam,
+
+    /// The level of the per-band noise layer. The noise follows every band's level and grows
+    /// with Amount. The layer is off at 0%.
+    #[id = "noise"]
+    pub noise: FloatParam,
+
+    /// Whether the noise layer hisses or crackles.
+    #[id = "noise_texture"]
+    pub noise_texture: EnumParam<DspEnum<NoiseTexture>>,
+
+    /// What the band envelope followers measure.
+    #[id = "detector_mode"]
+    pub detector_mode: EnumParam<DetectorMode>,
+
+    /// Whether the band envelope followers listen to the bands before or after their gain
+    /// reduction.
+    #[id = "topology"]
+    pub topology: EnumParam<Topology>,
+
+    /// Whether the bands are saturated before or after their gain reduction.
+    #[id = "processing_order"]
+    pub processing_order: EnumParam<ProcessingOrder>,
+
+    /// Enables the reactive EQ's compensation boosts after the bands. With this off only the
+    /// multiband dynamics are left. The Dynamic EQ mode's filters apply the GR, so they stay on.
+    #[id = "reactive_eq"]
+    pub reactive_eq: BoolParam,
+
+    /// The reactive EQ's boost when its band is reduced by 24 dB, before Amount, Tilt and the
+    /// character scale it.
+    #[id = "eq_depth"]
+    pub eq_depth: FloatParam,
+
+    /// Shifts the reactive EQ's Qs away from Amount. Negative values keep the boosts broad and
+    /// positive values make them ring, at any Amount. At 0% the Qs follow Amount.
+    #[id = "color"]
+    pub color: FloatParam,
+
+    /// The cutoff of the high-pass filter in front of the band detectors, so the low end doesn't
+    /// dominate the gain reduction. The filter is off at the lowest setting.
+    #[id = "detector_hpf"]
+    pub detector_hpf: FloatParam,
+
+    /// Weights the band detectors with a perceptual curve, so the gain reduction follows how loud
+    /// a band sounds rather than its raw power.
+    #[id = "detector_weighting"]
+    pub detector_weighting: EnumParam<DspEnum<Weighting>>,
+
+    /// Monitors the signal the band envelope followers measure instead of the processed output.
+    #[id = "listen"]
+    pub listen: EnumParam<Listen>,
+
+    /// How the GR meter follows the gain reduction.
+    #[id = "meter_ballistics"]
+    pub meter_ballistics: EnumParam<MeterBallistics>,
+
+    /// How quickly the GR meter falls back with the peak ballistics.
+    #[id = "meter_decay"]
+    pub meter_decay: FloatParam,
+
+    /// How the band detectors are linked across channels.
+    #[id = "channel_link"]
+    pub channel_link: EnumParam<ChannelLink>,
+
+    /// Which of the lowest bands are collapsed to mono before they're processed.
+    #[id = "mono_below"]
+    pub mono_below: EnumParam<MonoBelow>,
+
+    /// How far the mono bands are collapsed. At 100% they're fully mono.
+    #[id = "mono_amount"]
+    pub mono_amount: FloatParam,
+
+    /// The extra gain reduction a MIDI note at full velocity pushes into the bands selected with
+    /// the bands' Duck switches. Ducking is off at 0 dB.
+    #[id = "duck_depth"]
+    pub duck_depth: FloatParam,
+
+    /// How long the ducking takes to reach its full depth after a note.
+    #[id = "duck_attack"]
+    pub duck_attack: FloatParam,
+
+    /// How long the ducking takes to recover after reaching its full depth.
+    #[id = "duck_release"]
+    pub duck_release: FloatParam,
+
+    /// The controls that exist once per band. nih-plug numbers the array, so hosts list them in
+    /// groups from Band 1 up, and their IDs get the band number as a suffix (`solo_1`).
+    #[nested(array, group = "Band")]
+    pub bands: [BandParams; MAX_BANDS],
+
+    /// Per-crossover frequency offsets on top of the band count's default layout.
+    #[nested(array, group = "Crossover")]
+    pub crossovers: [CrossoverParams; MAX_BANDS - 1],
+
+    /// The LFOs that can be routed through the modulation matrix.
+    #[nested(array, group = "LFO")]
+    pub lfos: [LfoParams; NUM_LFOS],
+
+    /// The modulation matrix slots, each routing a modulation source to a control.
+    #[nested(array, group = "Modulation")]
+    pub mod_slots: [ModSlotParams; NUM_MOD_SLOTS],
+
+    /// Selects the control the next incoming MIDI CC gets assigned to.
+    #[id = "midi_learn"]
+    pub midi_learn: EnumParam<MidiLearn>,
+
+    /// The range and smoothing of the CC mapping for every control that can be driven by MIDI.
+    #[nested(array, group = "MIDI")]
+    pub midi_mappings: [MidiMappingParams; NUM_MIDI_TARGETS],
+
+    /// The CC assigned to every control. See [`MidiCcAssignments`].
+    #[persist = "midi-cc-assignments"]
+    midi_cc_assignments: Arc<MidiCcAssignments>,
+
+    /// Blends between snapshots A and B. Only has an effect once both snapshots are stored.
+    #[id = "morph"]
+    pub morph: FloatParam,
+
+    /// The format version of the saved state, used to migrate sessions saved by older versions of
+    /// the plugin. See [`state`].
+    #[persist = "state-version"]
+    state_version: Arc<std::sync::atomic::AtomicU32>,
+
+    /// The snapshots the Morph parameter blends between. See [`MorphSnapshots`].
+    #[persist = "morph-snapshots"]
+    morph_snapshots: Arc<MorphSnapshots>,
+}
+
+/// Turns one of the DSP crate's [`Choice`]s into an [`Enum`], so it can be used as an
+/// [`EnumParam`]. The option names and their order come from the DSP crate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct DspEnum<T>(T);
+
+impl<T: Choice> Enum for DspEnum<T> {
+    fn variants() -> &'static [&'static str] {
+        T::NAMES
+    }
+
+    fn ids() -> Option<&'static [&'static str]> {
+        None
+    }
+
+    fn to_index(self) -> usize {
+        self.0.to_index()
+    }
+
+    fn from_index(index: usize) -> Self {
+        DspEnum(T::from_index(index))
+    }
+}
+
+/// What the per-band envelope followers measure.
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
+enum DetectorMode {
+    /// Follows the instantaneous signal power with the attack/release envelope. This is the
+    /// original ColorFall detector.
+    #[name = "Power"]
+    Power,
+    /// Follows the power averaged over a short sliding window, for smoother mastering-style
+    /// behavior.
+    #[name = "RMS"]
+    Rms,
+    /// Follows the absolute sample value, reacting to every peak.
+    #[name = "Peak"]
+    Peak,
+}
+
+/// How the input is split into bands and processed.
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /// Crossover filters split the input into the bands, which are saturated and compressed one
+    /// by one and summed back together.
+    #[name = "Multiband"]
+    Multiband,
+    /// The spectral engine compresses dozens of pseudo-bands on FFT bins, grouped by the bands.
+    /// There's no saturation, per-band extras, or reactive EQ in this mode. See
+    /// [`spectral::SpectralEngine`].
+    #[name = "Spectral"]
+    Spectral,
+    /// The bands are split and detected like in the multiband mode, but their downward gain
+    /// reduction is applied by the reactive EQ's peaking filters instead of turning the whole band
+    /// down. The compensation boost is left out.
+    #[name = "Dynamic EQ"]
+    DynamicEq,
+}
+
+/// What the plugin's main output carries.
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
+enum Listen {
+    /// The processed output.
+    #[name = "Output"]
+    Output,
+    /// The sum of the audible bands' detector signals, after the detector HPF and weighting, for
+    /// dialing those in by ear. This skips the reactive EQ, the loudness compensation, and the
+    /// dry/wet mix.
+    #[name = "Detector"]
+    Detector,
+}
+
+/// How the GR meter follows the gain reduction.
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
+enum MeterBallistics {
+    /// Jumps to the deepest gain reduction of every block and falls back over the Meter Decay
+    /// time.
+    #[name = "Peak"]
+    Peak,
+    /// Integrates the average gain reduction like a VU meter, rising and falling over about
+    /// 300 ms.
+    #[name = "VU"]
+    Vu,
+    /// Shows the deepest gain reduction of every block as it is.
+    #[name = "Instant"]
+    Instant,
+}
+
+impl MeterBallistics {
+    /// A meter's next reading, from its `current` reading and the `peak` and `average` readings
+    /// of a block that lasted `block_ms`. Higher readings are a larger deflection. `decay_ms` is
+    /// the peak ballistics' fall time constant.
+    fn next(self, current: f32, peak: f32, average: f32, block_ms: f32, decay_ms: f32) -> f32 {
+        match self {
+            MeterBallistics::Peak if peak > current => peak,
+            MeterBallistics::Peak => {
+                let decay = (-block_ms / decay_ms).exp();
+                (current * decay) + (peak * (1.0 - decay))
+            }
+            MeterBallistics::Vu => {
+                let alpha = 1.0 - (-block_ms / VU_TIME_CONSTANT_MS).exp();
+                current + (alpha * (average - current))
+            }
+            MeterBallistics::Instant => peak,
+        }
+    }
+}
+
+/// Where the per-band envelope followers pick up the signal they measure.
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
+enum Topology {
+    /// The detectors listen to the band before its gain reduction. This is the original ColorFall
+    /// behavior, with precise, predictable gain reduction.
+    #[name = "Feed-Forward"]
+    FeedForward,
+    /// The detectors listen to the band after its gain reduction, like many vintage compressors.
+    /// The compressor backs off as it works, which gives softer ratios and a smoother, gluey
+    /// response.
+    #[name = "Feedback"]
+    Feedback,
+}
+
+/// The order of the saturator and the gain stages within each band.
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
+enum ProcessingOrder {
+    /// The bands are saturated first and the compressors control the saturated signal. This is
+    /// the original ColorFall behavior.
+    #[name = "Saturate → Compress"]
+    SaturateFirst,
+    /// The bands are compressed first and the saturator colors the controlled signal, which
+    /// drives it more evenly for a cleaner, more consistent color.
+    #[name = "Compress → Saturate"]
+    CompressFirst,
+}
+
+/// How the per-band envelope detectors are linked across channels.
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
+enum ChannelLink {
+    /// Every channel has its own detector. For stereo this is the classic dual-mono behavior.
+    #[name = "Independent"]
+    Independent,
+    /// Channels are linked in pairs. Surround layouts link L/R and each pair of surround channels,
+    /// while the center and LFE channels get their own detectors.
+    #[name = "Pairs"]
+    Pairs,
+    /// All channels share one detector, which keeps the surround image stable.
+    #[name = "All"]
+    All,
+}
+
+impl ChannelLink {
+    /// Returns the detector group a channel belongs to. Channels in the same group share their
+    /// detector input.
+    fn group(self, channel: usize, num_channels: usize) -> usize {
+        match self {
+            ChannelLink::Independent => channel,
+            ChannelLink::All => 0,
+            // Surround layouts use the L, R, C, LFE, Ls, Rs, (Lrs, Rrs) channel order
+            ChannelLink::Pairs if num_channels >= 6 => match channel {
+                0 | 1 => 0,
+                2 => 1,
+                3 => 2,
+                4 | 5 => 3,
+                _ => 4,
+            },
+            ChannelLink::Pairs => channel / 2,
+        }
+    }
+}
+
+/// Which of the lowest bands get collapsed to mono, for a tight mono low end.
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
+enum MonoBelow {
+    #[name = "Off"]
+    Off,
+    #[name = "Lowest Band"]
+    LowestBand,
+    #[name = "Lowest Two Bands"]
+    LowestTwoBands,
+}
+
+impl MonoBelow {
+    /// The number of bands, counting up from the lowest one, that are collapsed to mono.
+    fn num_bands(self) -> usize {
+        match self {
+            MonoBelow::Off => 0,
+            MonoBelow::LowestBand => 1,
+            MonoBelow::LowestTwoBands => 2,
+        }
+    }
+}
+
+/// The left and right channels of every stereo pair in a layout. Surround layouts use the L, R, C,
+/// LFE, Ls, Rs, (Lrs, Rrs) channel order, so the center and LFE channels aren't part of a pair.
+fn stereo_pairs(num_channels: usize) -> &'static [(usize, usize)] {
+    match num_channels {
+        0 | 1 => &[],
+        2..=5 => &[(0, 1)],
+        6 | 7 => &[(0, 1), (4, 5)],
+        _ => &[(0, 1), (4, 5), (6, 7)],
+    }
+}
+
+/// The parameters that exist once per crossover.
+#[derive(Params)]
+struct CrossoverParams {
+    /// Moves the crossover away from its default frequency for the current band count, in
+    /// semitones. Tilt is applied on top of this.
+    #[id = "shift"]
+    pub shift: FloatParam,
+}
+
+impl CrossoverParams {
+    fn new(crossover_idx: usize) -> Self {
+        Self {
+            shift: FloatParam::new(
+                format!("Crossover {} Shift", crossover_idx + 1),
+                0.0,
+                FloatRange::Linear {
+                    min: -CROSSOVER_MAX_SHIFT_SEMITONES,
+                    max: CROSSOVER_MAX_SHIFT_SEMITONES,
+                },
+            )
+            .with_unit(" st")
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+        }
+    }
+}
+
+/// The parameters that exist once per band.
+#[derive(Params)]
+struct BandParams {
+    /// Only listen to this band (and any other soloed bands). Solo takes precedence over mute.
+    #[id = "solo"]
+    pub solo: BoolParam,
+
+    /// Remove this band from the wet signal. The band is still processed so that its gain
+    /// reduction keeps driving the reactive EQ.
+    #[id = "mute"]
+    pub mute: BoolParam,
+
+    /// Passes this band through untouched: no saturation, no gain reduction, and no contribution
+    /// to the reactive EQ. The band is still processed in the background so it's ready when the
+    /// bypass is turned off.
+    #[id = "bypass"]
+    pub bypass: BoolParam,
+
+    /// Scales the compression intensity derived from Amount and Tilt for this band, from -50% to
+    /// +50%.
+    #[id = "amount_trim"]
+    pub amount_trim: FloatParam,
+
+    /// Offsets the saturation drive for this band on top of the drive derived from Amount.
+    #[id = "drive"]
+    pub drive: FloatParam,
+
+    /// Scales the side signal of this band's stereo pairs after compression. 0% is mono and 200%
+    /// doubles the width.
+    #[id = "width"]
+    pub width: FloatParam,
+
+    /// Blends the processed band with the band's own unprocessed signal, for parallel compression
+    /// on a single band.
+    #[id = "mix"]
+    pub mix: FloatParam,
+
+    /// Lets MIDI notes duck this band by the Duck Depth.
+    #[id = "duck"]
+    pub duck: BoolParam,
+
+    /// Enables downward expansion for this band, with a ratio derived from Amount.
+    #[id = "expand"]
+    pub expand: BoolParam,
+
+    /// The level below which the band's expander starts pushing the signal down.
+    #[id = "expand_threshold"]
+    pub expand_threshold: FloatParam,
+
+    /// Turns the band into a de-esser: the detector only hears the sibilance range, the
+    /// compressor reacts faster and cuts deeper, and the band isn't saturated. Meant for the
+    /// upper bands.
+    #[id = "de_ess"]
+    pub de_ess: BoolParam,
+}
+
+impl BandParams {
+    fn new(band_idx: usize) -> Self {
+        Self {
+            solo: BoolParam::new(format!("Band {} Solo", band_idx + 1), false),
+            mute: BoolParam::new(format!("Band {} Mute", band_idx + 1), false),
+            bypass: BoolParam::new(format!("Band {} Bypass", band_idx + 1), false),
+            amount_trim: FloatParam::new(
+                format!("Band {} Amount", band_idx + 1),
+                0.0,
+                FloatRange::Linear {
+                    min: -0.5,
+                    max: 0.5,
+                },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_unit(" %")
+            .with_value_to_string(formatters::v2s_f32_percentage(1))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            drive: FloatParam::new(
+                format!("Band {} Drive", band_idx + 1),
+                0.0,
+                FloatRange::Linear {
+                    min: -12.0,
+                    max: 12.0,
+                },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+            width: FloatParam::new(
+                format!("Band {} Width", band_idx + 1),
+                1.0,
+                FloatRange::Linear { min: 0.0, max: 2.0 },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_unit(" %")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            mix: FloatParam::new(
+                format!("Band {} Mix", band_idx + 1),
+                1.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_unit(" %")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            // Kick-keyed ducking is mostly used on the low end
+            duck: BoolParam::new(format!("Band {} Duck", band_idx + 1), band_idx == 0),
+            expand: BoolParam::new(format!("Band {} Expand", band_idx + 1), false),
+            expand_threshold: FloatParam::new(
+                format!("Band {} Expand Threshold", band_idx + 1),
+                -50.0,
+                FloatRange::Linear {
+                    min: -80.0,
+                    max: -10.0,
+                },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+            de_ess: BoolParam::new(format!("Band {} De-Ess", band_idx + 1), false),
+        }
+    }
 }
 
 impl Default for ColorFallParams {
@@ -82,6 +740,56 @@ impl Default for ColorFallParams {
             .with_unit(" Semitones")
             .with_value_to_string(formatters::v2s_f32_rounded(2))
             .with_smoother(SmoothingStyle::Exponential(50.0)),
+            tilt_eq: FloatParam::new(
+                "Tilt EQ",
+                0.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: TILT_EQ_MAX_DB,
+                },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+            tilt_follow: FloatParam::new(
+                "Tilt Follow",
+                0.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_unit(" %")
+            .with_value_to_string(formatters::v2s_f32_percentage(1))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            tilt_follow_speed: FloatParam::new(
+                "Tilt Follow Speed",
+                1000.0,
+                FloatRange::Skewed {
+                    min: 100.0,
+                    max: 10000.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_unit(" ms")
+            .with_value_to_string(formatters::v2s_f32_rounded(0)),
+            phase_color: FloatParam::new(
+                "Phase Color",
+                0.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_unit(" %")
+            .with_value_to_string(formatters::v2s_f32_percentage(1))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            reference_match: FloatParam::new(
+                "Reference Match",
+                0.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_unit(" %")
+            .with_value_to_string(formatters::v2s_f32_percentage(1))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            bypass: BoolParam::new("Bypass", false).make_bypass(),
             mix: FloatParam::new(
                 "Mix",
                 1.0, // Default Mix: 100% wet to showcase the effect immediately
@@ -89,6 +797,7 @@ impl Default for ColorFallParams {
             )
             .with_unit("%")
             .with_value_to_string(formatters::v2s_f32_percentage(1))
+            .with_string_to_value(formatters::s2v_f32_percentage())
             // Using a constant power pan smoother for a more perceptually linear dry/wet mix
             .with_smoother(SmoothingStyle::Linear(50.0)),
             output: FloatParam::new(
@@ -100,17 +809,329 @@ impl Default for ColorFallParams {
                 },
             )
             .with_unit(" dB")
-            .with_value_to_string(formatters::v2s_f32_gain_to_db(1))
+            .with_value_to_string(formatters::v2s_f32_rounded(1))
             .with_smoother(SmoothingStyle::Exponential(50.0)),
+            auto_gain: BoolParam::new("Auto Gain", true),
+            auto_gain_strength: FloatParam::new(
+                "Auto Gain Strength",
+                1.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_unit(" %")
+            .with_value_to_string(formatters::v2s_f32_percentage(1))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            ceiling: FloatParam::new(
+                "Ceiling",
+                0.0,
+                FloatRange::Linear {
+                    min: -24.0,
+                    max: 0.0,
+                },
+            )
+            .with_unit(" dBTP")
+            .with_value_to_string(formatters::v2s_f32_rounded(1))
+            .with_smoother(SmoothingStyle::Linear(50.0)),
+            mode: EnumParam::new("Mode", Mode::Multiband),
+            band_count: IntParam::new(
+                "Bands",
+                DEFAULT_BANDS as i32,
+                IntRange::Linear {
+                    min: MIN_BANDS as i32,
+                    max: MAX_BANDS as i32,
+                },
+            ),
+            crossover_slope: EnumParam::new("Crossover Slope", DspEnum(CrossoverSlope::Slope24)),
+            attack_scale: FloatParam::new("Attack Scale", 1.0, TIME_SCALE_RANGE)
+                .with_smoother(SmoothingStyle::Exponential(50.0))
+                .with_unit("x")
+                .with_value_to_string(formatters::v2s_f32_rounded(2)),
+            release_scale: FloatParam::new("Release Scale", 1.0, TIME_SCALE_RANGE)
+                .with_smoother(SmoothingStyle::Exponential(50.0))
+                .with_unit("x")
+                .with_value_to_string(formatters::v2s_f32_rounded(2)),
+            adaptive_timing: FloatParam::new(
+                "Adaptive Timing",
+                0.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_unit(" %")
+            .with_value_to_string(formatters::v2s_f32_percentage(1))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            gr_smoothing: FloatParam::new(
+                "GR Smoothing",
+                DEFAULT_GR_SMOOTHING,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_unit(" %")
+            .with_value_to_string(formatters::v2s_f32_percentage(1))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            attack_curve: EnumParam::new("Attack Curve", DspEnum(EnvelopeCurve::Exponential)),
+            release_curve: EnumParam::new("Release Curve", DspEnum(EnvelopeCurve::Exponential)),
+            release_model: EnumParam::new("Release Model", DspEnum(ReleaseModel::Vca)),
+            range: FloatParam::new(
+                "Range",
+                GR_RANGE_MAX_DB,
+                FloatRange::Skewed {
+                    min: 1.0,
+                    max: GR_RANGE_MAX_DB,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+            auto_intensity: BoolParam::new("Auto Intensity", false),
+            character: EnumParam::new("Character", DspEnum(Character::Smooth)),
+            saturation: EnumParam::new("Saturation", DspEnum(SaturationModel::Cubic)),
+            anti_aliasing: EnumParam::new("Anti-Aliasing", DspEnum(AntiAliasing::Off)),
+            quality: EnumParam::new("Quality", DspEnum(Quality::Normal)),
+            delta: BoolParam::new("Delta", false),
+            up_down: FloatParam::new("Up/Down", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_smoother(SmoothingStyle::Linear(50.0))
+                .with_unit(" %")
+                .with_value_to_string(formatters::v2s_f32_percentage(1))
+                .with_string_to_value(formatters::s2v_f32_percentage()),
+            punch: FloatParam::new(
+                "Punch",
+                0.0,
+                FloatRange::Linear {
+                    min: -1.0,
+                    max: 1.0,
+                },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_unit(" %")
+            .with_value_to_string(formatters::v2s_f32_percentage(1))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            air: FloatParam::new("Air", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_smoother(SmoothingStyle::Linear(50.0))
+                .with_unit(" %")
+                .with_value_to_string(formatters::v2s_f32_percentage(1))
+                .with_string_to_value(formatters::s2v_f32_percentage()),
+            noise: FloatParam::new("Noise", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_smoother(SmoothingStyle::Linear(50.0))
+                .with_unit(" %")
+                .with_value_to_string(formatters::v2s_f32_percentage(1))
+                .with_string_to_value(formatters::s2v_f32_percentage()),
+            noise_texture: EnumParam::new("Noise Texture", DspEnum(NoiseTexture::Hiss)),
+            detector_mode: EnumParam::new("Detector", DetectorMode::Power),
+            topology: EnumParam::new("Topology", Topology::FeedForward),
+            processing_order: EnumParam::new("Order", ProcessingOrder::SaturateFirst),
+            reactive_eq: BoolParam::new("Reactive EQ", true),
+            eq_depth: FloatParam::new(
+                "EQ Depth",
+                MAX_COMPENSATION_DB,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: EQ_DEPTH_MAX_DB,
+                },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+            color: FloatParam::new(
+                "Color",
+                0.0,
+                FloatRange::Linear {
+                    min: -1.0,
+                    max: 1.0,
+                },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_unit(" %")
+            .with_value_to_string(formatters::v2s_f32_percentage(1))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            detector_hpf: FloatParam::new(
+                "Detector HPF",
+                DETECTOR_HPF_MIN_HZ,
+                FloatRange::Skewed {
+                    min: DETECTOR_HPF_MIN_HZ,
+                    max: 500.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_unit(" Hz")
+            .with_value_to_string(formatters::v2s_f32_rounded(0)),
+            detector_weighting: EnumParam::new("Detector Weighting", DspEnum(Weighting::Flat)),
+            listen: EnumParam::new("Listen", Listen::Output),
+            meter_ballistics: EnumParam::new("Meter Ballistics", MeterBallistics::Peak),
+            meter_decay: FloatParam::new(
+                "Meter Decay",
+                METER_DECAY_MS,
+                FloatRange::Skewed {
+                    min: 50.0,
+                    max: 3000.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_unit(" ms")
+            .with_value_to_string(formatters::v2s_f32_rounded(0)),
+            channel_link: EnumParam::new("Channel Link", ChannelLink::Independent),
+            mono_below: EnumParam::new("Mono Below", MonoBelow::Off),
+            mono_amount: FloatParam::new(
+                "Mono Amount",
+                1.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_unit(" %")
+            .with_value_to_string(formatters::v2s_f32_percentage(1))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            duck_depth: FloatParam::new(
+                "Duck Depth",
+                0.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 24.0,
+                },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+            duck_attack: FloatParam::new(
+                "Duck Attack",
+                2.0,
+                FloatRange::Skewed {
+                    min: 0.0,
+                    max: 50.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_unit(" ms")
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+            duck_release: FloatParam::new(
+                "Duck Release",
+                150.0,
+                FloatRange::Skewed {
+                    min: 10.0,
+                    max: 1000.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_unit(" ms")
+            .with_value_to_string(formatters::v2s_f32_rounded(0)),
+            bands: array_init::array_init(BandParams::new),
+            crossovers: array_init::array_init(CrossoverParams::new),
+            lfos: array_init::array_init(LfoParams::new),
+            mod_slots: array_init::array_init(ModSlotParams::new),
+            midi_learn: EnumParam::new("MIDI Learn", MidiLearn::Off),
+            midi_mappings: array_init::array_init(MidiMappingParams::new),
+            midi_cc_assignments: Arc::new(MidiCcAssignments::new([-1; NUM_MIDI_TARGETS])),
+            morph: FloatParam::new("Morph", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_smoother(SmoothingStyle::Linear(50.0))
+                .with_unit(" %")
+                .with_value_to_string(formatters::v2s_f32_percentage(1))
+                .with_string_to_value(formatters::s2v_f32_percentage()),
+            state_version: Arc::new(std::sync::atomic::AtomicU32::new(state::STATE_VERSION)),
+            morph_snapshots: Arc::new(MorphSnapshots::new([None, None])),
             // GUI state
             #[cfg(feature = "vizia")]
             editor_state: Self::default_editor_state(),
+            #[cfg(feature = "vizia")]
+            editor_theme: Arc::new(std::sync::atomic::AtomicU32::new(0)),
+            #[cfg(feature = "vizia")]
+            dice_seed: Arc::new(std::sync::atomic::AtomicU64::new(0)),
+            #[cfg(feature = "vizia")]
+            dice_locks: Arc::new(std::sync::atomic::AtomicU32::new(0)),
+            #[cfg(feature = "vizia")]
+            preset_name: Arc::new(std::sync::RwLock::new(String::new())),
+            #[cfg(feature = "vizia")]
+            learn_target: Arc::new(std::sync::atomic::AtomicU32::new(
+                TargetCurve::default().to_index() as u32,
+            )),
         }
     }
 }
+/// How far each crossover can be moved from its default frequency, in semitones.
+const CROSSOVER_MAX_SHIFT_SEMITONES: f32 = 24.0;
+
+/// The highest EQ Depth, twice the reactive EQ's original boost.
+const EQ_DEPTH_MAX_DB: f32 = 12.0;
+/// The maximum tilt of the broadband tilt EQ, from the lows to the highs.
+const TILT_EQ_MAX_DB: f32 = 12.0;
+/// The frequency the tilt EQ pivots around. Both shelves sit here.
+const TILT_EQ_PIVOT_HZ: f32 = 1000.0;
+/// The Q of the tilt EQ's shelves. This spreads the slope out over several octaves.
+const TILT_EQ_Q: f32 = 0.5;
+/// The lowest Q of the Dynamic EQ mode's peaking filters. The outer bands run out to 20 Hz and
+/// Nyquist, which would make their filters wider than useful.
+const DYNAMIC_EQ_MIN_Q: f32 = 0.4;
+
+/// The number of wet and dry auxiliary outputs. These always come last.
+const NUM_WET_DRY_OUTPUTS: usize = 2;
+/// The names of the auxiliary outputs of the multi-out layout: one per band, then the wet and dry
+/// signals.
+const MULTI_OUTPUT_NAMES: [&str; MAX_BANDS + NUM_WET_DRY_OUTPUTS] = [
+    "Band 1", "Band 2", "Band 3", "Band 4", "Band 5", "Band 6", "Wet", "Dry",
+];
+/// The names of the auxiliary outputs of the wet/dry layout.
+const WET_DRY_OUTPUT_NAMES: [&str; NUM_WET_DRY_OUTPUTS] = ["Wet", "Dry"];
+
+/// The lowest setting of the detector high-pass filter, where it's switched off.
+const DETECTOR_HPF_MIN_HZ: f32 = 20.0;
+
+/// The length of the crossfade when the plugin is bypassed or re-engaged.
+const BYPASS_CROSSFADE_MS: f32 = 20.0;
+
+/// The range for the attack and release multipliers. 1x sits in the middle of the range.
+const TIME_SCALE_RANGE: FloatRange = FloatRange::SymmetricalSkewed {
+    min: 0.25,
+    max: 4.0,
+    factor: 1.0,
+    center: 1.0,
+};
+
 impl ColorFallParams {
+    /// The active band count.
+    pub(crate) fn num_bands(&self) -> usize {
+        (self.band_count.value() as usize).clamp(MIN_BANDS, MAX_BANDS)
+    }
+
+    /// The crossover frequencies at `sample_rate` for `num_bands` bands with the given tilt and the
+    /// crossover shift parameters applied. Only the first `num_bands - 1` frequencies are used.
+    /// The crossovers are kept in order with at least a third of an octave between them, and well
+    /// below Nyquist.
+    pub(crate) fn crossover_frequencies(
+        &self,
+        sample_rate: f32,
+        num_bands: usize,
+        tilt: f32,
+    ) -> [f32; MAX_BANDS - 1] {
+        self.crossover_frequencies_with(sample_rate, num_bands, tilt, |shift| shift.value())
+    }
+
+    /// [`Self::crossover_frequencies()`], with the crossover shifts read through `shift`. The
+    /// audio thread uses this to apply the snapshot morph.
+    fn crossover_frequencies_with(
+        &self,
+        sample_rate: f32,
+        num_bands: usize,
+        tilt: f32,
+        shift: impl Fn(&FloatParam) -> f32,
+    ) -> [f32; MAX_BANDS - 1] {
+        dsp::crossover_frequencies(sample_rate, num_bands, tilt, |j| {
+            shift(&self.crossovers[j].shift)
+        })
+    }
+
+    /// The value for crossover `crossover_idx`'s shift parameter that moves it to `freq`, ignoring
+    /// the neighbouring crossovers. This is the inverse of [`Self::crossover_frequencies()`].
+    pub(crate) fn crossover_shift_for_frequency(
+        &self,
+        crossover_idx: usize,
+        num_bands: usize,
+        tilt: f32,
+        freq: f32,
+    ) -> f32 {
+        let base_freq = shift_frequency(dsp::base_crossover_freqs(num_bands)[crossover_idx], tilt);
+
+        12.0 * (freq / base_freq).log2()
+    }
+
     fn default_editor_state() -> Arc<ViziaState> {
-        ViziaState::new(|| (500, 350))
+        ViziaState::new(|| (640, 800))
     }
 }
 
@@ -119,28 +1140,368 @@ impl ColorFallParams {
 /// The main plugin structure, holding the parameters and the DSP state.
 /// This is the heart of the plugin, where audio processing and parameter handling
 /// are orchestrated.
-struct ColorFall {
+pub struct ColorFall {
     params: Arc<ColorFallParams>,
     sample_rate: f32,
 
+    /// The number of main input channels for the active audio layout. With the mono-to-stereo
+    /// layout, the mono input feeds both output channels.
+    num_input_channels: usize,
+
+    /// The band count the filter states were last used with. Changing the band count moves every
+    /// crossover, so the filter and envelope states are reset when this changes.
+    active_bands: usize,
+
+    /// The low and high shelves of the broadband tilt EQ in front of the crossovers.
+    tilt_eq: [RampedBiquad; 2],
+    /// The Phase Color allpass chain on the summed bands.
+    phase_color: PhaseColor,
+    /// Replaces the crossovers and the bands in the spectral mode.
+    spectral: SpectralEngine,
+
     // Crossover filters to split the signal into bands
-    crossovers: [Biquad; MAX_BANDS - 1],
+    crossovers: [LinkwitzRileyCrossover; MAX_BANDS - 1],
+    /// The center frequency of every active band for the current crossover frequencies. These are
+    /// updated along with the crossovers, see [`Quality::coefficient_update_interval()`].
+    band_center_freqs: [f32; MAX_BANDS],
+    /// The Q of a peaking filter spanning every active band, for the Dynamic EQ mode. These are
+    /// updated along with the center frequencies.
+    band_qs: [f32; MAX_BANDS],
+    /// The reactive EQ's center frequency, Q and gain for every band, as last calculated. These
+    /// are updated along with the filter coefficients, which doesn't happen in every block, so
+    /// they're kept here for the GUI's EQ curve.
+    eq_settings: [(f32, f32, f32); MAX_BANDS],
+
+    /// Allpass filters that give the dry signal the same phase response as the summed bands, so
+    /// partial Mix settings don't comb filter.
+    dry_phase_compensation: [CrossoverAllpass; MAX_BANDS - 1],
+    /// The previous phase-compensated dry sample for every channel, used to delay the dry signal
+    /// by the same half or full sample the ADAA saturators add to the wet signal.
+    dry_history: [f32; MAX_CHANNELS],
+    /// Delays the dry signal by the latency of the wet path in front of the dry/wet mix.
+    dry_compensation_delay: DelayLine,
 
     // The processing chain for each band
     bands: [ProcessingBand; MAX_BANDS],
-
-    /// RMS trackers for the dry and wet signals, used for automatic gain compensation.
-    dry_rms_tracker: f32,
-    wet_rms_tracker: f32,
+    /// Smooths every band's gains before they're applied.
+    band_gain_smoothers: [BandGainSmoothers; MAX_BANDS],
+    /// Generates the upper harmonics the Air control adds to the top band.
+    exciter: Exciter,
+
+    /// The true-peak limiter at the very end of the signal chain.
+    limiter: TruePeakLimiter,
+    /// Measures the final output's true peak level for the clip indicator.
+    output_peak_detector: TruePeakDetector,
+
+    /// Delays the unprocessed input by the plugin's full latency so bypassing stays
+    /// sample-aligned.
+    bypass_delay: DelayLine,
+    /// Delays the per-band auxiliary outputs by the latency after the point they're taken from,
+    /// so they line up with the main output.
+    band_output_delays: [DelayLine; MAX_BANDS],
+    /// The same for the wet and dry auxiliary outputs.
+    wet_output_delay: DelayLine,
+    dry_output_delay: DelayLine,
+    /// Crossfades between the processed output (0.0) and the bypassed input (1.0).
+    bypass_smoother: Smoother<f32>,
+
+    /// K-weighted loudness trackers for the dry and wet signals, used for automatic gain
+    /// compensation.
+    dry_loudness: LoudnessTracker,
+    wet_loudness: LoudnessTracker,
+
+    /// Measures the dry signal's level and crest factor for Auto Intensity.
+    crest_tracker: CrestTracker,
+    /// Measures the input's spectral balance while Suggest Tilt is listening.
+    tilt_analysis: SpectralBalance,
+    /// The number of samples Suggest Tilt still needs to listen to, 0 when it isn't listening.
+    tilt_analysis_remaining: usize,
+    /// Measures the input's long-term spectrum while Learn is listening.
+    target_analysis: SpectralBalance,
+    /// The number of samples Learn still needs to listen to, 0 when it isn't listening.
+    target_analysis_remaining: usize,
+    /// The target curve of the current Learn measurement.
+    learn_target: TargetCurve,
+    /// The running spectra of the input and the sidechain for Reference Match.
+    match_input_balance: SpectralBalance,
+    match_reference_balance: SpectralBalance,
+    /// The band trims that push the input towards the sidechain's balance, before they're scaled
+    /// by Reference Match. These are updated with the crossovers.
+    reference_match_trims: [f32; MAX_BANDS],
+    /// The input's running spectrum for Tilt Follow.
+    tilt_follow_balance: SpectralBalance,
+    /// The Tilt that centers the processing on the input's spectral centroid, gliding at the Tilt
+    /// Follow Speed, before it's scaled by Tilt Follow. This is updated with the crossovers.
+    tilt_follow_offset: f32,
+    /// The last adaptation Auto Intensity measured. This is held while the input is silent.
+    intensity_adaptation: IntensityAdaptation,
 
     /// A smoother for the automatic gain correction factor to prevent sudden changes.
     loudness_correction_smoother: Smoother<f32>,
 
-    /// A smoother for the gain reduction meter to make it more readable.
-    gr_meter_smoother: Smoother<f32>,
+    /// The modulation sources and the routing between them and the controls.
+    modulation: ModMatrix,
+    /// The CC-driven values of the controls mapped to MIDI CCs.
+    midi_cc: MidiCcMapper,
+    /// Applies the A/B snapshot morph to the parameters.
+    morph: Morph,
+    /// Set by the editor when it stores or clears a snapshot.
+    morph_snapshots_changed: Arc<AtomicBool>,
+
+    /// The number of samples until the crossover and reactive EQ coefficients are recalculated.
+    coefficient_update_countdown: usize,
+    /// The latency the compensation delays are set for and that was last reported to the host.
+    /// See [`ColorFall::update_latency()`].
+    latency: Latency,
 
     /// The gain reduction value for the GUI meter.
     gain_reduction_meter: Arc<AtomicF32>,
+
+    /// The input and output levels for the GUI meters.
+    meter_levels: Arc<MeterLevels>,
+
+    /// The reactive EQ's current settings for the GUI's EQ curve.
+    eq_curve: Arc<EqCurve>,
+    /// The dry and wet signals for the GUI's oscilloscope, and the output for its goniometer.
+    scope_buffer: Arc<ScopeBuffer>,
+    /// The editor's Suggest Tilt requests and results.
+    tilt_suggestion: Arc<TiltSuggestion>,
+    /// The editor's Learn requests and results.
+    target_learn: Arc<TargetLearn>,
+    /// The running averages of `L * R`, `L * L` and `R * R` over the output, for the correlation
+    /// meter.
+    output_stereo_products: [f32; 3],
+}
+
+/// The input and output levels shown in the editor, in decibels.
+pub(crate) struct MeterLevels {
+    pub input_peak: AtomicF32,
+    pub input_rms: AtomicF32,
+    pub output_peak: AtomicF32,
+    pub output_rms: AtomicF32,
+    /// The RMS level of every band after processing.
+    pub band_levels: [AtomicF32; MAX_BANDS],
+    /// The highest true peak level of the final output since the clip indicator was last reset,
+    /// in dBTP. The editor resets this by storing [`util::MINUS_INFINITY_DB`].
+    pub output_true_peak_hold: AtomicF32,
+    /// The phase correlation between the output's left and right channels, from -1 (out of
+    /// phase) to 1 (mono). Silence reads as 0.
+    pub output_correlation: AtomicF32,
+}
+
+impl Default for MeterLevels {
+    fn default() -> Self {
+        Self {
+            input_peak: AtomicF32::new(util::MINUS_INFINITY_DB),
+            input_rms: AtomicF32::new(util::MINUS_INFINITY_DB),
+            output_peak: AtomicF32::new(util::MINUS_INFINITY_DB),
+            output_rms: AtomicF32::new(util::MINUS_INFINITY_DB),
+            band_levels: array_init::array_init(|_| AtomicF32::new(util::MINUS_INFINITY_DB)),
+            output_true_peak_hold: AtomicF32::new(util::MINUS_INFINITY_DB),
+            output_correlation: AtomicF32::new(0.0),
+        }
+    }
+}
+
+/// The reactive EQ's filter settings at the end of the last processed block, so the editor can
+/// draw the combined EQ curve.
+pub(crate) struct EqCurve {
+    pub sample_rate: AtomicF32,
+    pub num_bands: AtomicUsize,
+    pub frequencies: [AtomicF32; MAX_BANDS],
+    pub qs: [AtomicF32; MAX_BANDS],
+    pub gains_db: [AtomicF32; MAX_BANDS],
+}
+
+impl Default for EqCurve {
+    fn default() -> Self {
+        Self {
+            sample_rate: AtomicF32::new(44100.0),
+            num_bands: AtomicUsize::new(0),
+            frequencies: array_init::array_init(|_| AtomicF32::new(1000.0)),
+            qs: array_init::array_init(|_| AtomicF32::new(1.0)),
+            gains_db: array_init::array_init(|_| AtomicF32::new(0.0)),
+        }
+    }
+}
+
+impl EqCurve {
+    /// The combined magnitude response of the active bands' peaking filters at every frequency in
+    /// `freqs`, in decibels.
+    pub fn response_db(&self, freqs: &[f32]) -> Vec<f32> {
+        let sample_rate = self.sample_rate.load(Ordering::Relaxed);
+        let num_bands = self.num_bands.load(Ordering::Relaxed).min(MAX_BANDS);
+        let filters: Vec<BiquadCoefficients> = (0..num_bands)
+            .map(|band_idx| {
+                BiquadCoefficients::calculate_peaking(
+                    sample_rate,
+                    self.frequencies[band_idx].load(Ordering::Relaxed),
+                    self.qs[band_idx].load(Ordering::Relaxed),
+                    self.gains_db[band_idx].load(Ordering::Relaxed),
+                )
+            })
+            .collect();
+
+        freqs
+            .iter()
+            .map(|&freq| {
+                // Frequencies above Nyquist don't exist in the signal
+                let freq = freq.min(sample_rate / 2.0);
+                filters
+                    .iter()
+                    .map(|filter| util::gain_to_db(filter.magnitude_at(sample_rate, freq)))
+                    .sum()
+            })
+            .collect()
+    }
+}
+
+/// How long Suggest Tilt listens to the input.
+const SUGGEST_TILT_SECONDS: f32 = 5.0;
+
+/// The state of the editor's Suggest Tilt function, shared between the editor, the audio thread
+/// and the background thread. The audio thread measures the input's spectral balance for
+/// [`SUGGEST_TILT_SECONDS`], an [`AnalysisTask`] leaves the Tilt that centers the processing on its
+/// energy here, and the editor offers it to the user.
+pub(crate) struct TiltSuggestion {
+    /// Set by the editor to start a new measurement, and cleared by the audio thread once it has
+    /// started.
+    pub start: AtomicBool,
+    /// How much of the measurement is done, from 0 to 1. This stays at 1 between measurements.
+    pub progress: AtomicF32,
+    /// The Tilt suggested by the last measurement, or NaN if there is none or the input was
+    /// silent.
+    pub tilt: AtomicF32,
+}
+
+impl Default for TiltSuggestion {
+    fn default() -> Self {
+        Self {
+            start: AtomicBool::new(false),
+            progress: AtomicF32::new(1.0),
+            tilt: AtomicF32::new(f32::NAN),
+        }
+    }
+}
+
+/// How long Learn listens to the input. This is longer than Suggest Tilt, since the band trims
+/// should follow the long-term spectrum rather than a single passage.
+const LEARN_TARGET_SECONDS: f32 = 10.0;
+/// How far a band needs to be above or below the target for Learn and Reference Match to set its
+/// trim all the way to the end of its range, in decibels.
+const FULL_TRIM_EXCESS_DB: f32 = 6.0;
+/// The time constant of the spectra Reference Match compares. The bias follows the long-term
+/// balance of both signals rather than individual notes and hits.
+const REFERENCE_MATCH_AVERAGING_SECONDS: f32 = 3.0;
+/// The time constant of the spectrum Tilt Follow measures the centroid of. This only needs to
+/// smooth out the low octave bands' ripple, Tilt Follow Speed sets how fast the Tilt follows.
+const TILT_FOLLOW_AVERAGING_SECONDS: f32 = 0.1;
+
+/// The band trim that pushes a band `excess_db` louder than its target back towards it, in the
+/// `amount_trim` range: louder bands are compressed harder and quieter bands more gently.
+fn trim_for_excess(excess_db: f32) -> f32 {
+    (excess_db / FULL_TRIM_EXCESS_DB * 0.5).clamp(-0.5, 0.5)
+}
+
+/// The state of the editor's Learn function, shared between the editor, the audio thread and the
+/// background thread. The audio thread measures the input's spectrum for [`LEARN_TARGET_SECONDS`],
+/// an [`AnalysisTask`] compares it with the target curve and leaves the band trims that push the
+/// material towards the target here, and the editor offers them to the user. Bands that are louder than the target get compressed harder, and
+/// quieter bands more gently.
+pub(crate) struct TargetLearn {
+    /// Set by the editor to start a new measurement, and cleared by the audio thread once it has
+    /// started.
+    pub start: AtomicBool,
+    /// The index of the [`TargetCurve`] to compare the input with, read when the measurement
+    /// starts.
+    pub target: std::sync::atomic::AtomicU32,
+    /// How much of the measurement is done, from 0 to 1. This stays at 1 between measurements.
+    pub progress: AtomicF32,
+    /// The `amount_trim` of every band learned by the last measurement, NaN for the bands that
+    /// weren't active or if there is no result or the input was silent.
+    pub trims: [AtomicF32; MAX_BANDS],
+}
+
+impl Default for TargetLearn {
+    fn default() -> Self {
+        Self {
+            start: AtomicBool::new(false),
+            target: std::sync::atomic::AtomicU32::new(TargetCurve::default().to_index() as u32),
+            progress: AtomicF32::new(1.0),
+            trims: std::array::from_fn(|_| AtomicF32::new(f32::NAN)),
+        }
+    }
+}
+
+/// Turns a finished Suggest Tilt or Learn measurement into its result for the editor. The audio
+/// thread has to measure the live input itself, but the evaluation runs on the background thread.
+pub enum AnalysisTask {
+    /// Leaves the Tilt for the measured spectrum in `TiltSuggestion::tilt`.
+    SuggestTilt(SpectralBalance),
+    /// Leaves the band trims that push the measured spectrum towards `target` in
+    /// `TargetLearn::trims`, for a `num_bands` band split at `crossover_freqs`.
+    LearnTarget {
+        analysis: SpectralBalance,
+        target: TargetCurve,
+        sample_rate: f32,
+        crossover_freqs: [f32; MAX_BANDS - 1],
+        num_bands: usize,
+    },
+}
+
+impl AnalysisTask {
+    /// Evaluates the measurement and marks it as done.
+    fn run(self, tilt_suggestion: &TiltSuggestion, target_learn: &TargetLearn) {
+        match self {
+            AnalysisTask::SuggestTilt(analysis) => {
+                let tilt = analysis
+                    .centroid_octaves()
+                    .map_or(f32::NAN, balance::suggested_tilt);
+                tilt_suggestion.tilt.store(tilt, Ordering::Relaxed);
+                tilt_suggestion.progress.store(1.0, Ordering::Relaxed);
+            }
+            AnalysisTask::LearnTarget {
+                analysis,
+                target,
+                sample_rate,
+                crossover_freqs,
+                num_bands,
+            } => {
+                let excess_db =
+                    analysis.band_excess_db(target, sample_rate, &crossover_freqs, num_bands);
+                for (band_idx, trim) in target_learn.trims.iter().enumerate() {
+                    let value = match excess_db {
+                        Some(excess_db) if band_idx < num_bands => {
+                            trim_for_excess(excess_db[band_idx])
+                        }
+                        _ => f32::NAN,
+                    };
+                    trim.store(value, Ordering::Relaxed);
+                }
+                target_learn.progress.store(1.0, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// The time constant for the level meters falling back after a peak. This is also the GR
+/// meter's default decay.
+const METER_DECAY_MS: f32 = 300.0;
+/// The time constant of the VU meter ballistics, which reach 99% of a step in about 300 ms.
+const VU_TIME_CONSTANT_MS: f32 = 65.0;
+/// The time constant of the averages behind the correlation meter.
+const CORRELATION_TIME_MS: f32 = 300.0;
+
+/// Updates a level meter with a new value in decibels. Rising levels are shown immediately and
+/// falling levels decay smoothly. `decay` is the fraction of the old level to keep.
+fn update_meter(meter: &AtomicF32, level_db: f32, decay: f32) {
+    let current = meter.load(Ordering::Relaxed);
+    let new = if level_db > current {
+        level_db
+    } else {
+        (current * decay) + (level_db * (1.0 - decay))
+    };
+    meter.store(new, Ordering::Relaxed);
 }
 
 impl Default for ColorFall {
@@ -148,34 +1509,387 @@ impl Default for ColorFall {
         Self {
             params: Arc::default(),
             sample_rate: 44100.0,
+            num_input_channels: 2,
+            active_bands: DEFAULT_BANDS,
+            tilt_eq: Default::default(),
+            phase_color: PhaseColor::default(),
+            spectral: SpectralEngine::default(),
             crossovers: Default::default(),
+            band_center_freqs: [1000.0; MAX_BANDS],
+            band_qs: [1.0; MAX_BANDS],
+            eq_settings: [(1000.0, 1.0, 0.0); MAX_BANDS],
+            dry_phase_compensation: Default::default(),
+            dry_history: [0.0; MAX_CHANNELS],
+            dry_compensation_delay: DelayLine::default(),
             bands: Default::default(),
-            dry_rms_tracker: 0.0,
-            wet_rms_tracker: 0.0,
+            band_gain_smoothers: Default::default(),
+            exciter: Exciter::default(),
+            limiter: TruePeakLimiter::default(),
+            output_peak_detector: TruePeakDetector::default(),
+            bypass_delay: DelayLine::default(),
+            band_output_delays: Default::default(),
+            wet_output_delay: DelayLine::default(),
+            dry_output_delay: DelayLine::default(),
+            bypass_smoother: Smoother::new(SmoothingStyle::Linear(BYPASS_CROSSFADE_MS)),
+            dry_loudness: LoudnessTracker::default(),
+            wet_loudness: LoudnessTracker::default(),
+            crest_tracker: CrestTracker::default(),
+            tilt_analysis: SpectralBalance::default(),
+            tilt_analysis_remaining: 0,
+            target_analysis: SpectralBalance::default(),
+            target_analysis_remaining: 0,
+            learn_target: TargetCurve::default(),
+            match_input_balance: SpectralBalance::with_averaging_time(
+                REFERENCE_MATCH_AVERAGING_SECONDS,
+            ),
+            match_reference_balance: SpectralBalance::with_averaging_time(
+                REFERENCE_MATCH_AVERAGING_SECONDS,
+            ),
+            reference_match_trims: [0.0; MAX_BANDS],
+            tilt_follow_balance: SpectralBalance::with_averaging_time(
+                TILT_FOLLOW_AVERAGING_SECONDS,
+            ),
+            tilt_follow_offset: 0.0,
+            intensity_adaptation: IntensityAdaptation::default(),
             loudness_correction_smoother: Smoother::new(SmoothingStyle::Exponential(200.0)),
-            gr_meter_smoother: Smoother::new(SmoothingStyle::Exponential(50.0)),
+            modulation: ModMatrix::default(),
+            midi_cc: MidiCcMapper::default(),
+            morph: Morph::default(),
+            morph_snapshots_changed: Arc::new(AtomicBool::new(false)),
+            coefficient_update_countdown: 0,
+            latency: Latency::default(),
             gain_reduction_meter: Arc::new(AtomicF32::new(0.0)),
+            meter_levels: Arc::new(MeterLevels::default()),
+            eq_curve: Arc::new(EqCurve::default()),
+            scope_buffer: Arc::new(ScopeBuffer::default()),
+            tilt_suggestion: Arc::new(TiltSuggestion::default()),
+            target_learn: Arc::new(TargetLearn::default()),
+            output_stereo_products: [0.0; 3],
+        }
+    }
+}
+
+/// The latency of every stage that delays the signal by whole samples. The dry paths are delayed
+/// to match, and the total is reported to the host. Stages with a variable latency add their
+/// current latency in [`ColorFall::update_latency()`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Latency {
+    /// The oversampled saturators inside the bands.
+    oversampling: usize,
+    /// The spectral engine's frame, which replaces the bands in the spectral mode.
+    spectral: usize,
+    /// The limiter's lookahead, after the dry/wet mix.
+    limiter: usize,
+}
+
+impl Latency {
+    /// The latency of the band processing, which the bands' dry signals are delayed by.
+    fn band(self) -> usize {
+        self.oversampling
+    }
+
+    /// The latency of the wet path up to the dry/wet mix, which the dry signal is delayed by.
+    fn wet_path(self) -> usize {
+        self.band() + self.spectral
+    }
+
+    /// The plugin's total latency.
+    fn total(self) -> usize {
+        self.wet_path() + self.limiter
+    }
+}
+
+/// Smooths a band's gains for every channel. The DSP crate's gain computers return the target
+/// gains, and these glide towards them.
+struct BandGainSmoothers {
+    /// The downward compressor's gain reduction. This follows the band's attack and release times,
+    /// so it can't use a plain nih-plug smoother.
+    gr: [GrSmoother; MAX_CHANNELS],
+    /// The GR smoothers' attack and release coefficients. These are updated along with
+    /// `ProcessingBand::envelope_alphas`.
+    gr_alphas: (f32, f32),
+    upward_gain: [Smoother<f32>; MAX_CHANNELS],
+    expander_gain: [Smoother<f32>; MAX_CHANNELS],
+}
+
+impl Default for BandGainSmoothers {
+    fn default() -> Self {
+        Self {
+            gr: [GrSmoother::default(); MAX_CHANNELS],
+            gr_alphas: (1.0, 1.0),
+            upward_gain: array_init::array_init(|_| {
+                Smoother::new(SmoothingStyle::Exponential(1.0))
+            }),
+            expander_gain: array_init::array_init(|_| {
+                Smoother::new(SmoothingStyle::Exponential(1.0))
+            }),
+        }
+    }
+}
+
+impl BandGainSmoothers {
+    /// Resets every smoother to unity gain.
+    fn reset(&mut self) {
+        for smoother in &mut self.gr {
+            smoother.reset();
+        }
+        for smoother in self.upward_gain.iter().chain(&self.expander_gain) {
+            smoother.reset(1.0);
         }
     }
 }
 
 // --- DSP LOGIC ---
 
-/// The base crossover frequencies before any tilt is applied.
-const BASE_CROSSOVER_FREQS: [f32; MAX_BANDS - 1] = [150.0, 800.0, 4000.0, 9000.0];
+/// The coefficients of the tilt EQ's low and high shelves for the given Tilt and Tilt EQ
+/// settings. See [`ColorFall::update_tilt_eq()`].
+fn tilt_eq_coefficients(sample_rate: f32, tilt: f32, tilt_eq_db: f32) -> [BiquadCoefficients; 2] {
+    let shelf_gain_db = tilt * tilt_eq_db / 2.0;
+    [
+        BiquadCoefficients::calculate_low_shelf(
+            sample_rate,
+            TILT_EQ_PIVOT_HZ,
+            TILT_EQ_Q,
+            -shelf_gain_db,
+        ),
+        BiquadCoefficients::calculate_high_shelf(
+            sample_rate,
+            TILT_EQ_PIVOT_HZ,
+            TILT_EQ_Q,
+            shelf_gain_db,
+        ),
+    ]
+}
 
 impl ColorFall {
+    /// The active band count, with the snapshot morph applied.
+    fn num_bands(&self) -> usize {
+        (self.morph.value(&self.params.band_count) as usize).clamp(MIN_BANDS, MAX_BANDS)
+    }
+
     /// Updates all dynamically changing parameters based on the main controls.
-    /// This is called once per block to set the "base" for the per-sample smoothers.
-    /// It calculates the target crossover frequencies based on the current 'Tilt' value.
-    fn update_crossover_filters(&mut self, tilt: f32) {
+    /// This is called every [`Quality::coefficient_update_interval()`] samples with the smoothed
+    /// Tilt value.
+    /// It calculates the target crossover frequencies based on the current 'Tilt' value and the
+    /// crossover shifts, and the band center frequencies derived from them.
+    fn update_crossover_filters(
+        &mut self,
+        num_bands: usize,
+        tilt: f32,
+        phase_color: f32,
+        ramp_steps: u32,
+    ) {
         // --- Dynamic Frequency Shifting ---
-        // The crossover frequencies are shifted up or down based on the 'Tilt' control. This only
-        // needs to be done once per block for efficiency.
-        for j in 0..(MAX_BANDS - 1) {
-            let shifted_freq = shift_frequency(BASE_CROSSOVER_FREQS[j], tilt);
-            self.crossovers[j].update_lr_lowpass(self.sample_rate, shifted_freq);
+        // The crossover frequencies are shifted up or down based on the 'Tilt' control. The
+        // filters glide to the new frequencies over `ramp_steps` samples so Tilt sweeps don't
+        // step audibly.
+        let crossover_freqs =
+            self.params
+                .crossover_frequencies_with(self.sample_rate, num_bands, tilt, |shift| {
+                    self.morph.value(shift)
+                });
+
+        // Every band's center frequency is the geometric mean of its edges, with the outer bands
+        // running out to 20 Hz and Nyquist, or 24 kHz at high sample rates. These drive the
+        // compressor time constants and the reactive EQ. The Dynamic EQ mode's peaking filters get
+        // the Q that puts their bandwidth on the band's edges.
+        for i in 0..num_bands {
+            let (lower_bound, upper_bound) =
+                dsp::band_edges(self.sample_rate, &crossover_freqs, num_bands, i);
+            self.band_center_freqs[i] = (lower_bound * upper_bound).sqrt();
+
+            let edge_ratio = upper_bound / lower_bound;
+            self.band_qs[i] = (edge_ratio.sqrt() / (edge_ratio - 1.0)).max(DYNAMIC_EQ_MIN_Q);
+        }
+        for (band, &center_freq) in self.bands[..num_bands]
+            .iter_mut()
+            .zip(&self.band_center_freqs)
+        {
+            band.noise.update(self.sample_rate, center_freq, ramp_steps);
+        }
+
+        let slope = self.morph.value(&self.params.crossover_slope).0;
+        for (j, &shifted_freq) in crossover_freqs[..(num_bands - 1)].iter().enumerate() {
+            self.crossovers[j].update(self.sample_rate, shifted_freq, slope, ramp_steps);
+
+            // The allpass chains mirror the crossover phase response: the dry path gets every
+            // crossover, and every band above crossover `j + 1` gets crossover `j`.
+            self.dry_phase_compensation[j].update(
+                self.sample_rate,
+                shifted_freq,
+                slope,
+                ramp_steps,
+            );
+            for band in &mut self.bands[(j + 2)..num_bands] {
+                band.phase_compensation[j].update(
+                    self.sample_rate,
+                    shifted_freq,
+                    slope,
+                    ramp_steps,
+                );
+            }
         }
+
+        // The exciter only keeps the harmonics that land above the top band's lower edge
+        self.exciter
+            .update(self.sample_rate, crossover_freqs[num_bands - 2], ramp_steps);
+        self.phase_color.update(
+            self.sample_rate,
+            &crossover_freqs[..(num_bands - 1)],
+            phase_color,
+            ramp_steps,
+        );
+    }
+
+    /// Compares the input's running spectrum with the sidechain's at the current crossovers, for
+    /// Reference Match. The trims fall back to 0 while either signal is silent.
+    fn update_reference_match_trims(&mut self, num_bands: usize, tilt: f32) {
+        let crossover_freqs =
+            self.params
+                .crossover_frequencies_with(self.sample_rate, num_bands, tilt, |shift| {
+                    self.morph.value(shift)
+                });
+        let excess_db = self.match_input_balance.band_excess_db_relative(
+            &self.match_reference_balance,
+            self.sample_rate,
+            &crossover_freqs,
+            num_bands,
+        );
+        self.reference_match_trims =
+            excess_db.map_or([0.0; MAX_BANDS], |excess_db| excess_db.map(trim_for_excess));
+    }
+
+    /// Moves the Tilt Follow offset towards the Tilt [`balance::suggested_tilt()`] gives for the
+    /// input's current spectral centroid. This runs every `interval` samples, and follows with a
+    /// time constant of `speed_ms`. The offset holds while the input is silent.
+    fn update_tilt_follow(&mut self, interval: usize, speed_ms: f32) {
+        if let Some(centroid) = self.tilt_follow_balance.centroid_octaves() {
+            let target = balance::suggested_tilt(centroid);
+            let coefficient = (-(interval as f32) * 1000.0 / (speed_ms * self.sample_rate)).exp();
+            self.tilt_follow_offset = target + coefficient * (self.tilt_follow_offset - target);
+        }
+    }
+
+    /// Moves the tilt EQ's shelves to the gains for the current Tilt, gliding there over the next
+    /// `ramp_steps` samples. Positive Tilt values cut the lows and boost the highs by half of the
+    /// total tilt each, so the level around the pivot frequency stays put.
+    fn update_tilt_eq(&mut self, tilt: f32, tilt_eq_db: f32, ramp_steps: u32) {
+        let coefficients = tilt_eq_coefficients(self.sample_rate, tilt, tilt_eq_db);
+        for (shelf, coefficients) in self.tilt_eq.iter_mut().zip(coefficients) {
+            shelf.ramp_to(coefficients, ramp_steps);
+        }
+    }
+
+    /// Resets the filter and envelope state of the crossover network, the bands, and the dry path.
+    fn reset_filters(&mut self) {
+        for shelf in &mut self.tilt_eq {
+            shelf.reset();
+        }
+        self.phase_color.reset();
+        for crossover in &mut self.crossovers {
+            crossover.reset();
+        }
+        for allpass in &mut self.dry_phase_compensation {
+            allpass.reset();
+        }
+        self.dry_history = [0.0; MAX_CHANNELS];
+        self.dry_compensation_delay.reset();
+        for band in &mut self.bands {
+            band.reset();
+        }
+        for smoothers in &mut self.band_gain_smoothers {
+            smoothers.reset();
+        }
+        self.exciter.reset();
+    }
+
+    /// Clears everything that holds on to past audio: the filters and envelopes, the delays, the
+    /// limiter, and the loudness and meter measurements. This is what [`Plugin::reset()`] does
+    /// minus the modulation and the bypass state, and it's also how the processing recovers after
+    /// a non-finite value made it into one of these.
+    fn flush_audio_state(&mut self) {
+        self.reset_filters();
+        self.limiter.reset();
+        self.output_peak_detector.reset();
+        self.output_stereo_products = [0.0; 3];
+        self.bypass_delay.reset();
+        for delay in &mut self.band_output_delays {
+            delay.reset();
+        }
+        self.wet_output_delay.reset();
+        self.dry_output_delay.reset();
+        // The loudness correction goes back to its neutral state
+        self.loudness_correction_smoother.reset(1.0);
+        self.dry_loudness.reset();
+        self.crest_tracker.reset();
+        self.wet_loudness.reset();
+        self.spectral.reset();
+        // Tilt Follow starts over on the new material from the Tilt as it's set
+        self.tilt_follow_balance.reset();
+        self.tilt_follow_offset = 0.0;
+    }
+
+    /// The task that evaluates the finished Learn measurement. The bands are compared at the
+    /// crossovers the controls are set to.
+    fn target_learn_task(&self) -> AnalysisTask {
+        let num_bands = self.num_bands();
+        AnalysisTask::LearnTarget {
+            analysis: self.target_analysis.clone(),
+            target: self.learn_target,
+            sample_rate: self.sample_rate,
+            crossover_freqs: self.params.crossover_frequencies_with(
+                self.sample_rate,
+                num_bands,
+                self.morph.value(&self.params.tilt),
+                |shift| self.morph.value(shift),
+            ),
+            num_bands,
+        }
+    }
+
+    /// Works out the latency of every latency-affecting stage for the current settings, and
+    /// updates the compensation delays when it changed. Returns `true` if the latency changed, in
+    /// which case it needs to be reported to the host with [`Latency::total()`]. The delays start
+    /// from silence after a change, and so do the oversamplers and the spectral engine, since they
+    /// may have been idle. The spectral mode has no saturators to oversample.
+    fn update_latency(&mut self, quality: Quality, mode: Mode) -> bool {
+        let latency = Latency {
+            oversampling: if quality.oversampling() && mode != Mode::Spectral {
+                OVERSAMPLING_LATENCY
+            } else {
+                0
+            },
+            spectral: if mode == Mode::Spectral {
+                SPECTRAL_LATENCY
+            } else {
+                0
+            },
+            limiter: self.limiter.latency_samples() as usize,
+        };
+        if latency == self.latency {
+            return false;
+        }
+        self.latency = latency;
+
+        self.dry_compensation_delay.set_delay(latency.wet_path());
+        for band in &mut self.bands {
+            band.dry_compensation_delay.set_delay(latency.band());
+            band.oversampler.reset();
+        }
+        self.spectral.reset();
+        // The band outputs are taken after the band processing, so they only need to make up for
+        // what comes after it
+        for delay in &mut self.band_output_delays {
+            delay.set_delay(latency.total() - latency.band());
+        }
+        // The wet and dry outputs are taken right before the limiter
+        self.wet_output_delay
+            .set_delay(latency.total() - latency.wet_path());
+        self.dry_output_delay
+            .set_delay(latency.total() - latency.wet_path());
+        self.bypass_delay.set_delay(latency.total());
+
+        true
     }
 }
 // --- NIH-PLUG IMPLEMENTATION ---
@@ -187,323 +1901,1459 @@ impl Plugin for ColorFall {
     const EMAIL: &'static str = "contact@example.com"; // TODO: Update this
     const VERSION: &'static str = env!("CARGO_PKG_VERSION");
 
-    const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[AudioIOLayout {
-        main_input_channels: NonZeroU32::new(2),
-        main_output_channels: NonZeroU32::new(2),
-        ..AudioIOLayout::const_default()
-    }];
+    const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[
+        // The stereo layouts have a sidechain input for the modulation matrix
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(2),
+            main_output_channels: NonZeroU32::new(2),
+            aux_input_ports: &[new_nonzero_u32(2)],
+            names: PortNames {
+                aux_inputs: &["Sidechain"],
+                ..PortNames::const_default()
+            },
+            ..AudioIOLayout::const_default()
+        },
+        // Stereo with an extra stereo output for every band, so the bands can be routed to their
+        // own tracks, followed by the wet and dry outputs
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(2),
+            main_output_channels: NonZeroU32::new(2),
+            aux_input_ports: &[new_nonzero_u32(2)],
+            aux_output_ports: &[new_nonzero_u32(2); MAX_BANDS + NUM_WET_DRY_OUTPUTS],
+            names: PortNames {
+                layout: Some("Stereo Multi-Out"),
+                aux_inputs: &["Sidechain"],
+                aux_outputs: &MULTI_OUTPUT_NAMES,
+                ..PortNames::const_default()
+            },
+        },
+        // Stereo with only the wet and dry outputs, for parallel routing in the host
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(2),
+            main_output_channels: NonZeroU32::new(2),
+            aux_input_ports: &[new_nonzero_u32(2)],
+            aux_output_ports: &[new_nonzero_u32(2); NUM_WET_DRY_OUTPUTS],
+            names: PortNames {
+                layout: Some("Stereo Wet/Dry Out"),
+                aux_inputs: &["Sidechain"],
+                aux_outputs: &WET_DRY_OUTPUT_NAMES,
+                ..PortNames::const_default()
+            },
+        },
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(1),
+            main_output_channels: NonZeroU32::new(1),
+            ..AudioIOLayout::const_default()
+        },
+        // Mono in, stereo out. The mono input is processed as a centered stereo signal.
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(1),
+            main_output_channels: NonZeroU32::new(2),
+            ..AudioIOLayout::const_default()
+        },
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(6),
+            main_output_channels: NonZeroU32::new(6),
+            names: PortNames {
+                layout: Some("5.1"),
+                ..PortNames::const_default()
+            },
+            ..AudioIOLayout::const_default()
+        },
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(8),
+            main_output_channels: NonZeroU32::new(8),
+            names: PortNames {
+                layout: Some("7.1"),
+                ..PortNames::const_default()
+            },
+            ..AudioIOLayout::const_default()
+        },
+    ];
 
-    const MIDI_INPUT: MidiConfig = MidiConfig::None;
+    // This includes the note events that trigger the ducking
+    const MIDI_INPUT: MidiConfig = MidiConfig::MidiCCs;
     const SAMPLE_ACCURATE_AUTOMATION: bool = true;
 
-    // We're not using any background tasks or SysEx messages in this plugin.
+    // We're not using any SysEx messages in this plugin. The background tasks evaluate the
+    // editor's spectrum measurements.
     type SysExMessage = ();
-    type BackgroundTask = ();
+    type BackgroundTask = AnalysisTask;
 
     fn params(&self) -> Arc<dyn Params> {
         self.params.clone()
     }
 
+    fn task_executor(&mut self) -> TaskExecutor<Self> {
+        let tilt_suggestion = self.tilt_suggestion.clone();
+        let target_learn = self.target_learn.clone();
+        Box::new(move |task| task.run(&tilt_suggestion, &target_learn))
+    }
+
+    fn filter_state(state: &mut PluginState) {
+        state::migrate(state);
+    }
+
     fn initialize(
         &mut self,
-        _audio_io_layout: &AudioIOLayout,
+        audio_io_layout: &AudioIOLayout,
         buffer_config: &BufferConfig,
-        _context: &mut impl InitContext<Self>,
+        context: &mut impl InitContext<Self>,
     ) -> bool {
         // The sample rate may change on initialization, so we need to update it here
         self.sample_rate = buffer_config.sample_rate;
+        self.scope_buffer.set_sample_rate(self.sample_rate);
+        self.num_input_channels = audio_io_layout
+            .main_input_channels
+            .map_or(0, |channels| channels.get() as usize);
+        for band in &mut self.bands {
+            band.initialize(self.sample_rate);
+        }
+        self.dry_loudness.set_sample_rate(self.sample_rate);
+        self.crest_tracker.set_sample_rate(self.sample_rate);
+        self.wet_loudness.set_sample_rate(self.sample_rate);
+        self.tilt_analysis.set_sample_rate(self.sample_rate);
+        self.target_analysis.set_sample_rate(self.sample_rate);
+        self.match_input_balance.set_sample_rate(self.sample_rate);
+        self.match_reference_balance
+            .set_sample_rate(self.sample_rate);
+        self.tilt_follow_balance.set_sample_rate(self.sample_rate);
+        self.limiter.initialize(self.sample_rate);
+        self.spectral.initialize(self.sample_rate);
+        // The compensation delays are sized for the longest latency every stage can have, and
+        // the latency bookkeeping picks the actual delays from the current settings. The delays
+        // were just resized, so the bookkeeping starts over and always reports the latency here.
+        let max_latency = Latency {
+            oversampling: OVERSAMPLING_LATENCY,
+            spectral: SPECTRAL_LATENCY,
+            limiter: self.limiter.latency_samples() as usize,
+        };
+        self.bypass_delay.set_length(max_latency.total());
+        self.dry_compensation_delay
+            .set_length(max_latency.wet_path());
+        for delay in &mut self.band_output_delays {
+            delay.set_length(max_latency.total() - max_latency.band());
+        }
+        self.wet_output_delay
+            .set_length(max_latency.total() - max_latency.wet_path());
+        self.dry_output_delay
+            .set_length(max_latency.total() - max_latency.wet_path());
+        // The morph is set up first, since it decides the processing mode
+        self.morph
+            .initialize(&*self.params, &self.params.morph_snapshots);
+        self.latency = Latency::default();
+        self.update_latency(
+            self.params.quality.value().0,
+            self.morph.value(&self.params.mode),
+        );
+        context.set_latency_samples(self.latency.total() as u32);
         // Then, call reset() to ensure all state is initialized correctly for the new sample rate.
         self.reset();
+        // The crossovers start out at their current frequencies instead of gliding there from
+        // wherever they were at the old sample rate
+        self.active_bands = self.num_bands();
+        let tilt = self.morph.value(&self.params.tilt);
+        let phase_color = self.morph.value(&self.params.phase_color);
+        self.update_crossover_filters(self.active_bands, tilt, phase_color, 0);
+        self.update_tilt_eq(tilt, self.morph.value(&self.params.tilt_eq), 0);
         true
     }
 
     fn reset(&mut self) {
         // Reset all DSP state, including filters and smoothers.
-        for crossover in &mut self.crossovers {
-            crossover.reset();
-        }
-        for band in &mut self.bands {
-            band.reset();
-        }
-        // Reset smoothers to their neutral state and trackers to a safe, non-zero value.
-        self.loudness_correction_smoother.reset(1.0);
-        self.gr_meter_smoother.reset(0.0);
-        // Using a small epsilon prevents division by zero on the first processing block.
-        self.dry_rms_tracker = 1.0e-6;
-        self.wet_rms_tracker = 1.0e-6;
+        self.flush_audio_state();
+        self.bypass_smoother
+            .reset(if self.params.bypass.value() { 1.0 } else { 0.0 });
+        self.coefficient_update_countdown = 0;
+        self.modulation.reset();
+        self.intensity_adaptation = IntensityAdaptation::default();
     }
 
     fn process(
         &mut self,
         buffer: &mut Buffer,
-        _aux: &mut AuxiliaryBuffers,
-        _context: &mut impl ProcessContext<Self>,
+        aux: &mut AuxiliaryBuffers,
+        context: &mut impl ProcessContext<Self>,
+    ) -> ProcessStatus {
+        self.process_block(buffer, aux, context)
+    }
+
+    fn editor(&mut self, _async_executor: AsyncExecutor<Self>) -> Option<Box<dyn Editor>> {
+        #[cfg(feature = "vizia")]
+        editor::create(
+            self.params.clone(),
+            self.gain_reduction_meter.clone(),
+            self.meter_levels.clone(),
+            self.eq_curve.clone(),
+            self.scope_buffer.clone(),
+            self.tilt_suggestion.clone(),
+            self.target_learn.clone(),
+            self.morph_snapshots_changed.clone(),
+        )
+    }
+}
+
+/// What the processing needs from the host. This is implemented for every plugin API's process
+/// context, and by the offline renderer, which runs without a host.
+trait BlockContext {
+    /// The host's transport at the start of the block.
+    fn transport(&self) -> TransportInfo;
+    /// The next MIDI event in the block. Events arrive in order of their timing.
+    fn next_event(&mut self) -> Option<PluginNoteEvent<ColorFall>>;
+    /// Reports a new latency to the host.
+    fn set_latency_samples(&self, samples: u32);
+    /// Runs a task on the background thread.
+    fn execute_background(&self, task: AnalysisTask);
+}
+
+impl<C: ProcessContext<ColorFall>> BlockContext for C {
+    fn transport(&self) -> TransportInfo {
+        ProcessContext::transport(self).into()
+    }
+
+    fn next_event(&mut self) -> Option<PluginNoteEvent<ColorFall>> {
+        ProcessContext::next_event(self)
+    }
+
+    fn set_latency_samples(&self, samples: u32) {
+        ProcessContext::set_latency_samples(self, samples);
+    }
+
+    fn execute_background(&self, task: AnalysisTask) {
+        ProcessContext::execute_background(self, task);
+    }
+}
+
+impl ColorFall {
+    /// Processes a block of audio. This is [`Plugin::process()`], minus the plugin API.
+    fn process_block(
+        &mut self,
+        buffer: &mut Buffer,
+        aux: &mut AuxiliaryBuffers,
+        context: &mut impl BlockContext,
     ) -> ProcessStatus {
-        let mut block_avg_input = 0.0;
-        let mut block_avg_output = 0.0;
+        // Decaying filter states and envelopes are flushed to zero instead of turning denormal
+        let _ftz = ScopedFtz::enable();
+
         let mut total_gr_db = 0.0;
+        // The deepest gain reduction of any sample in the block, for the GR meter
+        let mut block_peak_gr_db = 0.0f32;
+        let mut block_input_peak = 0.0f32;
+        let mut block_input_power = 0.0;
+        let mut block_output_peak = 0.0f32;
+        let mut block_output_power = 0.0;
+        let mut block_output_true_peak = 0.0f32;
+        let mut block_stereo_products = [0.0f32; 3];
+        let mut block_band_power = [0.0f32; MAX_BANDS];
+
+        let num_channels = buffer.channels().min(MAX_CHANNELS);
+        if num_channels == 0 {
+            return ProcessStatus::Normal;
+        }
+        let num_input_channels = self.num_input_channels.clamp(1, num_channels);
+
+        // The snapshot morph goes first, since it decides the values of nearly every parameter
+        // read below. It moves at block rate, with the smoothed parameters gliding in between.
+        let morph_position = self
+            .params
+            .morph
+            .smoothed
+            .next_step(buffer.samples() as u32);
+        self.morph.begin_block(
+            &self.params.morph_snapshots,
+            &self.morph_snapshots_changed,
+            morph_position,
+            self.sample_rate,
+        );
+
+        // The quality mode and the processing mode are only read once per block. Both can change
+        // the latency, which is reported to the host as soon as it changes.
+        let quality = self.params.quality.value().0;
+        let mode = self.morph.value(&self.params.mode);
+        if self.update_latency(quality, mode) {
+            context.set_latency_samples(self.latency.total() as u32);
+        }
+        // The spectral engine has no reactive EQ, so its curve is drawn flat
+        if mode == Mode::Spectral {
+            for (_, _, gain_db) in &mut self.eq_settings {
+                *gain_db = 0.0;
+            }
+        }
+        let oversampling = quality.oversampling() && mode != Mode::Spectral;
+        let coefficient_update_interval = quality.coefficient_update_interval(self.sample_rate);
+
+        // The band count is a stepped parameter, so it's only read once per block. Switching it
+        // rearranges every crossover, so we start from a clean filter state when it changes.
+        let num_bands = self.num_bands();
+        if num_bands != self.active_bands {
+            self.active_bands = num_bands;
+            self.reset_filters();
+        }
 
-        // --- 1. DYNAMIC PARAMETER UPDATE ---
-        // We update the filter coefficients once per block based on the unsmoothed parameter values.
-        // This is a compromise for efficiency. While per-sample updates would be more accurate for
-        // fast automation, it's computationally expensive. This block-based update is sufficient
-        // for most use cases and avoids performance issues.
-        self.update_crossover_filters(self.params.tilt.value());
-
-        // --- 2. LOUDNESS CORRECTION ---        // Calculate a makeup gain factor to match the wet signal's power (from the *previous* block)
-        // to the dry signal's power. This introduces a one-block latency to the loudness
-        // compensation, but it's a standard, stable, and efficient approach.
-        let required_correction = if self.wet_rms_tracker > 1.0e-6 && self.dry_rms_tracker > 1.0e-6
-        {
-            (self.dry_rms_tracker / self.wet_rms_tracker).sqrt()
+        // --- 1. LOUDNESS CORRECTION ---
+        // Calculate a makeup gain factor to match the wet signal's short-term loudness to the dry
+        // signal's loudness. The K-weighting makes this track perceived loudness instead of being
+        // dominated by the low end. The strength scales the correction in decibels, so 50%
+        // applies half of the makeup gain in dB. Silence (below the -70 LUFS gate) leaves the
+        // correction alone. The measurement only changes when a 100 ms segment completes, so the
+        // target is set here and then again after every segment that completes during the block.
+        // The smoother glides towards it.
+        let auto_gain = self.morph.value(&self.params.auto_gain);
+        let auto_gain_strength = self.morph.value(&self.params.auto_gain_strength);
+        let required_correction = if auto_gain {
+            loudness::correction_gain(
+                self.dry_loudness.mean_square(),
+                self.wet_loudness.mean_square(),
+                auto_gain_strength,
+            )
         } else {
             1.0
         };
-
-        // Smooth the correction factor
-        // We set the target here, and the smoother will gradually approach it over the block.
         self.loudness_correction_smoother
             .set_target(self.sample_rate, required_correction);
 
-        // --- 3. SAMPLE PROCESSING LOOP ---
-        let mut channels = buffer.iter_samples();
-        let mut left = channels.next().unwrap();
-        let mut right = channels.next().unwrap();
+        // Work out which bands end up in the wet sum. If any band is soloed, only the soloed bands
+        // are heard and the mute switches are ignored.
+        let band_solo: [bool; MAX_BANDS] =
+            array_init::array_init(|i| self.morph.value(&self.params.bands[i].solo));
+        let any_solo = band_solo.contains(&true);
+        let band_audible: [bool; MAX_BANDS] = array_init::array_init(|i| {
+            if any_solo {
+                band_solo[i]
+            } else {
+                !self.morph.value(&self.params.bands[i].mute)
+            }
+        });
+
+        let band_bypass: [bool; MAX_BANDS] =
+            array_init::array_init(|i| self.morph.value(&self.params.bands[i].bypass));
+        let band_expand: [bool; MAX_BANDS] =
+            array_init::array_init(|i| self.morph.value(&self.params.bands[i].expand));
+        let band_de_ess: [bool; MAX_BANDS] =
+            array_init::array_init(|i| self.morph.value(&self.params.bands[i].de_ess));
+        let band_duck: [bool; MAX_BANDS] =
+            array_init::array_init(|i| self.morph.value(&self.params.bands[i].duck));
+
+        // The ducking envelopes ramp up linearly over the attack time and decay exponentially
+        let duck_attack_samples =
+            self.morph.value(&self.params.duck_attack) * self.sample_rate / 1000.0;
+        let duck_attack_step = 1.0 / duck_attack_samples.max(1.0);
+        let duck_release_samples =
+            self.morph.value(&self.params.duck_release) * self.sample_rate / 1000.0;
+        let duck_release_coefficient = (-1.0 / duck_release_samples).exp();
+
+        let tilt_follow_speed = self.morph.value(&self.params.tilt_follow_speed);
+        let saturation_model = self.morph.value(&self.params.saturation).0;
+        let character = self.morph.value(&self.params.character).0;
+        // Auto Intensity picks up the measured adaptation at block rate. The measurement window is
+        // seconds long, so this doesn't need to be any faster.
+        let adaptation = if self.morph.value(&self.params.auto_intensity) {
+            if let Some(adaptation) = self.crest_tracker.adaptation() {
+                self.intensity_adaptation = adaptation;
+            }
+            self.intensity_adaptation
+        } else {
+            IntensityAdaptation::default()
+        };
+        // The oversampled saturators don't need ADAA on top, and the spectral mode has no
+        // saturators at all
+        let anti_aliasing = if oversampling || mode == Mode::Spectral {
+            AntiAliasing::Off
+        } else {
+            self.morph.value(&self.params.anti_aliasing).0
+        };
+        let delta = self.morph.value(&self.params.delta);
+        let listen = self.params.listen.value();
+        let noise_texture = self.morph.value(&self.params.noise_texture).0;
+
+        // The processing keeps running while bypassed so the filters and envelopes are warm when
+        // the plugin is re-engaged
+        self.bypass_smoother.set_target(
+            self.sample_rate,
+            if self.params.bypass.value() { 1.0 } else { 0.0 },
+        );
+        let detector_mode = self.morph.value(&self.params.detector_mode);
+        let topology = self.morph.value(&self.params.topology);
+        let processing_order = self.morph.value(&self.params.processing_order);
+        let reactive_eq = self.morph.value(&self.params.reactive_eq);
+        let attack_curve = self.morph.value(&self.params.attack_curve).0;
+        let release_curve = self.morph.value(&self.params.release_curve).0;
+        let release_model = self.morph.value(&self.params.release_model).0;
+        // The detector high-pass filter only changes at block rate. It only feeds the detectors,
+        // so a coefficient jump never reaches the output directly.
+        let detector_hpf_freq = self.morph.value(&self.params.detector_hpf);
+        let detector_hpf_enabled = detector_hpf_freq > DETECTOR_HPF_MIN_HZ;
+        if detector_hpf_enabled {
+            let coefficients =
+                BiquadCoefficients::calculate_lr_highpass(self.sample_rate, detector_hpf_freq);
+            for band in &mut self.bands {
+                band.detector_hpf.set_coefficients(coefficients);
+            }
+        }
+        let detector_weighting = self.morph.value(&self.params.detector_weighting).0;
+        for band in &mut self.bands {
+            band.detector_weighting
+                .update(detector_weighting, self.sample_rate);
+        }
+
+        // Channels in the same link group share their detector input.
+        let channel_link = self.morph.value(&self.params.channel_link);
+        let link_groups: [usize; MAX_CHANNELS] =
+            array_init::array_init(|channel| channel_link.group(channel, num_channels));
+        let num_mono_bands = self.morph.value(&self.params.mono_below).num_bands();
+
+        // The transient shaper's fast envelope uses a fixed attack time
+        let transient_attack_alpha =
+            1.0 - (-1.0 / to_real(dsp::TRANSIENT_ATTACK_MS * self.sample_rate / 1000.0)).exp();
+
+        self.modulation.begin_block(
+            &self.params.mod_slots,
+            &self.params.lfos,
+            &self.morph,
+            &context.transport(),
+            self.sample_rate,
+        );
+        // The sidechain only exists in the stereo layouts. It drives the sidechain envelope
+        // modulation source and is the reference for Reference Match.
+        let sidechain = aux.inputs.first().map(|buffer| buffer.as_slice_immutable());
+
+        let midi_learn = self.params.midi_learn.value();
+        self.midi_cc.begin_block(
+            &self.params.midi_cc_assignments,
+            midi_learn,
+            &self.params.midi_mappings,
+            self.sample_rate,
+        );
+
+        // A new Suggest Tilt measurement starts at the beginning of the block
+        if self.tilt_suggestion.start.swap(false, Ordering::Relaxed) {
+            self.tilt_analysis.reset();
+            self.tilt_analysis_remaining =
+                ((SUGGEST_TILT_SECONDS * self.sample_rate) as usize).max(1);
+        }
+        if self.target_learn.start.swap(false, Ordering::Relaxed) {
+            self.learn_target =
+                TargetCurve::from_index(self.target_learn.target.load(Ordering::Relaxed) as usize);
+            self.target_analysis.reset();
+            self.target_analysis_remaining =
+                ((LEARN_TARGET_SECONDS * self.sample_rate) as usize).max(1);
+        }
+
+        // --- 2. SAMPLE PROCESSING LOOP ---
+        let num_samples = buffer.samples();
+        let mut next_event = context.next_event();
         // Store the per-sample GR factors here to pass to the reactive EQ stage.
-        let mut gr_factors_l = [1.0; MAX_BANDS];
-        let mut gr_factors_r = [1.0; MAX_BANDS];
-        for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+        let mut gr_factors = [[1.0; MAX_CHANNELS]; MAX_BANDS];
+        for (sample_idx, mut frame) in buffer.iter_samples().enumerate() {
+            // Handle the MIDI events that land on this sample. Notes trigger the ducking envelopes
+            // of the selected bands, with the velocity setting the depth.
+            while let Some(event) = next_event {
+                if event.timing() > sample_idx as u32 {
+                    break;
+                }
+
+                match event {
+                    NoteEvent::NoteOn { velocity, .. } => {
+                        for (band, duck) in self.bands.iter_mut().zip(band_duck) {
+                            if duck {
+                                band.duck_envelope.trigger(velocity);
+                            }
+                        }
+                    }
+                    NoteEvent::MidiCC { cc, value, .. } => {
+                        self.modulation.handle_cc(cc, value);
+                        self.midi_cc.handle_cc(
+                            cc,
+                            value,
+                            midi_learn,
+                            &self.params.midi_cc_assignments,
+                            &self.params.midi_mappings,
+                        );
+                    }
+                    _ => (),
+                }
+                next_event = context.next_event();
+            }
+
+            // Gather this frame's input. Output channels without a matching input channel (the
+            // second channel of the mono-to-stereo layout starts out silent) are fed from the first
+            // input channel.
+            let mut input = [0.0; MAX_CHANNELS];
+            for (channel, sample) in frame.iter_mut().enumerate().take(num_channels) {
+                input[channel] = if channel < num_input_channels {
+                    *sample
+                } else {
+                    input[0]
+                };
+            }
+            // NaN and infinite input samples are replaced with silence before they can reach any
+            // filter state
+            dsp::sanitize_frame(&mut input, num_channels);
+            for sample in &input[..num_channels] {
+                block_input_peak = block_input_peak.max(sample.abs());
+            }
+            block_input_power +=
+                input[..num_channels].iter().map(|s| s * s).sum::<f32>() / num_channels as f32;
+
+            // Suggest Tilt and Learn listen to the input summed to mono
+            if self.tilt_analysis_remaining > 0 || self.target_analysis_remaining > 0 {
+                let mono =
+                    input[..num_input_channels].iter().sum::<f32>() / num_input_channels as f32;
+                if self.tilt_analysis_remaining > 0 {
+                    self.tilt_analysis.process(mono);
+                    self.tilt_analysis_remaining -= 1;
+                    if self.tilt_analysis_remaining == 0 {
+                        context.execute_background(AnalysisTask::SuggestTilt(
+                            self.tilt_analysis.clone(),
+                        ));
+                    }
+                }
+                if self.target_analysis_remaining > 0 {
+                    self.target_analysis.process(mono);
+                    self.target_analysis_remaining -= 1;
+                    if self.target_analysis_remaining == 0 {
+                        context.execute_background(self.target_learn_task());
+                    }
+                }
+            }
+
             // Get smoothed parameter values for this sample
             // This is the core of the sample-accurate automation. Each parameter's smoother
-            // provides the next value in its trajectory.
-            let amount = self.params.amount.smoothed.next();
-            let tilt = self.params.tilt.smoothed.next();
-            let mix = self.params.mix.smoothed.next();
-            let output_gain = util::db_to_gain(self.params.output.smoothed.next());
+            // provides the next value in its trajectory. Controls driven by a MIDI CC follow the
+            // CC instead, and the modulation matrix is added on top of that, so everything
+            // downstream follows the modulated values.
+            let sidechain_peak = sidechain.map_or(0.0, |channels| {
+                channels.iter().fold(0.0f32, |peak, samples| {
+                    peak.max(dsp::sanitize(samples[sample_idx]).abs())
+                })
+            });
+            let input_peak = input[..num_channels]
+                .iter()
+                .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+            let modulation =
+                self.modulation
+                    .next(&self.params.mod_slots, input_peak, sidechain_peak);
+            let params = &self.params;
+            let amount = self.midi_cc.apply(
+                MidiTarget::Amount,
+                &params.amount,
+                params.amount.smoothed.next(),
+            );
+            let tilt =
+                self.midi_cc
+                    .apply(MidiTarget::Tilt, &params.tilt, params.tilt.smoothed.next());
+            let tilt_eq_db = self.params.tilt_eq.smoothed.next();
+            let phase_color = self.params.phase_color.smoothed.next();
+            let reference_match = self.params.reference_match.smoothed.next();
+            let tilt_follow = self.params.tilt_follow.smoothed.next();
+            let mix = self
+                .midi_cc
+                .apply(MidiTarget::Mix, &params.mix, params.mix.smoothed.next());
+            let output_gain = util::db_to_gain(self.midi_cc.apply(
+                MidiTarget::Output,
+                &params.output,
+                params.output.smoothed.next(),
+            ));
+
+            let amount = (amount + modulation.amount).clamp(0.0, 1.0);
+            let tilt =
+                (tilt + modulation.tilt + tilt_follow * self.tilt_follow_offset).clamp(-1.0, 1.0);
+            let mix = (mix + modulation.mix).clamp(0.0, 1.0);
+            let ceiling = util::db_to_gain(self.params.ceiling.smoothed.next());
+            let bypass_mix = self.bypass_smoother.next();
+            let attack_scale = self.params.attack_scale.smoothed.next();
+            let release_scale = self.params.release_scale.smoothed.next();
+            let adaptive_timing = self.params.adaptive_timing.smoothed.next();
+            let gr_smoothing = self.params.gr_smoothing.smoothed.next();
+            // The Range cap as the lowest GR factor the compressors may reach
+            let range_db = self.params.range.smoothed.next();
+            let min_gr_factor = if range_db < GR_RANGE_MAX_DB {
+                util::db_to_gain(-range_db)
+            } else {
+                0.0
+            };
+            let up_down = self.params.up_down.smoothed.next();
+            let punch = self.params.punch.smoothed.next();
+            let eq_depth = self.params.eq_depth.smoothed.next();
+            let color = self.params.color.smoothed.next();
+            let air = self.params.air.smoothed.next();
+            let noise = self.params.noise.smoothed.next();
+            let mono_amount = self.params.mono_amount.smoothed.next();
+            let duck_depth = self.params.duck_depth.smoothed.next();
             let loudness_correction = self.loudness_correction_smoother.next();
 
+            // Reference Match follows the spectra of the input and the sidechain, both summed to
+            // mono
+            if reference_match > 0.0
+                && let Some(sidechain) = sidechain
+            {
+                let input_mono =
+                    input[..num_input_channels].iter().sum::<f32>() / num_input_channels as f32;
+                let reference_mono = sidechain
+                    .iter()
+                    .map(|samples| dsp::sanitize(samples[sample_idx]))
+                    .sum::<f32>()
+                    / sidechain.len() as f32;
+                self.match_input_balance.process(input_mono);
+                self.match_reference_balance.process(reference_mono);
+            }
+            // Tilt Follow measures the input summed to mono as well. This keeps running at 0%, so
+            // turning it up doesn't start from an offset measured on older material.
+            self.tilt_follow_balance.process(
+                input[..num_input_channels].iter().sum::<f32>() / num_input_channels as f32,
+            );
+
+            // The crossover and reactive EQ coefficients are recalculated from the smoothed
+            // parameters every few samples, and the filters glide between the results. This keeps
+            // fast Tilt sweeps smooth without calculating new coefficients for every sample.
+            let update_coefficients = self.coefficient_update_countdown == 0;
+            if update_coefficients {
+                self.coefficient_update_countdown = coefficient_update_interval;
+                self.update_crossover_filters(
+                    num_bands,
+                    tilt,
+                    phase_color,
+                    coefficient_update_interval as u32,
+                );
+                self.update_tilt_eq(tilt, tilt_eq_db, coefficient_update_interval as u32);
+                if reference_match > 0.0 {
+                    self.update_reference_match_trims(num_bands, tilt);
+                }
+                self.update_tilt_follow(coefficient_update_interval, tilt_follow_speed);
+            }
+            // Switching to a faster update rate can leave a longer countdown behind
+            self.coefficient_update_countdown = self
+                .coefficient_update_countdown
+                .min(coefficient_update_interval)
+                - 1;
+
             let mix_phase = mix * FRAC_PI_2;
             // A constant-power crossfade for the dry/wet mix. This is perceptually more
             // linear than a linear crossfade.
             let (dry_gain, wet_gain) = (mix_phase.cos(), mix_phase.sin());
 
-            // We need to process interleaved audio, so we'll zip the two channel iterators
-            let (sample_l, sample_r) = (*l, *r); // Dereference to get the values
-
-            let (dry_l, dry_r) = (sample_l, sample_r);
+            // Both compressors run at full depth in the middle of the Up/Down range, OTT style,
+            // and fade out towards the opposite end.
+            let downward_depth = (2.0 * (1.0 - up_down)).min(1.0);
+            let upward_depth = (2.0 * up_down).min(1.0);
+
+            // Punch is a macro, so it has some effect at low Amount settings but only reaches
+            // full depth with Amount all the way up
+            let transient_depth = punch * (0.25 + (0.75 * amount));
+            let air_gain = dsp::calculate_air_gain(air, amount, tilt);
+
+            // The dry signal goes through the same allpass response as the summed bands so the
+            // dry/wet blend stays phase-coherent. The spectral engine doesn't shift the phase.
+            let mut dry = input;
+            if mode != Mode::Spectral {
+                for allpass in &mut self.dry_phase_compensation[..(num_bands - 1)] {
+                    allpass.process(&mut dry, num_channels);
+                }
+            }
+            // The ADAA saturators and the oversampler delay the wet signal, so the dry signal is
+            // delayed to match
+            for (channel, sample) in dry.iter_mut().enumerate().take(num_channels) {
+                *sample = anti_aliasing.align(*sample, &mut self.dry_history[channel]);
+            }
+            self.dry_compensation_delay.process(&mut dry, num_channels);
 
-            // --- A. Track Dry Signal Power for Loudness Compensation ---
-            block_avg_input += (dry_l * dry_l + dry_r * dry_r) * 0.5;
+            // --- A. Track Dry Signal Loudness for Loudness Compensation ---
+            self.dry_loudness.process(&dry, num_channels);
+            self.crest_tracker.process(&dry, num_channels);
 
             // --- B. Parallel Processing Stage ---
-            let (mut wet_l, mut wet_r) = {
-                let mut band_signals_l = [0.0; MAX_BANDS];
-                let mut band_signals_r = [0.0; MAX_BANDS];
-                let mut last_lp_l = sample_l;
-                let mut last_lp_r = sample_r;
-
-                // B.1: Split into 5 bands using the crossover filters
-                for i in (0..(MAX_BANDS - 1)).rev() {
-                    let (lp_l, lp_r) = self.crossovers[i].process(last_lp_l, last_lp_r);
-                    band_signals_l[i + 1] = last_lp_l - lp_l;
-                    band_signals_r[i + 1] = last_lp_r - lp_r;
-                    last_lp_l = lp_l;
-                    last_lp_r = lp_r;
+            let (mut wet, mut band_signals, detector_sum) = if mode == Mode::Spectral {
+                // The spectral engine splits, tilts and compresses the input on its FFT bins. It
+                // picks up the settings once per frame. There are no band signals or detector
+                // signals to send out in this mode.
+                let band_trims: [f32; MAX_BANDS] = array_init::array_init(|i| {
+                    (self.params.bands[i].amount_trim.smoothed.next()
+                        + modulation.band_amounts[i]
+                        + reference_match * self.reference_match_trims[i])
+                        .clamp(-0.5, 0.5)
+                });
+                if self.spectral.frame_due() {
+                    self.spectral.update(SpectralSettings {
+                        num_bands,
+                        crossover_freqs: self.params.crossover_frequencies_with(
+                            self.sample_rate,
+                            num_bands,
+                            tilt,
+                            |shift| self.morph.value(shift),
+                        ),
+                        tilt_eq: tilt_eq_coefficients(self.sample_rate, tilt, tilt_eq_db),
+                        amount,
+                        tilt,
+                        character,
+                        adaptation,
+                        band_trims,
+                        band_audible,
+                        band_bypass,
+                        min_gr_factor,
+                        downward_depth,
+                        upward_depth,
+                        attack_scale,
+                        release_scale,
+                    });
+                }
+                let wet = self.spectral.process(&input, num_channels);
+                let current_sample_gr_db =
+                    self.spectral.band_gr_db()[..num_bands].iter().sum::<f32>();
+                total_gr_db += current_sample_gr_db;
+                block_peak_gr_db = block_peak_gr_db.min(current_sample_gr_db);
+
+                (wet, [[0.0; MAX_CHANNELS]; MAX_BANDS], [0.0; MAX_CHANNELS])
+            } else {
+                // B.1: Tilt the input's spectral balance along with the Tilt control, then split it
+                // into the active bands using the crossover filters, starting from the highest
+                // crossover. The tilt EQ is neutral when it's set to 0 dB.
+                let mut tilted = input;
+                for shelf in &mut self.tilt_eq {
+                    shelf.process(&mut tilted, num_channels);
                 }
-                band_signals_l[0] = last_lp_l;
-                band_signals_r[0] = last_lp_r;
 
-                let (mut wet_l, mut wet_r) = (0.0, 0.0);
+                let mut band_signals = [[0.0; MAX_CHANNELS]; MAX_BANDS];
+                let mut last_lp = tilted;
+                for i in (0..(num_bands - 1)).rev() {
+                    let (lp, hp) = self.crossovers[i].process(&last_lp, num_channels);
+                    band_signals[i + 1] = hp;
+                    last_lp = lp;
+                }
+                band_signals[0] = last_lp;
+
+                let mut wet = [0.0; MAX_CHANNELS];
+                let mut detector_sum = [0.0; MAX_CHANNELS];
                 let mut current_sample_gr_db = 0.0;
 
-                // B.2: Process each band independently (Saturation -> Compression)
-                for i in 0..MAX_BANDS {
-                    let (mut band_l, mut band_r) = (band_signals_l[i], band_signals_r[i]);
+                // B.2: Process each band independently (Saturation -> Compression, or the other
+                // way around)
+                for i in 0..num_bands {
+                    let band = &mut self.bands[i];
+                    let gain_smoothers = &mut self.band_gain_smoothers[i];
+                    let band_signal = &mut band_signals[i];
+                    let band_trim = (self.params.bands[i].amount_trim.smoothed.next()
+                        + modulation.band_amounts[i]
+                        + reference_match * self.reference_match_trims[i])
+                        .clamp(-0.5, 0.5);
+                    let drive_scale = util::db_to_gain(self.params.bands[i].drive.smoothed.next());
+                    let expand_threshold = self.params.bands[i].expand_threshold.smoothed.next();
+                    let width = self.params.bands[i].width.smoothed.next();
+                    let band_mix = self.params.bands[i].mix.smoothed.next();
+                    // The ducking envelopes keep running for bands that aren't selected so
+                    // deselecting a band mid-duck releases it instead of jumping back up
+                    let duck_gain = util::db_to_gain(
+                        -duck_depth
+                            * band
+                                .duck_envelope
+                                .next(duck_attack_step, duck_release_coefficient),
+                    );
 
-                    // Saturate first
-                    band_l = dsp::saturate(band_l, amount);
-                    band_r = dsp::saturate(band_r, amount);
+                    // The lowest bands can be collapsed towards their mono sum before anything else
+                    // happens to them, which keeps the low end tight and mono compatible
+                    if i < num_mono_bands && num_channels > 1 && !band_bypass[i] {
+                        let mono =
+                            band_signal[..num_channels].iter().sum::<f32>() / num_channels as f32;
+                        for sample in &mut band_signal[..num_channels] {
+                            *sample = mono + (1.0 - mono_amount) * (*sample - mono);
+                        }
+                    }
+
+                    // Line up the phase of the upper bands with the lower ones, then saturate and
+                    // remove any DC offset the saturator added so it doesn't skew the detectors.
+                    // With the compressor first the saturator runs after the gain stages instead.
+                    // Either way the band goes through it once, so its latency stays the same.
+                    band.compensate_phase(i, band_signal, num_channels);
+                    let mut band_dry = *band_signal;
+                    for (channel, sample) in band_dry.iter_mut().enumerate().take(num_channels) {
+                        *sample = anti_aliasing.align(*sample, &mut band.dry_history[channel]);
+                    }
+                    band.dry_compensation_delay
+                        .process(&mut band_dry, num_channels);
+                    let saturate_band =
+                        |band: &mut ProcessingBand, band_signal: &mut [f32; MAX_CHANNELS]| {
+                            for (channel, sample) in
+                                band_signal.iter_mut().enumerate().take(num_channels)
+                            {
+                                let adaa_state = &mut band.adaa_states[channel];
+                                let hysteresis_state = &mut band.hysteresis_states[channel];
+                                let mut saturate = |sample| {
+                                    dsp::saturate(
+                                        sample,
+                                        amount,
+                                        drive_scale,
+                                        saturation_model,
+                                        anti_aliasing,
+                                        adaa_state,
+                                        hysteresis_state,
+                                    )
+                                };
+                                let saturated = if oversampling {
+                                    band.oversampler.process(channel, *sample, saturate)
+                                } else {
+                                    saturate(*sample)
+                                };
+                                // The tape model's high frequency loss keeps running for the other
+                                // models so switching to it doesn't start from a stale state
+                                let rolled_off = band.tape_rolloff.process(channel, saturated);
+                                let saturated =
+                                    if saturation_model == SaturationModel::TapeHysteresis {
+                                        rolled_off
+                                    } else {
+                                        saturated
+                                    };
+                                *sample = band.dc_blocker.process(channel, saturated);
+                            }
+                        };
+                    // De-essing bands skip the saturator in either order, so they keep the
+                    // original one
+                    let compress_first =
+                        processing_order == ProcessingOrder::CompressFirst && !band_de_ess[i];
+                    if !compress_first {
+                        saturate_band(band, band_signal);
+                    }
+                    // De-essing bands aren't saturated. The saturator keeps running in the
+                    // background, like for bypassed bands, so switching doesn't start it cold.
+                    if band_de_ess[i] {
+                        *band_signal = band_dry;
+                    }
 
                     // Then, compress the saturated signal
-                    let shifted_crossovers: [f32; MAX_BANDS - 1] =
-                        array_init::array_init(|j| shift_frequency(BASE_CROSSOVER_FREQS[j], tilt));
-                    let lower_bound = if i == 0 {
-                        20.0
-                    } else {
-                        shifted_crossovers[i - 1]
+                    let band_center_freq = self.band_center_freqs[i];
+
+                    if quality.per_sample_time_constants() || update_coefficients {
+                        let (attack, release) = dsp::calculate_dynamic_time_constants(
+                            self.sample_rate,
+                            band_center_freq,
+                            i,
+                            amount,
+                        );
+                        let (attack, release) = if band_de_ess[i] {
+                            (
+                                attack * dsp::DE_ESS_ATTACK_SCALE,
+                                release * dsp::DE_ESS_RELEASE_SCALE,
+                            )
+                        } else {
+                            (attack, release)
+                        };
+                        // The global multipliers slow down or speed up the program-derived
+                        // timings. Long release times give coefficients very close to zero, so
+                        // these are calculated at the processing precision. The transient shaper's
+                        // slow envelope hangs on longer so the sustain portion of a note shows up
+                        // as the fast envelope dropping below it. Adaptive Timing moves the
+                        // detectors towards the last two around a hit.
+                        let attack = attack * attack_scale;
+                        let release = release * release_scale;
+                        band.envelope_alphas = [
+                            1.0 - (-1.0 / to_real(attack)).exp(),
+                            1.0 - (-1.0 / to_real(release)).exp(),
+                            1.0 - (-1.0 / to_real(release * 4.0)).exp(),
+                            1.0 - (-1.0 / to_real(attack * dsp::ADAPTIVE_ATTACK_SCALE)).exp(),
+                            1.0 - (-1.0 / to_real(release * dsp::ADAPTIVE_RELEASE_SCALE)).exp(),
+                        ];
+                        gain_smoothers.gr_alphas =
+                            GrSmoother::alphas(attack, release, gr_smoothing);
+                    }
+                    let [
+                        attack_alpha,
+                        release_alpha,
+                        transient_release_alpha,
+                        hit_attack_alpha,
+                        hit_release_alpha,
+                    ] = band.envelope_alphas;
+
+                    // The detectors listen to a high-passed and weighted copy of the band when
+                    // the detector HPF and weighting are on. With the feedback topology that's the
+                    // band's output from the previous sample. The transient shaper still follows
+                    // the unfiltered band.
+                    let mut detector_signal = match topology {
+                        Topology::FeedForward => *band_signal,
+                        Topology::Feedback => band.feedback_frame,
                     };
-                    let upper_bound = if i == MAX_BANDS - 1 {
-                        self.sample_rate / 2.0
-                    } else {
-                        shifted_crossovers[i]
-                    };
-                    let band_center_freq = (lower_bound * upper_bound).sqrt();
-
-                    let (attack, release) = dsp::calculate_dynamic_time_constants(
-                        self.sample_rate,
-                        band_center_freq,
-                        i,
-                        amount,
+                    if detector_hpf_enabled {
+                        band.detector_hpf
+                            .process(&mut detector_signal, num_channels);
+                    }
+                    band.detector_weighting
+                        .process(&mut detector_signal, num_channels);
+                    if band_de_ess[i] {
+                        band.de_ess_filter
+                            .process(&mut detector_signal, num_channels);
+                    }
+                    // The detector listen mode follows the bands' solo and mute switches
+                    if band_audible[i] {
+                        for channel in 0..num_channels {
+                            detector_sum[channel] += detector_signal[channel];
+                        }
+                    }
+
+                    // The Power and RMS detectors follow the signal power and the Peak detector
+                    // follows the signal level. Linked channels feed the loudest channel's value
+                    // into their detectors.
+                    let mut detector_input = [0.0f32; MAX_CHANNELS];
+                    let mut transient_input = [0.0f32; MAX_CHANNELS];
+                    for (channel, (sample, band_sample)) in detector_signal
+                        .iter()
+                        .zip(band_signal.iter())
+                        .enumerate()
+                        .take(num_channels)
+                    {
+                        let value = match detector_mode {
+                            DetectorMode::Power => sample * sample,
+                            DetectorMode::Rms => band.rms_windows[channel].process(sample * sample),
+                            DetectorMode::Peak => sample.abs(),
+                        };
+                        let group = link_groups[channel];
+                        detector_input[group] = detector_input[group].max(value);
+                        transient_input[group] = transient_input[group].max(band_sample.abs());
+                    }
+
+                    // The transient detector listens to the loudest channel, and blends the
+                    // detectors' timing towards the hit timing as far as Adaptive Timing goes. It
+                    // keeps running at 0% so turning it up doesn't start from a stale state. The
+                    // transient shaper's envelopes keep the regular timing.
+                    let hit = band.transient_detector.process(
+                        transient_input[..num_channels]
+                            .iter()
+                            .fold(0.0f32, |peak, &level| peak.max(level)),
                     );
-
-                    // Independent L/R envelope detection
-                    let band_power_l = band_l * band_l;
-                    let alpha_l = if band_power_l > self.bands[i].envelope_l {
-                        1.0 - (-1.0 / attack).exp()
+                    let adaptation_depth = to_real(adaptive_timing * hit);
+                    let detector_attack_alpha =
+                        attack_alpha + (hit_attack_alpha - attack_alpha) * adaptation_depth;
+                    let detector_release_alpha =
+                        release_alpha + (hit_release_alpha - release_alpha) * adaptation_depth;
+
+                    let de_ess_gain = if band_de_ess[i] {
+                        util::db_to_gain(dsp::DE_ESS_SENSITIVITY_DB)
                     } else {
-                        1.0 - (-1.0 / release).exp()
+                        1.0
                     };
-                    self.bands[i].envelope_l =
-                        (1.0 - alpha_l) * self.bands[i].envelope_l + alpha_l * band_power_l;
-                    let envelope_sqrt_l = self.bands[i].envelope_l.sqrt();
 
-                    let band_power_r = band_r * band_r;
-                    let alpha_r = if band_power_r > self.bands[i].envelope_r {
-                        1.0 - (-1.0 / attack).exp()
+                    // The noise layer only runs while it's turned up
+                    let noise_frame = if noise > 0.0 {
+                        band.noise.process(noise_texture, num_channels)
                     } else {
-                        1.0 - (-1.0 / release).exp()
+                        [0.0; MAX_CHANNELS]
                     };
-                    self.bands[i].envelope_r =
-                        (1.0 - alpha_r) * self.bands[i].envelope_r + alpha_r * band_power_r;
-                    let envelope_sqrt_r = self.bands[i].envelope_r.sqrt();
-
-                    // Calculate and apply gain reduction
-                    let target_gr_l = dsp::calculate_target_gr(i, amount, tilt, envelope_sqrt_l);
-                    let target_gr_r = dsp::calculate_target_gr(i, amount, tilt, envelope_sqrt_r);
 
-                    self.bands[i]
-                        .applied_gr_smoother_l
-                        .set_target(self.sample_rate, target_gr_l);
-                    self.bands[i]
-                        .applied_gr_smoother_r
-                        .set_target(self.sample_rate, target_gr_r);
-
-                    // Get the GR for this sample and store it for the reactive EQ
-                    gr_factors_l[i] = self.bands[i].applied_gr_smoother_l.next();
-                    gr_factors_r[i] = self.bands[i].applied_gr_smoother_r.next();
-
-                    current_sample_gr_db +=
-                        util::gain_to_db((gr_factors_l[i] + gr_factors_r[i]) / 2.0);
-
-                    band_l *= gr_factors_l[i];
-                    band_r *= gr_factors_r[i];
-
-                    // Sum the processed bands back together
-                    wet_l += band_l;
-                    wet_r += band_r;
+                    for channel in 0..num_channels {
+                        // Envelope detection, along the attack or release curve. The release
+                        // model adjusts the release's speed and target.
+                        let detector_value = to_real(detector_input[link_groups[channel]]);
+                        let envelope = &mut band.envelopes[channel];
+                        let release_peak = &mut band.release_peaks[channel];
+                        let attacking = detector_value > *envelope;
+                        let (target, alpha, curve) = if attacking {
+                            (detector_value, detector_attack_alpha, attack_curve)
+                        } else {
+                            let (target, alpha) = release_model.release_step(
+                                *envelope,
+                                *release_peak,
+                                detector_value,
+                                detector_release_alpha,
+                            );
+                            (target, alpha, release_curve)
+                        };
+                        dsp::follow_envelope(
+                            envelope,
+                            &mut band.envelope_stages[channel],
+                            target,
+                            alpha,
+                            curve,
+                        );
+                        if attacking {
+                            *release_peak = *envelope;
+                        }
+                        let envelope_level = from_real(match detector_mode {
+                            DetectorMode::Power | DetectorMode::Rms => envelope.sqrt(),
+                            DetectorMode::Peak => *envelope,
+                        });
+
+                        // Calculate and apply gain reduction, capped by the Range. The depth
+                        // scales the GR in dB. De-essing bands hear their detector louder, which
+                        // lowers the threshold.
+                        let target_gr = dsp::calculate_target_gr(
+                            i,
+                            num_bands,
+                            amount,
+                            tilt,
+                            band_trim,
+                            envelope_level * de_ess_gain,
+                            character,
+                            adaptation,
+                        )
+                        .max(min_gr_factor)
+                        .powf(downward_depth);
+
+                        // The upward gain has its own smoother and is kept out of `gr_factors`, so
+                        // the reactive EQ and the GR meter only follow the downward compressor
+                        let target_upward_gain = if upward_depth > 0.0 && !band_de_ess[i] {
+                            dsp::calculate_target_upward_gain(
+                                i,
+                                num_bands,
+                                amount,
+                                tilt,
+                                band_trim,
+                                envelope_level,
+                                character,
+                                adaptation,
+                            )
+                            .powf(upward_depth)
+                        } else {
+                            1.0
+                        };
+                        let upward_smoother = &gain_smoothers.upward_gain[channel];
+                        upward_smoother.set_target(self.sample_rate, target_upward_gain);
+
+                        // The same goes for the expander, which would otherwise make the reactive
+                        // EQ boost a band while it's gated
+                        let target_expander_gain = if band_expand[i] {
+                            dsp::calculate_target_expander_gain(
+                                amount,
+                                expand_threshold,
+                                envelope_level,
+                            )
+                        } else {
+                            1.0
+                        };
+                        let expander_smoother = &gain_smoothers.expander_gain[channel];
+                        expander_smoother.set_target(self.sample_rate, target_expander_gain);
+
+                        // The transient shaper compares a fast and a slow envelope of the band's
+                        // level. These keep running when Punch is at 0% so turning it up later
+                        // doesn't start from a stale state.
+                        let transient_level = to_real(transient_input[link_groups[channel]]);
+                        let (fast_envelope, slow_envelope) = &mut band.transient_envelopes[channel];
+                        let fast_alpha = if transient_level > *fast_envelope {
+                            transient_attack_alpha
+                        } else {
+                            release_alpha
+                        };
+                        let slow_alpha = if transient_level > *slow_envelope {
+                            attack_alpha
+                        } else {
+                            transient_release_alpha
+                        };
+                        *fast_envelope += fast_alpha * (transient_level - *fast_envelope);
+                        *slow_envelope += slow_alpha * (transient_level - *slow_envelope);
+                        let transient_gain = if transient_depth != 0.0 {
+                            dsp::calculate_transient_gain(
+                                transient_depth,
+                                from_real(*fast_envelope),
+                                from_real(*slow_envelope),
+                            )
+                        } else {
+                            1.0
+                        };
+
+                        // Get the GR for this sample and store it for the reactive EQ. The MIDI
+                        // ducking is kept out of it like the expander, so the reactive EQ doesn't
+                        // boost the band back up while it's ducked. In the Dynamic EQ mode the
+                        // reactive EQ applies the GR instead.
+                        gr_factors[i][channel] =
+                            gain_smoothers.gr[channel].next(target_gr, gain_smoothers.gr_alphas);
+                        let band_gr = if mode == Mode::DynamicEq {
+                            1.0
+                        } else {
+                            gr_factors[i][channel]
+                        };
+                        band_signal[channel] *= band_gr
+                            * upward_smoother.next()
+                            * expander_smoother.next()
+                            * transient_gain
+                            * duck_gain;
+                        band.feedback_frame[channel] = band_signal[channel];
+
+                        // The noise is added after the gain reduction so it follows the band's
+                        // level instead of being squashed along with it
+                        band_signal[channel] += noise_frame[channel]
+                            * dsp::calculate_noise_gain(noise, amount, envelope_level);
+                    }
+                    if compress_first {
+                        saturate_band(band, band_signal);
+                    }
+
+                    // B.3: The top band gets the exciter's harmonics on top of its compressed
+                    // signal, adding sheen the source doesn't have
+                    if i == num_bands - 1 && air_gain > 0.0 {
+                        let harmonics = self.exciter.process(band_signal, num_channels);
+                        for (sample, harmonic) in band_signal.iter_mut().zip(harmonics) {
+                            *sample += air_gain * harmonic;
+                        }
+                    }
+
+                    // B.4: Widen or narrow the band by scaling the side signal of every stereo pair
+                    for &(left, right) in stereo_pairs(num_channels) {
+                        let mid = (band_signal[left] + band_signal[right]) / 2.0;
+                        let side = width * (band_signal[left] - band_signal[right]) / 2.0;
+                        band_signal[left] = mid + side;
+                        band_signal[right] = mid - side;
+                    }
+
+                    // B.5: Blend the processed band with its unprocessed signal
+                    if band_mix < 1.0 {
+                        for (sample, dry) in band_signal.iter_mut().zip(band_dry).take(num_channels)
+                        {
+                            *sample = dry + (band_mix * (*sample - dry));
+                        }
+                    }
+
+                    // B.6: Bypassed bands pass on their unprocessed signal, and stay out of the
+                    // reactive EQ and the GR meter
+                    if band_bypass[i] {
+                        *band_signal = band_dry;
+                        gr_factors[i] = [1.0; MAX_CHANNELS];
+                    }
+
+                    // Sum the processed bands back together, skipping muted/non-soloed bands
+                    if band_audible[i] {
+                        for channel in 0..num_channels {
+                            wet[channel] += band_signal[channel];
+                        }
+                    }
+
+                    let avg_gr_factor =
+                        gr_factors[i][..num_channels].iter().sum::<f32>() / num_channels as f32;
+                    current_sample_gr_db += util::gain_to_db(avg_gr_factor);
+                    block_band_power[i] += band_signal[..num_channels]
+                        .iter()
+                        .map(|s| s * s)
+                        .sum::<f32>()
+                        / num_channels as f32;
                 }
                 total_gr_db += current_sample_gr_db;
+                block_peak_gr_db = block_peak_gr_db.min(current_sample_gr_db);
 
-                // Denormal guard
-                wet_l += 1.0e-20;
-                wet_r += 1.0e-20;
-
-                (wet_l, wet_r)
+                (wet, band_signals, detector_sum)
             };
 
+            // The Phase Color allpasses rotate the phase of the summed bands around the
+            // crossovers. The dry signal is left alone, so partial Mix settings add notches on top.
+            self.phase_color.process(&mut wet, num_channels);
+
             // --- C. Serial Compensation EQ Stage ---
             // After the parallel band processing, the summed wet signal is passed through
-            // the series of dynamic EQs.
-
-            for i in 0..MAX_BANDS {
-                // --- Reactive EQ Calculation (Per-Sample) ---
-                // We calculate the EQ coefficients for each sample, reacting to the GR of that sample.
-                let tilt_effect = tilt.abs().powf(1.5) * tilt.signum();
-                let band_tilt_factor = match i {
-                    0..=1 => 1.0 + (tilt_effect * -0.6),
-                    2 => 1.0,
-                    3..=4 => 1.0 + (tilt_effect * 0.6),
-                    _ => 1.0,
+            // the series of dynamic EQs. The spectral engine's gains already shape every band's
+            // spectrum, so it skips this stage.
+            if mode != Mode::Spectral {
+                for i in 0..num_bands {
+                    if update_coefficients {
+                        // --- Reactive EQ Calculation (At The Coefficient Update Rate) ---
+                        // We calculate the target EQ coefficients from the GR of the current
+                        // sample, and the filter glides towards them until the next update.
+                        let tilt_effect = tilt.abs().powf(1.5) * tilt.signum();
+                        let band_tilt_factor =
+                            (1.0 + tilt_effect * 0.6 * band_side(i, num_bands)).clamp(0.4, 1.6);
+
+                        // Color shifts the Qs away from Amount, towards broad or ringing boosts
+                        let resonance = (amount + color).clamp(0.0, 1.0);
+                        let q_base = 0.7 + (8.0 * resonance.powf(2.0));
+                        // The outermost bands get the strongest Q tilt, matching the original
+                        // 5-band layout where this was `(i - 2) * 0.4`.
+                        let q_tilt_factor = 1.0 + (tilt * band_position(i, num_bands) * 2.0 * 0.4);
+                        let q_factor = (q_base * q_tilt_factor).clamp(0.5, 20.0f32);
+
+                        // The EQ gain is a function of the *actual* gain reduction applied in this
+                        // sample.
+                        let avg_gr_factor =
+                            gr_factors[i][..num_channels].iter().sum::<f32>() / num_channels as f32;
+                        // We get the GR in dB, normalize it (assuming a max of ~-24dB is where we
+                        // want max boost), and then scale it by our max compensation value and
+                        // other dynamic factors.
+                        // In the Dynamic EQ mode the filter spans the whole band and cuts it by
+                        // the GR instead. De-essing bands get no compensation boost, which would
+                        // bring the sibilance right back. With the reactive EQ off the filters
+                        // glide to flat, so switching it doesn't click.
+                        let gr_db = util::gain_to_db(avg_gr_factor);
+                        let (q_factor, compensation_gain_db) = if mode == Mode::DynamicEq {
+                            (self.band_qs[i], gr_db)
+                        } else if band_de_ess[i] || !reactive_eq {
+                            (q_factor, 0.0)
+                        } else {
+                            let compensation_gain_db = (gr_db.abs() / 24.0)
+                                * eq_depth
+                                * (amount * band_tilt_factor)
+                                * character.eq_compensation_depth();
+                            (q_factor, compensation_gain_db)
+                        };
+
+                        // The same center frequency as in the parallel stage, so the EQ stays in
+                        // sync with the band it compensates.
+                        let band_center_freq = self.band_center_freqs[i];
+                        self.eq_settings[i] = (band_center_freq, q_factor, compensation_gain_db);
+
+                        self.bands[i].compensation_eq.ramp_to(
+                            BiquadCoefficients::calculate_peaking(
+                                self.sample_rate,
+                                band_center_freq,
+                                q_factor,
+                                compensation_gain_db,
+                            ),
+                            coefficient_update_interval as u32,
+                        );
+                    }
+
+                    self.bands[i]
+                        .compensation_eq
+                        .process(&mut wet, num_channels);
                 }
-                .clamp(0.4, 1.6);
-
-                let q_base = 0.7 + (8.0 * amount.powf(2.0));
-                let q_tilt_factor = 1.0 + (tilt * (i as f32 - 2.0) * 0.4);
-                let q_factor = (q_base * q_tilt_factor).clamp(0.5, 20.0f32);
-
-                // The EQ gain is a function of the *actual* gain reduction applied in this sample.
-                let avg_gr_factor = (gr_factors_l[i] + gr_factors_r[i]) / 2.0;
-                // We get the GR in dB, normalize it (assuming a max of ~-24dB is where we want max boost),
-                // and then scale it by our max compensation value and other dynamic factors.
-                let gr_db_abs = util::gain_to_db(avg_gr_factor).abs();
-                let compensation_gain_db =
-                    (gr_db_abs / 24.0) * MAX_COMPENSATION_DB * (amount * band_tilt_factor);
-
-                // This calculation must be identical to the one in the parallel stage to ensure sync.
-                let shifted_crossovers: [f32; MAX_BANDS - 1] =
-                    array_init::array_init(|j| shift_frequency(BASE_CROSSOVER_FREQS[j], tilt));
-                let lower_bound = if i == 0 {
-                    20.0
-                } else {
-                    shifted_crossovers[i - 1]
-                };
-                let upper_bound = if i == MAX_BANDS - 1 {
-                    self.sample_rate / 2.0
-                } else {
-                    shifted_crossovers[i]
-                };
-                let band_center_freq = (lower_bound * upper_bound).sqrt();
+            }
 
-                self.bands[i].compensation_eq.update_peaking(
+            // --- D. Track Wet Signal Loudness for Loudness Compensation ---
+            // This is measured before the correction is applied. Otherwise the correction would
+            // be measuring its own output and the partial strength settings wouldn't hold.
+            self.wet_loudness.process(&wet, num_channels);
+            if auto_gain && self.wet_loudness.segment_completed() {
+                self.loudness_correction_smoother.set_target(
                     self.sample_rate,
-                    band_center_freq,
-                    q_factor,
-                    compensation_gain_db,
+                    loudness::correction_gain(
+                        self.dry_loudness.mean_square(),
+                        self.wet_loudness.mean_square(),
+                        auto_gain_strength,
+                    ),
                 );
+            }
 
-                (wet_l, wet_r) = self.bands[i].compensation_eq.process(wet_l, wet_r);
+            // --- E. Final Loudness Compensation ---
+            for sample in &mut wet[..num_channels] {
+                *sample *= loudness_correction;
             }
 
-            // --- D. Final Loudness Compensation ---
-            wet_l *= loudness_correction;
+            // The wet and dry outputs get both signals in full, regardless of Mix and Delta, with
+            // the output gain applied
+            let mut wet_output = wet;
+            let mut dry_output = dry;
+            for (wet_sample, dry_sample) in wet_output
+                .iter_mut()
+                .zip(&mut dry_output)
+                .take(num_channels)
+            {
+                *wet_sample *= output_gain;
+                *dry_sample *= output_gain;
+            }
 
-            wet_r *= loudness_correction;
+            // --- F. Constant Power Dry/Wet Mix and Output Gain ---
+            // In delta mode only the difference the processing makes at the current Mix setting
+            // is heard. Both signals are aligned so this cancels out completely at 0% Mix.
+            let mut output = [0.0; MAX_CHANNELS];
+            for (channel, sample) in output.iter_mut().enumerate().take(num_channels) {
+                let mixed = (dry[channel] * dry_gain) + (wet[channel] * wet_gain);
+                *sample = if listen == Listen::Detector {
+                    detector_sum[channel] * output_gain
+                } else if delta {
+                    (mixed - dry[channel]) * output_gain
+                } else {
+                    mixed * output_gain
+                };
+            }
 
-            // --- E. Track Wet Signal Power for Loudness Compensation ---
-            let wet_power = (wet_l * wet_l + wet_r * wet_r) * 0.5;
-            block_avg_output += wet_power;
+            // The oscilloscope shows the aligned dry signal against the loudness-compensated wet
+            // signal, both summed to mono. These aren't checked for non-finite values until after
+            // the limiter, so the scope gets sanitized copies.
+            self.scope_buffer.push(
+                dsp::sanitize(dry[..num_channels].iter().sum::<f32>() / num_channels as f32),
+                dsp::sanitize(wet[..num_channels].iter().sum::<f32>() / num_channels as f32),
+            );
+
+            // --- G. True Peak Limiter ---
+            self.limiter.process(
+                &mut output,
+                num_channels,
+                ceiling,
+                quality.true_peak_detection(),
+            );
+
+            // Extreme settings or input levels can still make a filter or an envelope blow up. If
+            // anything non-finite comes out of the processing, every state is flushed and the
+            // sample is silenced, so the plugin recovers instead of staying stuck on NaN.
+            if !(dsp::is_finite_frame(&output, num_channels)
+                && dsp::is_finite_frame(&wet_output, num_channels)
+                && dsp::is_finite_frame(&dry_output, num_channels)
+                && band_signals[..num_bands]
+                    .iter()
+                    .all(|band_signal| dsp::is_finite_frame(band_signal, num_channels)))
+            {
+                self.flush_audio_state();
+                output = [0.0; MAX_CHANNELS];
+                wet_output = [0.0; MAX_CHANNELS];
+                dry_output = [0.0; MAX_CHANNELS];
+                band_signals = [[0.0; MAX_CHANNELS]; MAX_BANDS];
+            }
 
-            // --- F. Constant Power Dry/Wet Mix and Output Gain ---
-            *l = ((dry_l * dry_gain) + (wet_l * wet_gain)) * output_gain;
-            *r = ((dry_r * dry_gain) + (wet_r * wet_gain)) * output_gain;
+            // --- H. Soft Bypass ---
+            let mut bypassed = input;
+            self.bypass_delay.process(&mut bypassed, num_channels);
+            let mut final_output = [0.0; MAX_CHANNELS];
+            for (channel, sample) in frame.iter_mut().enumerate().take(num_channels) {
+                *sample = (output[channel] * (1.0 - bypass_mix)) + (bypassed[channel] * bypass_mix);
+                final_output[channel] = *sample;
 
-            // Apply Master Output Gain
+                block_output_peak = block_output_peak.max(sample.abs());
+                block_output_power += *sample * *sample / num_channels as f32;
+            }
+            // The clip indicator always measures true peaks, regardless of the quality mode
+            let output_true_peak =
+                self.output_peak_detector
+                    .process(&final_output, num_channels, true);
+            block_output_true_peak = block_output_true_peak.max(output_true_peak);
+
+            // The goniometer and the correlation meter look at the first two channels, so a mono
+            // output shows up as a vertical line with full correlation
+            let left = final_output[0];
+            let right = final_output[if num_channels > 1 { 1 } else { 0 }];
+            self.scope_buffer.push_stereo(left, right);
+            block_stereo_products[0] += left * right;
+            block_stereo_products[1] += left * left;
+            block_stereo_products[2] += right * right;
+
+            // --- I. Band Outputs ---
+            // The multi-out layout gets every processed band on its own output, before the bands
+            // are summed and go through the reactive EQ. Muted bands are still sent out, and
+            // the outputs of inactive bands are silent. The wet and dry outputs come last.
+            let num_band_outputs = aux.outputs.len().saturating_sub(NUM_WET_DRY_OUTPUTS);
+            let (band_outputs, wet_dry_outputs) = aux.outputs.split_at_mut(num_band_outputs);
+            for (band_idx, (output, delay)) in band_outputs
+                .iter_mut()
+                .zip(&mut self.band_output_delays)
+                .enumerate()
+            {
+                let mut band_frame = if band_idx < num_bands {
+                    band_signals[band_idx]
+                } else {
+                    [0.0; MAX_CHANNELS]
+                };
+                delay.process(&mut band_frame, num_channels);
+                for (channel, samples) in output.as_slice().iter_mut().enumerate() {
+                    samples[sample_idx] = band_frame.get(channel).copied().unwrap_or(0.0);
+                }
+            }
+            if let [wet_aux, dry_aux] = wet_dry_outputs {
+                self.wet_output_delay.process(&mut wet_output, num_channels);
+                self.dry_output_delay.process(&mut dry_output, num_channels);
+                for (output, frame) in [(wet_aux, &wet_output), (dry_aux, &dry_output)] {
+                    for (channel, samples) in output.as_slice().iter_mut().enumerate() {
+                        samples[sample_idx] = frame.get(channel).copied().unwrap_or(0.0);
+                    }
+                }
+            }
         }
 
-        // --- 4. Post-Block RMS Update ---
-        // After processing the entire block, we update the RMS trackers. These values will be
-        // used in the *next* block's loudness correction calculation.
-        let block_size = buffer.samples() as f32;
+        // --- 3. Post-Block Meter Update ---
+        let block_size = num_samples as f32;
         if block_size > 0.0 {
-            let avg_input_power = block_avg_input / block_size;
-            let avg_output_power = block_avg_output / block_size;
-            self.dry_rms_tracker = avg_input_power;
-            self.wet_rms_tracker = avg_output_power;
+            if self.tilt_analysis_remaining > 0 {
+                let total_samples = SUGGEST_TILT_SECONDS * self.sample_rate;
+                self.tilt_suggestion.progress.store(
+                    1.0 - self.tilt_analysis_remaining as f32 / total_samples,
+                    Ordering::Relaxed,
+                );
+            }
+            if self.target_analysis_remaining > 0 {
+                let total_samples = LEARN_TARGET_SECONDS * self.sample_rate;
+                self.target_learn.progress.store(
+                    1.0 - self.target_analysis_remaining as f32 / total_samples,
+                    Ordering::Relaxed,
+                );
+            }
 
-            // Update the GR meter parameter for the GUI to read.
-            let avg_gr_db = total_gr_db / block_size;
-            self.gr_meter_smoother
-                .set_target(self.sample_rate, avg_gr_db);
+            // The clip indicator latches even while the GUI is closed, so overs aren't missed
+            self.meter_levels
+                .output_true_peak_hold
+                .fetch_max(util::gain_to_db(block_output_true_peak), Ordering::Relaxed);
 
             // If the GUI is open, update the shared atomic value for the meter.
             #[cfg(feature = "vizia")]
             if self.params.editor_state.is_open() {
-                self.gain_reduction_meter
-                    .store(self.gr_meter_smoother.next(), Ordering::Relaxed);
+                // The GR meter follows the selected ballistics. The GR is negative, so it's
+                // flipped to make deeper GR the larger deflection.
+                let avg_gr_db = total_gr_db / block_size;
+                let block_ms = block_size / self.sample_rate * 1000.0;
+                let gr_meter = &self.gain_reduction_meter;
+                let reading = self.params.meter_ballistics.value().next(
+                    -gr_meter.load(Ordering::Relaxed),
+                    -block_peak_gr_db,
+                    -avg_gr_db,
+                    block_ms,
+                    self.params.meter_decay.value(),
+                );
+                gr_meter.store(-reading, Ordering::Relaxed);
+
+                // Update the level meters, which hold peaks and then fall back with a time constant
+                // of METER_DECAY_MS
+                let meter_decay =
+                    (-block_size / (self.sample_rate * METER_DECAY_MS / 1000.0)).exp();
+                let levels = &self.meter_levels;
+                update_meter(
+                    &levels.input_peak,
+                    util::gain_to_db(block_input_peak),
+                    meter_decay,
+                );
+                update_meter(
+                    &levels.input_rms,
+                    util::gain_to_db((block_input_power / block_size).sqrt()),
+                    meter_decay,
+                );
+                update_meter(
+                    &levels.output_peak,
+                    util::gain_to_db(block_output_peak),
+                    meter_decay,
+                );
+                update_meter(
+                    &levels.output_rms,
+                    util::gain_to_db((block_output_power / block_size).sqrt()),
+                    meter_decay,
+                );
+                for (band_idx, band_level) in levels.band_levels.iter().enumerate() {
+                    if band_idx < num_bands {
+                        update_meter(
+                            band_level,
+                            util::gain_to_db((block_band_power[band_idx] / block_size).sqrt()),
+                            meter_decay,
+                        );
+                    } else {
+                        band_level.store(util::MINUS_INFINITY_DB, Ordering::Relaxed);
+                    }
+                }
+
+                // The correlation is the normalized average of L * R, which is 1 for identical
+                // channels, 0 for unrelated channels and -1 for channels that cancel out
+                let correlation_decay =
+                    (-block_size / (self.sample_rate * CORRELATION_TIME_MS / 1000.0)).exp();
+                for (average, block_sum) in self
+                    .output_stereo_products
+                    .iter_mut()
+                    .zip(block_stereo_products)
+                {
+                    *average = (*average * correlation_decay)
+                        + (block_sum / block_size * (1.0 - correlation_decay));
+                }
+                let [lr, ll, rr] = self.output_stereo_products;
+                let correlation =
+                    if ll > loudness::SILENCE_MEAN_SQUARE && rr > loudness::SILENCE_MEAN_SQUARE {
+                        (lr / (ll * rr).sqrt()).clamp(-1.0, 1.0)
+                    } else {
+                        0.0
+                    };
+                levels
+                    .output_correlation
+                    .store(correlation, Ordering::Relaxed);
+
+                let eq_curve = &self.eq_curve;
+                eq_curve
+                    .sample_rate
+                    .store(self.sample_rate, Ordering::Relaxed);
+                eq_curve.num_bands.store(num_bands, Ordering::Relaxed);
+                for (band_idx, &(freq, q, gain_db)) in
+                    self.eq_settings[..num_bands].iter().enumerate()
+                {
+                    eq_curve.frequencies[band_idx].store(freq, Ordering::Relaxed);
+                    eq_curve.qs[band_idx].store(q, Ordering::Relaxed);
+                    eq_curve.gains_db[band_idx].store(gain_db, Ordering::Relaxed);
+                }
             }
         }
 
         ProcessStatus::Normal
     }
-
-    fn editor(&mut self, _async_executor: AsyncExecutor<Self>) -> Option<Box<dyn Editor>> {
-        #[cfg(feature = "vizia")]
-        editor::create(
-            self.params.clone(),
-            self.gain_reduction_meter.clone(),
-            self.params.editor_state.clone(),
-        )
-    }
 }
 
 impl Vst3Plugin for ColorFall {
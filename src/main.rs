@@ -0,0 +1,11 @@
+//! Standalone entry point for ColorFall.
+//!
+//! Runs the plugin outside of a DAW using nih-plug's standalone wrapper. Audio and MIDI I/O go
+//! through JACK or CPAL, and the backend, devices, sample rate, and buffer size can all be chosen
+//! on the command line. Run with `--help` to see the available options.
+use colorfall::ColorFall;
+use nih_plug::prelude::*;
+
+fn main() {
+    nih_export_standalone::<ColorFall>();
+}
@@ -0,0 +1,270 @@
+//! # MIDI CC Mapping
+//!
+//! Lets incoming MIDI CCs drive the main controls, so `Amount` can be ridden from a hardware
+//! controller without setting up a mapping in the host. Every control has its own CC assignment,
+//! range, and smoothing. CCs are assigned with MIDI learn: select a control with the `MIDI Learn`
+//! parameter and move the controller. Once a control has received a CC, the CC overrides the
+//! control's own value.
+use nih_plug::prelude::*;
+use std::sync::RwLock;
+
+/// The number of controls a CC can be mapped to.
+pub const NUM_MIDI_TARGETS: usize = 4;
+
+/// The controls a CC can be mapped to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MidiTarget {
+    Amount,
+    Tilt,
+    Mix,
+    Output,
+}
+
+impl MidiTarget {
+    /// The control's name, as used in the mapping parameter names.
+    pub fn name(self) -> &'static str {
+        match self {
+            MidiTarget::Amount => "Amount",
+            MidiTarget::Tilt => "Tilt",
+            MidiTarget::Mix => "Mix",
+            MidiTarget::Output => "Output",
+        }
+    }
+
+    fn from_index(index: usize) -> Self {
+        match index {
+            0 => MidiTarget::Amount,
+            1 => MidiTarget::Tilt,
+            2 => MidiTarget::Mix,
+            _ => MidiTarget::Output,
+        }
+    }
+}
+
+/// What MIDI learn does with the next incoming CC.
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MidiLearn {
+    #[name = "Off"]
+    Off,
+    #[name = "Amount"]
+    Amount,
+    #[name = "Tilt"]
+    Tilt,
+    #[name = "Mix"]
+    Mix,
+    #[name = "Output"]
+    Output,
+    /// Removes every CC assignment as soon as it's selected.
+    #[name = "Clear All"]
+    ClearAll,
+}
+
+impl MidiLearn {
+    /// The control the next CC gets assigned to, if any.
+    fn target(self) -> Option<MidiTarget> {
+        match self {
+            MidiLearn::Amount => Some(MidiTarget::Amount),
+            MidiLearn::Tilt => Some(MidiTarget::Tilt),
+            MidiLearn::Mix => Some(MidiTarget::Mix),
+            MidiLearn::Output => Some(MidiTarget::Output),
+            MidiLearn::Off | MidiLearn::ClearAll => None,
+        }
+    }
+}
+
+/// The CC number assigned to every control, indexed by [`MidiTarget`], with -1 for unassigned
+/// controls. MIDI learn writes these on the audio thread, and they're stored with the plugin's
+/// state.
+pub type MidiCcAssignments = RwLock<[i32; NUM_MIDI_TARGETS]>;
+
+/// The range and smoothing of a control's CC mapping.
+#[derive(Params)]
+pub struct MidiMappingParams {
+    /// Where a CC value of 0 lands in the control's range.
+    #[id = "min"]
+    pub min: FloatParam,
+
+    /// Where a CC value of 127 lands in the control's range. This can be below the minimum to
+    /// invert the mapping.
+    #[id = "max"]
+    pub max: FloatParam,
+
+    /// How long the control takes to follow a CC change.
+    #[id = "smoothing"]
+    pub smoothing: FloatParam,
+}
+
+impl MidiMappingParams {
+    pub fn new(target_idx: usize) -> Self {
+        let name = MidiTarget::from_index(target_idx).name();
+        Self {
+            min: FloatParam::new(
+                format!("{name} CC Min"),
+                0.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_unit(" %")
+            .with_value_to_string(formatters::v2s_f32_percentage(1))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            max: FloatParam::new(
+                format!("{name} CC Max"),
+                1.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_unit(" %")
+            .with_value_to_string(formatters::v2s_f32_percentage(1))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            smoothing: FloatParam::new(
+                format!("{name} CC Smoothing"),
+                50.0,
+                FloatRange::Skewed {
+                    min: 0.0,
+                    max: 1000.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_unit(" ms")
+            .with_value_to_string(formatters::v2s_f32_rounded(0)),
+        }
+    }
+}
+
+/// The audio thread's side of the CC mappings. This keeps track of the CC-driven value of every
+/// control and glides it towards the last received CC value.
+pub struct MidiCcMapper {
+    /// A copy of the CC assignments, refreshed at the start of every block.
+    assignments: [Option<u8>; NUM_MIDI_TARGETS],
+    /// The MIDI learn selection the last assignment was made for. Every selection assigns a single
+    /// CC, so moving a second controller doesn't steal the mapping.
+    learned_for: MidiLearn,
+    /// The normalized value every control glides towards, set by the last CC it received. `None`
+    /// for controls that haven't received a CC since they were assigned.
+    targets: [Option<f32>; NUM_MIDI_TARGETS],
+    /// Whether a control's glide has started, in which case `values` holds its current value.
+    gliding: [bool; NUM_MIDI_TARGETS],
+    /// The current normalized value of every control.
+    values: [f32; NUM_MIDI_TARGETS],
+    /// The one-pole smoothing coefficient of every control for the current block.
+    smoothing_alphas: [f32; NUM_MIDI_TARGETS],
+}
+
+impl Default for MidiCcMapper {
+    fn default() -> Self {
+        Self {
+            assignments: [None; NUM_MIDI_TARGETS],
+            learned_for: MidiLearn::Off,
+            targets: [None; NUM_MIDI_TARGETS],
+            gliding: [false; NUM_MIDI_TARGETS],
+            values: [0.0; NUM_MIDI_TARGETS],
+            smoothing_alphas: [1.0; NUM_MIDI_TARGETS],
+        }
+    }
+}
+
+impl MidiCcMapper {
+    /// Picks up assignment and smoothing changes. Called at the start of every block.
+    pub fn begin_block(
+        &mut self,
+        assignments: &MidiCcAssignments,
+        learn: MidiLearn,
+        mappings: &[MidiMappingParams; NUM_MIDI_TARGETS],
+        sample_rate: f32,
+    ) {
+        if learn != self.learned_for {
+            match learn {
+                // Switching learn off arms it again for the next selection
+                MidiLearn::Off => self.learned_for = MidiLearn::Off,
+                MidiLearn::ClearAll => {
+                    if let Ok(mut assignments) = assignments.try_write() {
+                        *assignments = [-1; NUM_MIDI_TARGETS];
+                        self.learned_for = MidiLearn::ClearAll;
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        // The lock is only ever held briefly, so if it's taken the last copy is used for one more
+        // block
+        if let Ok(assignments) = assignments.try_read() {
+            for (assignment, &cc) in self.assignments.iter_mut().zip(assignments.iter()) {
+                *assignment = u8::try_from(cc).ok();
+            }
+        }
+        for idx in 0..NUM_MIDI_TARGETS {
+            if self.assignments[idx].is_none() {
+                self.release(idx);
+            }
+        }
+
+        for (alpha, mapping) in self.smoothing_alphas.iter_mut().zip(mappings) {
+            let smoothing_samples = mapping.smoothing.value() * sample_rate / 1000.0;
+            *alpha = if smoothing_samples > 1.0 {
+                1.0 - (-1.0 / smoothing_samples).exp()
+            } else {
+                1.0
+            };
+        }
+    }
+
+    /// Handles an incoming CC. If MIDI learn is waiting for a CC, the CC gets assigned to the
+    /// selected control first, and is removed from any other control it was assigned to.
+    pub fn handle_cc(
+        &mut self,
+        cc: u8,
+        value: f32,
+        learn: MidiLearn,
+        assignments: &MidiCcAssignments,
+        mappings: &[MidiMappingParams; NUM_MIDI_TARGETS],
+    ) {
+        if learn != self.learned_for
+            && let Some(target) = learn.target()
+            && let Ok(mut assignments) = assignments.try_write()
+        {
+            for (idx, assignment) in assignments.iter_mut().enumerate() {
+                *assignment = if idx == target as usize {
+                    i32::from(cc)
+                } else if *assignment == i32::from(cc) {
+                    -1
+                } else {
+                    *assignment
+                };
+                self.assignments[idx] = u8::try_from(*assignment).ok();
+                if self.assignments[idx].is_none() {
+                    self.release(idx);
+                }
+            }
+            self.learned_for = learn;
+        }
+
+        for (idx, mapping) in mappings.iter().enumerate() {
+            if self.assignments[idx] == Some(cc) {
+                let (min, max) = (mapping.min.value(), mapping.max.value());
+                self.targets[idx] = Some(min + (value * (max - min)));
+            }
+        }
+    }
+
+    /// Advances a control's smoothing by one sample and returns the control's value. Controls
+    /// that haven't received a CC return `value`, the control's own smoothed value, and the first
+    /// CC glides away from that value instead of jumping.
+    pub fn apply(&mut self, target: MidiTarget, param: &FloatParam, value: f32) -> f32 {
+        let idx = target as usize;
+        let Some(target_value) = self.targets[idx] else {
+            return value;
+        };
+        if !self.gliding[idx] {
+            self.values[idx] = param.preview_normalized(value);
+            self.gliding[idx] = true;
+        }
+
+        self.values[idx] += self.smoothing_alphas[idx] * (target_value - self.values[idx]);
+        param.preview_plain(self.values[idx])
+    }
+
+    /// Hands a control that lost its CC assignment back to its own value.
+    fn release(&mut self, idx: usize) {
+        self.targets[idx] = None;
+        self.gliding[idx] = false;
+    }
+}
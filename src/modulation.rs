@@ -0,0 +1,290 @@
+//! # Modulation Matrix
+//!
+//! A small routing table that connects the modulation sources (the LFOs, the input and sidechain
+//! envelopes, and MIDI CCs) to the controls they can modulate. Every slot routes one source to one
+//! destination with its own depth. The routing is picked up at the start of every block, and the
+//! summed offsets are added on top of the smoothed control values for every sample, so everything
+//! downstream follows the modulated values.
+use colorfall_dsp::MAX_BANDS;
+use nih_plug::prelude::*;
+
+use crate::lfo::{Lfo, LfoParams, NUM_LFOS, TransportInfo};
+use crate::morph::Morph;
+
+/// The number of slots in the modulation matrix.
+pub const NUM_MOD_SLOTS: usize = 8;
+
+/// The envelope sources map this range of levels below 0 dBFS to 0-1.
+const ENVELOPE_RANGE_DB: f32 = 60.0;
+const ENVELOPE_ATTACK_MS: f32 = 5.0;
+const ENVELOPE_RELEASE_MS: f32 = 100.0;
+
+/// What a slot is modulated by. The LFOs are bipolar, the other sources go from 0 to 1.
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModSource {
+    #[name = "None"]
+    None,
+    #[name = "LFO 1"]
+    Lfo1,
+    #[name = "LFO 2"]
+    Lfo2,
+    /// The level of the main input.
+    #[name = "Input Envelope"]
+    InputEnvelope,
+    /// The level of the sidechain input, silent when nothing is connected to it.
+    #[name = "Sidechain Envelope"]
+    SidechainEnvelope,
+    /// The slot's CC.
+    #[name = "MIDI CC"]
+    MidiCc,
+}
+
+/// The control a slot modulates.
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModDestination {
+    #[name = "Amount"]
+    Amount,
+    #[name = "Tilt"]
+    Tilt,
+    #[name = "Mix"]
+    Mix,
+    #[name = "Band 1 Amount"]
+    Band1Amount,
+    #[name = "Band 2 Amount"]
+    Band2Amount,
+    #[name = "Band 3 Amount"]
+    Band3Amount,
+    #[name = "Band 4 Amount"]
+    Band4Amount,
+    #[name = "Band 5 Amount"]
+    Band5Amount,
+    #[name = "Band 6 Amount"]
+    Band6Amount,
+}
+
+impl ModDestination {
+    /// The width of the destination's range. At 100% depth a source moves its destination across
+    /// the full range.
+    fn span(self) -> f32 {
+        match self {
+            ModDestination::Tilt => 2.0,
+            _ => 1.0,
+        }
+    }
+}
+
+/// The summed modulation of every destination for a single sample, in the destinations' own
+/// units.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ModOffsets {
+    pub amount: f32,
+    pub tilt: f32,
+    pub mix: f32,
+    pub band_amounts: [f32; MAX_BANDS],
+}
+
+impl ModOffsets {
+    fn add(&mut self, destination: ModDestination, offset: f32) {
+        let value = match destination {
+            ModDestination::Amount => &mut self.amount,
+            ModDestination::Tilt => &mut self.tilt,
+            ModDestination::Mix => &mut self.mix,
+            ModDestination::Band1Amount => &mut self.band_amounts[0],
+            ModDestination::Band2Amount => &mut self.band_amounts[1],
+            ModDestination::Band3Amount => &mut self.band_amounts[2],
+            ModDestination::Band4Amount => &mut self.band_amounts[3],
+            ModDestination::Band5Amount => &mut self.band_amounts[4],
+            ModDestination::Band6Amount => &mut self.band_amounts[5],
+        };
+        *value += offset;
+    }
+}
+
+/// The parameters of a single modulation matrix slot.
+#[derive(Params)]
+pub struct ModSlotParams {
+    #[id = "source"]
+    pub source: EnumParam<ModSource>,
+
+    #[id = "destination"]
+    pub destination: EnumParam<ModDestination>,
+
+    /// How far the source moves the destination. Negative depths invert the modulation.
+    #[id = "depth"]
+    pub depth: FloatParam,
+
+    /// The CC the slot listens to when its source is set to MIDI CC.
+    #[id = "cc"]
+    pub cc: IntParam,
+}
+
+impl ModSlotParams {
+    pub fn new(slot_idx: usize) -> Self {
+        Self {
+            source: EnumParam::new(format!("Mod {} Source", slot_idx + 1), ModSource::None),
+            destination: EnumParam::new(
+                format!("Mod {} Destination", slot_idx + 1),
+                ModDestination::Amount,
+            ),
+            depth: FloatParam::new(
+                format!("Mod {} Depth", slot_idx + 1),
+                0.0,
+                FloatRange::Linear {
+                    min: -1.0,
+                    max: 1.0,
+                },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_unit(" %")
+            .with_value_to_string(formatters::v2s_f32_percentage(1))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            // The mod wheel by default
+            cc: IntParam::new(
+                format!("Mod {} CC", slot_idx + 1),
+                1,
+                IntRange::Linear { min: 0, max: 127 },
+            ),
+        }
+    }
+}
+
+/// A peak envelope follower for the envelope sources.
+#[derive(Default)]
+struct LevelFollower {
+    envelope: f32,
+}
+
+impl LevelFollower {
+    /// Follows `peak` and returns the envelope mapped to 0-1 over [`ENVELOPE_RANGE_DB`].
+    fn next(&mut self, peak: f32, attack_alpha: f32, release_alpha: f32) -> f32 {
+        let alpha = if peak > self.envelope {
+            attack_alpha
+        } else {
+            release_alpha
+        };
+        self.envelope += alpha * (peak - self.envelope);
+
+        (1.0 + (util::gain_to_db(self.envelope) / ENVELOPE_RANGE_DB)).clamp(0.0, 1.0)
+    }
+}
+
+/// The audio thread's side of the modulation matrix. This owns the modulation sources and sums
+/// their contributions to every destination.
+pub struct ModMatrix {
+    lfos: [Lfo; NUM_LFOS],
+    input_follower: LevelFollower,
+    sidechain_follower: LevelFollower,
+    attack_alpha: f32,
+    release_alpha: f32,
+
+    /// Every slot's source and destination for the current block. Slots without a source are
+    /// skipped.
+    routes: [Option<(ModSource, ModDestination)>; NUM_MOD_SLOTS],
+    /// Every slot's CC and the last value received for it.
+    ccs: [u8; NUM_MOD_SLOTS],
+    cc_values: [f32; NUM_MOD_SLOTS],
+}
+
+impl Default for ModMatrix {
+    fn default() -> Self {
+        Self {
+            // Every LFO gets its own sample & hold sequence
+            lfos: array_init::array_init(|lfo_idx| Lfo::new(lfo_idx as u32 + 1)),
+            input_follower: LevelFollower::default(),
+            sidechain_follower: LevelFollower::default(),
+            attack_alpha: 1.0,
+            release_alpha: 1.0,
+            routes: [None; NUM_MOD_SLOTS],
+            ccs: [1; NUM_MOD_SLOTS],
+            cc_values: [0.0; NUM_MOD_SLOTS],
+        }
+    }
+}
+
+impl ModMatrix {
+    /// Picks up the routing and the LFO settings for the coming block, with the snapshot morph
+    /// applied.
+    pub fn begin_block(
+        &mut self,
+        slots: &[ModSlotParams; NUM_MOD_SLOTS],
+        lfos: &[LfoParams; NUM_LFOS],
+        morph: &Morph,
+        transport: &TransportInfo,
+        sample_rate: f32,
+    ) {
+        for (lfo, lfo_params) in self.lfos.iter_mut().zip(lfos) {
+            lfo.begin_block(lfo_params, morph, transport, sample_rate);
+        }
+        self.attack_alpha = 1.0 - (-1.0 / (ENVELOPE_ATTACK_MS * sample_rate / 1000.0)).exp();
+        self.release_alpha = 1.0 - (-1.0 / (ENVELOPE_RELEASE_MS * sample_rate / 1000.0)).exp();
+
+        for (slot_idx, slot) in slots.iter().enumerate() {
+            self.routes[slot_idx] = match morph.value(&slot.source) {
+                ModSource::None => None,
+                source => Some((source, morph.value(&slot.destination))),
+            };
+            self.ccs[slot_idx] = morph.value(&slot.cc) as u8;
+        }
+    }
+
+    /// Stores an incoming CC value for every slot listening to that CC.
+    pub fn handle_cc(&mut self, cc: u8, value: f32) {
+        for (&slot_cc, cc_value) in self.ccs.iter().zip(&mut self.cc_values) {
+            if slot_cc == cc {
+                *cc_value = value;
+            }
+        }
+    }
+
+    /// Advances the sources by one sample and returns the summed modulation of every destination.
+    /// `input_peak` and `sidechain_peak` are the highest absolute sample values of the inputs'
+    /// channels.
+    pub fn next(
+        &mut self,
+        slots: &[ModSlotParams; NUM_MOD_SLOTS],
+        input_peak: f32,
+        sidechain_peak: f32,
+    ) -> ModOffsets {
+        // The sources keep running when nothing is routed from them, so adding a route doesn't
+        // start from a stale state
+        let lfo_values: [f32; NUM_LFOS] =
+            array_init::array_init(|lfo_idx| self.lfos[lfo_idx].next());
+        let input_envelope =
+            self.input_follower
+                .next(input_peak, self.attack_alpha, self.release_alpha);
+        let sidechain_envelope =
+            self.sidechain_follower
+                .next(sidechain_peak, self.attack_alpha, self.release_alpha);
+
+        let mut offsets = ModOffsets::default();
+        for (slot_idx, slot) in slots.iter().enumerate() {
+            let Some((source, destination)) = self.routes[slot_idx] else {
+                continue;
+            };
+
+            let value = match source {
+                ModSource::None => 0.0,
+                ModSource::Lfo1 => lfo_values[0],
+                ModSource::Lfo2 => lfo_values[1],
+                ModSource::InputEnvelope => input_envelope,
+                ModSource::SidechainEnvelope => sidechain_envelope,
+                ModSource::MidiCc => self.cc_values[slot_idx],
+            };
+            offsets.add(
+                destination,
+                slot.depth.smoothed.next() * value * destination.span(),
+            );
+        }
+
+        offsets
+    }
+
+    /// Restarts the LFOs and clears the envelopes.
+    pub fn reset(&mut self) {
+        for lfo in &mut self.lfos {
+            lfo.reset();
+        }
+        self.input_follower = LevelFollower::default();
+        self.sidechain_follower = LevelFollower::default();
+    }
+}
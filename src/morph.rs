@@ -0,0 +1,226 @@
+//! # Snapshot Morphing
+//!
+//! Two snapshots of the plugin's parameters, A and B, and the `Morph` parameter blending between
+//! them. Once both snapshots are stored, every parameter they contain follows the morph instead of
+//! its own value. Continuous parameters are interpolated in their normalized range, so skewed
+//! ranges morph the way their knobs move. Stepped parameters (switches, choices, and the band
+//! count) jump from A's value to B's value halfway through the morph.
+//!
+//! Plugins can't set their own parameters from the audio thread, so the morphed values are applied
+//! on the audio thread instead: the smoothed parameters get their smoothers retargeted at the start
+//! of every block, and everything else is read through [`Morph::value()`].
+use nih_plug::prelude::*;
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Every morphable parameter's normalized value, keyed by parameter ID.
+pub type Snapshot = HashMap<String, f32>;
+
+/// The A and B snapshots. These are stored by the editor and saved with the plugin's state.
+pub type MorphSnapshots = RwLock<[Option<Snapshot>; 2]>;
+
+/// The parameters that are never part of a snapshot: the morph itself, bypass, MIDI learn, which
+/// is an action rather than a setting, the quality mode, which doesn't change the sound, and the
+/// listen mode and the meter settings, which are monitoring aids.
+const EXCLUDED_PARAM_IDS: [&str; 7] = [
+    "morph",
+    "bypass",
+    "midi_learn",
+    "quality",
+    "listen",
+    "meter_ballistics",
+    "meter_decay",
+];
+/// The MIDI CC mappings belong to the controller setup rather than to the sound, so they're left
+/// out as well.
+const EXCLUDED_GROUP_PREFIX: &str = "MIDI";
+
+/// Whether the parameter with this ID and group is part of the snapshots. Presets cover the same
+/// parameters.
+pub fn is_morphable(id: &str, group: &str) -> bool {
+    !EXCLUDED_PARAM_IDS.contains(&id) && !group.starts_with(EXCLUDED_GROUP_PREFIX)
+}
+
+/// Captures the current value of every morphable parameter. This is called from the editor.
+pub fn capture(params: &dyn Params) -> Snapshot {
+    params
+        .param_map()
+        .into_iter()
+        .filter(|(id, _, group)| is_morphable(id, group))
+        // SAFETY: The pointers come straight from the parameter object, which outlives this call
+        .map(|(id, ptr, _)| (id, unsafe { ptr.unmodulated_normalized_value() }))
+        .collect()
+}
+
+/// Stores `snapshot` in snapshot slot `slot_idx`, or clears the slot, and lets the audio thread
+/// know it needs to pick up the new snapshots.
+pub fn store(
+    snapshots: &MorphSnapshots,
+    changed: &AtomicBool,
+    slot_idx: usize,
+    snapshot: Option<Snapshot>,
+) {
+    if let Ok(mut snapshots) = snapshots.write() {
+        snapshots[slot_idx] = snapshot;
+    }
+    changed.store(true, Ordering::Release);
+}
+
+/// A parameter the morph can take over.
+struct MorphEntry {
+    id: String,
+    ptr: ParamPtr,
+    /// Stepped parameters switch between the snapshots instead of being interpolated.
+    stepped: bool,
+    /// The parameter's normalized values in snapshots A and B. `None` if either snapshot is
+    /// missing the parameter, in which case the parameter keeps its own value.
+    values: Option<(f32, f32)>,
+    /// The normalized value at the current morph position.
+    normalized: f32,
+}
+
+/// The audio thread's side of the snapshot morph.
+#[derive(Default)]
+pub struct Morph {
+    entries: Vec<MorphEntry>,
+    /// Maps parameters to their index in `entries`.
+    index: HashMap<ParamPtr, usize>,
+    /// Whether both snapshots are stored, in which case the morph overrides the parameters.
+    active: bool,
+}
+
+impl Morph {
+    /// Collects the morphable parameters and loads the current snapshots. This allocates, so it's
+    /// only called from `initialize()`. The plugin is also reinitialized after its state has been
+    /// restored, which is how restored snapshots get picked up.
+    pub fn initialize(&mut self, params: &dyn Params, snapshots: &MorphSnapshots) {
+        self.entries = params
+            .param_map()
+            .into_iter()
+            .filter(|(id, _, group)| is_morphable(id, group))
+            .map(|(id, ptr, _)| MorphEntry {
+                id,
+                ptr,
+                // SAFETY: See `capture()`
+                stepped: unsafe { ptr.step_count() }.is_some(),
+                values: None,
+                normalized: 0.0,
+            })
+            .collect();
+        self.index = self
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(entry_idx, entry)| (entry.ptr, entry_idx))
+            .collect();
+        self.active = false;
+
+        if let Ok(snapshots) = snapshots.read() {
+            self.load(&snapshots);
+        }
+    }
+
+    /// Picks up snapshot changes and moves every morphed parameter to `position`, from 0 for
+    /// snapshot A to 1 for snapshot B. The smoothed parameters glide to their new values.
+    pub fn begin_block(
+        &mut self,
+        snapshots: &MorphSnapshots,
+        changed: &AtomicBool,
+        position: f32,
+        sample_rate: f32,
+    ) {
+        if changed.swap(false, Ordering::Acquire) {
+            // The editor only holds the lock briefly, so if it's taken the snapshots are picked
+            // up on the next block instead
+            match snapshots.try_read() {
+                Ok(snapshots) => {
+                    let was_active = self.active;
+                    self.load(&snapshots);
+                    if was_active && !self.active {
+                        self.release_smoothers(sample_rate);
+                    }
+                }
+                Err(_) => changed.store(true, Ordering::Release),
+            }
+        }
+        if !self.active {
+            return;
+        }
+
+        for entry in &mut self.entries {
+            let Some((a, b)) = entry.values else {
+                continue;
+            };
+            entry.normalized = if !entry.stepped {
+                a + (position * (b - a))
+            } else if position < 0.5 {
+                a
+            } else {
+                b
+            };
+
+            // SAFETY: The pointers point into the plugin's parameter object, which lives as long
+            //         as the plugin
+            match entry.ptr {
+                ParamPtr::FloatParam(param) => {
+                    let param = unsafe { &*param };
+                    param
+                        .smoothed
+                        .set_target(sample_rate, param.preview_plain(entry.normalized));
+                }
+                ParamPtr::IntParam(param) => {
+                    let param = unsafe { &*param };
+                    param
+                        .smoothed
+                        .set_target(sample_rate, param.preview_plain(entry.normalized));
+                }
+                ParamPtr::BoolParam(_) | ParamPtr::EnumParam(_) => (),
+            }
+        }
+    }
+
+    /// The parameter's value at the current morph position, or its own value if the morph isn't
+    /// active or the snapshots don't contain the parameter.
+    pub fn value<P: Param>(&self, param: &P) -> P::Plain {
+        if self.active
+            && let Some(&entry_idx) = self.index.get(&param.as_ptr())
+            && self.entries[entry_idx].values.is_some()
+        {
+            param.preview_plain(self.entries[entry_idx].normalized)
+        } else {
+            param.modulated_plain_value()
+        }
+    }
+
+    /// Looks up every parameter's values in the snapshots. This doesn't allocate.
+    fn load(&mut self, snapshots: &[Option<Snapshot>; 2]) {
+        let [Some(a), Some(b)] = snapshots else {
+            self.active = false;
+            return;
+        };
+
+        for entry in &mut self.entries {
+            entry.values = a.get(&entry.id).copied().zip(b.get(&entry.id).copied());
+        }
+        self.active = true;
+    }
+
+    /// Sends the smoothed parameters back to their own values when the morph stops.
+    fn release_smoothers(&self, sample_rate: f32) {
+        for entry in &self.entries {
+            // SAFETY: See `begin_block()`
+            match entry.ptr {
+                ParamPtr::FloatParam(param) => {
+                    let param = unsafe { &*param };
+                    param.smoothed.set_target(sample_rate, param.value());
+                }
+                ParamPtr::IntParam(param) => {
+                    let param = unsafe { &*param };
+                    param.smoothed.set_target(sample_rate, param.value());
+                }
+                ParamPtr::BoolParam(_) | ParamPtr::EnumParam(_) => (),
+            }
+        }
+    }
+}
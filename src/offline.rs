@@ -0,0 +1,209 @@
+//! # Offline Rendering
+//!
+//! Runs the full plugin over audio without a host, for batch processing and for comparing the
+//! output between versions. The `colorfall-render` command line tool is built on this.
+//!
+//! nih-plug only lets the host change parameters, so the renderer applies its settings the way the
+//! snapshot morph applies its snapshots: both snapshots hold the settings, which puts the morph in
+//! charge of every morphable parameter. The parameters the morph leaves alone, like the quality
+//! mode, keep their default values.
+use nih_plug::prelude::*;
+use std::num::NonZeroU32;
+
+use crate::lfo::TransportInfo;
+use crate::morph::{self, Snapshot};
+use crate::presets;
+use crate::{AnalysisTask, BlockContext, ColorFall};
+
+/// The audio is processed in blocks of this many samples.
+const BLOCK_SIZE: usize = 512;
+
+/// Renders audio through ColorFall without a host.
+pub struct OfflineRenderer {
+    plugin: ColorFall,
+    audio_io_layout: AudioIOLayout,
+    sample_rate: f32,
+    /// The normalized value of every morphable parameter, starting out at their defaults.
+    settings: Snapshot,
+    /// Whether the plugin has been initialized with the current settings.
+    initialized: bool,
+}
+
+impl OfflineRenderer {
+    /// Creates a renderer for audio with `num_channels` channels at `sample_rate`. Every channel
+    /// count the plugin has a layout for works: mono, stereo, 5.1 and 7.1.
+    pub fn new(sample_rate: f32, num_channels: usize) -> Result<Self, String> {
+        let audio_io_layout = ColorFall::AUDIO_IO_LAYOUTS
+            .iter()
+            .find(|layout| {
+                layout.main_input_channels.map(NonZeroU32::get) == Some(num_channels as u32)
+                    && layout.main_output_channels == layout.main_input_channels
+                    && layout.aux_output_ports.is_empty()
+            })
+            .copied()
+            .ok_or_else(|| format!("ColorFall can't process {num_channels} channel audio"))?;
+
+        let plugin = ColorFall::default();
+        let settings = morph::capture(&*plugin.params);
+        Ok(Self {
+            plugin,
+            audio_io_layout,
+            sample_rate,
+            settings,
+            initialized: false,
+        })
+    }
+
+    /// Sets a parameter by its ID, with the value written the way the plugin displays it, like
+    /// `60%` for the Amount or `Tape` for the Saturation.
+    pub fn set_parameter(&mut self, id: &str, value: &str) -> Result<(), String> {
+        let (_, param_ptr, _) = self
+            .plugin
+            .params
+            .param_map()
+            .into_iter()
+            .find(|(param_id, _, _)| param_id == id)
+            .ok_or_else(|| format!("There is no parameter called '{id}'"))?;
+        if !self.settings.contains_key(id) {
+            return Err(format!("'{id}' can't be set for offline rendering"));
+        }
+
+        // SAFETY: The pointer comes straight from the parameter object, which outlives this call
+        let normalized = unsafe { param_ptr.string_to_normalized_value(value) }
+            .ok_or_else(|| format!("'{value}' isn't a valid value for '{id}'"))?;
+        self.settings.insert(id.to_owned(), normalized);
+        self.initialized = false;
+
+        Ok(())
+    }
+
+    /// Applies a preset with one `parameter_id = value` setting per line, see [`presets`]. The
+    /// values are parsed like in [`set_parameter()`][Self::set_parameter()]. Unlike loading a
+    /// preset in the editor, the parameters the preset doesn't mention keep their current values.
+    pub fn apply_preset(&mut self, preset: &str) -> Result<(), String> {
+        presets::for_each_setting(preset, |id, value| self.set_parameter(id, value))
+    }
+
+    /// Processes `channels` in place. The plugin's latency is compensated for, so the output lines
+    /// up with the input and has the same length. The plugin is reset before every render, so
+    /// every render starts from silence.
+    pub fn render(&mut self, channels: &mut [Vec<f32>]) {
+        let num_channels = self
+            .audio_io_layout
+            .main_output_channels
+            .map_or(0, NonZeroU32::get) as usize;
+        assert_eq!(channels.len(), num_channels);
+        if self.initialized {
+            self.plugin.reset();
+        } else {
+            self.initialize();
+        }
+
+        // The latency is rendered past the end of the input and then trimmed off the start
+        let num_samples = channels.first().map_or(0, Vec::len);
+        let latency = self.plugin.latency.total();
+        for channel in channels.iter_mut() {
+            channel.resize(num_samples + latency, 0.0);
+        }
+        let mut block_start = 0;
+        while block_start < num_samples + latency {
+            let block_end = (block_start + BLOCK_SIZE).min(num_samples + latency);
+            let mut buffer = Buffer::default();
+            // SAFETY: The slices outlive the buffer, which is dropped at the end of the iteration
+            unsafe {
+                buffer.set_slices(block_end - block_start, |slices| {
+                    *slices = channels
+                        .iter_mut()
+                        .map(|channel| &mut channel[block_start..block_end])
+                        .collect();
+                });
+            }
+            let mut aux = AuxiliaryBuffers {
+                inputs: &mut [],
+                outputs: &mut [],
+            };
+            self.plugin
+                .process_block(&mut buffer, &mut aux, &mut OfflineContext);
+
+            block_start = block_end;
+        }
+        for channel in channels.iter_mut() {
+            channel.drain(..latency);
+        }
+    }
+
+    /// Hands the settings to the morph and initializes the plugin with them.
+    fn initialize(&mut self) {
+        let params = &self.plugin.params;
+        for slot_idx in 0..2 {
+            morph::store(
+                &params.morph_snapshots,
+                &self.plugin.morph_snapshots_changed,
+                slot_idx,
+                Some(self.settings.clone()),
+            );
+        }
+
+        // Without a host nothing resets the parameter smoothers, so they start out at their
+        // targets here instead of gliding there from zero
+        for (id, param_ptr, _) in params.param_map() {
+            // SAFETY: See `set_parameter()`
+            unsafe {
+                let normalized = self
+                    .settings
+                    .get(&id)
+                    .copied()
+                    .unwrap_or_else(|| param_ptr.default_normalized_value());
+                match param_ptr {
+                    ParamPtr::FloatParam(param) => {
+                        (*param).smoothed.reset((*param).preview_plain(normalized));
+                    }
+                    ParamPtr::IntParam(param) => {
+                        (*param).smoothed.reset((*param).preview_plain(normalized));
+                    }
+                    ParamPtr::BoolParam(_) | ParamPtr::EnumParam(_) => (),
+                }
+            }
+        }
+
+        let buffer_config = BufferConfig {
+            sample_rate: self.sample_rate,
+            min_buffer_size: None,
+            max_buffer_size: BLOCK_SIZE as u32,
+            process_mode: ProcessMode::Offline,
+        };
+        self.plugin
+            .initialize(&self.audio_io_layout, &buffer_config, &mut OfflineContext);
+        self.initialized = true;
+    }
+}
+
+/// Stands in for the host. There is no transport and there are no MIDI events.
+struct OfflineContext;
+
+impl InitContext<ColorFall> for OfflineContext {
+    fn plugin_api(&self) -> PluginApi {
+        PluginApi::Standalone
+    }
+
+    fn execute(&self, _task: AnalysisTask) {}
+
+    fn set_latency_samples(&self, _samples: u32) {}
+
+    fn set_current_voice_capacity(&self, _capacity: u32) {}
+}
+
+impl BlockContext for OfflineContext {
+    fn transport(&self) -> TransportInfo {
+        TransportInfo::default()
+    }
+
+    fn next_event(&mut self) -> Option<PluginNoteEvent<ColorFall>> {
+        None
+    }
+
+    fn set_latency_samples(&self, _samples: u32) {}
+
+    // There's no editor to show the measurements to
+    fn execute_background(&self, _task: AnalysisTask) {}
+}
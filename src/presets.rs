@@ -0,0 +1,206 @@
+//! # Presets
+//!
+//! The factory presets ship inside the plugin, and user presets are files in the user's preset
+//! directory. Both use the format the `colorfall-render` tool reads: one `parameter_id = value`
+//! line per setting, with the values written the way the plugin displays them and everything
+//! after a `#` being a comment. A preset covers the same parameters as a morph snapshot, so loading
+//! one leaves the quality mode, the monitoring controls and the MIDI mappings alone. Parameters a
+//! preset doesn't mention are set to their defaults.
+use nih_plug::prelude::*;
+use std::fmt::Write;
+use std::path::PathBuf;
+
+use crate::morph::{self, Snapshot};
+
+/// The extension of user preset files.
+pub const PRESET_EXTENSION: &str = "preset";
+
+/// The factory presets' names and settings, in the order the browser lists them.
+pub const FACTORY_PRESETS: [(&str, &str); 5] = [
+    ("Gentle Glue", include_str!("../presets/gentle-glue.preset")),
+    ("Warm Tape", include_str!("../presets/warm-tape.preset")),
+    (
+        "Punchy Drums",
+        include_str!("../presets/punchy-drums.preset"),
+    ),
+    ("Airy Vocal", include_str!("../presets/airy-vocal.preset")),
+    ("Crushed Bus", include_str!("../presets/crushed-bus.preset")),
+];
+
+/// Characters that can't appear in a user preset's name, since the name is also its file name.
+const INVALID_NAME_CHARS: [char; 9] = ['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+
+/// A factory or user preset.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Preset {
+    pub name: String,
+    pub source: PresetSource,
+}
+
+/// Where a preset's settings come from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PresetSource {
+    /// The settings are compiled into the plugin.
+    Factory(&'static str),
+    /// The settings are in this file.
+    User(PathBuf),
+}
+
+impl Preset {
+    /// Factory presets can't be overwritten.
+    pub fn is_factory(&self) -> bool {
+        matches!(self.source, PresetSource::Factory(_))
+    }
+
+    /// The preset's settings, in the preset format.
+    pub fn read(&self) -> Result<String, String> {
+        match &self.source {
+            PresetSource::Factory(settings) => Ok((*settings).to_owned()),
+            PresetSource::User(path) => std::fs::read_to_string(path)
+                .map_err(|err| format!("Couldn't read '{}': {err}", path.display())),
+        }
+    }
+}
+
+/// The directory user presets are stored in, if the platform's data directory can be found.
+pub fn user_preset_dir() -> Option<PathBuf> {
+    let env_path = |name| std::env::var_os(name).map(PathBuf::from);
+    if cfg!(target_os = "windows") {
+        env_path("APPDATA").map(|dir| dir.join("ColorFall").join("Presets"))
+    } else if cfg!(target_os = "macos") {
+        env_path("HOME").map(|dir| dir.join("Library/Audio/Presets/ColorFall"))
+    } else {
+        env_path("XDG_DATA_HOME")
+            .or_else(|| env_path("HOME").map(|dir| dir.join(".local/share")))
+            .map(|dir| dir.join("ColorFall").join("Presets"))
+    }
+}
+
+/// The factory presets, followed by the user presets sorted by name. User presets that can't be
+/// listed are skipped.
+pub fn list() -> Vec<Preset> {
+    let mut user_presets: Vec<Preset> = user_preset_dir()
+        .and_then(|dir| std::fs::read_dir(dir).ok())
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == PRESET_EXTENSION))
+        .filter_map(|path| {
+            Some(Preset {
+                name: path.file_stem()?.to_string_lossy().into_owned(),
+                source: PresetSource::User(path),
+            })
+        })
+        .collect();
+    user_presets.sort_by_key(|preset| preset.name.to_lowercase());
+
+    FACTORY_PRESETS
+        .iter()
+        .map(|&(name, settings)| Preset {
+            name: name.to_owned(),
+            source: PresetSource::Factory(settings),
+        })
+        .chain(user_presets)
+        .collect()
+}
+
+/// Calls `set` with the parameter ID and the value of every setting in a preset, in order. Errors
+/// from `set` are returned with the line they happened on.
+pub fn for_each_setting(
+    preset: &str,
+    mut set: impl FnMut(&str, &str) -> Result<(), String>,
+) -> Result<(), String> {
+    for (line_idx, line) in preset.lines().enumerate() {
+        let setting = line.split('#').next().unwrap_or_default().trim();
+        if setting.is_empty() {
+            continue;
+        }
+
+        let (id, value) = setting
+            .split_once('=')
+            .ok_or_else(|| format!("Line {}: expected 'parameter = value'", line_idx + 1))?;
+        set(id.trim(), value.trim()).map_err(|err| format!("Line {}: {err}", line_idx + 1))?;
+    }
+
+    Ok(())
+}
+
+/// Turns a preset into the normalized value of every morphable parameter. The parameters the
+/// preset doesn't mention get their default values.
+pub fn parse(params: &dyn Params, preset: &str) -> Result<Snapshot, String> {
+    let param_map = params.param_map();
+    let mut snapshot: Snapshot = param_map
+        .iter()
+        .filter(|(id, _, group)| morph::is_morphable(id, group))
+        // SAFETY: The pointers come straight from the parameter object, which outlives this call
+        .map(|(id, ptr, _)| (id.clone(), unsafe { ptr.default_normalized_value() }))
+        .collect();
+
+    for_each_setting(preset, |id, value| {
+        let (_, ptr, _) = param_map
+            .iter()
+            .find(|(param_id, _, _)| param_id == id)
+            .ok_or_else(|| format!("There is no parameter called '{id}'"))?;
+        if !snapshot.contains_key(id) {
+            return Err(format!("'{id}' can't be set by a preset"));
+        }
+
+        // SAFETY: See above
+        let normalized = unsafe { ptr.string_to_normalized_value(value) }
+            .ok_or_else(|| format!("'{value}' isn't a valid value for '{id}'"))?;
+        snapshot.insert(id.to_owned(), normalized);
+
+        Ok(())
+    })?;
+
+    Ok(snapshot)
+}
+
+/// Writes the current value of every morphable parameter as a preset called `name`.
+pub fn format(params: &dyn Params, name: &str) -> String {
+    let mut preset = format!("# ColorFall preset: {name}\n");
+    for (id, ptr, group) in params.param_map() {
+        if morph::is_morphable(&id, &group) {
+            // SAFETY: The pointers come straight from the parameter object, which outlives this
+            //         call
+            let value =
+                unsafe { ptr.normalized_value_to_string(ptr.unmodulated_normalized_value(), true) };
+            let _ = writeln!(preset, "{id} = {value}");
+        }
+    }
+
+    preset
+}
+
+/// Saves the current settings as the user preset `name`, replacing the preset if it already
+/// exists. Returns the name with the surrounding whitespace trimmed.
+pub fn save(params: &dyn Params, name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() || name.starts_with('.') || name.contains(INVALID_NAME_CHARS) {
+        return Err(format!("'{name}' can't be used as a preset name"));
+    }
+    let dir = user_preset_dir().ok_or("Couldn't find the user preset directory")?;
+    std::fs::create_dir_all(&dir)
+        .map_err(|err| format!("Couldn't create '{}': {err}", dir.display()))?;
+
+    let path = dir.join(format!("{name}.{PRESET_EXTENSION}"));
+    std::fs::write(&path, format(params, name))
+        .map_err(|err| format!("Couldn't write '{}': {err}", path.display()))?;
+
+    Ok(name.to_owned())
+}
+
+/// Calls `set` with the normalized value of every parameter in `snapshot` that has a different
+/// value right now, so the editor can set them with gestures.
+pub fn apply(params: &dyn Params, snapshot: &Snapshot, mut set: impl FnMut(ParamPtr, f32)) {
+    for (id, ptr, _) in params.param_map() {
+        if let Some(&normalized) = snapshot.get(&id)
+            // SAFETY: The pointers come straight from the parameter object, which outlives this
+            //         call
+            && unsafe { ptr.unmodulated_normalized_value() } != normalized
+        {
+            set(ptr, normalized);
+        }
+    }
+}
@@ -0,0 +1,151 @@
+//! # Scope Buffers
+//!
+//! Lock-free ring buffers that carry signals from the audio thread to the editor's scopes. The
+//! audio thread writes one sample of every signal for every sample it processes, and the editor
+//! reads back the most recent window when it draws.
+//!
+//! The oscilloscope gets the dry and wet signals summed to mono. Its window starts at a rising
+//! zero crossing of the dry signal, which keeps periodic material standing still on the screen,
+//! with the wet trace lined up against the dry one so the saturation and the envelope shaping can
+//! be compared directly. The goniometer gets the left and right channels of the final output.
+use nih_plug::prelude::AtomicF32;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The number of samples kept for every signal. This holds the oscilloscope's window plus the same
+/// length again to search for a trigger point, at up to 192 kHz.
+const SCOPE_BUFFER_LENGTH: usize = 16384;
+/// The length of the oscilloscope's window. A full window is searched for the trigger point, so
+/// periodic material down to 40 Hz stands still.
+const SCOPE_WINDOW_MS: f32 = 25.0;
+/// The length of the goniometer's window. This is long enough for a few cycles of the bass, and
+/// short enough for the picture to follow the music.
+const GONIOMETER_WINDOW_MS: f32 = 20.0;
+
+/// Two signals recorded side by side.
+struct SampleRing {
+    first: Vec<AtomicF32>,
+    second: Vec<AtomicF32>,
+    /// The index the next sample is written to. Everything before it has been written.
+    write_pos: AtomicUsize,
+}
+
+impl Default for SampleRing {
+    fn default() -> Self {
+        Self {
+            first: (0..SCOPE_BUFFER_LENGTH)
+                .map(|_| AtomicF32::new(0.0))
+                .collect(),
+            second: (0..SCOPE_BUFFER_LENGTH)
+                .map(|_| AtomicF32::new(0.0))
+                .collect(),
+            write_pos: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl SampleRing {
+    fn push(&self, first: f32, second: f32) {
+        let pos = self.write_pos.load(Ordering::Relaxed);
+        self.first[pos].store(first, Ordering::Relaxed);
+        self.second[pos].store(second, Ordering::Relaxed);
+        self.write_pos
+            .store((pos + 1) % SCOPE_BUFFER_LENGTH, Ordering::Release);
+    }
+
+    /// The current write position, which all reads are relative to. Taking this once per read
+    /// gives every sample in the read the same reference point.
+    fn write_pos(&self) -> usize {
+        self.write_pos.load(Ordering::Acquire)
+    }
+
+    /// Maps an age in samples, with 0 being the most recent sample, to a buffer index.
+    fn index(write_pos: usize, age: usize) -> usize {
+        (write_pos + SCOPE_BUFFER_LENGTH - 1 - age) % SCOPE_BUFFER_LENGTH
+    }
+
+    /// The first signal's sample `age` samples before the most recent one.
+    fn first_at(&self, write_pos: usize, age: usize) -> f32 {
+        self.first[Self::index(write_pos, age)].load(Ordering::Relaxed)
+    }
+
+    /// Reads `length` samples of both signals, starting `start_age` samples before the most recent
+    /// one.
+    fn read(&self, write_pos: usize, start_age: usize, length: usize) -> (Vec<f32>, Vec<f32>) {
+        (0..length)
+            .map(|offset| {
+                let pos = Self::index(write_pos, start_age - offset);
+                (
+                    self.first[pos].load(Ordering::Relaxed),
+                    self.second[pos].load(Ordering::Relaxed),
+                )
+            })
+            .unzip()
+    }
+}
+
+/// The signals for the editor's oscilloscope and goniometer.
+pub(crate) struct ScopeBuffer {
+    sample_rate: AtomicF32,
+    /// The dry and wet signals, summed to mono.
+    waveforms: SampleRing,
+    /// The left and right channels of the output.
+    stereo: SampleRing,
+}
+
+impl Default for ScopeBuffer {
+    fn default() -> Self {
+        Self {
+            sample_rate: AtomicF32::new(44100.0),
+            waveforms: SampleRing::default(),
+            stereo: SampleRing::default(),
+        }
+    }
+}
+
+impl ScopeBuffer {
+    /// Sets the sample rate the window lengths are computed from.
+    pub fn set_sample_rate(&self, sample_rate: f32) {
+        self.sample_rate.store(sample_rate, Ordering::Relaxed);
+    }
+
+    /// Appends a single sample of the dry and wet signals. This is called from the audio thread.
+    pub fn push(&self, dry: f32, wet: f32) {
+        self.waveforms.push(dry, wet);
+    }
+
+    /// Appends a single sample of the output's left and right channels. This is called from the
+    /// audio thread.
+    pub fn push_stereo(&self, left: f32, right: f32) {
+        self.stereo.push(left, right);
+    }
+
+    /// Reads the most recent window of the dry and wet signals. The window starts at the latest
+    /// rising zero crossing of the dry signal that still leaves room for a full window, or at the
+    /// oldest sample of the most recent window if there is no crossing to trigger on.
+    pub fn read_triggered(&self) -> (Vec<f32>, Vec<f32>) {
+        let window_length = self.window_length(SCOPE_WINDOW_MS);
+        let write_pos = self.waveforms.write_pos();
+        let dry = |age| self.waveforms.first_at(write_pos, age);
+        let start_age = (window_length - 1..(2 * window_length) - 1)
+            .find(|&age| dry(age + 1) <= 0.0 && dry(age) > 0.0)
+            .unwrap_or(window_length - 1);
+
+        self.waveforms.read(write_pos, start_age, window_length)
+    }
+
+    /// Reads the most recent window of the output's left and right channels.
+    pub fn read_stereo(&self) -> (Vec<f32>, Vec<f32>) {
+        let window_length = self.window_length(GONIOMETER_WINDOW_MS);
+        let write_pos = self.stereo.write_pos();
+
+        self.stereo
+            .read(write_pos, window_length - 1, window_length)
+    }
+
+    /// Converts a window length to samples, leaving enough room in the buffer to search a window's
+    /// length for a trigger point.
+    fn window_length(&self, window_ms: f32) -> usize {
+        let sample_rate = self.sample_rate.load(Ordering::Relaxed);
+        ((sample_rate * window_ms / 1000.0) as usize).clamp(2, SCOPE_BUFFER_LENGTH / 2)
+    }
+}
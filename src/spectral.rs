@@ -0,0 +1,497 @@
+//! # Spectral Engine
+//!
+//! An alternative to the crossover-based band processing that works on FFT bins instead. The
+//! spectrum is grouped into [`NUM_PSEUDO_BANDS`] logarithmically spaced pseudo-bands, and every
+//! pseudo-band gets its own envelope follower and gain computer. The pseudo-bands take their
+//! intensity, trims and solo/mute state from the macro band they fall into, so Amount, Tilt and
+//! the per-band controls keep their meaning while the squashing happens at a much finer
+//! resolution.
+//!
+//! The signal is analyzed with Hann windowed frames at 75% overlap, and resynthesized with the
+//! same window using overlap-add. This delays the signal by [`SPECTRAL_LATENCY`] samples.
+use colorfall_dsp::{
+    self as dsp, BiquadCoefficients, Character, IntensityAdaptation, MAX_BANDS, MAX_CHANNELS,
+};
+use nih_plug::prelude::*;
+use std::ops::{Add, Mul, Sub};
+
+/// The FFT frame length. This gives a resolution of about 23 Hz at 48 kHz.
+pub const FFT_SIZE: usize = 2048;
+/// The distance between consecutive frames, for 75% overlap.
+const HOP_SIZE: usize = FFT_SIZE / 4;
+/// The engine outputs every sample one full frame after it came in.
+pub const SPECTRAL_LATENCY: usize = FFT_SIZE;
+/// The number of bins from DC up to and including Nyquist.
+const NUM_BINS: usize = (FFT_SIZE / 2) + 1;
+/// The number of pseudo-bands the spectrum is split into.
+pub const NUM_PSEUDO_BANDS: usize = 64;
+/// The lowest pseudo-band edge. Everything below it ends up in the lowest pseudo-band.
+const LOWEST_EDGE_HZ: f32 = 30.0;
+/// A Hann window squared sums to this at 75% overlap.
+const OVERLAP_ADD_GAIN: f32 = 1.5;
+/// The channels are transformed in pairs, with one channel in the real part and the other in the
+/// imaginary part of a single complex FFT.
+const NUM_CHANNEL_PAIRS: usize = MAX_CHANNELS / 2;
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Complex {
+    re: f32,
+    im: f32,
+}
+
+impl Complex {
+    fn new(re: f32, im: f32) -> Self {
+        Self { re, im }
+    }
+
+    fn conj(self) -> Self {
+        Self::new(self.re, -self.im)
+    }
+
+    fn norm_sqr(self) -> f32 {
+        (self.re * self.re) + (self.im * self.im)
+    }
+}
+
+impl Add for Complex {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        Self::new(
+            (self.re * other.re) - (self.im * other.im),
+            (self.re * other.im) + (self.im * other.re),
+        )
+    }
+}
+
+impl Mul<f32> for Complex {
+    type Output = Self;
+
+    fn mul(self, gain: f32) -> Self {
+        Self::new(self.re * gain, self.im * gain)
+    }
+}
+
+/// An in-place iterative radix-2 FFT of [`FFT_SIZE`] points. The inverse transform isn't scaled,
+/// so a round trip multiplies the signal by [`FFT_SIZE`].
+#[derive(Default)]
+struct Fft {
+    /// `e^(-2 pi i k / N)` for the first half of the circle.
+    twiddles: Vec<Complex>,
+    bit_reversed: Vec<usize>,
+}
+
+impl Fft {
+    /// Precalculates the twiddle factors and the bit reversal permutation. This allocates.
+    fn initialize(&mut self) {
+        self.twiddles = (0..(FFT_SIZE / 2))
+            .map(|k| {
+                let phase = -std::f64::consts::TAU * k as f64 / FFT_SIZE as f64;
+                Complex::new(phase.cos() as f32, phase.sin() as f32)
+            })
+            .collect();
+        let bits = FFT_SIZE.trailing_zeros();
+        self.bit_reversed = (0..FFT_SIZE)
+            .map(|idx| idx.reverse_bits() >> (usize::BITS - bits))
+            .collect();
+    }
+
+    fn process(&self, data: &mut [Complex], inverse: bool) {
+        for (idx, &reversed_idx) in self.bit_reversed.iter().enumerate() {
+            if reversed_idx > idx {
+                data.swap(idx, reversed_idx);
+            }
+        }
+
+        let mut len = 2;
+        while len <= FFT_SIZE {
+            let half_len = len / 2;
+            let twiddle_step = FFT_SIZE / len;
+            for start in (0..FFT_SIZE).step_by(len) {
+                for k in 0..half_len {
+                    let twiddle = self.twiddles[k * twiddle_step];
+                    let twiddle = if inverse { twiddle.conj() } else { twiddle };
+                    let even = data[start + k];
+                    let odd = data[start + k + half_len] * twiddle;
+                    data[start + k] = even + odd;
+                    data[start + k + half_len] = even - odd;
+                }
+            }
+            len *= 2;
+        }
+    }
+}
+
+/// The settings the spectral engine picks up for the next frame, see
+/// [`SpectralEngine::frame_due()`].
+#[derive(Clone, Copy)]
+pub struct SpectralSettings {
+    pub num_bands: usize,
+    /// The current crossover frequencies, which decide the macro band of every pseudo-band.
+    pub crossover_freqs: [f32; MAX_BANDS - 1],
+    /// The tilt EQ's low and high shelves. Their magnitude responses are applied to the bins.
+    pub tilt_eq: [BiquadCoefficients; 2],
+    pub amount: f32,
+    pub tilt: f32,
+    pub character: Character,
+    pub adaptation: IntensityAdaptation,
+    pub band_trims: [f32; MAX_BANDS],
+    /// Whether every macro band is heard, following the solo and mute switches.
+    pub band_audible: [bool; MAX_BANDS],
+    /// Whether every macro band's pseudo-bands are left uncompressed.
+    pub band_bypass: [bool; MAX_BANDS],
+    /// The Range cap as the lowest GR factor the pseudo-bands may reach.
+    pub min_gr_factor: f32,
+    pub downward_depth: f32,
+    pub upward_depth: f32,
+    pub attack_scale: f32,
+    pub release_scale: f32,
+}
+
+/// The FFT-based band processing. This allocates its buffers in [`SpectralEngine::initialize()`].
+pub struct SpectralEngine {
+    sample_rate: f32,
+    fft: Fft,
+    window: Vec<f32>,
+    /// Turns the summed bin powers of a pseudo-band into the mean square of that part of the
+    /// signal.
+    power_scale: f32,
+
+    /// The last [`FFT_SIZE`] input frames and the overlap-added output, as ring buffers.
+    input: Vec<[f32; MAX_CHANNELS]>,
+    output: Vec<[f32; MAX_CHANNELS]>,
+    /// The ring buffers' write position, which is also where the oldest input frame sits.
+    position: usize,
+    /// The number of samples until the next frame is processed.
+    hop_countdown: usize,
+    spectra: [Vec<Complex>; NUM_CHANNEL_PAIRS],
+
+    /// The first bin of every pseudo-band, followed by [`NUM_BINS`].
+    band_edges: [usize; NUM_PSEUDO_BANDS + 1],
+    /// The geometric center frequency of every pseudo-band.
+    band_freqs: [f32; NUM_PSEUDO_BANDS],
+    /// For every bin, the pseudo-band at or below it and how far the bin is towards the next
+    /// pseudo-band's center. The gains are interpolated between the centers so they don't step
+    /// at the edges.
+    bin_positions: Vec<(usize, f32)>,
+
+    settings: Option<SpectralSettings>,
+    /// Every pseudo-band's envelope follower, following the signal power. All channels share
+    /// these, so the stereo image stays put.
+    envelopes: [f32; NUM_PSEUDO_BANDS],
+    gains: [f32; NUM_PSEUDO_BANDS],
+    /// The average downward gain reduction of every macro band's pseudo-bands in decibels, for
+    /// the GR meter.
+    band_gr_db: [f32; MAX_BANDS],
+}
+
+impl Default for SpectralEngine {
+    fn default() -> Self {
+        Self {
+            sample_rate: 44100.0,
+            fft: Fft::default(),
+            window: Vec::new(),
+            power_scale: 0.0,
+            input: Vec::new(),
+            output: Vec::new(),
+            position: 0,
+            hop_countdown: HOP_SIZE,
+            spectra: Default::default(),
+            band_edges: [0; NUM_PSEUDO_BANDS + 1],
+            band_freqs: [0.0; NUM_PSEUDO_BANDS],
+            bin_positions: Vec::new(),
+            settings: None,
+            envelopes: [0.0; NUM_PSEUDO_BANDS],
+            gains: [1.0; NUM_PSEUDO_BANDS],
+            band_gr_db: [0.0; MAX_BANDS],
+        }
+    }
+}
+
+impl SpectralEngine {
+    /// Allocates the buffers and lays out the pseudo-bands for the sample rate. This should only
+    /// be called from `initialize()`.
+    pub fn initialize(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.fft.initialize();
+
+        // A periodic Hann window, which overlap-adds to a constant at 75% overlap
+        self.window = (0..FFT_SIZE)
+            .map(|idx| {
+                let phase = std::f32::consts::TAU * idx as f32 / FFT_SIZE as f32;
+                0.5 - (0.5 * phase.cos())
+            })
+            .collect();
+        // Parseval's theorem for the windowed frame, with the negative frequencies folded onto the
+        // positive ones
+        let window_power = self.window.iter().map(|w| w * w).sum::<f32>();
+        self.power_scale = 2.0 / (FFT_SIZE as f32 * window_power);
+
+        self.input = vec![[0.0; MAX_CHANNELS]; FFT_SIZE];
+        self.output = vec![[0.0; MAX_CHANNELS]; FFT_SIZE];
+        for spectrum in &mut self.spectra {
+            *spectrum = vec![Complex::default(); FFT_SIZE];
+        }
+
+        // The edges are spaced logarithmically from LOWEST_EDGE_HZ up to Nyquist, and every
+        // pseudo-band gets at least one bin
+        let bin_hz = sample_rate / FFT_SIZE as f32;
+        let nyquist = sample_rate / 2.0;
+        self.band_edges[0] = 0;
+        for band_idx in 1..NUM_PSEUDO_BANDS {
+            let edge_hz = LOWEST_EDGE_HZ
+                * (nyquist / LOWEST_EDGE_HZ).powf(band_idx as f32 / NUM_PSEUDO_BANDS as f32);
+            let max_edge = NUM_BINS - (NUM_PSEUDO_BANDS - band_idx);
+            self.band_edges[band_idx] = ((edge_hz / bin_hz).round() as usize)
+                .max(self.band_edges[band_idx - 1] + 1)
+                .min(max_edge);
+        }
+        self.band_edges[NUM_PSEUDO_BANDS] = NUM_BINS;
+        for (band_idx, freq) in self.band_freqs.iter_mut().enumerate() {
+            let lower = (self.band_edges[band_idx] as f32 * bin_hz).max(LOWEST_EDGE_HZ / 2.0);
+            let upper = self.band_edges[band_idx + 1] as f32 * bin_hz;
+            *freq = (lower * upper).sqrt();
+        }
+
+        self.bin_positions = (0..NUM_BINS)
+            .map(|bin_idx| {
+                let freq = (bin_idx as f32 * bin_hz).max(self.band_freqs[0]);
+                let band_idx = self.band_freqs[..(NUM_PSEUDO_BANDS - 1)]
+                    .iter()
+                    .rposition(|&center| center <= freq)
+                    .unwrap_or(0);
+                let (lower, upper) = (self.band_freqs[band_idx], self.band_freqs[band_idx + 1]);
+                let fraction = ((freq / lower).ln() / (upper / lower).ln()).clamp(0.0, 1.0);
+                (band_idx, fraction)
+            })
+            .collect();
+    }
+
+    /// Whether the next call to [`Self::process()`] completes a frame. The settings should be
+    /// updated with [`Self::update()`] right before that call.
+    pub fn frame_due(&self) -> bool {
+        self.hop_countdown == 1
+    }
+
+    /// Stores the settings for the next frame.
+    pub fn update(&mut self, settings: SpectralSettings) {
+        self.settings = Some(settings);
+    }
+
+    /// Feeds a frame into the engine and returns the output from [`SPECTRAL_LATENCY`] samples
+    /// ago.
+    pub fn process(
+        &mut self,
+        input: &[f32; MAX_CHANNELS],
+        num_channels: usize,
+    ) -> [f32; MAX_CHANNELS] {
+        self.input[self.position] = *input;
+        let output = std::mem::replace(&mut self.output[self.position], [0.0; MAX_CHANNELS]);
+        self.position = (self.position + 1) % FFT_SIZE;
+
+        self.hop_countdown -= 1;
+        if self.hop_countdown == 0 {
+            self.hop_countdown = HOP_SIZE;
+            self.process_frame(num_channels);
+        }
+
+        output
+    }
+
+    /// The average downward gain reduction of every macro band in decibels, as of the last frame.
+    pub fn band_gr_db(&self) -> &[f32; MAX_BANDS] {
+        &self.band_gr_db
+    }
+
+    /// Clears the buffers and the envelopes.
+    pub fn reset(&mut self) {
+        self.input.fill([0.0; MAX_CHANNELS]);
+        self.output.fill([0.0; MAX_CHANNELS]);
+        self.hop_countdown = HOP_SIZE;
+        self.envelopes = [0.0; NUM_PSEUDO_BANDS];
+        self.gains = [1.0; NUM_PSEUDO_BANDS];
+        self.band_gr_db = [0.0; MAX_BANDS];
+    }
+
+    /// Transforms the last [`FFT_SIZE`] input frames, applies the pseudo-band gains, and adds the
+    /// result to the output.
+    fn process_frame(&mut self, num_channels: usize) {
+        let num_pairs = num_channels.div_ceil(2);
+
+        // The loudest channel's power in every pseudo-band. The two channels of a pair are pulled
+        // apart using the symmetry of a real signal's spectrum.
+        let mut band_powers = [0.0f32; NUM_PSEUDO_BANDS];
+        for (pair_idx, spectrum) in self.spectra[..num_pairs].iter_mut().enumerate() {
+            let (left, right) = (2 * pair_idx, (2 * pair_idx) + 1);
+            for (idx, (bin, &window)) in spectrum.iter_mut().zip(&self.window).enumerate() {
+                let frame = &self.input[(self.position + idx) % FFT_SIZE];
+                *bin = Complex::new(frame[left] * window, frame[right] * window);
+            }
+            self.fft.process(spectrum, false);
+
+            for (band_power, edges) in band_powers.iter_mut().zip(self.band_edges.windows(2)) {
+                let (mut left_power, mut right_power) = (0.0, 0.0);
+                for bin_idx in edges[0]..edges[1] {
+                    let bin = spectrum[bin_idx];
+                    let mirrored = spectrum[(FFT_SIZE - bin_idx) % FFT_SIZE].conj();
+                    left_power += (bin + mirrored).norm_sqr() / 4.0;
+                    right_power += (bin - mirrored).norm_sqr() / 4.0;
+                }
+                *band_power = band_power.max(left_power).max(right_power);
+            }
+        }
+
+        self.update_gains(&band_powers);
+
+        let output_scale = 1.0 / (FFT_SIZE as f32 * OVERLAP_ADD_GAIN);
+        for (pair_idx, spectrum) in self.spectra[..num_pairs].iter_mut().enumerate() {
+            // Both channels of a pair get the same real gains, and the mirrored bins get the same
+            // gains as their counterparts, so the two channels stay separated
+            for (bin_idx, &(band_idx, fraction)) in self.bin_positions.iter().enumerate() {
+                let lower_gain = self.gains[band_idx];
+                let upper_gain = self.gains[band_idx + 1];
+                let gain = lower_gain + (fraction * (upper_gain - lower_gain));
+                spectrum[bin_idx] = spectrum[bin_idx] * gain;
+                if bin_idx > 0 && bin_idx < FFT_SIZE / 2 {
+                    spectrum[FFT_SIZE - bin_idx] = spectrum[FFT_SIZE - bin_idx] * gain;
+                }
+            }
+            self.fft.process(spectrum, true);
+
+            let (left, right) = (2 * pair_idx, (2 * pair_idx) + 1);
+            for (idx, (bin, &window)) in spectrum.iter().zip(&self.window).enumerate() {
+                let frame = &mut self.output[(self.position + idx) % FFT_SIZE];
+                frame[left] += bin.re * window * output_scale;
+                frame[right] += bin.im * window * output_scale;
+            }
+        }
+    }
+
+    /// Runs every pseudo-band's envelope follower and gain computer for a new frame.
+    fn update_gains(&mut self, band_powers: &[f32; NUM_PSEUDO_BANDS]) {
+        let Some(settings) = self.settings else {
+            return;
+        };
+        let num_bands = settings.num_bands;
+
+        // Every pseudo-band belongs to the macro band its center falls into. The pseudo-bands are
+        // much narrower than the macro bands, so their powers are scaled up as if they spanned
+        // their whole macro band. That keeps the thresholds where they are in the band engine.
+        let crossover_freqs = &settings.crossover_freqs[..(num_bands - 1)];
+        let macro_bands: [usize; NUM_PSEUDO_BANDS] = array_init::array_init(|band_idx| {
+            crossover_freqs
+                .iter()
+                .take_while(|&&freq| freq < self.band_freqs[band_idx])
+                .count()
+        });
+        let mut macro_band_bins = [0usize; MAX_BANDS];
+        for (band_idx, &macro_band) in macro_bands.iter().enumerate() {
+            macro_band_bins[macro_band] +=
+                self.band_edges[band_idx + 1] - self.band_edges[band_idx];
+        }
+
+        let mut band_gr_db = [0.0; MAX_BANDS];
+        let mut band_counts = [0usize; MAX_BANDS];
+        for band_idx in 0..NUM_PSEUDO_BANDS {
+            let macro_band = macro_bands[band_idx];
+            let freq = self.band_freqs[band_idx];
+            let [low_shelf, high_shelf] = settings.tilt_eq;
+            let tilt_gain = low_shelf.magnitude_at(self.sample_rate, freq)
+                * high_shelf.magnitude_at(self.sample_rate, freq);
+
+            let num_bins = self.band_edges[band_idx + 1] - self.band_edges[band_idx];
+            let power = band_powers[band_idx]
+                * self.power_scale
+                * (macro_band_bins[macro_band] as f32 / num_bins as f32)
+                * tilt_gain
+                * tilt_gain;
+
+            // The envelopes move once per frame, so the time constants are converted to frames
+            let (attack, release) = dsp::calculate_dynamic_time_constants(
+                self.sample_rate,
+                freq,
+                macro_band,
+                settings.amount,
+            );
+            let envelope = &mut self.envelopes[band_idx];
+            let time_constant = if power > *envelope {
+                attack * settings.attack_scale
+            } else {
+                release * settings.release_scale
+            };
+            let alpha = 1.0 - (-(HOP_SIZE as f32) / time_constant.max(1.0)).exp();
+            *envelope += alpha * (power - *envelope);
+            let envelope_level = envelope.sqrt();
+
+            let band_trim = settings.band_trims[macro_band];
+            let gr = dsp::calculate_target_gr(
+                macro_band,
+                num_bands,
+                settings.amount,
+                settings.tilt,
+                band_trim,
+                envelope_level,
+                settings.character,
+                settings.adaptation,
+            )
+            .max(settings.min_gr_factor)
+            .powf(settings.downward_depth);
+            let upward_gain = if settings.upward_depth > 0.0 {
+                dsp::calculate_target_upward_gain(
+                    macro_band,
+                    num_bands,
+                    settings.amount,
+                    settings.tilt,
+                    band_trim,
+                    envelope_level,
+                    settings.character,
+                    settings.adaptation,
+                )
+                .powf(settings.upward_depth)
+            } else {
+                1.0
+            };
+
+            // Bypassed bands only keep the tilt, which comes before the band split in the
+            // multiband mode
+            let (gr, upward_gain) = if settings.band_bypass[macro_band] {
+                (1.0, 1.0)
+            } else {
+                (gr, upward_gain)
+            };
+            self.gains[band_idx] = if settings.band_audible[macro_band] {
+                gr * upward_gain * tilt_gain
+            } else {
+                0.0
+            };
+            band_gr_db[macro_band] += util::gain_to_db(gr);
+            band_counts[macro_band] += 1;
+        }
+
+        for ((gr_db, total_gr_db), count) in
+            self.band_gr_db.iter_mut().zip(band_gr_db).zip(band_counts)
+        {
+            *gr_db = if count > 0 {
+                total_gr_db / count as f32
+            } else {
+                0.0
+            };
+        }
+    }
+}
@@ -0,0 +1,69 @@
+//! # State Versioning
+//!
+//! The plugin's saved state carries a format version in its `state-version` field. When a session
+//! or a host preset is loaded, [`migrate()`] runs every migration between the saved version and
+//! [`STATE_VERSION`] over the raw state before nih-plug applies it. That way a parameter whose
+//! default or meaning changes can be mapped onto its new form, instead of old sessions silently
+//! sounding different.
+//!
+//! A change that would make an old session sound different needs a bump of [`STATE_VERSION`] and a
+//! migration at the end of [`MIGRATIONS`]. That includes new parameters with a default that isn't
+//! neutral: nih-plug leaves the parameters a state doesn't mention at their defaults, so the
+//! migration needs to set them to the value that keeps the old behavior. Range changes don't need a
+//! migration on their own, since parameters are saved as plain values rather than normalized ones,
+//! unless the old values fall outside the new range.
+use nih_plug::prelude::*;
+
+/// The current version of the state format.
+pub const STATE_VERSION: u32 = 2;
+/// The key of the version in the state's persisted fields. This needs to match the `#[persist]`
+/// attribute on `ColorFallParams::state_version`.
+const STATE_VERSION_KEY: &str = "state-version";
+
+/// The migrations between the state versions. The one at index `i` turns a version `i` state into
+/// a version `i + 1` state.
+const MIGRATIONS: [fn(&mut PluginState); STATE_VERSION as usize] = [
+    // Version 0 is every state saved before the version was added. It's laid out like version 1.
+    |_| (),
+    // Version 1 smoothed the applied gain reduction over a fixed 1 ms instead of a share of the
+    // band's attack and release times. No GR Smoothing comes closest to that.
+    |state| {
+        state
+            .params
+            .insert("gr_smoothing".to_owned(), ParamValue::F32(0.0));
+    },
+];
+
+/// The format version `state` was saved with. States from before the version was added are
+/// version 0.
+pub fn saved_version(state: &PluginState) -> u32 {
+    // A `u32` is stored as a plain JSON number
+    state
+        .fields
+        .get(STATE_VERSION_KEY)
+        .and_then(|version| version.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+/// Brings `state` up to [`STATE_VERSION`]. States from newer versions of the plugin can't be
+/// migrated back, so they're loaded as they are. They're still marked with this version's
+/// [`STATE_VERSION`], since saving them again drops every parameter this version doesn't know
+/// about, and the newer version's migrations will then need to fill those in again.
+pub fn migrate(state: &mut PluginState) {
+    let version = saved_version(state);
+    if version > STATE_VERSION {
+        nih_warn!(
+            "The state was saved with state version {version}, newer than this version of \
+             ColorFall's {STATE_VERSION}. Some settings may not load correctly."
+        );
+    } else if version < STATE_VERSION {
+        for migration in &MIGRATIONS[version as usize..] {
+            migration(state);
+        }
+        nih_log!("Migrated the state from state version {version} to {STATE_VERSION}");
+    }
+
+    state
+        .fields
+        .insert(STATE_VERSION_KEY.to_owned(), STATE_VERSION.to_string());
+}